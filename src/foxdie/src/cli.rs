@@ -17,8 +17,29 @@
 // STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE,
 // EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
-use chrono::{DateTime, FixedOffset};
+use crate::logging::LogFormat;
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, Utc};
 use clap::{crate_version, App, AppSettings, Arg, ArgMatches, SubCommand};
+use foxdie_core::actions::push_requests::Batching;
+use foxdie_core::actions::AuthorFilter;
+use foxdie_core::error::FoxdieError;
+use glob::Pattern;
+use log::warn;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Certificate, Proxy};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::path::{Path, PathBuf};
+use what_git::ClientOptions;
+
+/// The config file Foxdie looks for in the working directory when `--config` isn't passed.
+pub const CONFIG_FILE_NAME: &str = ".foxdie.toml";
+
+/// The User-Agent sent with API requests when `--user-agent` isn't passed. Including the version helps whoever runs the
+/// server tell which release of Foxdie a request came from.
+const DEFAULT_USER_AGENT: &str = concat!("foxdie/", crate_version!());
 
 pub fn build_cli<'a, 'b>() -> App<'a, 'b> {
     let args = [
@@ -29,25 +50,199 @@ pub fn build_cli<'a, 'b>() -> App<'a, 'b> {
         Arg::with_name("since")
             .short("s")
             .long("since")
-            .required(true)
-            .help("Date in RFC 3339 format")
+            .help("Date in RFC 3339 format, or an ISO 8601 duration before now, such as P30D. Required, unless set in the config file.")
             .takes_value(true)
             .validator(validate_date),
-        Arg::with_name("token")
-            .short("t")
-            .long("token")
-            .required(true)
-            .help("Personal access token for use with GitHub or Gitlab.")
-            .env("TOKEN")
-            .hide_env_values(true),
+        token_arg()
+            .required(false)
+            .help("Personal access token for use with GitHub, Gitlab or Gitea. Required, unless read with --token-file or set in the config file."),
+        Arg::with_name("token-file")
+            .long("token-file")
+            .help("Reads the personal access token from the first line of this file, instead of passing it with --token.")
+            .takes_value(true)
+            .value_name("PATH"),
+        Arg::with_name("limit")
+            .short("l")
+            .long("limit")
+            .help("Maximum number of objects to delete or close in a single run.")
+            .takes_value(true)
+            .validator(validate_limit),
+        allowed_hosts_arg(),
+        dump_raw_responses_arg(),
+        user_agent_arg(),
+        header_arg(),
+        proxy_arg(),
+        no_progress_arg(),
+        ca_cert_arg(),
+        insecure_arg(),
+        Arg::with_name("batch-summary")
+            .long("batch-summary")
+            .help("Output path for a JSON summary of the outcome for every repository in the run.")
+            .takes_value(true)
+            .value_name("path"),
+        Arg::with_name("plan")
+            .long("plan")
+            .help("Prints the pending changes as a plan, in the style of `terraform plan`. With --json, the plan is written to stderr so that stdout carries only the summary."),
+        Arg::with_name("explain")
+            .long("explain")
+            .help("Logs why each branch or push request examined is or isn't eligible, such as its date or the rule that protects it.")
+            .conflicts_with("delete"),
+        Arg::with_name("stream-plan")
+            .long("stream-plan")
+            .help("Streams each pending change to stdout as a line of JSON as soon as it is found. Everything else is written to stderr.")
+            .conflicts_with_all(&["delete", "json"]),
+        Arg::with_name("plan-out")
+            .long("plan-out")
+            .help("Writes the branches to delete or push requests to close to this path as JSON, so that exactly those can be deleted or closed later with --from-plan once reviewed. Only on dry runs.")
+            .takes_value(true)
+            .value_name("PATH")
+            .conflicts_with("delete"),
+        Arg::with_name("from-plan")
+            .long("from-plan")
+            .help("Deletes or closes exactly what the plan written with --plan-out lists, rather than deciding afresh what is stale. Anything deleted, pushed to or closed since the plan was made is skipped with a warning. Makes --since unnecessary.")
+            .takes_value(true)
+            .value_name("PATH")
+            .conflicts_with_all(&["plan-out", "stream-plan", "explain"]),
+        json_arg(),
+        Arg::with_name("exclude")
+            .long("exclude")
+            .help("Glob pattern of branch names to leave alone. Push requests from matching branches are left open. May be passed multiple times.")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .value_name("PATTERN")
+            .validator(validate_pattern),
+        Arg::with_name("author")
+            .long("author")
+            .help("Only deletes branches whose latest commit was authored by, or closes push requests opened by, this author. Matches commit author names and SCM usernames exactly, ignoring case. May be passed multiple times.")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .value_name("NAME"),
+        Arg::with_name("exclude-author")
+            .long("exclude-author")
+            .help("Leaves alone branches whose latest commit was authored by, and push requests opened by, this author. May be passed multiple times.")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .value_name("NAME"),
+        skip_auth_check_arg(),
+        Arg::with_name("delay")
+            .long("delay")
+            .help("Milliseconds to wait between deleting or closing one object through the SCM's API and the next, to stay clear of secondary rate limits. Only applies with --delete.")
+            .takes_value(true)
+            .value_name("MILLIS")
+            .default_value("1000")
+            .validator(validate_limit),
+        Arg::with_name("config")
+            .long("config")
+            .help("Reads default values for these options from the given TOML file, instead of ./.foxdie.toml. Options passed on the command line take precedence.")
+            .takes_value(true)
+            .value_name("PATH"),
     ];
     App::new("foxdie")
         .setting(AppSettings::ArgRequiredElseHelp)
+        .arg(
+            Arg::with_name("log-format")
+                .long("log-format")
+                .help("Whether logs are written to stderr as plain text, or as one JSON object per line for log pipelines.")
+                .takes_value(true)
+                .possible_values(&["text", "json"])
+                .default_value("text")
+                .env("FOXDIE_LOG_FORMAT")
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("timeout")
+                .long("timeout")
+                .help("Gives up on each request to the SCM's API, including those made to detect the SCM, after this many seconds. Fetches and pushes are cancelled after this long too, as long as the remote is still responding.")
+                .takes_value(true)
+                .value_name("SECONDS")
+                .validator(validate_timeout)
+                .global(true),
+        )
         .subcommand(
             SubCommand::with_name("branches")
                 .about("Destroy remote branches from a given Git repository.")
                 .long_about("Destroy remote branches from a given Git repository that have not been updated since the specified date.")
                 .args(&args)
+                .arg(
+                    Arg::with_name("delete-via")
+                        .long("delete-via")
+                        .help("Whether to delete branches through the SCM's API, or by pushing to the remote.")
+                        .takes_value(true)
+                        .possible_values(&["api", "push"])
+                        .default_value("api"),
+                )
+                .arg(
+                    Arg::with_name("protect-recently-checked-out")
+                        .long("protect-recently-checked-out")
+                        .help("Protects branches checked out locally within the given number of days.")
+                        .takes_value(true)
+                        .value_name("days")
+                        .validator(validate_days),
+                )
+                .arg(
+                    Arg::with_name("protect-recent-author-days")
+                        .long("protect-recent-author-days")
+                        .help("Protects branches whose latest commit is by an author who has committed anywhere in the repository within the given number of days, as they are likely still working on them.")
+                        .takes_value(true)
+                        .value_name("days")
+                        .validator(validate_days),
+                )
+                .arg(
+                    Arg::with_name("interactive")
+                        .long("interactive")
+                        .short("i")
+                        .help("Lists the eligible branches and asks which of them to delete, then deletes those once confirmed. Implies --delete, and needs a terminal.")
+                        .conflicts_with_all(&["plan", "stream-plan", "json", "explain", "plan-out", "from-plan"]),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .help("Writes the decision about every branch examined to this path as JSON, with its last commit, the reason it is or isn't eligible, and whether it was deleted.")
+                        .takes_value(true)
+                        .value_name("PATH"),
+                )
+                .arg(
+                    Arg::with_name("delete-merged")
+                        .long("delete-merged")
+                        .help("Also deletes branches whose push request was merged, even when the merge updated them recently. Branches with an open push request, or pushed to since the merge, are kept."),
+                )
+                .arg(
+                    Arg::with_name("activity-metric")
+                        .long("activity-metric")
+                        .help("Which date of a branch is compared with --since: when its latest commit was committed, when it was authored, which rebasing and cherry-picking leave alone, or when the branch was last updated according to its reflog.")
+                        .takes_value(true)
+                        .possible_values(&["committer", "author", "reflog"])
+                        .default_value("committer"),
+                )
+                .arg(
+                    Arg::with_name("keep-with-any-pr")
+                        .long("keep-with-any-pr")
+                        .help("Keeps stale branches that any push request was ever opened from, including closed and merged ones. Lists every push request in the repository, so it takes extra requests.")
+                        .conflicts_with("delete-merged"),
+                )
+                .arg(
+                    Arg::with_name("prune-local")
+                        .long("prune-local")
+                        .help("Also deletes the local branches that track each deleted branch. Local branches that are checked out or have unpushed commits are kept."),
+                )
+                .arg(
+                    Arg::with_name("include")
+                        .long("include")
+                        .help("Glob pattern of branch names to consider. When passed, only matching branches can be deleted, and --exclude still applies to them. May be passed multiple times.")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .value_name("PATTERN")
+                        .validator(validate_pattern),
+                )
+                .arg(base_arg())
+                .arg(remote_arg())
+                .arg(ssh_key_arg())
+                .arg(ssh_passphrase_arg())
                 .arg(
                     Arg::with_name("DIRECTORY")
                         .help("Sets the Git directory to work from.")
@@ -60,16 +255,182 @@ pub fn build_cli<'a, 'b>() -> App<'a, 'b> {
                 .about("Close push branches filed with a given Git repository URL.")
                 .long_about("Close push branches filed with a given Git repository URL that have not been updated since the specified date.")
                 .args(&args)
+                .arg(
+                    Arg::with_name("journal")
+                        .long("journal")
+                        .help("Appends a record of every push request closed to the given file, for use with `reopen`.")
+                        .takes_value(true)
+                        .value_name("path"),
+                )
+                .arg(
+                    Arg::with_name("project-id")
+                        .long("project-id")
+                        .help("Numeric ID of the Gitlab project, used in place of the owner and name in the URL. Useful for projects that have been renamed or moved.")
+                        .takes_value(true)
+                        .value_name("ID")
+                        .validator(validate_project_id),
+                )
+                .arg(
+                    Arg::with_name("page-size")
+                        .long("page-size")
+                        .help("How many push requests to request in each page on GitHub and Gitlab, up to their maximum of 100. Defaults to 100.")
+                        .takes_value(true)
+                        .value_name("N")
+                        .validator(validate_page_size),
+                )
+                .arg(
+                    Arg::with_name("repos-file")
+                        .long("repos-file")
+                        .help("Cleans up every repository in this file, which lists one URL per line, in place of URL. Blank lines and lines starting with # are ignored.")
+                        .takes_value(true)
+                        .value_name("PATH")
+                        .conflicts_with_all(&["URL", "project-id", "from-plan"]),
+                )
+                .arg(
+                    Arg::with_name("group")
+                        .long("group")
+                        .help("Cleans up every project in this Gitlab group, subgroups included, by its full path. URL then names the Gitlab instance, like https://gitlab.example.com. --limit and --delay apply across the whole group. The token needs the read_api scope.")
+                        .takes_value(true)
+                        .value_name("PATH")
+                        .requires("URL")
+                        .conflicts_with_all(&["repos-file", "project-id", "from-plan", "concurrency"]),
+                )
+                .arg(
+                    Arg::with_name("concurrency")
+                        .long("concurrency")
+                        .help("How many of the repositories in --repos-file to clean up at once. Push requests on the same host are still closed --delay apart. Defaults to 1.")
+                        .takes_value(true)
+                        .value_name("N")
+                        .requires("repos-file")
+                        .conflicts_with("batch-size")
+                        .validator(validate_concurrency),
+                )
+                .arg(
+                    Arg::with_name("staleness-field")
+                        .long("staleness-field")
+                        .help("Whether push requests are stale when they were created before --since, or last updated before it.")
+                        .takes_value(true)
+                        .possible_values(&["created", "updated"])
+                        .default_value("updated"),
+                )
+                .arg(
+                    Arg::with_name("state")
+                        .long("state")
+                        .help("Which push requests to list. Closed ones are reported, for a look at historical churn, but only open ones are ever closed.")
+                        .takes_value(true)
+                        .possible_values(&["open", "closed", "all"])
+                        .default_value("open"),
+                )
+                .arg(
+                    Arg::with_name("batch-size")
+                        .long("batch-size")
+                        .help("Closes push requests in batches of this size, waiting for --batch-delay between batches.")
+                        .takes_value(true)
+                        .value_name("N")
+                        .validator(validate_batch_size),
+                )
+                .arg(
+                    Arg::with_name("confirm-each-batch")
+                        .long("confirm-each-batch")
+                        .help("Asks for confirmation before each batch after the first. When stdin isn't a terminal, waits for --batch-delay instead.")
+                        .requires("batch-size"),
+                )
+                .arg(
+                    Arg::with_name("batch-delay")
+                        .long("batch-delay")
+                        .help("Seconds to wait between batches, when not asking for confirmation.")
+                        .takes_value(true)
+                        .value_name("SECONDS")
+                        .default_value("30")
+                        .validator(validate_limit),
+                )
+                .arg(
+                    Arg::with_name("skip-drafts")
+                        .long("skip-drafts")
+                        .help("Leaves draft push requests open, regardless of their age."),
+                )
+                .arg(
+                    Arg::with_name("comment")
+                        .long("comment")
+                        .help("Posts a comment on each push request immediately before closing it.")
+                        .takes_value(true)
+                        .value_name("TEXT"),
+                )
+                .arg(
+                    Arg::with_name("target-branch")
+                        .long("target-branch")
+                        .help("Glob pattern of target branches. Only push requests into matching branches are closed. May be passed multiple times.")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .value_name("PATTERN")
+                        .validator(validate_pattern),
+                )
+                .arg(
+                    Arg::with_name("delete-source-branch")
+                        .long("delete-source-branch")
+                        .help("Deletes the source branch of each push request closed, unless it was opened from a fork, is protected, is the default branch, or is in use by another open push request."),
+                )
+                .arg(
+                    Arg::with_name("URL")
+                        .help("Sets the URL to a Git repository to work from.")
+                        .required_unless_one(&["repos-file", "from-plan"])
+                        .conflicts_with("from-plan")
+                        .index(1),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("reopen")
+                .about("Reopen push requests filed with a given Git repository URL.")
+                .long_about("Reopen push requests filed with a given Git repository URL, such as ones closed in error.")
+                .arg(token_arg())
+                .arg(allowed_hosts_arg())
+                .arg(dump_raw_responses_arg())
+                .arg(user_agent_arg())
+                .arg(header_arg())
+                .arg(proxy_arg())
+                .arg(no_progress_arg())
+                .arg(ca_cert_arg())
+                .arg(insecure_arg())
+                .arg(
+                    Arg::with_name("reopen-from")
+                        .long("reopen-from")
+                        .help("Reopens the push requests closed in the given journal, recreating any deleted source branches at the commits they pointed to when closed.")
+                        .takes_value(true)
+                        .value_name("journal"),
+                )
                 .arg(
                     Arg::with_name("URL")
                         .help("Sets the URL to a Git repository to work from.")
                         .required(true)
                         .index(1),
+                )
+                .arg(
+                    Arg::with_name("IDS")
+                        .help("Numbers of the push requests to reopen.")
+                        .required_unless("reopen-from")
+                        .multiple(true)
+                        .index(2)
+                        .validator(validate_push_request_id),
                 ),
         )
         .subcommand(
             SubCommand::with_name("report")
                 .about("Generate a JSON report of stale branches from a given Git repository.")
+                .arg(
+                    token_arg()
+                        .required(false)
+                        .help("Personal access token for use with GitHub, Gitlab or Gitea. When given, the report notes which branches are protected on the server."),
+                )
+                .arg(allowed_hosts_arg())
+                .arg(dump_raw_responses_arg())
+                .arg(user_agent_arg())
+                .arg(header_arg())
+                .arg(proxy_arg())
+                .arg(no_progress_arg())
+                .arg(ca_cert_arg())
+                .arg(insecure_arg())
+                .arg(skip_auth_check_arg())
                 .arg(
                     Arg::with_name("output")
                         .short("o")
@@ -77,6 +438,81 @@ pub fn build_cli<'a, 'b>() -> App<'a, 'b> {
                         .help("Output path for the report.")
                         .takes_value(true),
                 )
+                .arg(
+                    Arg::with_name("append")
+                        .long("append")
+                        .help("Appends each report to --output as a line of JSON instead of overwriting it, to keep a history of reports from scheduled runs. HTML reports are always overwritten.")
+                        .requires("output"),
+                )
+                .arg(
+                    Arg::with_name("timestamp-output")
+                        .long("timestamp-output")
+                        .help("Inserts the time of the run into the name of --output, as in report-2021-01-01T00:00:00Z.json, so that each run writes its own file.")
+                        .requires("output"),
+                )
+                .arg(
+                    Arg::with_name("top")
+                        .long("top")
+                        .help("Only reports this many of the stalest branches on each remote. Branches are always listed from the least to the most recently updated.")
+                        .takes_value(true)
+                        .value_name("N")
+                        .validator(validate_limit),
+                )
+                .arg(
+                    Arg::with_name("jobs")
+                        .long("jobs")
+                        .short("j")
+                        .help("How many threads to work out how far branches have diverged on, which dominates reports on repositories with many branches. Defaults to the number of CPUs.")
+                        .takes_value(true)
+                        .value_name("N")
+                        .validator(validate_jobs),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .help("Format to write the report to --output in. HTML reports are a single page with a sortable table of branches for each remote.")
+                        .takes_value(true)
+                        .possible_values(&["json", "html"])
+                        .default_value("json")
+                        .requires_if("html", "output"),
+                )
+                .arg(
+                    Arg::with_name("with-prs")
+                        .long("with-prs")
+                        .help("Lists the open push requests from or into each branch. Requires a token.")
+                        .requires("token"),
+                )
+                .arg(
+                    Arg::with_name("with-review-state")
+                        .long("with-review-state")
+                        .help("Notes whether the open push request from each branch is approved, has changes requested or is awaiting review. Requires a token, and makes a request per open push request.")
+                        .requires("token"),
+                )
+                .arg(
+                    Arg::with_name("orphaned-refs")
+                        .long("orphaned-refs")
+                        .help("Lists remote-tracking branches that no longer exist on their remote, without fetching or pruning."),
+                )
+                .arg(
+                    Arg::with_name("audit-protection")
+                        .long("audit-protection")
+                        .help("Counts and lists the branches each protection rule matches on each remote, to find rules that are too broad or match nothing. Rules configured on the server are only included with a token."),
+                )
+                .arg(
+                    Arg::with_name("mine")
+                        .long("mine")
+                        .help("Only reports branches whose latest commit was authored by the user.name or user.email in the Git config."),
+                )
+                .arg(
+                    Arg::with_name("first-parent")
+                        .long("first-parent")
+                        .help("Counts divergence between branches along first-parent history only."),
+                )
+                .arg(base_arg())
+                .arg(remote_arg())
+                .arg(ssh_key_arg())
+                .arg(ssh_passphrase_arg())
+                .arg(json_arg())
                 .arg(
                     Arg::with_name("DIRECTORY")
                         .help("Sets the Git directory to work from.")
@@ -84,37 +520,1155 @@ pub fn build_cli<'a, 'b>() -> App<'a, 'b> {
                         .index(1),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("detect")
+                .about("Show which SCM hosts a given Git repository URL, without making any changes to it.")
+                .long_about("Show which SCM hosts a given Git repository URL, and the API base URL, owner and repository Foxdie would use for it, without making any changes to it. Useful for diagnosing a misconfigured enterprise host.")
+                .arg(
+                    token_arg()
+                        .required(false)
+                        .help("Personal access token for use with GitHub, Gitlab or Gitea. Only used to probe hosts that aren't known by name."),
+                )
+                .arg(user_agent_arg())
+                .arg(header_arg())
+                .arg(proxy_arg())
+                .arg(ca_cert_arg())
+                .arg(insecure_arg())
+                .arg(
+                    json_arg()
+                        .help("Prints the detected SCM to stdout as a single JSON object."),
+                )
+                .arg(
+                    Arg::with_name("URL")
+                        .help("Sets the URL to a Git repository to detect the SCM of.")
+                        .required(true)
+                        .index(1),
+                ),
+        )
         .version(crate_version!())
 }
 
+fn token_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("token")
+        .short("t")
+        .long("token")
+        .required(true)
+        .help("Personal access token for use with GitHub, Gitlab or Gitea.")
+        .env("TOKEN")
+        .hide_env_values(true)
+}
+
+fn dump_raw_responses_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("dump-raw-responses")
+        .long("dump-raw-responses")
+        .help("Writes the body of every API response to a file in this directory before parsing it, for attaching to bug reports.")
+        .takes_value(true)
+        .value_name("dir")
+}
+
+fn json_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("json")
+        .long("json")
+        .help("Prints a summary of the run to stdout as a single JSON object once it finishes. Logs are still written to stderr.")
+}
+
+fn user_agent_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("user-agent")
+        .long("user-agent")
+        .help("User-Agent to send with every request to the SCM's API, including those made to detect the SCM.")
+        .takes_value(true)
+        .default_value(DEFAULT_USER_AGENT)
+        .validator(validate_user_agent)
+}
+
+fn header_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("header")
+        .long("header")
+        .help("Header to send with every request to the SCM's API, such as X-GitHub-Api-Version:2022-11-28. Overrides the Accept header sent to GitHub. Can be repeated.")
+        .takes_value(true)
+        .value_name("NAME:VALUE")
+        .multiple(true)
+        .number_of_values(1)
+        .validator(validate_header)
+}
+
+fn proxy_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("proxy")
+        .long("proxy")
+        .help("Proxy to send every request to the SCM's API through, as a URL. Takes precedence over the HTTP_PROXY, HTTPS_PROXY and NO_PROXY environment variables, which are used by default.")
+        .takes_value(true)
+        .value_name("URL")
+        .validator(validate_proxy)
+}
+
+fn skip_auth_check_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("skip-auth-check")
+        .long("skip-auth-check")
+        .help("Skips checking that the SCM accepts the token before using it, on runs that change nothing. With --delete, the token is always checked.")
+}
+
+fn no_progress_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("no-progress")
+        .long("no-progress")
+        .help("Hides the progress of fetches and paginated API requests, which is otherwise shown on stderr when run in a terminal without --json.")
+}
+
+fn ca_cert_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("ca-cert")
+        .long("ca-cert")
+        .help("PEM file with a root certificate to trust in addition to the system's, for SCMs whose certificates are signed by a private CA.")
+        .takes_value(true)
+        .value_name("PATH")
+}
+
+fn insecure_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("insecure")
+        .long("insecure")
+        .help("Accepts any certificate from the SCM, including self-signed ones. Requests can then be intercepted, so prefer --ca-cert.")
+}
+
+fn ssh_key_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("ssh-key")
+        .long("ssh-key")
+        .help("Private key to authenticate with SSH remotes. Takes precedence over the SSH agent, which is used by default.")
+        .takes_value(true)
+        .value_name("PATH")
+}
+
+fn ssh_passphrase_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("ssh-passphrase")
+        .long("ssh-passphrase")
+        .help("Passphrase for the key given with --ssh-key.")
+        .takes_value(true)
+        .env("FOXDIE_SSH_PASSPHRASE")
+        .hide_env_values(true)
+}
+
+fn remote_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("remote")
+        .long("remote")
+        .help("Only works on the remote with this name, rather than on every remote of the repository. May be passed multiple times.")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .value_name("NAME")
+}
+
+fn base_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("base")
+        .long("base")
+        .help("Base branch to use for a remote, as REMOTE=BRANCH, in place of the remote's default branch. May be passed once per remote.")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .value_name("REMOTE=BRANCH")
+        .validator(validate_base)
+}
+
+fn allowed_hosts_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("allowed-hosts")
+        .long("allowed-hosts")
+        .help("Comma-separated list of hosts Foxdie may contact. By default, any host may be contacted.")
+        .takes_value(true)
+        .value_name("hosts")
+        .env("FOXDIE_ALLOWED_HOSTS")
+        .hide_env_values(true)
+}
+
 #[allow(clippy::needless_pass_by_value)]
 fn validate_date(s: String) -> Result<(), String> {
-    DateTime::parse_from_rfc3339(&s)
+    parse_since(&s, Utc::now()).map(|_| ())
+}
+
+/// Parses a `--since` value, which is either an RFC 3339 date or an ISO 8601 duration, such as `P30D` or `P1Y2M`, that
+/// is taken as that long before `now`.
+fn parse_since(s: &str, now: DateTime<Utc>) -> Result<DateTime<FixedOffset>, String> {
+    if s.starts_with('P') {
+        let duration = parse_iso8601_duration(s)
+            .map_err(|reason| format!("Invalid ISO 8601 duration {}: {}", s, reason))?;
+        return duration
+            .before(now)
+            .map(Into::into)
+            .ok_or_else(|| format!("Invalid ISO 8601 duration {}: it is too long", s));
+    }
+    DateTime::parse_from_rfc3339(s).map_err(|e| {
+        format!(
+            "Expected an RFC 3339 date or an ISO 8601 duration, but could not read {}: {}",
+            s, e
+        )
+    })
+}
+
+/// The `PnYnMnWnDTnHnMnS` form of an ISO 8601 duration, limited to whole numbers.
+#[derive(Debug, Default, PartialEq)]
+struct IsoDuration {
+    years: u32,
+    months: u32,
+    weeks: u32,
+    days: u32,
+    hours: u32,
+    minutes: u32,
+    seconds: u32,
+}
+
+impl IsoDuration {
+    /// The instant this long before `now`. Years and months are calendar years and months, so `P1M` before March 31st
+    /// is the last day of February.
+    fn before(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let months = u64::from(self.years) * 12 + u64::from(self.months);
+        let date = months_before(now, months)?;
+        let exact = Duration::weeks(i64::from(self.weeks))
+            + Duration::days(i64::from(self.days))
+            + Duration::hours(i64::from(self.hours))
+            + Duration::minutes(i64::from(self.minutes))
+            + Duration::seconds(i64::from(self.seconds));
+        date.checked_sub_signed(exact)
+    }
+}
+
+fn parse_iso8601_duration(s: &str) -> Result<IsoDuration, String> {
+    let rest = s
+        .strip_prefix('P')
+        .ok_or_else(|| String::from("it must start with P"))?;
+    let (date_part, time_part) = match rest.split_once('T') {
+        Some((date_part, time_part)) => (date_part, Some(time_part)),
+        None => (rest, None),
+    };
+    let mut duration = IsoDuration::default();
+    let date_components = parse_duration_components(date_part, &['Y', 'M', 'W', 'D'])?;
+    for (designator, value) in &date_components {
+        match designator {
+            'Y' => duration.years = *value,
+            'M' => duration.months = *value,
+            'W' => duration.weeks = *value,
+            _ => duration.days = *value,
+        }
+    }
+    let time_components = match time_part {
+        Some("") => {
+            return Err(String::from(
+                "T must be followed by hours, minutes or seconds",
+            ))
+        }
+        Some(time_part) => parse_duration_components(time_part, &['H', 'M', 'S'])?,
+        None => vec![],
+    };
+    for (designator, value) in &time_components {
+        match designator {
+            'H' => duration.hours = *value,
+            'M' => duration.minutes = *value,
+            _ => duration.seconds = *value,
+        }
+    }
+    if date_components.is_empty() && time_components.is_empty() {
+        return Err(String::from("it has no components"));
+    }
+    Ok(duration)
+}
+
+/// Splits `part` of a duration into numbers and their designators, which must appear in the order of `designators`, at
+/// most once each.
+fn parse_duration_components(part: &str, designators: &[char]) -> Result<Vec<(char, u32)>, String> {
+    let mut components = vec![];
+    let mut next_allowed = 0;
+    let mut number = String::new();
+    for c in part.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let position = designators
+            .iter()
+            .position(|&designator| designator == c)
+            .ok_or_else(|| format!("unexpected character {:?}", c))?;
+        if position < next_allowed {
+            return Err(format!("{} is repeated or out of order", c));
+        }
+        if number.is_empty() {
+            return Err(format!("{} must be preceded by a whole number", c));
+        }
+        let value = number
+            .parse::<u32>()
+            .map_err(|_| format!("the number before {} is too large", c))?;
+        components.push((c, value));
+        number.clear();
+        next_allowed = position + 1;
+    }
+    if !number.is_empty() {
+        return Err(format!("{} is missing a designator", number));
+    }
+    Ok(components)
+}
+
+/// The same time of day `months` calendar months before `date`, on the same day of the month, or on the last day of the
+/// month if it is shorter.
+fn months_before(date: DateTime<Utc>, months: u64) -> Option<DateTime<Utc>> {
+    let total =
+        i64::from(date.year()) * 12 + i64::from(date.month0()) - i64::try_from(months).ok()?;
+    let year = i32::try_from(total.div_euclid(12)).ok()?;
+    let month = u32::try_from(total.rem_euclid(12)).ok()? + 1;
+    let day = (1..=date.day())
+        .rev()
+        .find_map(|day| NaiveDate::from_ymd_opt(year, month, day))?;
+    Some(DateTime::from_utc(day.and_time(date.time()), Utc))
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn validate_limit(s: String) -> Result<(), String> {
+    s.parse::<usize>().map(|_| ()).map_err(|e| e.to_string())
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn validate_concurrency(s: String) -> Result<(), String> {
+    match s.parse::<usize>() {
+        Ok(0) => Err(String::from("The concurrency must be at least 1.")),
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn validate_page_size(s: String) -> Result<(), String> {
+    match s.parse::<usize>() {
+        Ok(0) => Err(String::from("The page size must be at least 1.")),
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn validate_jobs(s: String) -> Result<(), String> {
+    match s.parse::<usize>() {
+        Ok(0) => Err(String::from("The number of jobs must be at least 1.")),
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn validate_timeout(s: String) -> Result<(), String> {
+    match s.parse::<u64>() {
+        Ok(0) => Err(String::from("The timeout must be at least 1 second.")),
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn validate_batch_size(s: String) -> Result<(), String> {
+    match s.parse::<usize>() {
+        Ok(0) => Err(String::from("The batch size must be at least 1.")),
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn validate_project_id(s: String) -> Result<(), String> {
+    s.parse::<u64>().map(|_| ()).map_err(|e| e.to_string())
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn validate_days(s: String) -> Result<(), String> {
+    s.parse::<u32>().map(|_| ()).map_err(|e| e.to_string())
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn validate_pattern(s: String) -> Result<(), String> {
+    Pattern::new(&s).map(|_| ()).map_err(|e| e.to_string())
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn validate_base(s: String) -> Result<(), String> {
+    match s.split_once('=') {
+        Some((remote, branch)) if !remote.is_empty() && !branch.is_empty() => Ok(()),
+        _ => Err(String::from("expected REMOTE=BRANCH")),
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn validate_user_agent(s: String) -> Result<(), String> {
+    HeaderValue::from_str(&s)
+        .map(|_| ())
+        .map_err(|_| String::from("The User-Agent must be printable ASCII."))
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn validate_header(s: String) -> Result<(), String> {
+    parse_header(&s).map(|_| ())
+}
+
+/// Splits a header passed as `NAME:VALUE` into its name and value, without the whitespace around either.
+fn parse_header(s: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or_else(|| String::from("expected NAME:VALUE"))?;
+    let name = HeaderName::try_from(name.trim())
+        .map_err(|_| format!("{:?} is not a valid header name", name.trim()))?;
+    let value = HeaderValue::from_str(value.trim())
+        .map_err(|_| String::from("The header value must be printable ASCII."))?;
+    Ok((name, value))
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn validate_proxy(s: String) -> Result<(), String> {
+    Proxy::all(&s).map(|_| ()).map_err(|_| {
+        String::from("The proxy must be a URL, such as http://proxy.example.com:3128.")
+    })
+}
+
+fn validate_push_request_id(s: String) -> Result<(), String> {
+    s.trim_start_matches('#')
+        .parse::<i32>()
         .map(|_| ())
         .map_err(|e| e.to_string())
 }
 
+/// Parses the push request numbers passed as the argument `name`, which may be written with a leading `#`.
+pub fn parse_push_request_ids(app_m: &ArgMatches, name: &str) -> Vec<i32> {
+    app_m
+        .values_of(name)
+        .into_iter()
+        .flatten()
+        .filter_map(|id_str| id_str.trim_start_matches('#').parse::<i32>().ok())
+        .collect()
+}
+
 pub struct SharedArguments<'a> {
     pub should_delete: bool,
     pub since: DateTime<FixedOffset>,
-    pub token: &'a str,
+    pub token: String,
+    pub limit: Option<usize>,
+    pub plan: bool,
+    pub stream_plan: bool,
+    pub allowed_hosts: Option<Vec<String>>,
+    pub batch_summary: Option<&'a str>,
+    pub raw_response_dir: Option<&'a Path>,
+    pub exclude: Vec<Pattern>,
+    pub authors: AuthorFilter,
 }
 
-pub fn parse_shared_arguments<'a, 'b>(app_m: &'b ArgMatches<'a>) -> SharedArguments<'b> {
+/// Default values for the shared arguments, read from a config file. `--delete` is deliberately absent, so that a
+/// config file can never turn off the dry run.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    since: Option<String>,
+    token: Option<String>,
+    limit: Option<usize>,
+    plan: bool,
+    allowed_hosts: Option<Vec<String>>,
+    batch_summary: Option<String>,
+    dump_raw_responses: Option<PathBuf>,
+    exclude: Vec<String>,
+    author: Vec<String>,
+    exclude_author: Vec<String>,
+}
+
+/// Reads the config file passed with `--config`, or `.foxdie.toml` in the working directory if there is one.
+pub fn load_config(app_m: &ArgMatches) -> Result<Config, FoxdieError> {
+    let path = match app_m.value_of("config") {
+        Some(path) => PathBuf::from(path),
+        None if Path::new(CONFIG_FILE_NAME).is_file() => PathBuf::from(CONFIG_FILE_NAME),
+        None => return Ok(Config::default()),
+    };
+    fs::read_to_string(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| parse_config(&contents))
+        .map_err(|err| FoxdieError::InvalidConfig(path, err))
+}
+
+fn parse_config(contents: &str) -> Result<Config, String> {
+    let config: Config = toml::from_str(contents).map_err(|e| e.to_string())?;
+    if let Some(ref since) = config.since {
+        validate_date(since.clone())?;
+    }
+    for pattern in &config.exclude {
+        validate_pattern(pattern.clone())?;
+    }
+    Ok(config)
+}
+
+/// Parses the shared arguments, falling back to the values in `config` for any that weren't passed.
+pub fn parse_shared_arguments<'a, 'b>(
+    app_m: &'b ArgMatches<'a>,
+    config: &'b Config,
+) -> Result<SharedArguments<'b>, FoxdieError> {
     let should_delete = app_m.is_present("delete");
 
     let since = app_m
         .value_of("since")
-        .and_then(|date_str| DateTime::parse_from_rfc3339(date_str).ok())
-        .expect("Should have already validated a date, which is a required argument.");
+        .or(config.since.as_deref())
+        .and_then(|since_str| parse_since(since_str, Utc::now()).ok())
+        // A plan carried out with --from-plan was already decided on, so it needs no cutoff.
+        .or_else(|| app_m.is_present("from-plan").then(|| Utc::now().into()))
+        .ok_or(FoxdieError::MissingArgument("since"))?;
+
+    let token = match (app_m.value_of("token"), app_m.value_of("token-file")) {
+        (Some(_), Some(_)) => return Err(FoxdieError::ConflictingArguments("token", "token-file")),
+        (Some(token), None) => token.to_string(),
+        (None, Some(path)) => read_token_file(Path::new(path))?,
+        (None, None) => config
+            .token
+            .clone()
+            .ok_or(FoxdieError::MissingArgument("token"))?,
+    };
+
+    let limit = app_m
+        .value_of("limit")
+        .and_then(|limit_str| limit_str.parse::<usize>().ok())
+        .or(config.limit);
+
+    let plan = app_m.is_present("plan") || config.plan;
+
+    let stream_plan = app_m.is_present("stream-plan");
+
+    let allowed_hosts = parse_allowed_hosts(app_m).or_else(|| config.allowed_hosts.clone());
+
+    let batch_summary = app_m
+        .value_of("batch-summary")
+        .or(config.batch_summary.as_deref());
+
+    let raw_response_dir = parse_raw_response_dir(app_m).or(config.dump_raw_responses.as_deref());
 
-    let token: &'b str = app_m.value_of("token").expect(
-        "Should have passed a token, which is a required argument or environment variable.",
-    );
+    let exclude = match app_m.values_of("exclude") {
+        Some(patterns) => patterns.filter_map(|p| Pattern::new(p).ok()).collect(),
+        None => config
+            .exclude
+            .iter()
+            .filter_map(|p| Pattern::new(p).ok())
+            .collect(),
+    };
 
-    SharedArguments {
+    let values_or = |name: &str, fallback: &[String]| match app_m.values_of(name) {
+        Some(values) => values.map(String::from).collect(),
+        None => fallback.to_vec(),
+    };
+    let authors = AuthorFilter {
+        include: values_or("author", &config.author),
+        exclude: values_or("exclude-author", &config.exclude_author),
+    };
+
+    Ok(SharedArguments {
         should_delete,
         since,
         token,
+        limit,
+        plan,
+        stream_plan,
+        allowed_hosts,
+        batch_summary,
+        raw_response_dir,
+        exclude,
+        authors,
+    })
+}
+
+/// Parses how many repositories `push-requests` cleans up at once, from `--concurrency`.
+pub fn parse_concurrency(app_m: &ArgMatches) -> usize {
+    app_m
+        .value_of("concurrency")
+        .and_then(|concurrency| concurrency.parse::<usize>().ok())
+        .unwrap_or(1)
+}
+
+/// Parses how many threads `report` computes divergence on, from `--jobs`. Defaults to the parallelism available to
+/// the process.
+pub fn parse_jobs(app_m: &ArgMatches) -> usize {
+    app_m
+        .value_of("jobs")
+        .and_then(|jobs| jobs.parse::<usize>().ok())
+        .or_else(|| std::thread::available_parallelism().ok().map(usize::from))
+        .unwrap_or(1)
+}
+
+/// Parses the batching options of `push-requests`, if a batch size was passed.
+pub fn parse_batching(app_m: &ArgMatches) -> Option<Batching> {
+    let size = app_m.value_of("batch-size")?.parse::<usize>().ok()?;
+    let delay = app_m
+        .value_of("batch-delay")
+        .and_then(|delay_str| delay_str.parse::<u64>().ok())
+        .unwrap_or_default();
+    Some(Batching {
+        size,
+        confirm: app_m.is_present("confirm-each-batch"),
+        delay: std::time::Duration::from_secs(delay),
+    })
+}
+
+/// Parses how long to wait between destructive API calls, from `--delay`.
+pub fn parse_delay(app_m: &ArgMatches) -> std::time::Duration {
+    let millis = app_m
+        .value_of("delay")
+        .and_then(|delay_str| delay_str.parse::<u64>().ok())
+        .unwrap_or_default();
+    std::time::Duration::from_millis(millis)
+}
+
+/// Parses the value of the argument `name` as a number of days, and returns the date that many days ago.
+pub fn parse_days_ago(app_m: &ArgMatches, name: &str) -> Option<DateTime<FixedOffset>> {
+    app_m
+        .value_of(name)
+        .and_then(|days_str| days_str.parse::<u32>().ok())
+        .map(|days| (Utc::now() - Duration::days(i64::from(days))).into())
+}
+
+/// Reads a token from the first line of the file at `path`, without its surrounding whitespace.
+fn read_token_file(path: &Path) -> Result<String, FoxdieError> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| FoxdieError::InvalidTokenFile(path.to_path_buf(), err.to_string()))?;
+    match contents.lines().next().map(str::trim) {
+        Some(token) if !token.is_empty() => Ok(token.to_string()),
+        _ => Err(FoxdieError::InvalidTokenFile(
+            path.to_path_buf(),
+            String::from("the first line is empty"),
+        )),
+    }
+}
+
+pub fn parse_token<'a, 'b>(app_m: &'b ArgMatches<'a>) -> &'b str {
+    app_m
+        .value_of("token")
+        .expect("Should have passed a token, which is a required argument or environment variable.")
+}
+
+pub fn parse_client_options<'a, 'b>(
+    app_m: &'b ArgMatches<'a>,
+) -> Result<ClientOptions<'b>, FoxdieError> {
+    let accept_invalid_certs = app_m.is_present("insecure");
+    if accept_invalid_certs {
+        warn!(
+            "--insecure is set, so the SCM's certificate is not verified and requests to it can be intercepted. \
+             Pass the CA that signed it with --ca-cert instead, if you can."
+        );
+    }
+    Ok(ClientOptions {
+        user_agent: app_m
+            .value_of("user-agent")
+            .expect("The User-Agent has a default value, so it should always be set."),
+        default_headers: app_m
+            .values_of("header")
+            .map(|headers| {
+                headers
+                    .map(|header| {
+                        parse_header(header).expect("Headers should have been validated.")
+                    })
+                    .collect::<HeaderMap>()
+            })
+            .unwrap_or_default(),
+        proxy: app_m
+            .value_of("proxy")
+            .map(|url| Proxy::all(url).expect("The proxy should have been validated as a URL.")),
+        root_certificate: app_m
+            .value_of("ca-cert")
+            .map(|path| read_ca_cert(Path::new(path)))
+            .transpose()?,
+        accept_invalid_certs,
+        timeout: app_m
+            .value_of("timeout")
+            .and_then(|seconds| seconds.parse::<u64>().ok())
+            .map(std::time::Duration::from_secs),
+        provider_cache: None,
+    })
+}
+
+/// Reads the PEM-encoded certificate at `path`, for use as a root certificate.
+fn read_ca_cert(path: &Path) -> Result<Certificate, FoxdieError> {
+    let pem = fs::read(path)
+        .map_err(|err| FoxdieError::InvalidCaCert(path.to_path_buf(), err.to_string()))?;
+    Certificate::from_pem(&pem)
+        .map_err(|err| FoxdieError::InvalidCaCert(path.to_path_buf(), err.to_string()))
+}
+
+pub fn parse_target_branches(app_m: &ArgMatches<'_>) -> Vec<Pattern> {
+    app_m
+        .values_of("target-branch")
+        .map(|patterns| patterns.filter_map(|p| Pattern::new(p).ok()).collect())
+        .unwrap_or_default()
+}
+
+/// Parses the patterns passed with `--include`, which limit the branches considered for deletion.
+pub fn parse_include(app_m: &ArgMatches<'_>) -> Vec<Pattern> {
+    app_m
+        .values_of("include")
+        .map(|patterns| patterns.filter_map(|p| Pattern::new(p).ok()).collect())
+        .unwrap_or_default()
+}
+
+/// Returns the repository URLs to clean up: those listed in the file passed with `--repos-file`, or else `URL`.
+pub fn parse_repository_urls(app_m: &ArgMatches<'_>) -> Result<Vec<String>, FoxdieError> {
+    match app_m.value_of("repos-file") {
+        Some(path) => read_repos_file(Path::new(path)),
+        None => Ok(vec![app_m
+            .value_of("URL")
+            .expect("URL is required unless --repos-file is passed.")
+            .to_string()]),
+    }
+}
+
+/// Reads the repository URLs listed in the file at `path`, one per line, skipping blank lines and `#` comments.
+fn read_repos_file(path: &Path) -> Result<Vec<String>, FoxdieError> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| FoxdieError::InvalidReposFile(path.to_path_buf(), err.to_string()))?;
+    let urls = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect::<Vec<_>>();
+    if urls.is_empty() {
+        return Err(FoxdieError::InvalidReposFile(
+            path.to_path_buf(),
+            String::from("it lists no repositories"),
+        ));
+    }
+    Ok(urls)
+}
+
+/// Parses the log format, which may be passed before or after the subcommand.
+pub fn parse_log_format(app_m: &ArgMatches) -> LogFormat {
+    let format = match app_m.subcommand() {
+        (_, Some(sub_m)) => sub_m.value_of("log-format"),
+        _ => app_m.value_of("log-format"),
+    };
+    match format {
+        Some("json") => LogFormat::Json,
+        _ => LogFormat::Text,
+    }
+}
+
+pub fn parse_raw_response_dir<'a, 'b>(app_m: &'b ArgMatches<'a>) -> Option<&'b Path> {
+    app_m.value_of("dump-raw-responses").map(Path::new)
+}
+
+/// Parses the SSH key and its passphrase, returning them as a pair.
+pub fn parse_ssh_key<'a, 'b>(app_m: &'b ArgMatches<'a>) -> (Option<&'b Path>, Option<&'b str>) {
+    (
+        app_m.value_of("ssh-key").map(Path::new),
+        app_m.value_of("ssh-passphrase"),
+    )
+}
+
+/// Parses the remotes named by `--remote`, which are empty when every remote is to be cleaned up.
+pub fn parse_remotes(app_m: &ArgMatches) -> Vec<String> {
+    app_m
+        .values_of("remote")
+        .into_iter()
+        .flatten()
+        .map(String::from)
+        .collect()
+}
+
+/// Parses each `--base REMOTE=BRANCH` into a map from remote name to base branch name.
+pub fn parse_base_branches(app_m: &ArgMatches) -> HashMap<String, String> {
+    app_m
+        .values_of("base")
+        .into_iter()
+        .flatten()
+        .filter_map(|base| base.split_once('='))
+        .map(|(remote, branch)| (remote.to_string(), branch.to_string()))
+        .collect()
+}
+
+pub fn parse_allowed_hosts(app_m: &ArgMatches) -> Option<Vec<String>> {
+    app_m.value_of("allowed-hosts").map(|hosts_str| {
+        hosts_str
+            .split(',')
+            .map(str::trim)
+            .filter(|host| !host.is_empty())
+            .map(String::from)
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    /// A self-signed CA certificate, to trust with `--ca-cert`.
+    const TEST_CA_CERT: &str = "-----BEGIN CERTIFICATE-----
+MIIBiTCCAS+gAwIBAgIUBanD2SeZVI395SFtpnnMbVsbTPQwCgYIKoZIzj0EAwIw
+GTEXMBUGA1UEAwwORm94ZGllIFRlc3QgQ0EwIBcNMjYxMDE1MDQ0NTQwWhgPMjEy
+NjA5MjEwNDQ1NDBaMBkxFzAVBgNVBAMMDkZveGRpZSBUZXN0IENBMFkwEwYHKoZI
+zj0CAQYIKoZIzj0DAQcDQgAEEyT9MsP20z67rv4vQeB0esYl1GP671p4k26H2Nqz
+hZPNybx8pQWdy5u9Y2e+yvQNHJuAYrf7uFPzIO2D4xrhh6NTMFEwHQYDVR0OBBYE
+FEqikt/ryfcBjOPE3wuNAXmfVXQHMB8GA1UdIwQYMBaAFEqikt/ryfcBjOPE3wuN
+AXmfVXQHMA8GA1UdEwEB/wQFMAMBAf8wCgYIKoZIzj0EAwIDSAAwRQIhAIiCZfzP
+AJ+U2gZKK2JGB7WBkcYiWWbPbcWUZm4W+85RAiAnACxutScRAcbAC2bbiqSNd+rM
+KL6oE+6xuj9ITEIk8Q==
+-----END CERTIFICATE-----
+";
+
+    fn push_requests_matches<'a>(args: &[&str]) -> ArgMatches<'a> {
+        let mut argv = vec!["foxdie", "push-requests"];
+        argv.extend_from_slice(args);
+        argv.push("https://github.com/wayfair/foxdie");
+        build_cli()
+            .get_matches_from_safe(argv)
+            .unwrap()
+            .subcommand_matches("push-requests")
+            .unwrap()
+            .clone()
+    }
+
+    fn config() -> Config {
+        parse_config(
+            r#"
+            since = "2019-01-01T00:00:00Z"
+            token = "from-config"
+            exclude = ["release/*", "keep-*"]
+            exclude-author = ["renovate[bot]"]
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_shared_arguments_falls_back_to_config() {
+        let config = config();
+        let app_m = push_requests_matches(&[]);
+        let args = parse_shared_arguments(&app_m, &config).unwrap();
+        assert_eq!(
+            args.since,
+            DateTime::parse_from_rfc3339("2019-01-01T00:00:00Z").unwrap()
+        );
+        assert_eq!(args.token, "from-config");
+        assert_eq!(
+            args.exclude.iter().map(Pattern::as_str).collect::<Vec<_>>(),
+            vec!["release/*", "keep-*"]
+        );
+        assert!(args.authors.include.is_empty());
+        assert_eq!(args.authors.exclude, vec!["renovate[bot]"]);
+    }
+
+    #[test]
+    fn test_parse_shared_arguments_prefers_flags_over_config() {
+        let config = config();
+        let app_m = push_requests_matches(&[
+            "--since",
+            "2020-06-01T00:00:00Z",
+            "--token",
+            "from-flag",
+            "--exclude",
+            "hotfix/*",
+        ]);
+        let args = parse_shared_arguments(&app_m, &config).unwrap();
+        assert_eq!(
+            args.since,
+            DateTime::parse_from_rfc3339("2020-06-01T00:00:00Z").unwrap()
+        );
+        assert_eq!(args.token, "from-flag");
+        assert_eq!(
+            args.exclude.iter().map(Pattern::as_str).collect::<Vec<_>>(),
+            vec!["hotfix/*"]
+        );
+    }
+
+    #[test]
+    fn test_parse_shared_arguments_requires_since() {
+        let config = Config::default();
+        let app_m = push_requests_matches(&["--token", "from-flag"]);
+        assert!(matches!(
+            parse_shared_arguments(&app_m, &config),
+            Err(FoxdieError::MissingArgument("since"))
+        ));
+    }
+
+    #[test]
+    fn test_from_plan_needs_no_url_or_since() {
+        let argv = [
+            "foxdie",
+            "push-requests",
+            "--token",
+            "t",
+            "--from-plan",
+            "plan.json",
+        ];
+        let app_m = build_cli().get_matches_from_safe(argv).unwrap();
+        let sub_m = app_m.subcommand_matches("push-requests").unwrap();
+        assert!(parse_shared_arguments(sub_m, &Config::default()).is_ok());
+
+        let with_url = [&argv[..], &["https://github.com/wayfair/foxdie"]].concat();
+        assert!(build_cli().get_matches_from_safe(&with_url).is_err());
+    }
+
+    #[test]
+    fn test_parse_shared_arguments_reads_token_file() {
+        let path = env::temp_dir().join(format!("foxdie-test-token-{}", process::id()));
+        fs::write(&path, "  from-file \nsecond line\n").unwrap();
+        let config = config();
+        let app_m = push_requests_matches(&["--token-file", path.to_str().unwrap()]);
+        let token = parse_shared_arguments(&app_m, &config).map(|args| args.token);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(token.unwrap(), "from-file");
+    }
+
+    #[test]
+    fn test_parse_shared_arguments_with_unreadable_token_file() {
+        let path = env::temp_dir().join(format!("foxdie-test-missing-token-{}", process::id()));
+        let config = config();
+        let app_m = push_requests_matches(&["--token-file", path.to_str().unwrap()]);
+        assert!(matches!(
+            parse_shared_arguments(&app_m, &config),
+            Err(FoxdieError::InvalidTokenFile(..))
+        ));
+    }
+
+    #[test]
+    fn test_parse_log_format() {
+        let log_format =
+            |argv: &[&str]| parse_log_format(&build_cli().get_matches_from_safe(argv).unwrap());
+        let url = "https://github.com/wayfair/foxdie";
+        assert_eq!(
+            log_format(&["foxdie", "push-requests", url]),
+            LogFormat::Text
+        );
+        assert_eq!(
+            log_format(&["foxdie", "--log-format", "json", "push-requests", url]),
+            LogFormat::Json
+        );
+        assert_eq!(
+            log_format(&["foxdie", "push-requests", "--log-format", "json", url]),
+            LogFormat::Json
+        );
+    }
+
+    #[test]
+    fn test_parse_shared_arguments_with_conflicting_tokens() {
+        let config = config();
+        let app_m = push_requests_matches(&["--token", "from-flag", "--token-file", "token.txt"]);
+        assert!(matches!(
+            parse_shared_arguments(&app_m, &config),
+            Err(FoxdieError::ConflictingArguments("token", "token-file"))
+        ));
+    }
+
+    #[test]
+    fn test_parse_client_options_configures_proxy() {
+        let app_m = push_requests_matches(&["--proxy", "http://proxy.example.com:3128"]);
+        let options = parse_client_options(&app_m).unwrap();
+        assert!(options.proxy.is_some());
+        let builder = format!("{:?}", what_git::client_builder(&options));
+        assert!(builder.contains("proxies"), "{}", builder);
+        assert!(builder.contains("proxy.example.com:3128"), "{}", builder);
+
+        let app_m = push_requests_matches(&[]);
+        let builder = format!(
+            "{:?}",
+            what_git::client_builder(&parse_client_options(&app_m).unwrap())
+        );
+        assert!(!builder.contains("proxies"), "{}", builder);
+    }
+
+    #[test]
+    fn test_parse_client_options_configures_headers() {
+        let app_m = push_requests_matches(&[
+            "--header",
+            "X-GitHub-Api-Version: 2022-11-28",
+            "--header",
+            "X-Audit-Id:foxdie",
+        ]);
+        let options = parse_client_options(&app_m).unwrap();
+        assert_eq!(options.default_headers.len(), 2);
+        assert_eq!(
+            options.default_headers["x-github-api-version"],
+            "2022-11-28"
+        );
+        assert_eq!(options.default_headers["x-audit-id"], "foxdie");
+
+        let app_m = push_requests_matches(&[]);
+        assert!(parse_client_options(&app_m)
+            .unwrap()
+            .default_headers
+            .is_empty());
+    }
+
+    #[test]
+    fn test_parse_client_options_configures_timeout() {
+        for argv in &[
+            vec!["foxdie", "--timeout", "30", "report", "."],
+            vec!["foxdie", "report", "--timeout", "30", "."],
+        ] {
+            let app_m = build_cli().get_matches_from_safe(argv).unwrap();
+            let options =
+                parse_client_options(app_m.subcommand_matches("report").unwrap()).unwrap();
+            assert_eq!(options.timeout, Some(std::time::Duration::from_secs(30)));
+            let builder = format!("{:?}", what_git::client_builder(&options));
+            assert!(builder.contains("timeout: 30s"), "{}", builder);
+        }
+
+        let app_m = push_requests_matches(&[]);
+        let options = parse_client_options(&app_m).unwrap();
+        assert_eq!(options.timeout, None);
+        assert!(!format!("{:?}", what_git::client_builder(&options)).contains("timeout"));
+        assert!(validate_timeout(String::from("0")).is_err());
+    }
+
+    #[test]
+    fn test_header_must_be_name_and_value() {
+        assert!(validate_header(String::from("X-GitHub-Api-Version:2022-11-28")).is_ok());
+        assert!(validate_header(String::from("X-GitHub-Api-Version")).is_err());
+        assert!(validate_header(String::from("Not A Header:value")).is_err());
+    }
+
+    #[test]
+    fn test_parse_client_options_configures_certificates() {
+        let app_m = push_requests_matches(&["--insecure"]);
+        let options = parse_client_options(&app_m).unwrap();
+        assert!(options.accept_invalid_certs);
+        let builder = what_git::client_builder(&options);
+        assert!(format!("{:?}", builder).contains("danger_accept_invalid_certs"));
+        assert!(builder.build().is_ok());
+
+        let path = env::temp_dir().join(format!("foxdie-test-ca-{}.pem", process::id()));
+        fs::write(&path, TEST_CA_CERT).unwrap();
+        let app_m = push_requests_matches(&["--ca-cert", path.to_str().unwrap()]);
+        let options = parse_client_options(&app_m);
+        fs::remove_file(&path).unwrap();
+        let options = options.unwrap();
+        assert!(options.root_certificate.is_some());
+        assert!(!options.accept_invalid_certs);
+        assert!(what_git::client_builder(&options).build().is_ok());
+    }
+
+    #[test]
+    fn test_parse_client_options_with_invalid_ca_cert() {
+        let path = env::temp_dir().join(format!("foxdie-test-bad-ca-{}.pem", process::id()));
+        fs::write(&path, "not a certificate").unwrap();
+        let app_m = push_requests_matches(&["--ca-cert", path.to_str().unwrap()]);
+        let options = parse_client_options(&app_m);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(options, Err(FoxdieError::InvalidCaCert(..))));
+
+        let app_m = push_requests_matches(&["--ca-cert", "/nonexistent/foxdie-ca.pem"]);
+        assert!(matches!(
+            parse_client_options(&app_m),
+            Err(FoxdieError::InvalidCaCert(..))
+        ));
+    }
+
+    #[test]
+    fn test_detect_takes_a_url() {
+        let app_m = build_cli()
+            .get_matches_from_safe(vec![
+                "foxdie",
+                "detect",
+                "--json",
+                "https://github.com/wayfair/foxdie",
+            ])
+            .unwrap();
+        let sub_m = app_m.subcommand_matches("detect").unwrap();
+        assert_eq!(
+            sub_m.value_of("URL"),
+            Some("https://github.com/wayfair/foxdie")
+        );
+        assert!(sub_m.is_present("json"));
+        assert!(build_cli()
+            .get_matches_from_safe(vec!["foxdie", "detect"])
+            .is_err());
+    }
+
+    #[test]
+    fn test_proxy_must_be_a_url() {
+        assert!(validate_proxy(String::from("http://proxy.example.com:3128")).is_ok());
+        assert!(validate_proxy(String::from("not a proxy")).is_err());
+    }
+
+    #[test]
+    fn test_read_repos_file() {
+        let path = env::temp_dir().join(format!("foxdie-test-repos-{}", process::id()));
+        fs::write(
+            &path,
+            "# Repositories to clean up\nhttps://github.com/wayfair/foxdie\n\n  git@gitlab.com:wayfair/foxdie.git  \n",
+        )
+        .unwrap();
+        let app_m = build_cli()
+            .get_matches_from_safe(vec![
+                "foxdie",
+                "push-requests",
+                "--repos-file",
+                path.to_str().unwrap(),
+            ])
+            .unwrap();
+        let urls = parse_repository_urls(app_m.subcommand_matches("push-requests").unwrap());
+        fs::write(&path, "# Nothing yet\n").unwrap();
+        let empty = read_repos_file(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            urls.unwrap(),
+            vec![
+                "https://github.com/wayfair/foxdie",
+                "git@gitlab.com:wayfair/foxdie.git"
+            ]
+        );
+        assert!(matches!(empty, Err(FoxdieError::InvalidReposFile(..))));
+    }
+
+    #[test]
+    fn test_group_names_the_gitlab_instance() {
+        let parse = |args: &[&str]| {
+            build_cli().get_matches_from_safe(
+                ["foxdie", "push-requests"]
+                    .iter()
+                    .chain(args)
+                    .collect::<Vec<_>>(),
+            )
+        };
+
+        let app_m = parse(&["--group", "wayfair/platform", "https://gitlab.example.com"]).unwrap();
+        let sub_m = app_m.subcommand_matches("push-requests").unwrap();
+        assert_eq!(sub_m.value_of("group"), Some("wayfair/platform"));
+        assert_eq!(sub_m.value_of("URL"), Some("https://gitlab.example.com"));
+        assert!(parse(&["--group", "wayfair/platform"]).is_err());
+        assert!(parse(&["--group", "wayfair", "--repos-file", "repos.txt"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let now = DateTime::parse_from_rfc3339("2021-05-31T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let since = |s| parse_since(s, now).map(|date| date.to_rfc3339());
+
+        assert_eq!(
+            since("2019-01-01T00:00:00-05:00"),
+            Ok(String::from("2019-01-01T00:00:00-05:00"))
+        );
+        assert_eq!(since("P30D"), Ok(String::from("2021-05-01T12:00:00+00:00")));
+        assert_eq!(since("P3M"), Ok(String::from("2021-02-28T12:00:00+00:00")));
+        assert_eq!(since("P2W"), Ok(String::from("2021-05-17T12:00:00+00:00")));
+        assert_eq!(
+            since("P1Y2M10DT2H30M"),
+            Ok(String::from("2020-03-21T09:30:00+00:00"))
+        );
+        assert_eq!(
+            since("PT36H"),
+            Ok(String::from("2021-05-30T00:00:00+00:00"))
+        );
+    }
+
+    #[test]
+    fn test_parse_since_rejects_invalid_durations() {
+        let now = Utc::now();
+        for invalid in &[
+            "P", "PT", "P1DT", "P1M2Y", "P1D1D", "P1.5D", "P-1D", "PD", "P30", "P1H", "30D",
+        ] {
+            assert!(
+                parse_since(invalid, now).is_err(),
+                "{} was accepted",
+                invalid
+            );
+        }
+        assert!(parse_since("P4294967295Y", now).is_err());
+    }
+
+    #[test]
+    fn test_parse_config_rejects_invalid_values() {
+        assert!(parse_config(r#"since = "yesterday""#).is_err());
+        assert!(parse_config(r#"exclude = ["[unclosed"]"#).is_err());
+        assert!(parse_config("delete = true").is_err());
     }
 }
@@ -0,0 +1,97 @@
+// Copyright (c) 2018-2019, Wayfair LLC
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+//  * Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//    disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//    following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING,
+// BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED.
+// IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY,
+// OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+// STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE,
+// EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Logging setup. Logs always go to stderr, so that they never interleave with the plans and summaries written to
+//! stdout.
+
+use chrono::{DateTime, Utc};
+use log::Record;
+use std::env;
+use std::io::Write;
+
+/// How log records are written.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LogFormat {
+    /// Plain text lines, for people.
+    Text,
+    /// One JSON object per line, for log pipelines.
+    Json,
+}
+
+/// Sets up the logger for the rest of the run. Filters are read from `RUST_LOG`, and default to the info logs of Foxdie
+/// and its core library.
+pub fn setup_logger(format: LogFormat) {
+    let rust_log = match env::var("RUST_LOG") {
+        Ok(var) => var,
+        _ => String::from("foxdie=info,foxdie_core=info"),
+    };
+    let mut builder = env_logger::builder();
+    builder.parse_filters(&rust_log);
+    match format {
+        LogFormat::Text => {
+            builder.format_module_path(false).format_timestamp(None);
+        }
+        LogFormat::Json => {
+            builder
+                .format(|buf, record| writeln!(buf, "{}", format_json_record(record, Utc::now())));
+        }
+    }
+    builder.init();
+}
+
+/// Renders `record`, logged at `timestamp`, as a single line of JSON.
+fn format_json_record(record: &Record, timestamp: DateTime<Utc>) -> String {
+    serde_json::json!({
+        "timestamp": timestamp.to_rfc3339(),
+        "level": record.level().to_string(),
+        "module": record.module_path(),
+        "message": record.args().to_string(),
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use log::Level;
+
+    #[test]
+    fn test_format_json_record() {
+        let line = format_json_record(
+            &Record::builder()
+                .args(format_args!("Deleted \"stale\"\nand more"))
+                .level(Level::Warn)
+                .module_path(Some("foxdie_core::actions::local"))
+                .build(),
+            Utc.timestamp(1_600_000_000, 0),
+        );
+
+        assert!(!line.contains('\n'));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&line).unwrap(),
+            serde_json::json!({
+                "timestamp": "2020-09-13T12:26:40+00:00",
+                "level": "WARN",
+                "module": "foxdie_core::actions::local",
+                "message": "Deleted \"stale\"\nand more",
+            })
+        );
+    }
+}
@@ -17,83 +17,333 @@
 // STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE,
 // EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
-mod actions;
 mod cli;
-mod error;
-mod services;
+mod logging;
 
-use cli::{build_cli, parse_shared_arguments, SharedArguments};
-use log::{error, warn};
-use std::env;
+use cli::{
+    build_cli, load_config, parse_allowed_hosts, parse_base_branches, parse_batching,
+    parse_client_options, parse_concurrency, parse_days_ago, parse_delay, parse_include,
+    parse_jobs, parse_log_format, parse_push_request_ids, parse_raw_response_dir, parse_remotes,
+    parse_repository_urls, parse_shared_arguments, parse_ssh_key, parse_target_branches,
+    parse_token, SharedArguments,
+};
+use foxdie_core::actions::plan::SavedPlan;
+use foxdie_core::services::{git, PushRequestState};
+use foxdie_core::{actions, error, progress};
+use log::{error, info, warn};
+use std::path::Path;
 use std::process;
+use std::time::Instant;
+use what_git::{ClientOptions, ProviderCache};
 
 #[tokio::main]
 async fn main() {
-    setup_logger();
     let app = build_cli();
     let app_m = app.get_matches();
+    logging::setup_logger(parse_log_format(&app_m));
     let res = run_matches(&app_m).await;
     if let Err(err) = res {
         error!("{}", err);
-        process::exit(1);
+        process::exit(err.exit_code());
     }
 }
 
 async fn run_matches(args: &clap::ArgMatches<'_>) -> Result<(), error::FoxdieError> {
+    if let (_, Some(sub_m)) = args.subcommand() {
+        progress::set_enabled(!sub_m.is_present("no-progress") && !sub_m.is_present("json"));
+    }
+    let provider_cache = ProviderCache::default();
     match args.subcommand() {
         ("branches", Some(sub_m)) => {
+            let config = load_config(sub_m)?;
             let SharedArguments {
                 should_delete,
                 since,
                 token,
-            } = parse_shared_arguments(&sub_m);
+                limit,
+                plan,
+                stream_plan,
+                allowed_hosts,
+                batch_summary,
+                raw_response_dir,
+                exclude,
+                authors,
+            } = parse_shared_arguments(sub_m, &config)?;
+            let interactive = sub_m.is_present("interactive");
+            let should_delete = should_delete || interactive;
             let path = sub_m.value_of("DIRECTORY");
+            let (ssh_key, ssh_passphrase) = parse_ssh_key(sub_m);
+            let protect_checked_out_since = parse_days_ago(sub_m, "protect-recently-checked-out");
+            let protect_recent_author_since = parse_days_ago(sub_m, "protect-recent-author-days");
+            let base_branches = parse_base_branches(sub_m);
+            let remotes = parse_remotes(sub_m);
+            let deletion_method = match sub_m.value_of("delete-via") {
+                Some("push") => actions::local::DeletionMethod::Push,
+                _ => actions::local::DeletionMethod::Api,
+            };
             if !should_delete {
                 print_dry_run_warning();
             }
-            actions::local::clean_remote_branches(
-                path,
-                actions::local::Options {
-                    should_delete,
-                    since_date: &since,
-                    token,
-                },
-            )
-            .await
+            let started = Instant::now();
+            let mut summaries = vec![];
+            let include = parse_include(sub_m);
+            let mut opts = actions::local::Options::new(&since, &token, &authors, &base_branches);
+            opts.should_delete = should_delete;
+            opts.interactive = interactive;
+            opts.explain = sub_m.is_present("explain");
+            opts.delete_merged = sub_m.is_present("delete-merged");
+            opts.keep_with_any_pr = sub_m.is_present("keep-with-any-pr");
+            opts.activity_metric = match sub_m.value_of("activity-metric") {
+                Some("author") => git::ActivityMetric::Author,
+                Some("reflog") => git::ActivityMetric::Reflog,
+                _ => git::ActivityMetric::Committer,
+            };
+            opts.prune_local = sub_m.is_present("prune-local");
+            opts.skip_auth_check = sub_m.is_present("skip-auth-check");
+            opts.limit = limit;
+            opts.protect_checked_out_since = protect_checked_out_since;
+            opts.protect_recent_author_since = protect_recent_author_since;
+            opts.plan = plan;
+            opts.stream_plan = stream_plan;
+            opts.json = sub_m.is_present("json");
+            opts.allowed_hosts = allowed_hosts.as_deref();
+            opts.deletion_method = deletion_method;
+            opts.delay = parse_delay(sub_m);
+            opts.raw_response_dir = raw_response_dir;
+            opts.client_options = client_options(sub_m, &provider_cache)?;
+            opts.ssh_key = ssh_key;
+            opts.ssh_passphrase = ssh_passphrase;
+            opts.exclude = &exclude;
+            opts.include = &include;
+            opts.remotes = &remotes;
+            opts.output_path = sub_m.value_of("output").map(Path::new);
+            opts.plan_out = sub_m.value_of("plan-out").map(Path::new);
+            let result = match sub_m.value_of("from-plan") {
+                Some(plan_path) => {
+                    let plan = SavedPlan::read(plan_path)?;
+                    actions::local::clean_remote_branches_from_plan(
+                        path,
+                        &plan,
+                        opts,
+                        &mut summaries,
+                    )
+                    .await
+                }
+                None => actions::local::clean_remote_branches(path, opts, &mut summaries).await,
+            };
+            info!(
+                "{}",
+                actions::summary::format_run_summary(
+                    &summaries,
+                    "branches",
+                    "deleted",
+                    !should_delete,
+                    started.elapsed()
+                )
+            );
+            if let Some(summary_path) = batch_summary {
+                actions::summary::write_batch_summary(&summaries, summary_path)?;
+            }
+            if sub_m.is_present("json") {
+                actions::summary::print_summary(&summaries)?;
+            }
+            result
         }
         ("push-requests", Some(sub_m)) => {
+            let config = load_config(sub_m)?;
             let SharedArguments {
                 should_delete,
                 since,
                 token,
-            } = parse_shared_arguments(&sub_m);
+                limit,
+                plan,
+                stream_plan,
+                allowed_hosts,
+                batch_summary,
+                raw_response_dir,
+                exclude,
+                authors,
+            } = parse_shared_arguments(sub_m, &config)?;
             if !should_delete {
                 print_dry_run_warning();
             }
+            let staleness_field = match sub_m.value_of("staleness-field") {
+                Some("created") => actions::push_requests::StalenessField::Created,
+                _ => actions::push_requests::StalenessField::Updated,
+            };
+            let state = match sub_m.value_of("state") {
+                Some("closed") => PushRequestState::Closed,
+                Some("all") => PushRequestState::All,
+                _ => PushRequestState::Opened,
+            };
+            let target_branches = parse_target_branches(sub_m);
+            let started = Instant::now();
+            let mut summaries = vec![];
+            let mut opts = actions::push_requests::Options::new(&since, &token, &authors);
+            opts.should_delete = should_delete;
+            opts.limit = limit;
+            opts.plan = plan;
+            opts.stream_plan = stream_plan;
+            opts.json = sub_m.is_present("json");
+            opts.allowed_hosts = allowed_hosts.as_deref();
+            opts.skip_drafts = sub_m.is_present("skip-drafts");
+            opts.journal_path = sub_m.value_of("journal");
+            opts.comment = sub_m.value_of("comment");
+            opts.delete_source_branch = sub_m.is_present("delete-source-branch");
+            opts.explain = sub_m.is_present("explain");
+            opts.skip_auth_check = sub_m.is_present("skip-auth-check");
+            opts.raw_response_dir = raw_response_dir;
+            opts.client_options = client_options(sub_m, &provider_cache)?;
+            opts.project_id = sub_m
+                .value_of("project-id")
+                .and_then(|id| id.parse::<u64>().ok());
+            opts.page_size = sub_m
+                .value_of("page-size")
+                .and_then(|size| size.parse::<usize>().ok());
+            opts.exclude = &exclude;
+            opts.target_branches = &target_branches;
+            opts.staleness_field = staleness_field;
+            opts.state = state;
+            opts.batching = parse_batching(sub_m);
+            opts.delay = parse_delay(sub_m);
+            opts.concurrency = parse_concurrency(sub_m);
+            opts.plan_out = sub_m.value_of("plan-out").map(Path::new);
+            let result = match sub_m.value_of("from-plan") {
+                Some(plan_path) => {
+                    let plan = SavedPlan::read(plan_path)?;
+                    actions::push_requests::clean_push_requests_from_plan(
+                        &plan,
+                        &opts,
+                        &mut summaries,
+                    )
+                    .await
+                }
+                None => match sub_m.value_of("group") {
+                    Some(group) => {
+                        let url = sub_m
+                            .value_of("URL")
+                            .expect("URL is required with --group.");
+                        actions::push_requests::clean_push_requests_in_group(
+                            url,
+                            group,
+                            &opts,
+                            &mut summaries,
+                        )
+                        .await
+                    }
+                    None => {
+                        let urls = parse_repository_urls(sub_m)?;
+                        actions::push_requests::clean_push_requests_in_repos(
+                            &urls,
+                            &opts,
+                            &mut summaries,
+                        )
+                        .await
+                    }
+                },
+            };
+            info!(
+                "{}",
+                actions::summary::format_run_summary(
+                    &summaries,
+                    "push requests",
+                    "closed",
+                    !should_delete,
+                    started.elapsed()
+                )
+            );
+            if let Some(summary_path) = batch_summary {
+                actions::summary::write_batch_summary(&summaries, summary_path)?;
+            }
+            if sub_m.is_present("json") {
+                actions::summary::print_summary(&summaries)?;
+            }
+            result
+        }
+        ("reopen", Some(sub_m)) => {
             let url = sub_m
                 .value_of("URL")
                 .expect("URL was supposed to be passed as a positional argument.");
-            actions::push_requests::clean_push_requests(should_delete, &since, &url, &token).await
+            let token = parse_token(sub_m);
+            let allowed_hosts = parse_allowed_hosts(sub_m);
+            let ids = parse_push_request_ids(sub_m, "IDS");
+            actions::push_requests::reopen_push_requests(
+                url,
+                &ids,
+                sub_m.value_of("reopen-from"),
+                token,
+                &client_options(sub_m, &provider_cache)?,
+                allowed_hosts.as_deref(),
+                parse_raw_response_dir(sub_m),
+            )
+            .await
         }
         ("report", Some(sub_m)) => {
             let output_path = sub_m.value_of("output");
             let repo_path = sub_m.value_of("DIRECTORY");
-            actions::report::report(&output_path, repo_path)
+            let (ssh_key, ssh_passphrase) = parse_ssh_key(sub_m);
+            let allowed_hosts = parse_allowed_hosts(sub_m);
+            let base_branches = parse_base_branches(sub_m);
+            let remotes = parse_remotes(sub_m);
+            let mut opts = actions::report::Options::new(&base_branches);
+            opts.first_parent = sub_m.is_present("first-parent");
+            opts.mine = sub_m.is_present("mine");
+            opts.with_push_requests = sub_m.is_present("with-prs");
+            opts.with_review_state = sub_m.is_present("with-review-state");
+            opts.orphaned_refs = sub_m.is_present("orphaned-refs");
+            opts.audit_protection = sub_m.is_present("audit-protection");
+            opts.token = sub_m.value_of("token");
+            opts.skip_auth_check = sub_m.is_present("skip-auth-check");
+            opts.allowed_hosts = allowed_hosts.as_deref();
+            opts.raw_response_dir = parse_raw_response_dir(sub_m);
+            opts.client_options = client_options(sub_m, &provider_cache)?;
+            opts.ssh_key = ssh_key;
+            opts.ssh_passphrase = ssh_passphrase;
+            opts.remotes = &remotes;
+            opts.json = sub_m.is_present("json");
+            opts.top = sub_m
+                .value_of("top")
+                .and_then(|top| top.parse::<usize>().ok());
+            opts.format = match sub_m.value_of("format") {
+                Some("html") => actions::report::OutputFormat::Html,
+                _ => actions::report::OutputFormat::Json,
+            };
+            opts.jobs = parse_jobs(sub_m);
+            opts.append = sub_m.is_present("append");
+            opts.timestamp_output = sub_m.is_present("timestamp-output");
+            actions::report::report(&output_path, repo_path, opts).await
+        }
+        ("detect", Some(sub_m)) => {
+            let url = sub_m
+                .value_of("URL")
+                .expect("URL was supposed to be passed as a positional argument.");
+            let detection = actions::detect::detect(
+                url,
+                sub_m.value_of("token").unwrap_or_default(),
+                &client_options(sub_m, &provider_cache)?,
+            )
+            .await?;
+            if sub_m.is_present("json") {
+                println!("{}", serde_json::to_string(&detection)?);
+            } else {
+                println!("{}", detection);
+            }
+            Ok(())
         }
         _ => unreachable!(),
     }
 }
 
-fn setup_logger() {
-    let rust_log = match env::var("RUST_LOG") {
-        Ok(var) => var,
-        _ => String::from("foxdie=info"),
-    };
-    env_logger::builder()
-        .format_module_path(false)
-        .format_timestamp(None)
-        .parse_filters(&rust_log)
-        .init();
+/// Parses the options for HTTP clients from `sub_m`, sharing `provider_cache` so that each self-hosted SCM is only
+/// probed once per run.
+fn client_options<'a>(
+    sub_m: &'a clap::ArgMatches<'_>,
+    provider_cache: &'a ProviderCache,
+) -> Result<ClientOptions<'a>, error::FoxdieError> {
+    Ok(ClientOptions {
+        provider_cache: Some(provider_cache),
+        ..parse_client_options(sub_m)?
+    })
 }
 
 fn print_dry_run_warning() {
@@ -0,0 +1,110 @@
+// Copyright (c) 2018-2019, Wayfair LLC
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+//  * Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//    disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//    following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING,
+// BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED.
+// IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY,
+// OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+// STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE,
+// EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::error::FoxdieError;
+use serde::Serialize;
+use std::fmt;
+use what_git::{ClientOptions, SCMKind};
+
+/// How the SCM hosting a repository was resolved from its URL, as printed by the `detect` subcommand.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Detection {
+    /// The name of the SCM: `GitHub`, `Gitlab` or `Gitea`.
+    pub kind: &'static str,
+    /// The base URL of the SCM's API.
+    pub base_url: String,
+    pub owner: String,
+    pub repo: String,
+}
+
+impl fmt::Display for Detection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Provider: {}", self.kind)?;
+        writeln!(f, "API base URL: {}", self.base_url)?;
+        writeln!(f, "Owner: {}", self.owner)?;
+        write!(f, "Repository: {}", self.repo)
+    }
+}
+
+/// Resolves the SCM hosting the repository at `url` the same way the other actions do, without making any changes to
+/// it. Hosts that aren't known by name are probed with `token`, which may be empty.
+pub async fn detect(
+    url: &str,
+    token: &str,
+    client_options: &ClientOptions<'_>,
+) -> Result<Detection, FoxdieError> {
+    let unsupported = || FoxdieError::UnsupportedProvider(url.to_string());
+    let scm = what_git::what_git_with_options(url, token, client_options)
+        .await
+        .map_err(|_| unsupported())?;
+    let kind = match scm.kind {
+        SCMKind::GitHub => "GitHub",
+        SCMKind::Gitlab => "Gitlab",
+        SCMKind::Gitea => "Gitea",
+        SCMKind::Unsupported => return Err(unsupported()),
+    };
+    Ok(Detection {
+        kind,
+        base_url: scm.base_url,
+        owner: scm.owner,
+        repo: scm.repo,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_detect_github() {
+        let detection = detect(
+            "https://github.com/wayfair/foxdie.git",
+            "",
+            &ClientOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            detection,
+            Detection {
+                kind: "GitHub",
+                base_url: String::from("https://api.github.com"),
+                owner: String::from("wayfair"),
+                repo: String::from("foxdie"),
+            }
+        );
+        assert_eq!(
+            serde_json::to_string(&detection).unwrap(),
+            r#"{"kind":"GitHub","base_url":"https://api.github.com","owner":"wayfair","repo":"foxdie"}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_detect_unknown_provider() {
+        for url in &["https://bitbucket.org/wayfair/foxdie", "not a url"] {
+            match detect(url, "", &ClientOptions::default()).await {
+                Err(FoxdieError::UnsupportedProvider(unsupported)) => assert_eq!(&unsupported, url),
+                other => panic!(
+                    "Expected an unsupported provider for {}, got {:?}",
+                    url, other
+                ),
+            }
+        }
+    }
+}
@@ -0,0 +1,139 @@
+// Copyright (c) 2018-2019, Wayfair LLC
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+//  * Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//    disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//    following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING,
+// BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED.
+// IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY,
+// OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+// STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE,
+// EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::error::FoxdieError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+/// A destructive action Foxdie took, recorded so that it can be undone later.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum JournalAction {
+    ClosedPushRequest {
+        id: i32,
+        url: String,
+        source_branch: String,
+        /// The commit the source branch pointed to, so that it can be recreated if it has since been deleted. Missing
+        /// from entries recorded before it was tracked.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source_sha: Option<String>,
+    },
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub action: JournalAction,
+}
+
+/// An append-only record of the actions taken during runs, stored as newline-delimited JSON.
+pub struct Journal {
+    file: File,
+}
+
+impl Journal {
+    pub fn open<P>(path: P) -> Result<Self, FoxdieError>
+    where
+        P: AsRef<Path>,
+    {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Journal { file })
+    }
+
+    pub fn record(&mut self, action: JournalAction) -> Result<(), FoxdieError> {
+        let entry = JournalEntry {
+            timestamp: Utc::now(),
+            action,
+        };
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        self.file.write_all(&line).map_err(FoxdieError::from)
+    }
+}
+
+/// Reads every entry recorded in the journal at `path`.
+pub fn read_journal<P>(path: P) -> Result<Vec<JournalEntry>, FoxdieError>
+where
+    P: AsRef<Path>,
+{
+    let reader = BufReader::new(File::open(path)?);
+    let mut entries = vec![];
+    for line in reader.lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            entries.push(serde_json::from_str(&line)?);
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    #[test]
+    fn test_journal_round_trip() {
+        let path = env::temp_dir().join(format!("foxdie-test-journal-{}.ndjson", process::id()));
+        let _ = fs::remove_file(&path);
+        {
+            let mut journal = Journal::open(&path).unwrap();
+            for id in 1..=2 {
+                journal
+                    .record(JournalAction::ClosedPushRequest {
+                        id,
+                        url: format!("https://github.com/wayfair/foxdie/pull/{}", id),
+                        source_branch: format!("branch-{}", id),
+                        source_sha: Some(format!("abc{}", id)),
+                    })
+                    .unwrap();
+            }
+        }
+
+        let actions = read_journal(&path)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.action)
+            .collect::<Vec<_>>();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            actions,
+            vec![
+                JournalAction::ClosedPushRequest {
+                    id: 1,
+                    url: String::from("https://github.com/wayfair/foxdie/pull/1"),
+                    source_branch: String::from("branch-1"),
+                    source_sha: Some(String::from("abc1")),
+                },
+                JournalAction::ClosedPushRequest {
+                    id: 2,
+                    url: String::from("https://github.com/wayfair/foxdie/pull/2"),
+                    source_branch: String::from("branch-2"),
+                    source_sha: Some(String::from("abc2")),
+                },
+            ]
+        );
+    }
+}
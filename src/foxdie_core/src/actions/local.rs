@@ -0,0 +1,1855 @@
+// Copyright (c) 2018-2019, Wayfair LLC
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+//  * Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//    disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//    following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING,
+// BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED.
+// IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY,
+// OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+// STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE,
+// EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use super::plan::{print_plan, Plan, PlannedChange, SavedPlan, StreamedChange};
+use super::summary::{BranchNames, CleanupSummary};
+use super::{
+    combined_result, create_output_file, is_archived, open_repository, select_remotes,
+    truncate_to_limit, truncation_message, verify_token, AuthorFilter,
+};
+use crate::error::FoxdieError;
+use crate::services::{
+    get_api_client_for_remote, git, ApiResult, ProtectedBranch, ProtectionSource, PushRequest,
+    PushRequestState, SCMProvider,
+};
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use dialoguer::{Confirm, MultiSelect};
+use glob::Pattern;
+use log::{error, info, warn};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::time::Duration;
+use what_git::ClientOptions;
+
+#[non_exhaustive]
+pub struct Options<'a> {
+    pub should_delete: bool,
+    /// Whether to ask which of the eligible branches to delete, rather than deleting all of them.
+    pub interactive: bool,
+    /// Whether to log why each branch is or isn't eligible for deletion.
+    pub explain: bool,
+    /// Whether to skip checking that the SCM accepts the token before listing branches. Only honored on dry runs.
+    pub skip_auth_check: bool,
+    /// Whether to also delete the local branches that track each remote branch deleted, when they have no unpushed
+    /// commits and aren't checked out.
+    pub prune_local: bool,
+    /// Whether branches whose push request was merged are eligible for deletion however recently they were updated.
+    pub delete_merged: bool,
+    /// Whether to protect branches that any push request, in any state, was ever opened from. Lists every push request
+    /// on the SCM, closed and merged ones included, which costs extra requests on busy repositories.
+    pub keep_with_any_pr: bool,
+    pub since_date: &'a DateTime<FixedOffset>,
+    pub token: &'a str,
+    pub limit: Option<usize>,
+    pub protect_checked_out_since: Option<DateTime<FixedOffset>>,
+    /// When set, branches whose latest commit was authored by someone who has committed anywhere in the repository
+    /// since this date are protected, as their authors are still active.
+    pub protect_recent_author_since: Option<DateTime<FixedOffset>>,
+    /// Which date is compared with `since_date` to tell whether a branch is stale.
+    pub activity_metric: git::ActivityMetric,
+    pub plan: bool,
+    /// Whether to stream each branch to delete to stdout as a line of JSON, as soon as it is found.
+    pub stream_plan: bool,
+    /// Whether a JSON summary of the run is printed to stdout once it finishes, so that the plan must go to stderr.
+    pub json: bool,
+    pub allowed_hosts: Option<&'a [String]>,
+    pub deletion_method: DeletionMethod,
+    /// How long to wait between deleting one branch through the SCM's API and the next, to stay clear of the SCM's
+    /// abuse detection.
+    pub delay: Duration,
+    pub raw_response_dir: Option<&'a Path>,
+    pub client_options: ClientOptions<'a>,
+    pub ssh_key: Option<&'a Path>,
+    pub ssh_passphrase: Option<&'a str>,
+    /// Branches matching any of these patterns are protected, as though by a local rule.
+    pub exclude: &'a [Pattern],
+    /// When not empty, only branches matching one of these patterns are considered for deletion.
+    pub include: &'a [Pattern],
+    /// Only branches whose tip was authored by one of the authors this selects are deleted.
+    pub authors: &'a AuthorFilter,
+    /// The base branch to use for each remote, by remote name, in place of the remote's default branch.
+    pub base_branches: &'a HashMap<String, String>,
+    /// The remotes to clean up, by name. When empty, every remote is.
+    pub remotes: &'a [String],
+    /// When set, the decision made about every branch examined is written to this path as JSON.
+    pub output_path: Option<&'a Path>,
+    /// When set, the branches to delete are written to this path as a `SavedPlan`, to be deleted later with
+    /// `clean_remote_branches_from_plan`.
+    pub plan_out: Option<&'a Path>,
+}
+
+impl<'a> Options<'a> {
+    /// Options for a dry run on every remote that deletes branches older than `since_date` through the SCM's API,
+    /// with every other option left off. Set the fields to change the rest.
+    pub fn new(
+        since_date: &'a DateTime<FixedOffset>,
+        token: &'a str,
+        authors: &'a AuthorFilter,
+        base_branches: &'a HashMap<String, String>,
+    ) -> Self {
+        Options {
+            should_delete: false,
+            interactive: false,
+            explain: false,
+            skip_auth_check: false,
+            prune_local: false,
+            delete_merged: false,
+            keep_with_any_pr: false,
+            since_date,
+            token,
+            limit: None,
+            protect_checked_out_since: None,
+            protect_recent_author_since: None,
+            activity_metric: git::ActivityMetric::Committer,
+            plan: false,
+            stream_plan: false,
+            json: false,
+            allowed_hosts: None,
+            deletion_method: DeletionMethod::Api,
+            delay: Duration::from_millis(0),
+            raw_response_dir: None,
+            client_options: ClientOptions::default(),
+            ssh_key: None,
+            ssh_passphrase: None,
+            exclude: &[],
+            include: &[],
+            authors,
+            base_branches,
+            remotes: &[],
+            output_path: None,
+            plan_out: None,
+        }
+    }
+}
+
+impl<'a> Options<'a> {
+    fn git_credentials(&self) -> git::Credentials<'a> {
+        git::Credentials {
+            token: Some(self.token),
+            ssh_key: self.ssh_key,
+            ssh_passphrase: self.ssh_passphrase,
+            timeout: self.client_options.timeout,
+        }
+    }
+}
+
+/// How remote branches are deleted.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DeletionMethod {
+    /// Delete branches through the SCM's API, authenticated with the API token.
+    Api,
+    /// Delete branches by pushing empty refspecs to the remote, authenticated the same way as fetching from it.
+    Push,
+}
+
+/// Cleans up stale branches on each of the repository's remotes, recording the outcome for each remote in `summaries`.
+pub async fn clean_remote_branches<P>(
+    path: Option<P>,
+    opts: Options<'_>,
+    summaries: &mut Vec<CleanupSummary>,
+) -> Result<(), FoxdieError>
+where
+    P: AsRef<Path>,
+{
+    if opts.interactive && !io::stdin().is_terminal() {
+        return Err(FoxdieError::NotATerminal("interactive"));
+    }
+    let repo = open_repository(path)?;
+    let ignored = read_ignore_file(&repo)?;
+    let exclude = [opts.exclude, &ignored].concat();
+    let opts = Options {
+        exclude: &exclude,
+        ..opts
+    };
+    let recently_checked_out = if let Some(ref date) = opts.protect_checked_out_since {
+        git::get_branches_checked_out_since(&repo, date)?
+    } else {
+        vec![]
+    };
+    let mut decisions = vec![];
+    let mut planned = vec![];
+    let mut errors = vec![];
+    let remotes = select_remotes(&repo, opts.remotes)?;
+    for remote in &remotes {
+        let mut summary = CleanupSummary::new(repo.find_remote(remote)?.url().unwrap_or_default());
+        let mut remote_decisions = vec![];
+        let result = clean_branches_on_remote(
+            remote,
+            &repo,
+            &opts,
+            &recently_checked_out,
+            &mut summary,
+            &mut remote_decisions,
+            &mut planned,
+        )
+        .await;
+        if let Err(err) = result {
+            if remotes.len() > 1 {
+                warn!(
+                    "Failed to clean up branches on {}, carrying on with the other remotes: {}",
+                    remote, err
+                );
+            }
+            summary.error = Some(err.to_string());
+            errors.push(err);
+        }
+        mark_deleted(&mut remote_decisions, &summary);
+        decisions.append(&mut remote_decisions);
+        summaries.push(summary);
+    }
+    // The decisions are written even when a remote fails, so that they record what was done on the others.
+    if let Some(path) = opts.output_path {
+        write_decisions_to_disk(&decisions, path)?;
+    }
+    // Unlike the decisions, a plan is only written when every remote was planned, so that it is never taken for one.
+    if let (true, Some(path)) = (errors.is_empty(), opts.plan_out) {
+        SavedPlan { changes: planned }.write(path)?;
+    }
+    combined_result(errors, remotes.len())
+}
+
+/// Deletes exactly the branches `plan` lists, as written by an earlier dry run with `plan_out`, rather than deciding
+/// afresh which branches are stale. A branch that no longer exists, or that has been pushed to since the plan was made,
+/// is skipped with a warning. Records the outcome for each remote in `summaries`.
+pub async fn clean_remote_branches_from_plan<P>(
+    path: Option<P>,
+    plan: &SavedPlan,
+    opts: Options<'_>,
+    summaries: &mut Vec<CleanupSummary>,
+) -> Result<(), FoxdieError>
+where
+    P: AsRef<Path>,
+{
+    let repo = open_repository(path)?;
+    let mut planned_by_remote = BTreeMap::<&str, Vec<(&str, &str)>>::new();
+    for change in &plan.changes {
+        if let PlannedChange::DeleteBranch {
+            remote,
+            branch,
+            commit,
+        } = change
+        {
+            planned_by_remote
+                .entry(remote)
+                .or_default()
+                .push((branch, commit));
+        }
+    }
+    if planned_by_remote.is_empty() {
+        info!("The plan has no branches to delete.");
+    }
+    let remote_count = planned_by_remote.len();
+    let mut errors = vec![];
+    for (remote, planned) in planned_by_remote {
+        let mut summary = CleanupSummary::new(repo.find_remote(remote)?.url().unwrap_or_default());
+        let result = delete_planned_branches(remote, &planned, &repo, &opts, &mut summary).await;
+        if let Err(err) = result {
+            if remote_count > 1 {
+                warn!(
+                    "Failed to delete the planned branches on {}, carrying on with the other remotes: {}",
+                    remote, err
+                );
+            }
+            summary.error = Some(err.to_string());
+            errors.push(err);
+        }
+        summaries.push(summary);
+    }
+    combined_result(errors, remote_count)
+}
+
+/// Deletes the branches `planned` on `remote_name`, each paired with the commit it pointed to when the plan was made,
+/// skipping those that have drifted since or that the SCM would refuse to delete.
+async fn delete_planned_branches(
+    remote_name: &str,
+    planned: &[(&str, &str)],
+    repository: &git::Repository,
+    opts: &Options<'_>,
+    summary: &mut CleanupSummary,
+) -> Result<(), FoxdieError> {
+    let mut remote = repository.find_remote(remote_name)?;
+    let api_client = get_api_client_for_remote(
+        &remote,
+        opts.token,
+        &opts.client_options,
+        opts.allowed_hosts,
+        opts.raw_response_dir,
+    )
+    .await?;
+    summary.provider = Some(api_client.name().to_string());
+    if opts.should_delete || !opts.skip_auth_check {
+        verify_token(&api_client).await?;
+    }
+    git::fetch_refs(&mut remote, &opts.git_credentials())?;
+    // Protection rules and push requests may have changed since the plan was made.
+    let (push_requests, mut protected_branches) =
+        list_push_requests_and_protected_branches(&api_client, &[], false).await?;
+    if let Some(name) = default_branch_name(repository, remote_name, &api_client).await {
+        protected_branches.push(ProtectedBranch::named(&name, ProtectionSource::Default));
+    }
+
+    let mut branches_to_delete = vec![];
+    for (branch_name, commit) in planned {
+        let branch = git::find_remote_branch(repository, remote_name, branch_name)?;
+        let skipped_because = drift_of(branch.as_ref(), commit)
+            .or_else(|| undeletable_reason(branch_name, &push_requests, &protected_branches));
+        match skipped_because {
+            Some(why) => {
+                warn!(
+                    "Skipping {}/{} from the plan, as {}.",
+                    remote_name, branch_name, why
+                );
+                summary.skipped += 1;
+            }
+            None => branches_to_delete.extend(branch),
+        }
+    }
+    summary.examined = planned.len();
+    summary.eligible = branches_to_delete.len();
+    summary.branches = Some(BranchNames {
+        eligible: bare_branch_names(&branches_to_delete, remote_name),
+        deleted: vec![],
+    });
+    print_branches_to_delete(&branches_to_delete, planned.len(), remote_name, None);
+
+    if !opts.should_delete {
+        return Ok(());
+    }
+    if is_archived(&api_client).await {
+        warn!(
+            "The repository behind {} is archived, so it is read-only. Leaving its branches alone.",
+            remote_name
+        );
+        return Ok(());
+    }
+    delete_branches_if_needed(
+        &branches_to_delete,
+        repository,
+        remote_name,
+        &api_client,
+        opts,
+        summary,
+    )
+    .await
+}
+
+/// Explains how a branch planned for deletion at `planned_commit` has drifted since the plan was made, given the
+/// `branch` as it is now, if it still exists. Returns nothing when it hasn't drifted.
+fn drift_of(branch: Option<&git::Branch>, planned_commit: &str) -> Option<String> {
+    let branch = match branch {
+        Some(branch) => branch,
+        None => return Some("it no longer exists".to_string()),
+    };
+    match branch.get().target() {
+        Some(tip) if tip.to_string() == planned_commit => None,
+        Some(tip) => Some(format!(
+            "it has been pushed to since the plan was made, and now points to {}",
+            tip
+        )),
+        None => Some("its latest commit could not be read".to_string()),
+    }
+}
+
+/// The decision made about a remote branch, as written to the `--output` file.
+#[derive(Debug, Serialize)]
+struct BranchDecision {
+    remote: String,
+    branch: String,
+    /// The date of the branch's latest commit, if it could be read.
+    last_commit_date: Option<DateTime<Utc>>,
+    /// The author of the branch's latest commit, if it could be read.
+    author: Option<String>,
+    disposition: Disposition,
+    /// Why the branch got its disposition, as logged by `--explain`.
+    reason: String,
+    deleted: bool,
+}
+
+impl BranchDecision {
+    fn new(
+        repository: &git::Repository,
+        remote_name: &str,
+        branch: &git::Branch,
+        disposition: Disposition,
+        reason: String,
+    ) -> Option<Self> {
+        let name = branch.name().ok()??;
+        let commit = git::commit_for_branch(repository, branch).ok();
+        Some(BranchDecision {
+            remote: remote_name.to_string(),
+            branch: git::bare_branch_name(name, remote_name),
+            last_commit_date: git::last_commit_date(repository, branch).ok(),
+            author: commit.as_ref().map(git::author_name),
+            disposition,
+            reason,
+            deleted: false,
+        })
+    }
+}
+
+/// The file in a repository's working directory that lists patterns of branches that are never deleted.
+const IGNORE_FILE_NAME: &str = ".foxdieignore";
+
+/// Reads the glob patterns listed one per line in the `.foxdieignore` file in `repository`'s working directory,
+/// skipping blank lines and `#` comments. A repository without one has no patterns.
+fn read_ignore_file(repository: &git::Repository) -> Result<Vec<Pattern>, FoxdieError> {
+    let path = match repository.workdir() {
+        Some(workdir) => workdir.join(IGNORE_FILE_NAME),
+        None => return Ok(vec![]),
+    };
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(FoxdieError::InvalidConfig(path, err.to_string())),
+    };
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            Pattern::new(line).map_err(|err| {
+                FoxdieError::InvalidConfig(path.clone(), format!("{}: {}", line, err))
+            })
+        })
+        .collect()
+}
+
+/// Marks the decisions about the branches `summary` records as deleted.
+fn mark_deleted(decisions: &mut [BranchDecision], summary: &CleanupSummary) {
+    let deleted = summary
+        .branches
+        .as_ref()
+        .map(|branches| &branches.deleted[..])
+        .unwrap_or_default();
+    for decision in decisions {
+        decision.deleted = deleted.contains(&decision.branch);
+    }
+}
+
+/// Writes `decisions` to `path` as a JSON array.
+fn write_decisions_to_disk<P>(decisions: &[BranchDecision], path: P) -> Result<(), FoxdieError>
+where
+    P: AsRef<Path>,
+{
+    let mut file = create_output_file(path.as_ref())?;
+    let json = serde_json::to_vec(decisions)?;
+    file.write_all(&json).map_err(FoxdieError::from)
+}
+
+async fn clean_branches_on_remote(
+    remote_name: &str,
+    repository: &git::Repository,
+    opts: &Options<'_>,
+    recently_checked_out: &[String],
+    summary: &mut CleanupSummary,
+    decisions: &mut Vec<BranchDecision>,
+    planned: &mut Vec<PlannedChange>,
+) -> Result<(), FoxdieError> {
+    let mut remote = repository.find_remote(remote_name)?;
+    let api_client = match get_api_client_for_remote(
+        &remote,
+        opts.token,
+        &opts.client_options,
+        opts.allowed_hosts,
+        opts.raw_response_dir,
+    )
+    .await
+    {
+        Ok(client) => client,
+        Err(err @ FoxdieError::UnsupportedProvider(_)) => {
+            warn!("{}", err);
+            return Ok(());
+        }
+        Err(err) => return Err(err),
+    };
+    summary.provider = Some(api_client.name().to_string());
+    if opts.should_delete || !opts.skip_auth_check {
+        verify_token(&api_client).await?;
+    }
+
+    git::fetch_refs(&mut remote, &opts.git_credentials())?;
+    let active_authors = match opts.protect_recent_author_since {
+        Some(ref date) => Some(git::get_authors_active_since(repository, date)?),
+        None => None,
+    };
+    let current_local_branch = git::get_current_branch(repository)?;
+    let current_remote_branch = current_local_branch.upstream()?;
+    let base_branch = git::get_base_branch(
+        repository,
+        remote_name,
+        opts.base_branches.get(remote_name).map(String::as_str),
+    );
+
+    let (all_push_requests, mut all_protected_branches) =
+        list_push_requests_and_protected_branches(
+            &api_client,
+            recently_checked_out,
+            opts.delete_merged,
+        )
+        .await?;
+    all_protected_branches.extend(opts.exclude.iter().map(|pattern| ProtectedBranch {
+        pattern: pattern.clone(),
+        source: ProtectionSource::Local,
+    }));
+    // The default branch is spared even when it is stale, unprotected and not the base branch.
+    if let Some(name) = default_branch_name(repository, remote_name, &api_client).await {
+        all_protected_branches.push(ProtectedBranch::named(&name, ProtectionSource::Default));
+    }
+
+    let any_push_requests = if opts.keep_with_any_pr {
+        Some(api_client.list_push_requests(PushRequestState::All).await?)
+    } else {
+        None
+    };
+
+    let all_branches = git::get_remote_branches(repository, remote_name)?.collect::<Vec<_>>();
+    let all_branches_count = all_branches.len();
+
+    let mut reason_for = branch_reason(
+        remote_name,
+        &current_remote_branch,
+        base_branch.as_ref(),
+        opts.since_date,
+        repository,
+        opts.activity_metric,
+        &all_push_requests,
+        &all_protected_branches,
+    );
+    let mut protected_count = 0;
+    let mut branches_to_delete = vec![];
+    for branch in all_branches
+        .into_iter()
+        .filter_map(|res| res.ok().map(|pair| pair.0))
+    {
+        let reason = if is_included(&branch, remote_name, opts.include) {
+            reason_for(&branch)
+        } else {
+            Reason::NotIncluded
+        };
+        let reason = match active_authors {
+            Some(ref active_authors) => {
+                let author = git::commit_for_branch(repository, &branch)
+                    .ok()
+                    .map(|commit| git::author_name(&commit));
+                active_author_reason(reason, author, active_authors)
+            }
+            None => reason,
+        };
+        let reason = match any_push_requests {
+            Some(ref push_requests) => match branch.name() {
+                Ok(Some(name)) => any_push_request_reason(
+                    reason,
+                    &git::bare_branch_name(name, remote_name),
+                    push_requests,
+                ),
+                _ => reason,
+            },
+            None => reason,
+        };
+        let disposition = match reason.disposition() {
+            Disposition::Delete if !is_by_selected_author(repository, &branch, opts.authors) => {
+                Disposition::Keep
+            }
+            disposition => disposition,
+        };
+        let explanation = match (reason.disposition(), disposition) {
+            (Disposition::Delete, Disposition::Keep) => {
+                "kept, as its latest commit isn't by a selected author".to_string()
+            }
+            _ => reason.explain(opts.since_date),
+        };
+        if opts.explain {
+            if let Ok(Some(name)) = branch.name() {
+                info!("{}: {}", name, explanation);
+            }
+        }
+        if opts.output_path.is_some() {
+            decisions.extend(BranchDecision::new(
+                repository,
+                remote_name,
+                &branch,
+                disposition,
+                explanation,
+            ));
+        }
+        match disposition {
+            Disposition::Delete => {
+                if opts.stream_plan && opts.limit.is_none_or(|l| branches_to_delete.len() < l) {
+                    if let Ok(Some(name)) = branch.name() {
+                        let reason = match reason {
+                            Reason::Merged(id) => format!("push request #{} was merged", id),
+                            _ => format!("no commits since {}", opts.since_date.to_rfc3339()),
+                        };
+                        StreamedChange::delete_branch(name, reason).stream();
+                    }
+                }
+                branches_to_delete.push(branch)
+            }
+            Disposition::Protect => protected_count += 1,
+            Disposition::Keep => {}
+        }
+    }
+
+    summary.examined = all_branches_count;
+    summary.eligible = branches_to_delete.len();
+    summary.skipped = protected_count;
+    summary.branches = Some(BranchNames {
+        eligible: bare_branch_names(&branches_to_delete, remote_name),
+        deleted: vec![],
+    });
+    let truncated_from = truncate_to_limit(&mut branches_to_delete, opts.limit);
+    planned.extend(branches_to_delete.iter().filter_map(|branch| {
+        let name = branch.name().ok()??;
+        Some(PlannedChange::DeleteBranch {
+            remote: remote_name.to_string(),
+            branch: git::bare_branch_name(name, remote_name),
+            commit: branch.get().target()?.to_string(),
+        })
+    }));
+
+    if opts.plan {
+        let mut plan = Plan::default();
+        for name in branches_to_delete
+            .iter()
+            .filter_map(|branch| branch.name().ok())
+            .flatten()
+        {
+            plan.delete_branch(name);
+        }
+        plan.protect(protected_count);
+        print_plan(&plan, opts.stream_plan || opts.json);
+    } else {
+        print_branches_to_delete(
+            &branches_to_delete,
+            all_branches_count,
+            remote_name,
+            truncated_from,
+        );
+    }
+
+    if !opts.should_delete {
+        return Ok(());
+    }
+    if is_archived(&api_client).await {
+        warn!(
+            "The repository behind {} is archived, so it is read-only. Leaving its branches alone.",
+            remote_name
+        );
+        return Ok(());
+    }
+    if opts.interactive {
+        branches_to_delete = select_branches(branches_to_delete, repository, remote_name)?;
+        if branches_to_delete.is_empty() {
+            info!("No branches selected for deletion on {}.", remote_name);
+            return Ok(());
+        }
+    }
+
+    delete_branches_if_needed(
+        &branches_to_delete,
+        repository,
+        remote_name,
+        &api_client,
+        opts,
+        summary,
+    )
+    .await
+}
+
+/// Lists the open push requests and the protected branches on the remote concurrently. The protected branches include
+/// those the SCM marks as undeletable and those `recently_checked_out`. With `include_merged`, the push requests also
+/// include those that were merged.
+async fn list_push_requests_and_protected_branches(
+    api_client: &SCMProvider,
+    recently_checked_out: &[String],
+    include_merged: bool,
+) -> ApiResult<(Vec<PushRequest>, Vec<ProtectedBranch>)> {
+    let merged_push_requests = async {
+        if !include_merged {
+            return Ok(vec![]);
+        }
+        let closed = api_client
+            .list_push_requests(PushRequestState::Closed)
+            .await?;
+        Ok(closed.into_iter().filter(|pr| pr.is_merged).collect())
+    };
+    let (mut push_requests, merged_push_requests, mut protected_branches, undeletable_branches) = futures::try_join!(
+        api_client.list_push_requests(PushRequestState::Opened),
+        merged_push_requests,
+        api_client.list_protected_branches(),
+        api_client.list_undeletable_branches(),
+    )?;
+    push_requests.extend(merged_push_requests);
+    protected_branches.extend(undeletable_branches);
+    protected_branches.extend(
+        recently_checked_out
+            .iter()
+            .map(|name| ProtectedBranch::named(name, ProtectionSource::Local)),
+    );
+    Ok((push_requests, protected_branches))
+}
+
+/// Finds the name of the default branch of `remote_name`, from `refs/remotes/<remote>/HEAD` when the repository
+/// records it, and otherwise from the SCM's API.
+async fn default_branch_name(
+    repository: &git::Repository,
+    remote_name: &str,
+    api_client: &SCMProvider,
+) -> Option<String> {
+    if let Some(name) = git::get_default_branch_name(repository, remote_name) {
+        return Some(name);
+    }
+    match api_client.default_branch().await {
+        Ok(name) => name,
+        Err(err) => {
+            warn!(
+                "Could not look up the default branch of {}: {}",
+                remote_name, err
+            );
+            None
+        }
+    }
+}
+
+/// What should happen to a remote branch during cleanup.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Disposition {
+    /// The branch is stale and eligible for deletion.
+    Delete,
+    /// The branch is stale, but is spared by a protection rule.
+    Protect,
+    /// The branch has been updated recently, or could not be inspected.
+    Keep,
+}
+
+/// Why a remote branch gets its `Disposition`, as logged by `--explain`.
+#[derive(Debug, Clone, PartialEq)]
+enum Reason {
+    /// The branch's latest commit, made on this date, is older than the cutoff, and nothing protects the branch.
+    Stale(DateTime<Utc>),
+    /// The branch's latest commit, made on this date, is newer than the cutoff.
+    Recent(DateTime<Utc>),
+    /// The branch's name or latest commit couldn't be read.
+    Unreadable,
+    /// The branch is the upstream of the branch checked out locally.
+    CheckedOut,
+    /// The branch is the base branch other branches are measured against.
+    Base,
+    /// The push request with this ID is open from the branch.
+    OpenPushRequest(i32),
+    /// The branch matches this protection rule.
+    Protected(String, ProtectionSource),
+    /// The push request with this ID was merged from the branch, and nothing has been pushed to it since.
+    Merged(i32),
+    /// The branch matches none of the patterns passed with `--include`.
+    NotIncluded,
+    /// The branch's latest commit is by this author, who has committed elsewhere in the repository recently.
+    ActiveAuthor(String),
+    /// The push request with this ID was opened from the branch at some point, and is now closed or merged.
+    PastPushRequest(i32),
+}
+
+impl Reason {
+    fn disposition(&self) -> Disposition {
+        match self {
+            Reason::Stale(_) | Reason::Merged(_) => Disposition::Delete,
+            Reason::Recent(_) | Reason::Unreadable | Reason::NotIncluded => Disposition::Keep,
+            Reason::CheckedOut
+            | Reason::Base
+            | Reason::OpenPushRequest(_)
+            | Reason::Protected(..)
+            | Reason::ActiveAuthor(_)
+            | Reason::PastPushRequest(_) => Disposition::Protect,
+        }
+    }
+
+    /// Explains the reason in a sentence fragment, given the cutoff `since_date`.
+    fn explain(&self, since_date: &DateTime<FixedOffset>) -> String {
+        let since = since_date.format("%Y-%m-%d");
+        match self {
+            Reason::Stale(date) => format!(
+                "eligible, as its latest commit on {} is before {}",
+                date.format("%Y-%m-%d"),
+                since
+            ),
+            Reason::Recent(date) => format!(
+                "kept, as its latest commit on {} is not before {}",
+                date.format("%Y-%m-%d"),
+                since
+            ),
+            Reason::Unreadable => "kept, as its latest commit could not be read".to_string(),
+            Reason::CheckedOut => {
+                "protected, as it is the upstream of the checked out branch".to_string()
+            }
+            Reason::Base => "protected, as it is the base branch".to_string(),
+            Reason::OpenPushRequest(id) => {
+                format!("protected, as push request #{} is open from it", id)
+            }
+            Reason::Protected(pattern, source) => {
+                format!("protected by the {} rule {}", source, pattern)
+            }
+            Reason::Merged(id) => format!("eligible, as push request #{} from it was merged", id),
+            Reason::NotIncluded => "kept, as it matches none of the included patterns".to_string(),
+            Reason::ActiveAuthor(author) => format!(
+                "protected, as its author {} has committed to the repository recently",
+                author
+            ),
+            Reason::PastPushRequest(id) => {
+                format!("protected, as push request #{} was opened from it", id)
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn branch_reason<'a>(
+    remote_name: &'a str,
+    current_branch: &'a git::Branch,
+    base_branch: Option<&'a git::Branch>,
+    since_date: &'a DateTime<FixedOffset>,
+    repository: &'a git::Repository,
+    activity_metric: git::ActivityMetric,
+    push_requests: &'a [PushRequest],
+    protected_branches: &'a [ProtectedBranch],
+) -> impl FnMut(&git::Branch<'a>) -> Reason {
+    move |branch| {
+        let branch_name = match branch.name() {
+            Ok(Some(name)) => git::bare_branch_name(name, remote_name),
+            _ => return Reason::Unreadable,
+        };
+        let last_activity_date = match git::last_activity_date(repository, branch, activity_metric)
+        {
+            Ok(date) => date,
+            Err(_) => return Reason::Unreadable,
+        };
+        // A merged push request makes its branch eligible however recently it was updated, unless it has been pushed
+        // to since the merge.
+        let merged_push_request = push_requests.iter().find(|pr| {
+            pr.is_merged
+                && pr.source_branch == branch_name
+                && pr.source_project == pr.target_project
+                && last_activity_date.timestamp() <= pr.updated_at.timestamp()
+        });
+        if last_activity_date.timestamp() > since_date.timestamp() && merged_push_request.is_none()
+        {
+            Reason::Recent(last_activity_date)
+        } else if branch.get() == current_branch.get() {
+            Reason::CheckedOut
+        } else if base_branch.is_some_and(|base| branch.get() == base.get()) {
+            Reason::Base
+        } else if let Some(pr) = push_requests
+            .iter()
+            .find(|pr| !pr.is_merged && pr.source_branch == branch_name)
+        {
+            Reason::OpenPushRequest(pr.id)
+        } else if let Some(protection) = protected_branches
+            .iter()
+            .find(|branch| branch.matches_branch(&branch_name))
+        {
+            Reason::Protected(protection.pattern.to_string(), protection.source)
+        } else if let Some(pr) = merged_push_request {
+            Reason::Merged(pr.id)
+        } else {
+            Reason::Stale(last_activity_date)
+        }
+    }
+}
+
+/// Protects a branch that `reason` makes eligible for deletion when its latest commit is by `author`, one of the
+/// `active_authors` who have committed recently. Any other reason is returned as is.
+fn active_author_reason(
+    reason: Reason,
+    author: Option<String>,
+    active_authors: &HashSet<String>,
+) -> Reason {
+    match author {
+        Some(author)
+            if reason.disposition() == Disposition::Delete && active_authors.contains(&author) =>
+        {
+            Reason::ActiveAuthor(author)
+        }
+        _ => reason,
+    }
+}
+
+/// Protects the branch `branch_name` that `reason` makes eligible for deletion when any of `push_requests`, whatever
+/// its state, was opened from it. Any other reason is returned as is.
+fn any_push_request_reason(
+    reason: Reason,
+    branch_name: &str,
+    push_requests: &[PushRequest],
+) -> Reason {
+    if reason.disposition() != Disposition::Delete {
+        return reason;
+    }
+    match push_requests
+        .iter()
+        .find(|pr| pr.source_branch == branch_name)
+    {
+        Some(pr) => Reason::PastPushRequest(pr.id),
+        None => reason,
+    }
+}
+
+/// Explains why the SCM would refuse to delete the branch `branch_name`, if it would: when a rule from the server or
+/// the default branch protects it, or when one of `push_requests` is open from it with merge conflicts.
+fn undeletable_reason(
+    branch_name: &str,
+    push_requests: &[PushRequest],
+    protected_branches: &[ProtectedBranch],
+) -> Option<String> {
+    if let Some(protection) = protected_branches.iter().find(|protection| {
+        protection.source != ProtectionSource::Local && protection.matches_branch(branch_name)
+    }) {
+        return Some(format!(
+            "the {} rule {} protects it",
+            protection.source, protection.pattern
+        ));
+    }
+    push_requests
+        .iter()
+        .find(|pr| pr.is_open && pr.mergeable == Some(false) && pr.source_branch == branch_name)
+        .map(|pr| format!("push request #{} from it has merge conflicts", pr.id))
+}
+
+/// Whether `branch` is considered for deletion at all: when `include` is empty every branch is, and otherwise only
+/// those whose names on `remote_name` match one of its patterns.
+fn is_included(branch: &git::Branch, remote_name: &str, include: &[Pattern]) -> bool {
+    if include.is_empty() {
+        return true;
+    }
+    match branch.name() {
+        Ok(Some(name)) => {
+            let branch_name = git::bare_branch_name(name, remote_name);
+            include.iter().any(|pattern| pattern.matches(&branch_name))
+        }
+        _ => false,
+    }
+}
+
+/// Whether the tip of `branch` was authored by one of the authors `authors` selects.
+fn is_by_selected_author(
+    repository: &git::Repository,
+    branch: &git::Branch,
+    authors: &AuthorFilter,
+) -> bool {
+    let author = git::commit_for_branch(repository, branch)
+        .ok()
+        .map(|commit| git::author_name(&commit));
+    authors.allows(author.as_deref())
+}
+
+fn print_branches_to_delete(
+    branches: &[git::Branch],
+    all_branches_count: usize,
+    remote_name: &str,
+    truncated_from: Option<usize>,
+) {
+    let branch_names = branches
+        .iter()
+        .filter_map(|branch| branch.name().ok())
+        .flatten()
+        .collect::<Vec<_>>();
+    info!(
+        "{}",
+        format_branches_to_delete(
+            &branch_names,
+            all_branches_count,
+            remote_name,
+            truncated_from
+        )
+    );
+}
+
+fn format_branches_to_delete(
+    branch_names: &[&str],
+    all_branches_count: usize,
+    remote_name: &str,
+    truncated_from: Option<usize>,
+) -> String {
+    format!(
+        "Found {} eligible branches out of {} total on {}{}{}",
+        truncated_from.unwrap_or(branch_names.len()),
+        all_branches_count,
+        remote_name,
+        truncation_message(branch_names.len(), truncated_from),
+        if !branch_names.is_empty() {
+            let branches_message = branch_names
+                .iter()
+                .map(|name| format!("• {}\n", name))
+                .collect::<String>();
+            format!(":\n{}", branches_message)
+        } else {
+            ".".to_string()
+        }
+    )
+}
+
+/// Lists `branches` with the date and author of their latest commits in a prompt, to pick which of them to delete.
+/// Returns the branches picked, or none if the deletion isn't then confirmed.
+fn select_branches<'repo>(
+    branches: Vec<git::Branch<'repo>>,
+    repository: &git::Repository,
+    remote_name: &str,
+) -> Result<Vec<git::Branch<'repo>>, FoxdieError> {
+    if branches.is_empty() {
+        return Ok(branches);
+    }
+    let items = branches
+        .iter()
+        .map(|branch| describe_branch(branch, repository))
+        .collect::<Vec<_>>();
+    let selection = MultiSelect::new()
+        .with_prompt(format!(
+            "Branches to delete on {} (space to pick, enter to accept)",
+            remote_name
+        ))
+        .items(&items)
+        .interact()?;
+    let selected = keep_selected(branches, &selection);
+    if selected.is_empty() {
+        return Ok(selected);
+    }
+    let confirmed = Confirm::new()
+        .with_prompt(format!(
+            "Delete {} branches from {}?",
+            selected.len(),
+            remote_name
+        ))
+        .default(false)
+        .interact()?;
+    Ok(if confirmed { selected } else { vec![] })
+}
+
+/// Describes `branch` by its name and the date and author of its latest commit.
+fn describe_branch(branch: &git::Branch, repository: &git::Repository) -> String {
+    let name = branch.name().ok().flatten().unwrap_or("(invalid UTF-8)");
+    match git::commit_for_branch(repository, branch) {
+        Ok(commit) => format!(
+            "{}  {}  {}",
+            name,
+            Utc.timestamp(commit.time().seconds(), 0).format("%Y-%m-%d"),
+            git::author_name(&commit)
+        ),
+        Err(_) => name.to_string(),
+    }
+}
+
+/// Keeps only the `items` whose indices are in `selection`, in their original order.
+fn keep_selected<T>(items: Vec<T>, selection: &[usize]) -> Vec<T> {
+    items
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| selection.contains(index))
+        .map(|(_, item)| item)
+        .collect()
+}
+
+async fn delete_branches_if_needed(
+    branches: &[git::Branch<'_>],
+    repository: &git::Repository,
+    remote_name: &str,
+    api_client: &SCMProvider,
+    opts: &Options<'_>,
+    summary: &mut CleanupSummary,
+) -> Result<(), FoxdieError> {
+    info!("Preparing to delete {} branches...", branches.len());
+
+    let branch_names = bare_branch_names(branches, remote_name);
+    // Read before deleting, as deleting by push also removes the remote-tracking branches.
+    let remote_tips = branches
+        .iter()
+        .filter_map(|branch| {
+            let name = branch.name().ok()??;
+            Some((
+                git::bare_branch_name(name, remote_name),
+                branch.get().target()?,
+            ))
+        })
+        .collect::<Vec<_>>();
+
+    let failed = match opts.deletion_method {
+        DeletionMethod::Api => delete_branches_via_api(&branch_names, api_client, opts.delay).await,
+        DeletionMethod::Push => delete_branches_via_push(
+            &branch_names,
+            repository,
+            remote_name,
+            &opts.git_credentials(),
+        )?,
+    };
+    let failed_count = failed.len();
+
+    let deleted = branch_names
+        .iter()
+        .filter(|name| !failed.contains(name))
+        .cloned()
+        .collect::<Vec<_>>();
+    summary.deleted = deleted.len();
+    summary
+        .branches
+        .get_or_insert_with(Default::default)
+        .deleted = deleted;
+    info!(
+        "Finished deleting branches. Deleted {} of {}.",
+        summary.deleted,
+        branch_names.len()
+    );
+    if opts.prune_local {
+        let deleted_tips = remote_tips
+            .into_iter()
+            .filter(|(name, _)| !failed.contains(name))
+            .collect::<Vec<_>>();
+        let pruned = git::prune_local_branches(repository, remote_name, &deleted_tips)?;
+        info!(
+            "Pruned {} local branches tracking deleted branches on {}.",
+            pruned.len(),
+            remote_name
+        );
+    }
+    if failed_count == 0 {
+        Ok(())
+    } else {
+        Err(FoxdieError::PartialFailure(
+            failed_count,
+            branch_names.len(),
+        ))
+    }
+}
+
+/// Deletes each branch through the SCM's API, waiting `delay` between one branch and the next. Returns the names of the
+/// branches that could not be deleted.
+async fn delete_branches_via_api(
+    branch_names: &[String],
+    api_client: &SCMProvider,
+    delay: Duration,
+) -> Vec<String> {
+    let mut failed = vec![];
+    for (index, branch_name) in branch_names.iter().enumerate() {
+        if index > 0 && !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        if let Err(err) = api_client.delete_branch(branch_name).await {
+            error!("Failed to delete {}: {}", branch_name, err);
+            failed.push(branch_name.to_string());
+        }
+    }
+    failed
+}
+
+/// Deletes the branches with a single push to the remote. Returns the names of the branches the remote refused to
+/// delete.
+fn delete_branches_via_push(
+    branch_names: &[String],
+    repository: &git::Repository,
+    remote_name: &str,
+    credentials: &git::Credentials<'_>,
+) -> Result<Vec<String>, FoxdieError> {
+    let refspecs = branch_names
+        .iter()
+        .map(|branch_name| format!("+:refs/heads/{}", branch_name))
+        .collect::<Vec<_>>();
+
+    let refspecs_slice = refspecs.iter().map(|spec| &**spec).collect::<Vec<_>>();
+    let rejected = git::push_to_remote(repository, remote_name, &refspecs_slice, credentials)?;
+    for (refname, message) in &rejected {
+        error!("Failed to delete {}: {}", refname, message);
+    }
+    Ok(rejected
+        .iter()
+        .map(|(refname, _)| git::bare_branch_name(refname, remote_name))
+        .collect())
+}
+
+/// Returns the names of `branches` as they are known on the remote named `remote_name`.
+fn bare_branch_names(branches: &[git::Branch<'_>], remote_name: &str) -> Vec<String> {
+    branches
+        .iter()
+        .filter_map(|branch| branch.name().ok())
+        .flatten()
+        .map(|branch_name| git::bare_branch_name(branch_name, remote_name))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::git::test_support::{commit, commit_as, TestRepo};
+    use crate::services::test_support::{github_pull_json, mock_provider, push_request};
+    use mockito::{mock, Matcher};
+    use what_git::{ProviderCache, SCMKind};
+
+    /// An open push request from `source_branch` into master, last updated at `updated_at`.
+    #[tokio::test]
+    async fn test_list_push_requests_and_protected_branches() {
+        let api_client = mock_provider(SCMKind::GitHub, "", "concurrent");
+        let pulls = mock("GET", "/repos/wayfair/concurrent/pulls")
+            .match_query(Matcher::Any)
+            .with_body(
+                serde_json::json!([github_pull_json(5, "open", "2019-01-01T00:00:00Z")])
+                    .to_string(),
+            )
+            .create();
+        let branches = mock("GET", "/repos/wayfair/concurrent/branches")
+            .match_query(Matcher::UrlEncoded("protected".into(), "true".into()))
+            .with_body(r#"[{"name": "release/*"}]"#)
+            .create();
+
+        let (push_requests, protected_branches) =
+            list_push_requests_and_protected_branches(&api_client, &[String::from("mine")], false)
+                .await
+                .unwrap();
+
+        pulls.assert();
+        branches.assert();
+        assert_eq!(
+            push_requests.iter().map(|pr| pr.id).collect::<Vec<_>>(),
+            vec![5]
+        );
+        assert_eq!(
+            protected_branches
+                .iter()
+                .map(|branch| (branch.pattern.as_str(), branch.source))
+                .collect::<Vec<_>>(),
+            vec![
+                ("release/*", ProtectionSource::Server),
+                ("mine", ProtectionSource::Local)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_by_selected_author() {
+        let repo = TestRepo::init("author");
+        let signature = git2::Signature::now("Departed Dev", "departed@example.com").unwrap();
+        let branch = repo
+            .branch("stale", &commit_as(&repo, &signature, "Stale", &[]), false)
+            .unwrap();
+        let filter = |include: &[&str], exclude: &[&str]| AuthorFilter {
+            include: include.iter().map(|name| name.to_string()).collect(),
+            exclude: exclude.iter().map(|name| name.to_string()).collect(),
+        };
+
+        assert!(is_by_selected_author(&repo, &branch, &filter(&[], &[])));
+        assert!(is_by_selected_author(
+            &repo,
+            &branch,
+            &filter(&["departed dev"], &[])
+        ));
+        assert!(!is_by_selected_author(
+            &repo,
+            &branch,
+            &filter(&["Someone Else"], &[])
+        ));
+        assert!(!is_by_selected_author(
+            &repo,
+            &branch,
+            &filter(&[], &["DEPARTED DEV"])
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_default_branch_is_never_deleted() {
+        let repo = TestRepo::init("default");
+        let time = git2::Time::new(1_500_000_000, 0);
+        let signature =
+            git2::Signature::new("Departed Dev", "departed@example.com", &time).unwrap();
+        repo.add_remote_branches("origin", &["main", "feature", "current"], &signature);
+        repo.reference_symbolic(
+            "refs/remotes/origin/HEAD",
+            "refs/remotes/origin/main",
+            false,
+            "test",
+        )
+        .unwrap();
+        // Never contacted, as the default branch is recorded by origin/HEAD.
+        let api_client = mock_provider(SCMKind::GitHub, "", "default-branch");
+
+        let default_branch = default_branch_name(&repo, "origin", &api_client)
+            .await
+            .unwrap();
+        assert_eq!(default_branch, "main");
+
+        let protected_branches = vec![ProtectedBranch::named(
+            &default_branch,
+            ProtectionSource::Default,
+        )];
+        let since = DateTime::parse_from_rfc3339("2020-01-01T00:00:00Z").unwrap();
+        let branch = |name| repo.find_branch(name, git2::BranchType::Remote).unwrap();
+        let current = branch("origin/current");
+        let mut reason_for = branch_reason(
+            "origin",
+            &current,
+            None,
+            &since,
+            &repo,
+            git::ActivityMetric::Committer,
+            &[],
+            &protected_branches,
+        );
+        assert_eq!(
+            reason_for(&branch("origin/main")),
+            Reason::Protected(String::from("main"), ProtectionSource::Default)
+        );
+        assert_eq!(
+            reason_for(&branch("origin/feature")).disposition(),
+            Disposition::Delete
+        );
+    }
+
+    #[tokio::test]
+    async fn test_clean_only_selected_remote() {
+        let repo = TestRepo::init("clean-remote");
+        let time = git2::Time::new(1_500_000_000, 0);
+        let signature =
+            git2::Signature::new("Departed Dev", "departed@example.com", &time).unwrap();
+        let main = commit_as(&repo, &signature, "main", &[]).id();
+        let stale = commit_as(&repo, &signature, "stale", &[]).id();
+        repo.branch("main", &repo.find_commit(main).unwrap(), false)
+            .unwrap();
+        repo.set_head("refs/heads/main").unwrap();
+        // Left over from an earlier fetch of upstream, which isn't a selected remote.
+        repo.reference("refs/remotes/upstream/stale", stale, false, "test")
+            .unwrap();
+        // The mock server is reached by name, as hosts are only detected by name, and is known to serve GitHub's
+        // API so that it isn't probed.
+        let port = mockito::server_address().port();
+        let provider_cache = ProviderCache::default();
+        provider_cache.remember("localhost", &mockito::server_url(), SCMKind::GitHub);
+        repo.remote(
+            "origin",
+            &format!("http://localhost:{}/wayfair/selected-remote.git", port),
+        )
+        .unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("branch.main.remote", "origin").unwrap();
+        config
+            .set_str("branch.main.merge", "refs/heads/main")
+            .unwrap();
+
+        // Every object origin advertises is already present, so the fetch only reads the advertisement.
+        let pkt_line = |line: String| format!("{:04x}{}", line.len() + 4, line);
+        let advertisement = [
+            pkt_line(String::from("# service=git-upload-pack\n")),
+            String::from("0000"),
+            pkt_line(format!("{} refs/heads/main\0ofs-delta\n", main)),
+            pkt_line(format!("{} refs/heads/stale\n", stale)),
+            String::from("0000"),
+        ]
+        .concat();
+        let _refs = mock("GET", "/wayfair/selected-remote.git/info/refs")
+            .match_query(Matcher::Any)
+            .with_header(
+                "content-type",
+                "application/x-git-upload-pack-advertisement",
+            )
+            .with_body(advertisement)
+            .create();
+        let _pulls = mock("GET", "/repos/wayfair/selected-remote/pulls")
+            .match_query(Matcher::Any)
+            .with_body("[]")
+            .create();
+        let _protected = mock("GET", "/repos/wayfair/selected-remote/branches")
+            .match_query(Matcher::Any)
+            .with_body("[]")
+            .create();
+        let _repo = mock("GET", "/repos/wayfair/selected-remote")
+            .with_body(r#"{"default_branch": "main"}"#)
+            .create();
+
+        let since = DateTime::parse_from_rfc3339("2020-01-01T00:00:00Z").unwrap();
+        let output_path = repo.path().join("decisions.json");
+        let remotes = [String::from("origin")];
+        let authors = AuthorFilter::default();
+        let base_branches = HashMap::new();
+        let opts = Options {
+            skip_auth_check: true,
+            client_options: ClientOptions {
+                provider_cache: Some(&provider_cache),
+                ..ClientOptions::default()
+            },
+            remotes: &remotes,
+            output_path: Some(&output_path),
+            ..Options::new(&since, "token", &authors, &base_branches)
+        };
+
+        let mut summaries = vec![];
+        clean_remote_branches(Some(repo.path()), opts, &mut summaries)
+            .await
+            .unwrap();
+
+        let written: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&output_path).unwrap()).unwrap();
+        let branches = written
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|decision| decision["branch"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(branches, vec!["main", "stale"]);
+        assert_eq!(summaries[0].examined, 2);
+    }
+
+    #[test]
+    fn test_merged_push_request_branches_are_deleted() {
+        let repo = TestRepo::init("merged");
+        let time = git2::Time::new(1_600_000_000, 0);
+        let signature = git2::Signature::new("Recent Dev", "recent@example.com", &time).unwrap();
+        repo.add_remote_branches(
+            "origin",
+            &["merged", "open", "reused", "current"],
+            &signature,
+        );
+        let open = |id: i32, source_branch: &str, updated_at: &str| PushRequest {
+            source_branch: source_branch.to_string(),
+            updated_at: DateTime::parse_from_rfc3339(updated_at).unwrap(),
+            ..push_request(id)
+        };
+        let merged = |id: i32, source_branch: &str, updated_at: &str| PushRequest {
+            is_open: false,
+            is_merged: true,
+            ..open(id, source_branch, updated_at)
+        };
+        let push_requests = vec![
+            merged(1, "merged", "2020-10-01T00:00:00Z"),
+            merged(2, "open", "2020-10-01T00:00:00Z"),
+            open(3, "open", "2020-10-01T00:00:00Z"),
+            // Merged before the branch's latest commit was pushed.
+            merged(4, "reused", "2020-06-01T00:00:00Z"),
+        ];
+
+        let since = DateTime::parse_from_rfc3339("2020-01-01T00:00:00Z").unwrap();
+        let branch = |name| repo.find_branch(name, git2::BranchType::Remote).unwrap();
+        let current = branch("origin/current");
+        let mut reason_for = branch_reason(
+            "origin",
+            &current,
+            None,
+            &since,
+            &repo,
+            git::ActivityMetric::Committer,
+            &push_requests,
+            &[],
+        );
+        assert_eq!(reason_for(&branch("origin/merged")), Reason::Merged(1));
+        assert_eq!(
+            reason_for(&branch("origin/open")),
+            Reason::OpenPushRequest(3)
+        );
+        assert_eq!(
+            reason_for(&branch("origin/reused")).disposition(),
+            Disposition::Keep
+        );
+    }
+
+    #[test]
+    fn test_include_and_exclude_patterns() {
+        let repo = TestRepo::init("include");
+        let time = git2::Time::new(1_500_000_000, 0);
+        let signature =
+            git2::Signature::new("Departed Dev", "departed@example.com", &time).unwrap();
+        repo.add_remote_branches(
+            "origin",
+            &["feature/old", "feature/keep-me", "hotfix/old", "current"],
+            &signature,
+        );
+        let include = vec![Pattern::new("feature/*").unwrap()];
+        let protected_branches = vec![ProtectedBranch {
+            pattern: Pattern::new("*/keep-*").unwrap(),
+            source: ProtectionSource::Local,
+        }];
+
+        let since = DateTime::parse_from_rfc3339("2020-01-01T00:00:00Z").unwrap();
+        let branch = |name| repo.find_branch(name, git2::BranchType::Remote).unwrap();
+        let current = branch("origin/current");
+        let mut reason_for = branch_reason(
+            "origin",
+            &current,
+            None,
+            &since,
+            &repo,
+            git::ActivityMetric::Committer,
+            &[],
+            &protected_branches,
+        );
+        let mut reason = |name| {
+            let branch = branch(name);
+            if is_included(&branch, "origin", &include) {
+                reason_for(&branch)
+            } else {
+                Reason::NotIncluded
+            }
+        };
+        assert_eq!(
+            reason("origin/feature/old").disposition(),
+            Disposition::Delete
+        );
+        assert_eq!(
+            reason("origin/feature/keep-me"),
+            Reason::Protected(String::from("*/keep-*"), ProtectionSource::Local)
+        );
+        assert_eq!(reason("origin/hotfix/old"), Reason::NotIncluded);
+
+        assert!(is_included(&branch("origin/hotfix/old"), "origin", &[]));
+    }
+
+    #[test]
+    fn test_activity_metric_drives_staleness() {
+        let repo = TestRepo::init("metric");
+        let signature_at = |seconds| {
+            git2::Signature::new("Dev", "dev@example.com", &git2::Time::new(seconds, 0)).unwrap()
+        };
+        let tree_oid = repo.treebuilder(None).unwrap().write().unwrap();
+        let tree = repo.find_tree(tree_oid).unwrap();
+        // Authored before the cutoff, but rebased after it.
+        let rebased = repo
+            .commit(
+                None,
+                &signature_at(1_500_000_000),
+                &signature_at(1_600_000_000),
+                "Rebased",
+                &tree,
+                &[],
+            )
+            .unwrap();
+        repo.reference("refs/remotes/origin/rebased", rebased, false, "test")
+            .unwrap();
+        let current = repo
+            .commit(
+                None,
+                &signature_at(1_600_000_000),
+                &signature_at(1_600_000_000),
+                "Current",
+                &tree,
+                &[],
+            )
+            .unwrap();
+        repo.reference("refs/remotes/origin/current", current, false, "test")
+            .unwrap();
+
+        let since = DateTime::parse_from_rfc3339("2019-01-01T00:00:00Z").unwrap();
+        let branch = |name| repo.find_branch(name, git2::BranchType::Remote).unwrap();
+        let current = branch("origin/current");
+        let reason = |metric| {
+            branch_reason("origin", &current, None, &since, &repo, metric, &[], &[])(&branch(
+                "origin/rebased",
+            ))
+        };
+        assert_eq!(
+            reason(git::ActivityMetric::Committer),
+            Reason::Recent(Utc.timestamp(1_600_000_000, 0))
+        );
+        assert_eq!(
+            reason(git::ActivityMetric::Author),
+            Reason::Stale(Utc.timestamp(1_500_000_000, 0))
+        );
+    }
+
+    #[test]
+    fn test_read_ignore_file() {
+        let repo = TestRepo::init("ignore");
+        assert!(read_ignore_file(&repo).unwrap().is_empty());
+
+        let ignore_file = repo.path().join(IGNORE_FILE_NAME);
+        std::fs::write(
+            &ignore_file,
+            "# Branches we release from\nrelease/*\n\n  main  \n",
+        )
+        .unwrap();
+        let patterns = read_ignore_file(&repo).unwrap();
+        assert_eq!(
+            patterns.iter().map(Pattern::as_str).collect::<Vec<_>>(),
+            vec!["release/*", "main"]
+        );
+        let protected = |name: &str| patterns.iter().any(|pattern| pattern.matches(name));
+        assert!(protected("release/1.0"));
+        assert!(protected("main"));
+        assert!(!protected("feature/main"));
+        assert!(!protected("# Branches we release from"));
+
+        std::fs::write(&ignore_file, "release/[\n").unwrap();
+        assert!(matches!(
+            read_ignore_file(&repo),
+            Err(FoxdieError::InvalidConfig(..))
+        ));
+    }
+
+    #[test]
+    fn test_write_decisions_to_disk() {
+        let repo = TestRepo::init("decisions");
+        let time = git2::Time::new(1_500_000_000, 0);
+        let signature =
+            git2::Signature::new("Departed Dev", "departed@example.com", &time).unwrap();
+        repo.add_remote_branches("origin", &["stale", "release"], &signature);
+        let branch = |name| repo.find_branch(name, git2::BranchType::Remote).unwrap();
+        let mut decisions = vec![
+            BranchDecision::new(
+                &repo,
+                "origin",
+                &branch("origin/stale"),
+                Disposition::Delete,
+                String::from("eligible"),
+            )
+            .unwrap(),
+            BranchDecision::new(
+                &repo,
+                "origin",
+                &branch("origin/release"),
+                Disposition::Protect,
+                String::from("protected"),
+            )
+            .unwrap(),
+        ];
+        let summary = CleanupSummary {
+            branches: Some(BranchNames {
+                eligible: vec![String::from("stale")],
+                deleted: vec![String::from("stale")],
+            }),
+            ..CleanupSummary::default()
+        };
+        mark_deleted(&mut decisions, &summary);
+        let output_path = repo.path().join("decisions.json");
+
+        write_decisions_to_disk(&decisions, &output_path).unwrap();
+
+        let written: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&output_path).unwrap()).unwrap();
+        assert_eq!(
+            written,
+            serde_json::json!([
+                {
+                    "remote": "origin",
+                    "branch": "stale",
+                    "last_commit_date": "2017-07-14T02:40:00Z",
+                    "author": "Departed Dev",
+                    "disposition": "delete",
+                    "reason": "eligible",
+                    "deleted": true,
+                },
+                {
+                    "remote": "origin",
+                    "branch": "release",
+                    "last_commit_date": "2017-07-14T02:40:00Z",
+                    "author": "Departed Dev",
+                    "disposition": "protect",
+                    "reason": "protected",
+                    "deleted": false,
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_drift_of() {
+        let repo = TestRepo::init("drift");
+        let planned = commit(&repo, "planned", &[]).id();
+        let pushed = commit(&repo, "pushed", &[]).id();
+        repo.reference("refs/remotes/origin/unchanged", planned, false, "test")
+            .unwrap();
+        repo.reference("refs/remotes/origin/moved", pushed, false, "test")
+            .unwrap();
+        let branch = |name| git::find_remote_branch(&repo, "origin", name).unwrap();
+
+        assert_eq!(
+            drift_of(branch("unchanged").as_ref(), &planned.to_string()),
+            None
+        );
+        assert_eq!(
+            drift_of(branch("moved").as_ref(), &planned.to_string()),
+            Some(format!(
+                "it has been pushed to since the plan was made, and now points to {}",
+                pushed
+            ))
+        );
+        assert!(branch("deleted").is_none());
+        assert_eq!(
+            drift_of(None, &planned.to_string()),
+            Some("it no longer exists".to_string())
+        );
+    }
+
+    #[test]
+    fn test_reason_explain() {
+        let since_date = DateTime::parse_from_rfc3339("2020-01-01T00:00:00-05:00").unwrap();
+        let date = Utc.timestamp(1_500_000_000, 0);
+        assert_eq!(
+            Reason::Stale(date).explain(&since_date),
+            "eligible, as its latest commit on 2017-07-14 is before 2020-01-01"
+        );
+        assert_eq!(
+            Reason::Recent(Utc.timestamp(1_600_000_000, 0)).explain(&since_date),
+            "kept, as its latest commit on 2020-09-13 is not before 2020-01-01"
+        );
+        assert_eq!(
+            Reason::OpenPushRequest(7).explain(&since_date),
+            "protected, as push request #7 is open from it"
+        );
+        assert_eq!(
+            Reason::Protected(String::from("release/*"), ProtectionSource::Server)
+                .explain(&since_date),
+            "protected by the server rule release/*"
+        );
+        assert_eq!(Reason::Stale(date).disposition(), Disposition::Delete);
+        assert_eq!(Reason::Recent(date).disposition(), Disposition::Keep);
+        assert_eq!(Reason::Base.disposition(), Disposition::Protect);
+        assert_eq!(Reason::NotIncluded.disposition(), Disposition::Keep);
+    }
+
+    #[test]
+    fn test_active_author_reason() {
+        let date = Utc.timestamp(1_500_000_000, 0);
+        let active_authors = [String::from("Active Dev")]
+            .iter()
+            .cloned()
+            .collect::<HashSet<_>>();
+        let reason = active_author_reason(
+            Reason::Stale(date),
+            Some(String::from("Active Dev")),
+            &active_authors,
+        );
+        assert_eq!(reason, Reason::ActiveAuthor(String::from("Active Dev")));
+        assert_eq!(reason.disposition(), Disposition::Protect);
+        assert_eq!(
+            active_author_reason(
+                Reason::Stale(date),
+                Some(String::from("Inactive Dev")),
+                &active_authors
+            ),
+            Reason::Stale(date)
+        );
+        assert_eq!(
+            active_author_reason(Reason::Stale(date), None, &active_authors),
+            Reason::Stale(date)
+        );
+        assert_eq!(
+            active_author_reason(
+                Reason::Recent(date),
+                Some(String::from("Active Dev")),
+                &active_authors
+            ),
+            Reason::Recent(date)
+        );
+    }
+
+    #[test]
+    fn test_any_push_request_reason() {
+        let date = Utc.timestamp(1_500_000_000, 0);
+        let closed = |id: i32, source_branch: &str, is_merged: bool| PushRequest {
+            is_open: false,
+            is_merged,
+            source_branch: source_branch.to_string(),
+            ..push_request(id)
+        };
+        let push_requests = [
+            closed(1, "closed-only", false),
+            closed(2, "merged-only", true),
+        ];
+
+        assert_eq!(
+            any_push_request_reason(Reason::Stale(date), "closed-only", &push_requests),
+            Reason::PastPushRequest(1)
+        );
+        let reason = any_push_request_reason(Reason::Merged(2), "merged-only", &push_requests);
+        assert_eq!(reason, Reason::PastPushRequest(2));
+        assert_eq!(reason.disposition(), Disposition::Protect);
+        assert_eq!(
+            any_push_request_reason(Reason::Stale(date), "no-push-requests", &push_requests),
+            Reason::Stale(date)
+        );
+        assert_eq!(
+            any_push_request_reason(Reason::Recent(date), "closed-only", &push_requests),
+            Reason::Recent(date)
+        );
+    }
+
+    #[test]
+    fn test_undeletable_reason() {
+        let open = |id: i32, source_branch: &str, mergeable: Option<bool>| PushRequest {
+            mergeable,
+            source_branch: source_branch.to_string(),
+            ..push_request(id)
+        };
+        let push_requests = [
+            open(1, "conflicted", Some(false)),
+            open(2, "clean", Some(true)),
+            open(3, "unchecked", None),
+        ];
+        let protected_branches = [
+            ProtectedBranch::named("main", ProtectionSource::Default),
+            ProtectedBranch {
+                pattern: Pattern::new("release/*").unwrap(),
+                source: ProtectionSource::Server,
+            },
+            ProtectedBranch::named("mine", ProtectionSource::Local),
+        ];
+        let reason = |name: &str| undeletable_reason(name, &push_requests, &protected_branches);
+
+        assert_eq!(
+            reason("main").as_deref(),
+            Some("the default rule main protects it")
+        );
+        assert_eq!(
+            reason("release/1.0").as_deref(),
+            Some("the server rule release/* protects it")
+        );
+        assert_eq!(
+            reason("conflicted").as_deref(),
+            Some("push request #1 from it has merge conflicts")
+        );
+        assert_eq!(reason("clean"), None);
+        assert_eq!(reason("unchecked"), None);
+        assert_eq!(reason("mine"), None);
+    }
+
+    #[test]
+    fn test_keep_selected() {
+        // The prompt reports the picks in the order they were made.
+        assert_eq!(
+            keep_selected(vec!["a", "b", "c", "d"], &[2, 0]),
+            vec!["a", "c"]
+        );
+        assert!(keep_selected(vec!["a", "b"], &[]).is_empty());
+    }
+
+    #[test]
+    fn test_format_branches_to_delete() {
+        assert_eq!(
+            format_branches_to_delete(&["origin/a", "origin/b"], 10, "origin", None),
+            "Found 2 eligible branches out of 10 total on origin:\n• origin/a\n• origin/b\n"
+        );
+        assert_eq!(
+            format_branches_to_delete(&[], 10, "origin", None),
+            "Found 0 eligible branches out of 10 total on origin."
+        );
+    }
+
+    #[test]
+    fn test_format_branches_to_delete_with_limit() {
+        let mut names = vec!["origin/a", "origin/b", "origin/c", "origin/d"];
+        let truncated_from = truncate_to_limit(&mut names, Some(2));
+        assert_eq!(
+            format_branches_to_delete(&names, 10, "origin", truncated_from),
+            "Found 4 eligible branches out of 10 total on origin (showing/acting on first 2 of 4):\n\
+             • origin/a\n• origin/b\n"
+        );
+    }
+}
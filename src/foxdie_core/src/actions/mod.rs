@@ -0,0 +1,358 @@
+// Copyright (c) 2018-2019, Wayfair LLC
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+//  * Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//    disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//    following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING,
+// BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED.
+// IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY,
+// OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+// STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE,
+// EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+pub mod detect;
+pub mod journal;
+pub mod local;
+pub mod plan;
+pub mod push_requests;
+pub mod report;
+pub mod summary;
+
+use crate::error::FoxdieError;
+use crate::services::{git, SCMProvider};
+use log::warn;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+
+/// Opens the repository at `path`, or in the current directory if no path was given, reporting a missing repository as
+/// [`FoxdieError::NotAGitRepository`].
+pub fn open_repository<P>(path: Option<P>) -> Result<git::Repository, FoxdieError>
+where
+    P: AsRef<Path>,
+{
+    let path = match path {
+        Some(p) => p.as_ref().to_path_buf(),
+        None => env::current_dir().unwrap_or_default(),
+    };
+    git::open_repository(&path).map_err(|err| {
+        if err.code() == git::ErrorCode::NotFound && err.class() == git::ErrorClass::Repository {
+            FoxdieError::NotAGitRepository(path)
+        } else {
+            FoxdieError::from(err)
+        }
+    })
+}
+
+/// Selects branches and push requests by their author, as passed with `--author` and `--exclude-author`. Authors are
+/// matched exactly, ignoring case.
+#[derive(Debug, Default)]
+pub struct AuthorFilter {
+    /// When not empty, only objects by one of these authors are selected.
+    pub include: Vec<String>,
+    /// Objects by any of these authors are never selected.
+    pub exclude: Vec<String>,
+}
+
+impl AuthorFilter {
+    /// Whether an object by `author` is selected. An object whose author isn't known is only selected when no authors
+    /// were included.
+    pub fn allows(&self, author: Option<&str>) -> bool {
+        let is_listed = |authors: &[String]| {
+            author.is_some_and(|author| authors.iter().any(|a| a.eq_ignore_ascii_case(author)))
+        };
+        (self.include.is_empty() || is_listed(&self.include)) && !is_listed(&self.exclude)
+    }
+}
+
+/// Creates the file at `path` for output, after expanding a leading `~` to the home directory and creating any missing
+/// parent directories. A path to an existing directory is an error.
+pub fn create_output_file(path: &Path) -> Result<File, FoxdieError> {
+    Ok(File::create(prepare_output_path(path)?)?)
+}
+
+/// Opens the file at `path` for appending output to, creating it if it doesn't exist yet. The path is handled as in
+/// `create_output_file`.
+pub fn append_output_file(path: &Path) -> Result<File, FoxdieError> {
+    Ok(OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(prepare_output_path(path)?)?)
+}
+
+/// Expands a leading `~` in the output path `path` and creates its missing parent directories, failing if it is a
+/// directory itself.
+fn prepare_output_path(path: &Path) -> Result<PathBuf, FoxdieError> {
+    let path = expand_home(path);
+    if path.is_dir() {
+        return Err(FoxdieError::OutputIsADirectory(path));
+    }
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(path)
+}
+
+/// Expands a leading `~` in `path` to the home directory, as a shell would. Left alone when `HOME` isn't set.
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => Path::new(&home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Returns the names of the remotes of `repo` to work on: those named in `selected`, or every remote when none are. A
+/// selected remote that doesn't exist is an error.
+pub fn select_remotes(
+    repo: &git::Repository,
+    selected: &[String],
+) -> Result<Vec<String>, FoxdieError> {
+    let remotes = repo.remotes()?;
+    let all_remotes = remotes
+        .iter()
+        .flatten()
+        .map(String::from)
+        .collect::<Vec<_>>();
+    if let Some(unknown) = selected.iter().find(|name| !all_remotes.contains(name)) {
+        return Err(FoxdieError::UnknownRemote(unknown.to_string()));
+    }
+    Ok(all_remotes
+        .into_iter()
+        .filter(|name| selected.is_empty() || selected.contains(name))
+        .collect())
+}
+
+/// Truncates `items` to at most `limit` elements. Returns the original number of items if the list was truncated, so
+/// callers can report how much was left out.
+pub fn truncate_to_limit<T>(items: &mut Vec<T>, limit: Option<usize>) -> Option<usize> {
+    match limit {
+        Some(limit) if items.len() > limit => {
+            let original_count = items.len();
+            items.truncate(limit);
+            Some(original_count)
+        }
+        _ => None,
+    }
+}
+
+/// Describes a truncation performed by [`truncate_to_limit`], if any.
+pub fn truncation_message(shown_count: usize, truncated_from: Option<usize>) -> String {
+    match truncated_from {
+        Some(original_count) => format!(
+            " (showing/acting on first {} of {})",
+            shown_count, original_count
+        ),
+        None => String::new(),
+    }
+}
+
+/// Combines the `errors` from working through `count` remotes or repositories, having carried on past each failure. A
+/// single failure out of a single remote or repository is returned as is; otherwise, an error counting the failures is.
+pub fn combined_result(mut errors: Vec<FoxdieError>, count: usize) -> Result<(), FoxdieError> {
+    match errors.len() {
+        0 => Ok(()),
+        1 if count == 1 => Err(errors.remove(0)),
+        failed_count => Err(FoxdieError::PartialFailure(failed_count, count)),
+    }
+}
+
+/// Checks that the SCM accepts the token, so that a bad token fails the run before anything is listed, rather than
+/// partway through.
+pub async fn verify_token(api_client: &SCMProvider) -> Result<(), FoxdieError> {
+    if api_client.verify_token().await? {
+        Ok(())
+    } else {
+        Err(FoxdieError::AuthenticationFailed(api_client.name()))
+    }
+}
+
+/// Whether the repository behind `api_client` is archived, and so can't be changed. A repository whose state can't be
+/// looked up is taken not to be, leaving any failure to the changes themselves.
+pub async fn is_archived(api_client: &SCMProvider) -> bool {
+    match api_client.is_archived().await {
+        Ok(archived) => archived,
+        Err(err) => {
+            warn!(
+                "Could not check whether the repository is archived: {}",
+                err
+            );
+            false
+        }
+    }
+}
+
+/// Whether `answer`, as typed at a `[y/N]` prompt, confirms the action.
+pub fn is_confirmation(answer: &str) -> bool {
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::git::test_support::TestRepo;
+    use std::process;
+
+    #[test]
+    fn test_open_repository_outside_a_repository() {
+        let path = env::temp_dir().join(format!("foxdie-test-not-a-repo-{}", process::id()));
+        fs::create_dir_all(&path).unwrap();
+
+        let result = open_repository(Some(&path));
+
+        fs::remove_dir_all(&path).unwrap();
+        match result {
+            Err(err @ FoxdieError::NotAGitRepository(_)) => assert_eq!(
+                err.to_string(),
+                format!(
+                    "{} is not a Git repository; run Foxdie from inside a repository or pass a valid DIRECTORY",
+                    path.display()
+                )
+            ),
+            Err(other) => panic!("Expected NotAGitRepository, got {:?}", other),
+            Ok(_) => panic!("Expected NotAGitRepository, got a repository"),
+        }
+    }
+
+    #[test]
+    fn test_select_remotes() {
+        let repo = TestRepo::init("select-remotes");
+        repo.remote("origin", "https://github.com/wayfair/foxdie.git")
+            .unwrap();
+        repo.remote("upstream", "https://github.com/wayfair-archive/foxdie.git")
+            .unwrap();
+        let names = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let all = select_remotes(&repo, &[]);
+        let selected = select_remotes(&repo, &names(&["upstream"]));
+        let unknown = select_remotes(&repo, &names(&["origin", "fork"]));
+
+        assert_eq!(all.unwrap(), names(&["origin", "upstream"]));
+        assert_eq!(selected.unwrap(), names(&["upstream"]));
+        match unknown {
+            Err(FoxdieError::UnknownRemote(name)) => assert_eq!(name, "fork"),
+            other => panic!("Expected UnknownRemote, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_create_output_file_creates_parent_directories() {
+        let dir = env::temp_dir().join(format!("foxdie-test-output-{}", process::id()));
+        let path = dir.join("reports").join("nested").join("report.json");
+
+        let result = create_output_file(&path);
+
+        let created = path.is_file();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(result.is_ok());
+        assert!(created);
+    }
+
+    #[test]
+    fn test_create_output_file_rejects_directories() {
+        let dir = env::temp_dir().join(format!("foxdie-test-output-dir-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let result = create_output_file(&dir);
+
+        fs::remove_dir_all(&dir).unwrap();
+        match result {
+            Err(err @ FoxdieError::OutputIsADirectory(_)) => assert_eq!(
+                err.to_string(),
+                format!(
+                    "Cannot write output to {}, as it is a directory; pass a file path instead",
+                    dir.display()
+                )
+            ),
+            other => panic!("Expected OutputIsADirectory, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_expand_home() {
+        let home = PathBuf::from(env::var_os("HOME").unwrap());
+        assert_eq!(
+            expand_home(Path::new("~/reports/report.json")),
+            home.join("reports/report.json")
+        );
+        assert_eq!(expand_home(Path::new("~")), home);
+        assert_eq!(
+            expand_home(Path::new("reports/~/report.json")),
+            PathBuf::from("reports/~/report.json")
+        );
+        assert_eq!(
+            expand_home(Path::new("~other/report.json")),
+            PathBuf::from("~other/report.json")
+        );
+    }
+
+    #[test]
+    fn test_is_confirmation() {
+        assert!(is_confirmation("y\n"));
+        assert!(is_confirmation(" Yes \n"));
+        assert!(!is_confirmation("\n"));
+        assert!(!is_confirmation("no\n"));
+    }
+
+    #[test]
+    fn test_author_filter() {
+        let everyone = AuthorFilter::default();
+        assert!(everyone.allows(Some("octocat")));
+        assert!(everyone.allows(None));
+
+        let included = AuthorFilter {
+            include: vec![String::from("Departed Dev"), String::from("octocat")],
+            exclude: vec![],
+        };
+        assert!(included.allows(Some("departed dev")));
+        assert!(included.allows(Some("OctoCat")));
+        assert!(!included.allows(Some("octocat2")));
+        assert!(!included.allows(None));
+
+        let excluded = AuthorFilter {
+            include: vec![],
+            exclude: vec![String::from("renovate[bot]")],
+        };
+        assert!(!excluded.allows(Some("Renovate[bot]")));
+        assert!(excluded.allows(Some("octocat")));
+        assert!(excluded.allows(None));
+    }
+
+    #[test]
+    fn test_truncate_to_limit() {
+        let mut items = vec![1, 2, 3, 4, 5];
+        assert_eq!(truncate_to_limit(&mut items, Some(2)), Some(5));
+        assert_eq!(items, vec![1, 2]);
+
+        let mut items = vec![1, 2, 3];
+        assert_eq!(truncate_to_limit(&mut items, Some(3)), None);
+        assert_eq!(items, vec![1, 2, 3]);
+
+        let mut items = vec![1, 2, 3];
+        assert_eq!(truncate_to_limit(&mut items, None), None);
+        assert_eq!(items, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_truncation_message() {
+        assert_eq!(
+            truncation_message(2, Some(5)),
+            " (showing/acting on first 2 of 5)"
+        );
+        assert_eq!(truncation_message(3, None), "");
+    }
+}
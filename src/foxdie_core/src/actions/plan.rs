@@ -0,0 +1,301 @@
+// Copyright (c) 2018-2019, Wayfair LLC
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+//  * Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//    disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//    following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING,
+// BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED.
+// IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY,
+// OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+// STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE,
+// EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use super::create_output_file;
+use crate::error::FoxdieError;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufReader, IsTerminal, Write};
+use std::path::Path;
+
+pub(super) const RED: &str = "\x1b[31m";
+pub(super) const YELLOW: &str = "\x1b[33m";
+pub(super) const RESET: &str = "\x1b[0m";
+
+/// A single change that Foxdie intends to make.
+#[derive(Debug, PartialEq)]
+enum Change {
+    DeleteBranch(String),
+    ClosePushRequest(i32),
+}
+
+impl Change {
+    fn symbol(&self) -> &'static str {
+        match self {
+            Change::DeleteBranch(_) => "-",
+            Change::ClosePushRequest(_) => "~",
+        }
+    }
+
+    fn color(&self) -> &'static str {
+        match self {
+            Change::DeleteBranch(_) => RED,
+            Change::ClosePushRequest(_) => YELLOW,
+        }
+    }
+
+    fn description(&self) -> String {
+        match self {
+            Change::DeleteBranch(name) => format!("{} (delete)", name),
+            Change::ClosePushRequest(id) => format!("pr #{} (close)", id),
+        }
+    }
+}
+
+/// Collects the changes Foxdie intends to make so they can be rendered in the style of `terraform plan`.
+#[derive(Debug, Default)]
+pub struct Plan {
+    changes: Vec<Change>,
+    protected_count: usize,
+}
+
+impl Plan {
+    pub fn delete_branch(&mut self, name: &str) {
+        self.changes.push(Change::DeleteBranch(name.to_string()));
+    }
+
+    pub fn close_push_request(&mut self, id: i32) {
+        self.changes.push(Change::ClosePushRequest(id));
+    }
+
+    pub fn protect(&mut self, count: usize) {
+        self.protected_count += count;
+    }
+
+    /// Renders the plan, one change per line, followed by a summary line. Symbols are wrapped in ANSI color codes when
+    /// `colored` is set.
+    pub fn render(&self, colored: bool) -> String {
+        let mut output = self
+            .changes
+            .iter()
+            .map(|change| {
+                if colored {
+                    format!(
+                        "{}{}{} {}\n",
+                        change.color(),
+                        change.symbol(),
+                        RESET,
+                        change.description()
+                    )
+                } else {
+                    format!("{} {}\n", change.symbol(), change.description())
+                }
+            })
+            .collect::<String>();
+        let delete_count = self
+            .changes
+            .iter()
+            .filter(|change| matches!(change, Change::DeleteBranch(_)))
+            .count();
+        let close_count = self.changes.len() - delete_count;
+        output.push_str(&format!(
+            "Plan: {} to delete, {} to close, {} protected.",
+            delete_count, close_count, self.protected_count
+        ));
+        output
+    }
+}
+
+/// Prints the plan to stdout, colored if stdout is a terminal. When stdout is kept for something else, such as a
+/// streamed plan or a JSON summary, the plan is printed to stderr instead.
+pub fn print_plan(plan: &Plan, to_stderr: bool) {
+    if to_stderr {
+        eprintln!("{}", plan.render(io::stderr().is_terminal()));
+    } else {
+        println!("{}", plan.render(io::stdout().is_terminal()));
+    }
+}
+
+/// A single planned change, streamed as a line of JSON with `--stream-plan` as soon as it has been decided.
+#[derive(Debug, Serialize)]
+pub struct StreamedChange<'a> {
+    action: &'static str,
+    target: &'a str,
+    reason: String,
+}
+
+impl<'a> StreamedChange<'a> {
+    pub fn delete_branch(name: &'a str, reason: String) -> Self {
+        StreamedChange {
+            action: "delete",
+            target: name,
+            reason,
+        }
+    }
+
+    pub fn close_push_request(url: &'a str, reason: String) -> Self {
+        StreamedChange {
+            action: "close",
+            target: url,
+            reason,
+        }
+    }
+
+    /// Writes the change to `out` as one line of JSON, flushing it so that consumers see it straight away.
+    pub fn write_to<W: Write>(&self, mut out: W) -> io::Result<()> {
+        serde_json::to_writer(&mut out, self)?;
+        writeln!(out)?;
+        out.flush()
+    }
+
+    /// Writes the change to stdout, logging rather than failing the run if stdout has gone away.
+    pub fn stream(&self) {
+        if let Err(err) = self.write_to(io::stdout().lock()) {
+            warn!(
+                "Could not stream planned change for {}: {}",
+                self.target, err
+            );
+        }
+    }
+}
+
+/// The changes a dry run decided on, written with `--plan-out` so that exactly those changes can be made later with
+/// `--from-plan`, once the plan has been reviewed.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedPlan {
+    pub changes: Vec<PlannedChange>,
+}
+
+/// A change in a `SavedPlan`, with enough of the state it was decided on to tell whether that state has since drifted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum PlannedChange {
+    /// Delete `branch` from the remote named `remote`, whose tip was `commit` when the plan was made.
+    DeleteBranch {
+        remote: String,
+        branch: String,
+        commit: String,
+    },
+    /// Close the push request numbered `id` in the repository at `repo_url`.
+    ClosePushRequest {
+        repo_url: String,
+        id: i32,
+        url: String,
+    },
+}
+
+impl SavedPlan {
+    /// Reads the plan written to `path` by `write`.
+    pub fn read<P>(path: P) -> Result<Self, FoxdieError>
+    where
+        P: AsRef<Path>,
+    {
+        let reader = BufReader::new(File::open(path)?);
+        serde_json::from_reader(reader).map_err(FoxdieError::from)
+    }
+
+    /// Writes the plan to `path` as JSON.
+    pub fn write<P>(&self, path: P) -> Result<(), FoxdieError>
+    where
+        P: AsRef<Path>,
+    {
+        let mut file = create_output_file(path.as_ref())?;
+        let json = serde_json::to_vec_pretty(self)?;
+        file.write_all(&json).map_err(FoxdieError::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_plan() {
+        let mut plan = Plan::default();
+        plan.delete_branch("origin/branch-x");
+        plan.close_push_request(42);
+        plan.protect(8);
+        assert_eq!(
+            plan.render(false),
+            "- origin/branch-x (delete)\n~ pr #42 (close)\nPlan: 1 to delete, 1 to close, 8 protected."
+        );
+    }
+
+    #[test]
+    fn test_render_colored_plan() {
+        let mut plan = Plan::default();
+        plan.delete_branch("origin/branch-x");
+        assert_eq!(
+            plan.render(true),
+            "\x1b[31m-\x1b[0m origin/branch-x (delete)\nPlan: 1 to delete, 0 to close, 0 protected."
+        );
+    }
+
+    #[test]
+    fn test_write_streamed_changes() {
+        let mut out = vec![];
+        StreamedChange::delete_branch(
+            "origin/branch-x",
+            String::from("no commits since 2019-01-01"),
+        )
+        .write_to(&mut out)
+        .unwrap();
+        StreamedChange::close_push_request(
+            "https://github.com/wayfair/foxdie/pull/42",
+            String::from("last updated 2018-12-01"),
+        )
+        .write_to(&mut out)
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"action\":\"delete\",\"target\":\"origin/branch-x\",\"reason\":\"no commits since 2019-01-01\"}\n\
+             {\"action\":\"close\",\"target\":\"https://github.com/wayfair/foxdie/pull/42\",\"reason\":\"last updated 2018-12-01\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_render_empty_plan() {
+        assert_eq!(
+            Plan::default().render(false),
+            "Plan: 0 to delete, 0 to close, 0 protected."
+        );
+    }
+
+    #[test]
+    fn test_saved_plan_round_trip() {
+        let path = std::env::temp_dir()
+            .join(format!("foxdie-test-saved-plan-{}", std::process::id()))
+            .join("plan.json");
+        let plan = SavedPlan {
+            changes: vec![
+                PlannedChange::DeleteBranch {
+                    remote: String::from("origin"),
+                    branch: String::from("branch-x"),
+                    commit: String::from("0123456789abcdef0123456789abcdef01234567"),
+                },
+                PlannedChange::ClosePushRequest {
+                    repo_url: String::from("https://github.com/wayfair/foxdie"),
+                    id: 42,
+                    url: String::from("https://github.com/wayfair/foxdie/pull/42"),
+                },
+            ],
+        };
+
+        plan.write(&path).unwrap();
+        let written: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        let read = SavedPlan::read(&path).unwrap();
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        assert_eq!(read, plan);
+        assert_eq!(written["changes"][0]["action"], "delete_branch");
+        assert_eq!(written["changes"][1]["action"], "close_push_request");
+    }
+}
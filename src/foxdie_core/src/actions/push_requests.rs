@@ -0,0 +1,2426 @@
+// Copyright (c) 2018-2019, Wayfair LLC
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+//  * Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//    disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//    following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING,
+// BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED.
+// IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY,
+// OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+// STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE,
+// EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use super::journal::{read_journal, Journal, JournalAction};
+use super::plan::{print_plan, Plan, PlannedChange, SavedPlan, StreamedChange};
+use super::summary::{CleanupSummary, PushRequestIds};
+use super::{
+    combined_result, is_archived, is_confirmation, truncate_to_limit, truncation_message,
+    verify_token, AuthorFilter,
+};
+use crate::error::FoxdieError;
+use crate::services::{
+    get_api_client_for_url, get_api_clients_for_gitlab_group, ApiResult, ProtectedBranch,
+    PushRequest, PushRequestState, SCMProvider,
+};
+use chrono::{DateTime, FixedOffset};
+use futures::lock::Mutex;
+use futures::stream::{self, StreamExt};
+use glob::Pattern;
+use log::{debug, error, info, warn};
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+use std::sync::{self, Arc};
+use std::time::{Duration, Instant};
+use what_git::ClientOptions;
+
+#[derive(Clone)]
+#[non_exhaustive]
+pub struct Options<'a> {
+    pub should_delete: bool,
+    pub since_date: &'a DateTime<FixedOffset>,
+    pub token: &'a str,
+    pub limit: Option<usize>,
+    pub plan: bool,
+    /// Whether to stream each push request to close to stdout as a line of JSON.
+    pub stream_plan: bool,
+    /// Whether a JSON summary of the run is printed to stdout once it finishes, so that the plan must go to stderr.
+    pub json: bool,
+    pub allowed_hosts: Option<&'a [String]>,
+    pub skip_drafts: bool,
+    pub journal_path: Option<&'a str>,
+    /// A comment to post on each push request immediately before closing it.
+    pub comment: Option<&'a str>,
+    /// Whether to delete the source branch of each push request closed, unless it lives in a fork.
+    pub delete_source_branch: bool,
+    /// Whether to log why each push request is or isn't eligible to be closed.
+    pub explain: bool,
+    /// Whether to skip checking that the SCM accepts the token before listing push requests. Only honored on dry runs.
+    pub skip_auth_check: bool,
+    pub raw_response_dir: Option<&'a Path>,
+    pub client_options: ClientOptions<'a>,
+    /// The numeric ID of the Gitlab project, used in place of the owner and name in the URL.
+    pub project_id: Option<u64>,
+    /// How many push requests to request in each page on GitHub and Gitlab, up to their maximum of 100. When unset,
+    /// the maximum is.
+    pub page_size: Option<usize>,
+    /// Push requests from branches matching any of these patterns are left open.
+    pub exclude: &'a [Pattern],
+    /// Only push requests targeting a branch matching one of these patterns are closed. When empty, push requests
+    /// targeting any branch are.
+    pub target_branches: &'a [Pattern],
+    /// Only push requests opened by one of the authors this selects are closed.
+    pub authors: &'a AuthorFilter,
+    pub staleness_field: StalenessField,
+    /// Which push requests to list. Only those still open are ever closed.
+    pub state: PushRequestState,
+    /// When set, push requests are closed in batches, with a checkpoint between each batch.
+    pub batching: Option<Batching>,
+    /// How long to wait between closing one push request and the next, to stay clear of the SCM's abuse detection.
+    /// Applies across all the repositories on a host, however many are cleaned up at once.
+    pub delay: Duration,
+    /// How many repositories to clean up at once.
+    pub concurrency: usize,
+    /// When set, the push requests to close are written to this path as a `SavedPlan`, to be closed later with
+    /// `clean_push_requests_from_plan`.
+    pub plan_out: Option<&'a Path>,
+}
+
+impl<'a> Options<'a> {
+    /// Options for a dry run that closes open push requests last updated before `since_date`, one repository at a
+    /// time, with every other option left off. Set the fields to change the rest.
+    pub fn new(
+        since_date: &'a DateTime<FixedOffset>,
+        token: &'a str,
+        authors: &'a AuthorFilter,
+    ) -> Self {
+        Options {
+            should_delete: false,
+            since_date,
+            token,
+            limit: None,
+            plan: false,
+            stream_plan: false,
+            json: false,
+            allowed_hosts: None,
+            skip_drafts: false,
+            journal_path: None,
+            comment: None,
+            delete_source_branch: false,
+            explain: false,
+            skip_auth_check: false,
+            raw_response_dir: None,
+            client_options: ClientOptions::default(),
+            project_id: None,
+            page_size: None,
+            exclude: &[],
+            target_branches: &[],
+            authors,
+            staleness_field: StalenessField::Updated,
+            state: PushRequestState::Opened,
+            batching: None,
+            delay: Duration::from_millis(0),
+            concurrency: 1,
+            plan_out: None,
+        }
+    }
+}
+
+/// How to close push requests in batches, so that a large run can be stopped partway through.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Batching {
+    /// The number of push requests closed in each batch.
+    pub size: usize,
+    /// Whether to ask for confirmation before each batch after the first. When stdin isn't a terminal, `delay` is
+    /// waited out instead.
+    pub confirm: bool,
+    /// How long to wait before each batch after the first, when not asking for confirmation.
+    pub delay: Duration,
+}
+
+/// What to do around closing each push request.
+#[derive(Debug, Default, Copy, Clone)]
+struct CloseOptions<'a> {
+    /// A comment to post on each push request immediately before closing it.
+    comment: Option<&'a str>,
+    /// When set, the source branch of each push request closed is deleted, unless it lives in a fork or must be
+    /// spared.
+    delete_source_branch: Option<&'a SparedBranches>,
+    /// How long to wait between one push request and the next.
+    delay: Duration,
+    /// Spaces out closes on the host. When unset, closes are only spaced out within this call.
+    throttle: Option<&'a Throttle>,
+}
+
+/// How many operations closing push requests attempted, and how many of them failed. Deleting the source branch of a
+/// push request counts as an operation of its own, apart from closing it.
+#[derive(Debug, Default, Copy, Clone)]
+struct OperationCounts {
+    attempted: usize,
+    failed: usize,
+}
+
+impl OperationCounts {
+    /// Returns an error summarizing the failures, if there were any.
+    fn into_result(self) -> Result<(), FoxdieError> {
+        if self.failed == 0 {
+            Ok(())
+        } else {
+            Err(FoxdieError::PartialFailure(self.failed, self.attempted))
+        }
+    }
+}
+
+/// The branches that deleting the source branches of closed push requests must leave alone, looked up once for each
+/// repository.
+#[derive(Debug, Default)]
+struct SparedBranches {
+    /// The branches protected on the SCM, along with those it refuses to delete.
+    protected: Vec<ProtectedBranch>,
+    default_branch: Option<String>,
+    /// The push requests that were open before any were closed.
+    open_push_requests: Vec<PushRequest>,
+}
+
+impl SparedBranches {
+    async fn look_up(api_client: &SCMProvider) -> ApiResult<Self> {
+        let (mut protected, undeletable, default_branch, open_push_requests) = futures::try_join!(
+            api_client.list_protected_branches(),
+            api_client.list_undeletable_branches(),
+            api_client.default_branch(),
+            api_client.list_push_requests(PushRequestState::Opened),
+        )?;
+        protected.extend(undeletable);
+        Ok(SparedBranches {
+            protected,
+            default_branch,
+            open_push_requests,
+        })
+    }
+
+    /// Explains why the source branch of `pr` must not be deleted, if it mustn't: it is protected, it is the default
+    /// branch, or another open push request is from or into it.
+    fn reason_to_spare(&self, pr: &PushRequest) -> Option<String> {
+        let branch = &pr.source_branch;
+        if let Some(protected) = self
+            .protected
+            .iter()
+            .find(|protected| protected.matches_branch(branch))
+        {
+            return Some(format!(
+                "it is protected by the {} rule {}",
+                protected.source, protected.pattern
+            ));
+        }
+        if self.default_branch.as_ref() == Some(branch) {
+            return Some(String::from("it is the default branch"));
+        }
+        self.open_push_requests
+            .iter()
+            .find(|other| {
+                other.id != pr.id
+                    && ((other.source_project == pr.source_project
+                        && &other.source_branch == branch)
+                        || (other.target_project == pr.source_project
+                            && &other.target_branch == branch))
+            })
+            .map(|other| format!("#{} is still open from or into it", other.id))
+    }
+}
+
+/// Spaces out the push requests closed on one host, so that cleaning up several of its repositories at once doesn't
+/// defeat the delay between closes.
+#[derive(Debug, Default)]
+struct Throttle {
+    last_close: Mutex<Option<Instant>>,
+}
+
+impl Throttle {
+    /// Waits until at least `delay` has passed since the last wait on this throttle finished.
+    async fn wait(&self, delay: Duration) {
+        let mut last_close = self.last_close.lock().await;
+        if let Some(elapsed) = last_close.map(|last| last.elapsed()) {
+            if elapsed < delay {
+                tokio::time::sleep(delay - elapsed).await;
+            }
+        }
+        *last_close = Some(Instant::now());
+    }
+}
+
+/// The `Throttle` for each host, shared by all of its repositories cleaned up in a run.
+#[derive(Debug, Default)]
+struct HostThrottles {
+    hosts: sync::Mutex<HashMap<String, Arc<Throttle>>>,
+}
+
+impl HostThrottles {
+    fn for_url(&self, url: &str) -> Arc<Throttle> {
+        let host = what_git::hostname(url).unwrap_or_default();
+        self.hosts
+            .lock()
+            .expect("The throttle lock was poisoned.")
+            .entry(host)
+            .or_default()
+            .clone()
+    }
+}
+
+/// Which of a push request's dates is compared against the cutoff to decide whether it is stale.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum StalenessField {
+    /// When the push request was opened. Unlike the update date, it isn't moved by bots touching the push request.
+    Created,
+    /// When the push request was last updated.
+    Updated,
+}
+
+impl StalenessField {
+    fn date_of(self, pr: &PushRequest) -> &DateTime<FixedOffset> {
+        match self {
+            StalenessField::Created => &pr.created_at,
+            StalenessField::Updated => &pr.updated_at,
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            StalenessField::Created => "created",
+            StalenessField::Updated => "last updated",
+        }
+    }
+}
+
+/// Cleans up push requests in each of `urls`, `opts.concurrency` repositories at a time, adding a summary for each to
+/// `summaries` in order of URL. A failure in one repository is logged and recorded in its summary without stopping the
+/// rest. With a single repository, its error is returned as is; otherwise, an error counting the repositories that
+/// failed is.
+pub async fn clean_push_requests_in_repos(
+    urls: &[String],
+    opts: &Options<'_>,
+    summaries: &mut Vec<CleanupSummary>,
+) -> Result<(), FoxdieError> {
+    let throttles = HostThrottles::default();
+    let mut outcomes = for_each_concurrently(urls, opts.concurrency, |url| {
+        let throttle = throttles.for_url(url);
+        async move {
+            if urls.len() > 1 {
+                info!("\nCleaning up push requests in {}", url);
+            }
+            let mut summary = CleanupSummary::new(url);
+            let mut planned = vec![];
+            let result =
+                clean_push_requests_on_host(url, opts, &throttle, &mut summary, &mut planned).await;
+            (summary, planned, result)
+        }
+    })
+    .await;
+    outcomes.sort_by(|(a, ..), (b, ..)| a.repo_url.cmp(&b.repo_url));
+    let mut changes = vec![];
+    let outcomes = outcomes
+        .into_iter()
+        .map(|(summary, mut planned, result)| {
+            changes.append(&mut planned);
+            (summary, result)
+        })
+        .collect();
+    let result = record_outcomes(outcomes, summaries);
+    // A plan is only written when every repository was planned, so that it is never taken for a complete one.
+    if let (Ok(()), Some(path)) = (&result, opts.plan_out) {
+        SavedPlan { changes }.write(path)?;
+    }
+    result
+}
+
+/// Cleans up push requests in each project of the Gitlab group at the path `group` on the Gitlab instance at `url`,
+/// adding a summary for each to `summaries` in the order Gitlab lists them, and carrying on past failures like
+/// `clean_push_requests_in_repos`. The projects are cleaned up one at a time, so that `opts.limit` caps the push requests
+/// closed across the whole group and `opts.delay` spaces out every close in it.
+pub async fn clean_push_requests_in_group(
+    url: &str,
+    group: &str,
+    opts: &Options<'_>,
+    summaries: &mut Vec<CleanupSummary>,
+) -> Result<(), FoxdieError> {
+    let projects = get_api_clients_for_gitlab_group(
+        url,
+        group,
+        opts.token,
+        &opts.client_options,
+        opts.page_size,
+        opts.allowed_hosts,
+        opts.raw_response_dir,
+    )
+    .await?;
+    info!("Found {} projects in group {}.", projects.len(), group);
+    let throttle = Throttle::default();
+    let mut remaining = opts.limit;
+    let mut changes = vec![];
+    let mut outcomes = vec![];
+    for (project_url, api_client) in &projects {
+        if remaining == Some(0) {
+            info!(
+                "Reached the limit; leaving the rest of group {} as is.",
+                group
+            );
+            break;
+        }
+        info!("\nCleaning up push requests in {}", project_url);
+        let mut summary = CleanupSummary::new(project_url);
+        summary.provider = Some(api_client.name().to_string());
+        let project_opts = Options {
+            limit: remaining,
+            ..opts.clone()
+        };
+        let planned_before = changes.len();
+        let result = clean_push_requests_with_client(
+            api_client,
+            &project_opts,
+            &throttle,
+            &mut summary,
+            &mut changes,
+        )
+        .await;
+        remaining = remaining.map(|limit| limit.saturating_sub(changes.len() - planned_before));
+        outcomes.push((summary, result));
+    }
+    let result = record_outcomes(outcomes, summaries);
+    if let (Ok(()), Some(path)) = (&result, opts.plan_out) {
+        SavedPlan { changes }.write(path)?;
+    }
+    result
+}
+
+/// Adds the summary of each of `outcomes`, one for each repository in a run, to `summaries`, logging and recording the
+/// error of each repository that failed. With a single repository, its error is returned as is; otherwise, an error
+/// counting the repositories that failed is.
+fn record_outcomes(
+    outcomes: Vec<(CleanupSummary, Result<(), FoxdieError>)>,
+    summaries: &mut Vec<CleanupSummary>,
+) -> Result<(), FoxdieError> {
+    let repo_count = outcomes.len();
+    let mut errors = vec![];
+    for (mut summary, result) in outcomes {
+        if let Err(err) = result {
+            if repo_count > 1 {
+                error!(
+                    "Failed to clean up push requests in {}: {}",
+                    summary.repo_url, err
+                );
+            }
+            summary.error = Some(err.to_string());
+            errors.push(err);
+        }
+        summaries.push(summary);
+    }
+    combined_result(errors, repo_count)
+}
+
+/// Closes exactly the push requests `plan` lists, as written by an earlier dry run with `plan_out`, rather than
+/// deciding afresh which push requests are stale. A push request that is no longer open is skipped with a warning. Adds
+/// a summary for each repository in the plan to `summaries`, in order of URL, carrying on past failures like
+/// `clean_push_requests_in_repos`.
+pub async fn clean_push_requests_from_plan(
+    plan: &SavedPlan,
+    opts: &Options<'_>,
+    summaries: &mut Vec<CleanupSummary>,
+) -> Result<(), FoxdieError> {
+    let mut planned_by_repo = BTreeMap::<&str, Vec<i32>>::new();
+    for change in &plan.changes {
+        if let PlannedChange::ClosePushRequest { repo_url, id, .. } = change {
+            planned_by_repo.entry(repo_url).or_default().push(*id);
+        }
+    }
+    if planned_by_repo.is_empty() {
+        info!("The plan has no push requests to close.");
+    }
+    let repo_count = planned_by_repo.len();
+    let throttles = HostThrottles::default();
+    let mut outcomes = vec![];
+    for (url, ids) in planned_by_repo {
+        if repo_count > 1 {
+            info!("\nClosing planned push requests in {}", url);
+        }
+        let mut summary = CleanupSummary::new(url);
+        let result = match get_api_client_for_url(
+            url,
+            opts.token,
+            &opts.client_options,
+            opts.project_id,
+            opts.page_size,
+            opts.allowed_hosts,
+            opts.raw_response_dir,
+        )
+        .await
+        {
+            Ok(api_client) => {
+                summary.provider = Some(api_client.name().to_string());
+                let throttle = throttles.for_url(url);
+                close_planned_push_requests(&api_client, &ids, opts, &throttle, &mut summary).await
+            }
+            Err(err) => Err(err),
+        };
+        outcomes.push((summary, result));
+    }
+    record_outcomes(outcomes, summaries)
+}
+
+/// Closes the push requests numbered `ids` through `api_client`, skipping those that are no longer open.
+async fn close_planned_push_requests(
+    api_client: &SCMProvider,
+    ids: &[i32],
+    opts: &Options<'_>,
+    throttle: &Throttle,
+    summary: &mut CleanupSummary,
+) -> Result<(), FoxdieError> {
+    if opts.should_delete || !opts.skip_auth_check {
+        verify_token(api_client).await?;
+    }
+    let push_requests = api_client
+        .list_push_requests(PushRequestState::Opened)
+        .await?
+        .into_iter()
+        .filter(|pr| pr.is_open && ids.contains(&pr.id))
+        .collect::<Vec<_>>();
+    for id in ids
+        .iter()
+        .filter(|id| !push_requests.iter().any(|pr| pr.id == **id))
+    {
+        warn!("Skipping #{} from the plan, as it is no longer open.", id);
+    }
+    summary.examined = ids.len();
+    summary.eligible = push_requests.len();
+    summary.skipped = ids.len() - push_requests.len();
+    summary.push_requests = Some(PushRequestIds {
+        eligible: push_requests.iter().map(|pr| pr.id).collect(),
+        closed: vec![],
+    });
+    print_push_requests_to_close(&push_requests, ids.len(), None);
+    close_push_requests_if_needed(api_client, &push_requests, opts, throttle, summary).await
+}
+
+/// Runs `f` on each of `urls`, at most `concurrency` at a time, and returns what each returned in the order they
+/// finished.
+async fn for_each_concurrently<'a, F, Fut>(
+    urls: &'a [String],
+    concurrency: usize,
+    f: F,
+) -> Vec<Fut::Output>
+where
+    F: FnMut(&'a String) -> Fut,
+    Fut: Future,
+{
+    stream::iter(urls)
+        .map(f)
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await
+}
+
+pub async fn clean_push_requests(
+    url: &str,
+    opts: &Options<'_>,
+    summary: &mut CleanupSummary,
+) -> Result<(), FoxdieError> {
+    let mut planned = vec![];
+    clean_push_requests_on_host(url, opts, &Throttle::default(), summary, &mut planned).await?;
+    if let Some(path) = opts.plan_out {
+        SavedPlan { changes: planned }.write(path)?;
+    }
+    Ok(())
+}
+
+/// Cleans up stale push requests like `clean_push_requests`, spacing out closes with `throttle`, which is shared with
+/// the other repositories on the same host. The push requests to close are added to `planned`.
+async fn clean_push_requests_on_host(
+    url: &str,
+    opts: &Options<'_>,
+    throttle: &Throttle,
+    summary: &mut CleanupSummary,
+    planned: &mut Vec<PlannedChange>,
+) -> Result<(), FoxdieError> {
+    let api_client = get_api_client_for_url(
+        url,
+        opts.token,
+        &opts.client_options,
+        opts.project_id,
+        opts.page_size,
+        opts.allowed_hosts,
+        opts.raw_response_dir,
+    )
+    .await?;
+    summary.provider = Some(api_client.name().to_string());
+    clean_push_requests_with_client(&api_client, opts, throttle, summary, planned).await
+}
+
+/// Cleans up stale push requests like `clean_push_requests`, through an `api_client` that has already been set up.
+async fn clean_push_requests_with_client(
+    api_client: &SCMProvider,
+    opts: &Options<'_>,
+    throttle: &Throttle,
+    summary: &mut CleanupSummary,
+    planned: &mut Vec<PlannedChange>,
+) -> Result<(), FoxdieError> {
+    if opts.should_delete || !opts.skip_auth_check {
+        verify_token(api_client).await?;
+    }
+    info!(
+        "Checking for push requests {} before {:?}.",
+        opts.staleness_field.description(),
+        opts.since_date
+    );
+    let all_push_requests = api_client.list_push_requests(opts.state).await?;
+    let all_push_requests_count = all_push_requests.len();
+    if opts.explain {
+        for pr in &all_push_requests {
+            info!("#{}: {}", pr.id, eligibility(pr, opts).explain(pr, opts));
+        }
+    }
+    let mut eligible_push_requests = select_eligible(all_push_requests, opts, summary);
+    let truncated_from = truncate_to_limit(&mut eligible_push_requests, opts.limit);
+    planned.extend(
+        eligible_push_requests
+            .iter()
+            .map(|pr| PlannedChange::ClosePushRequest {
+                repo_url: summary.repo_url.clone(),
+                id: pr.id,
+                url: pr.url.clone(),
+            }),
+    );
+
+    if opts.stream_plan {
+        for pr in &eligible_push_requests {
+            let reason = format!(
+                "{} {}, before {}",
+                opts.staleness_field.description(),
+                opts.staleness_field.date_of(pr).to_rfc3339(),
+                opts.since_date.to_rfc3339()
+            );
+            StreamedChange::close_push_request(&pr.url, reason).stream();
+        }
+    }
+
+    if opts.plan {
+        let mut plan = Plan::default();
+        for pr in &eligible_push_requests {
+            plan.close_push_request(pr.id);
+        }
+        print_plan(&plan, opts.stream_plan || opts.json);
+    } else {
+        print_push_requests_to_close(
+            &eligible_push_requests,
+            all_push_requests_count,
+            truncated_from,
+        );
+    }
+    close_push_requests_if_needed(api_client, &eligible_push_requests, opts, throttle, summary)
+        .await
+}
+
+/// Closes `push_requests` unless this is a dry run, or the repository is archived. On a dry run, what would be done
+/// around closing them is logged instead.
+async fn close_push_requests_if_needed(
+    api_client: &SCMProvider,
+    push_requests: &[PushRequest],
+    opts: &Options<'_>,
+    throttle: &Throttle,
+    summary: &mut CleanupSummary,
+) -> Result<(), FoxdieError> {
+    if !opts.should_delete {
+        if let Some(comment) = opts.comment {
+            info!(
+                "Would comment on each push request before closing it: {:?}",
+                comment
+            );
+        }
+        if opts.delete_source_branch {
+            info!(
+                "Would delete the source branch of each push request not opened from a fork, unless it is protected, \
+                 the default branch, or in use by another open push request"
+            );
+        }
+        return Ok(());
+    }
+    if is_archived(api_client).await {
+        warn!(
+            "{} is archived, so it is read-only. Leaving its push requests open.",
+            summary.repo_url
+        );
+        return Ok(());
+    }
+    info!("\nPreparing to close push requests...");
+    let spared_branches = if opts.delete_source_branch {
+        Some(SparedBranches::look_up(api_client).await?)
+    } else {
+        None
+    };
+    let mut journal = opts.journal_path.map(Journal::open).transpose()?;
+    close_push_requests_in_batches(
+        api_client,
+        push_requests,
+        &CloseOptions {
+            comment: opts.comment,
+            delete_source_branch: spared_branches.as_ref(),
+            delay: opts.delay,
+            throttle: Some(throttle),
+        },
+        journal.as_mut(),
+        opts.batching.as_ref(),
+        summary,
+    )
+    .await
+}
+
+/// Returns the push requests among `push_requests` that are eligible to be closed, tallying them in `summary`. Stale
+/// push requests that aren't eligible, such as drafts or those from forks, are tallied as skipped.
+fn select_eligible(
+    push_requests: Vec<PushRequest>,
+    opts: &Options<'_>,
+    summary: &mut CleanupSummary,
+) -> Vec<PushRequest> {
+    summary.examined += push_requests.len();
+    let stale_count = push_requests.iter().filter(|pr| is_stale(pr, opts)).count();
+    let eligible = push_requests
+        .into_iter()
+        .filter(|pr| is_eligible(pr, opts))
+        .collect::<Vec<_>>();
+    summary.eligible = eligible.len();
+    summary.skipped += stale_count - eligible.len();
+    summary.push_requests = Some(PushRequestIds {
+        eligible: eligible.iter().map(|pr| pr.id).collect(),
+        closed: vec![],
+    });
+    eligible
+}
+
+fn is_stale(pr: &PushRequest, opts: &Options<'_>) -> bool {
+    opts.staleness_field.date_of(pr) < opts.since_date
+}
+
+fn is_eligible(pr: &PushRequest, opts: &Options<'_>) -> bool {
+    eligibility(pr, opts) == Eligibility::Eligible
+}
+
+/// Whether a push request is eligible to be closed, and if not, why not. Logged by `--explain`.
+#[derive(Debug, Clone, PartialEq)]
+enum Eligibility {
+    Eligible,
+    /// The push request has already been closed or merged.
+    NotOpen,
+    /// The push request's staleness date isn't before the cutoff.
+    Recent,
+    /// The push request was opened from a fork.
+    FromFork,
+    /// The push request is a draft, and drafts are skipped.
+    Draft,
+    /// The push request wasn't opened by a selected author.
+    UnselectedAuthor,
+    /// The push request's source branch matches this `--exclude` pattern.
+    Excluded(String),
+    /// The push request's target branch matches none of the `--target-branch` patterns.
+    UnselectedTarget,
+}
+
+impl Eligibility {
+    /// Explains the eligibility of `pr` in a sentence fragment.
+    fn explain(&self, pr: &PushRequest, opts: &Options<'_>) -> String {
+        let date = opts.staleness_field.date_of(pr).format("%Y-%m-%d");
+        let since = opts.since_date.format("%Y-%m-%d");
+        let field = opts.staleness_field.description();
+        match self {
+            Eligibility::Eligible => {
+                format!("eligible, as it was {} {}, before {}", field, date, since)
+            }
+            Eligibility::NotOpen if pr.is_merged => "kept, as it is already merged".to_string(),
+            Eligibility::NotOpen => "kept, as it is already closed".to_string(),
+            Eligibility::Recent => {
+                format!("kept, as it was {} {}, not before {}", field, date, since)
+            }
+            Eligibility::FromFork => "skipped, as it was opened from a fork".to_string(),
+            Eligibility::Draft => "skipped, as it is a draft".to_string(),
+            Eligibility::UnselectedAuthor => format!(
+                "skipped, as its author {} isn't selected",
+                pr.author.as_deref().unwrap_or("(unknown)")
+            ),
+            Eligibility::Excluded(pattern) => format!(
+                "skipped, as its source branch {} matches --exclude {}",
+                pr.source_branch, pattern
+            ),
+            Eligibility::UnselectedTarget => format!(
+                "skipped, as its target branch {} matches no --target-branch",
+                pr.target_branch
+            ),
+        }
+    }
+}
+
+fn eligibility(pr: &PushRequest, opts: &Options<'_>) -> Eligibility {
+    if !pr.is_open {
+        Eligibility::NotOpen
+    } else if !is_stale(pr, opts) {
+        Eligibility::Recent
+    } else if pr.target_project != pr.source_project {
+        Eligibility::FromFork
+    } else if opts.skip_drafts && pr.is_draft {
+        Eligibility::Draft
+    } else if !opts.authors.allows(pr.author.as_deref()) {
+        Eligibility::UnselectedAuthor
+    } else if let Some(pattern) = opts
+        .exclude
+        .iter()
+        .find(|pattern| pattern.matches(&pr.source_branch))
+    {
+        Eligibility::Excluded(pattern.to_string())
+    } else if !opts.target_branches.is_empty()
+        && !opts
+            .target_branches
+            .iter()
+            .any(|pattern| pattern.matches(&pr.target_branch))
+    {
+        Eligibility::UnselectedTarget
+    } else {
+        Eligibility::Eligible
+    }
+}
+
+/// Closes each of `push_requests`, carrying on past individual failures, and records each close in `journal`. When
+/// a comment is given, it is posted on each push request first, and a push request that can't be commented on is left
+/// open. With `delete_source_branch`, the source branch of each push request closed is deleted afterwards, unless it
+/// lives in a fork or must be spared. Waits out the delay before each push request after the first, or since the last close on the host
+/// when a throttle is given. Returns an error summarizing the failures, if there were any.
+async fn close_push_requests(
+    api_client: &SCMProvider,
+    push_requests: &[PushRequest],
+    close_opts: &CloseOptions<'_>,
+    journal: Option<&mut Journal>,
+    summary: &mut CleanupSummary,
+) -> Result<(), FoxdieError> {
+    count_close_push_requests(api_client, push_requests, close_opts, journal, summary)
+        .await?
+        .into_result()
+}
+
+/// Closes `push_requests` like `close_push_requests`, returning how many operations were attempted and failed.
+async fn count_close_push_requests(
+    api_client: &SCMProvider,
+    push_requests: &[PushRequest],
+    close_opts: &CloseOptions<'_>,
+    mut journal: Option<&mut Journal>,
+    summary: &mut CleanupSummary,
+) -> Result<OperationCounts, FoxdieError> {
+    let mut counts = OperationCounts {
+        attempted: push_requests.len(),
+        failed: 0,
+    };
+    let mut closed = vec![];
+    let own_throttle = Throttle::default();
+    let throttle = close_opts.throttle.unwrap_or(&own_throttle);
+    for pr in push_requests {
+        // Closing a push request that has since been merged or locked fails, so leave it be.
+        if !pr.is_open {
+            debug!("Skipping #{}, as it is no longer open.", pr.id);
+            continue;
+        }
+        throttle.wait(close_opts.delay).await;
+        if let Some(body) = close_opts.comment {
+            if let Err(err) = api_client.comment_on_push_request(pr.id, body).await {
+                error!("Failed to comment on #{}, leaving it open: {}", pr.id, err);
+                counts.failed += 1;
+                continue;
+            }
+        }
+        let closed_result = match api_client.close_push_request(pr.id).await {
+            Err(err) => {
+                warn!(
+                    "Failed to close #{}, retrying if it is still open: {}",
+                    pr.id, err
+                );
+                api_client.ensure_closed(pr.id).await
+            }
+            ok => ok,
+        };
+        match closed_result {
+            Ok(()) => {
+                info!("Closed #{}", pr.id);
+                closed.push(pr.id);
+                if let Some(ref mut journal) = journal {
+                    journal.record(JournalAction::ClosedPushRequest {
+                        id: pr.id,
+                        url: pr.url.to_string(),
+                        source_branch: pr.source_branch.to_string(),
+                        source_sha: pr.source_sha.clone(),
+                    })?;
+                }
+                let spared_branches = close_opts
+                    .delete_source_branch
+                    .filter(|_| pr.source_project == pr.target_project);
+                if let Some(reason) = spared_branches.and_then(|spared| spared.reason_to_spare(pr))
+                {
+                    warn!(
+                        "Closed #{}, but left its source branch {} alone, as {}.",
+                        pr.id, pr.source_branch, reason
+                    );
+                } else if spared_branches.is_some() {
+                    counts.attempted += 1;
+                    match api_client.delete_branch(&pr.source_branch).await {
+                        Ok(()) => info!(
+                            "Deleted {}, the source branch of #{}",
+                            pr.source_branch, pr.id
+                        ),
+                        Err(err) => {
+                            error!(
+                                "Closed #{}, but failed to delete its source branch {}: {}",
+                                pr.id, pr.source_branch, err
+                            );
+                            counts.failed += 1;
+                        }
+                    }
+                }
+            }
+            Err(err) => {
+                error!("Failed to close #{}: {}", pr.id, err);
+                counts.failed += 1;
+            }
+        }
+    }
+    info!(
+        "All done closing push requests. Closed {} of {}.",
+        closed.len(),
+        push_requests.len()
+    );
+    summary.closed += closed.len();
+    summary
+        .push_requests
+        .get_or_insert_with(Default::default)
+        .closed
+        .extend(closed);
+    Ok(counts)
+}
+
+/// Closes `push_requests` like `close_push_requests`, but in batches when `batching` is set, stopping at a checkpoint
+/// before each batch after the first. Push requests are left open if the operator declines to carry on at a checkpoint.
+async fn close_push_requests_in_batches(
+    api_client: &SCMProvider,
+    push_requests: &[PushRequest],
+    close_opts: &CloseOptions<'_>,
+    mut journal: Option<&mut Journal>,
+    batching: Option<&Batching>,
+    summary: &mut CleanupSummary,
+) -> Result<(), FoxdieError> {
+    let batching = match batching {
+        Some(batching) => batching,
+        None => {
+            return close_push_requests(api_client, push_requests, close_opts, journal, summary)
+                .await
+        }
+    };
+    let mut counts = OperationCounts::default();
+    let mut attempted_count = 0;
+    for (index, batch) in push_requests.chunks(batching.size.max(1)).enumerate() {
+        if index > 0 && !batch_checkpoint(batching, attempted_count, push_requests.len()).await? {
+            info!(
+                "Stopping early. {} push requests were left open.",
+                push_requests.len() - attempted_count
+            );
+            break;
+        }
+        attempted_count += batch.len();
+        let batch_counts = count_close_push_requests(
+            api_client,
+            batch,
+            close_opts,
+            journal.as_deref_mut(),
+            summary,
+        )
+        .await?;
+        counts.attempted += batch_counts.attempted;
+        counts.failed += batch_counts.failed;
+    }
+    counts.into_result()
+}
+
+/// Waits at the checkpoint before the next batch, once `done_count` of `total_count` push requests have been worked
+/// through. Returns whether to carry on with the next batch.
+async fn batch_checkpoint(
+    batching: &Batching,
+    done_count: usize,
+    total_count: usize,
+) -> Result<bool, FoxdieError> {
+    if batching.confirm && io::stdin().is_terminal() {
+        eprint!(
+            "Worked through {} of {} push requests. Close the next batch? [y/N] ",
+            done_count, total_count
+        );
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        Ok(is_confirmation(&answer))
+    } else {
+        info!(
+            "Worked through {} of {} push requests. Waiting {} seconds before the next batch; interrupt to stop.",
+            done_count,
+            total_count,
+            batching.delay.as_secs()
+        );
+        tokio::time::sleep(batching.delay).await;
+        Ok(true)
+    }
+}
+
+/// Reopens each of the push requests numbered `ids`, along with every push request closed in the journal at
+/// `journal_path`, carrying on past individual failures. Push requests from the journal are only reopened if their
+/// source branch still exists, or can be recreated from the commit it pointed to when they were closed. Returns an error summarizing the failures, if there were any.
+pub async fn reopen_push_requests(
+    url: &str,
+    ids: &[i32],
+    journal_path: Option<&str>,
+    token: &str,
+    client_options: &ClientOptions<'_>,
+    allowed_hosts: Option<&[String]>,
+    raw_response_dir: Option<&Path>,
+) -> Result<(), FoxdieError> {
+    let api_client = get_api_client_for_url(
+        url,
+        token,
+        client_options,
+        None,
+        None,
+        allowed_hosts,
+        raw_response_dir,
+    )
+    .await?;
+    verify_token(&api_client).await?;
+    let mut targets = ids.iter().map(|id| (*id, None)).collect::<Vec<_>>();
+    if let Some(path) = journal_path {
+        for entry in read_journal(path)? {
+            match entry.action {
+                JournalAction::ClosedPushRequest {
+                    id,
+                    source_branch,
+                    source_sha,
+                    ..
+                } => targets.push((id, Some((source_branch, source_sha)))),
+            }
+        }
+    }
+
+    info!("Preparing to reopen {} push requests...", targets.len());
+    let mut failed_count = 0;
+    let mut skipped_count = 0;
+    for (id, source_branch) in &targets {
+        if let Some((branch, sha)) = source_branch {
+            match api_client.branch_exists(branch).await {
+                Ok(true) => {}
+                Ok(false) => match sha {
+                    Some(sha) => match api_client.create_branch(branch, sha).await {
+                        Ok(()) => info!(
+                            "Recreated {}, the source branch of #{}, at {}",
+                            branch, id, sha
+                        ),
+                        Err(err) => {
+                            error!(
+                                "Failed to recreate {}, the source branch of #{}: {}",
+                                branch, id, err
+                            );
+                            failed_count += 1;
+                            continue;
+                        }
+                    },
+                    None => {
+                        warn!(
+                            "Refusing to reopen #{}, as its source branch {} no longer exists.",
+                            id, branch
+                        );
+                        skipped_count += 1;
+                        continue;
+                    }
+                },
+                Err(err) => {
+                    error!("Failed to look up the source branch of #{}: {}", id, err);
+                    failed_count += 1;
+                    continue;
+                }
+            }
+        }
+        match api_client.reopen_push_request(*id).await {
+            Ok(()) => info!("Reopened #{}", id),
+            Err(err) => {
+                error!("Failed to reopen #{}: {}", id, err);
+                failed_count += 1;
+            }
+        }
+    }
+    info!(
+        "All done reopening push requests. Reopened {} of {}, skipped {}.",
+        targets.len() - failed_count - skipped_count,
+        targets.len(),
+        skipped_count
+    );
+    if failed_count == 0 {
+        Ok(())
+    } else {
+        Err(FoxdieError::PartialFailure(failed_count, targets.len()))
+    }
+}
+
+fn print_push_requests_to_close(
+    push_requests: &[PushRequest],
+    all_push_requests_count: usize,
+    truncated_from: Option<usize>,
+) {
+    info!(
+        "{}",
+        format_push_requests_to_close(push_requests, all_push_requests_count, truncated_from)
+    );
+}
+
+fn format_push_requests_to_close(
+    push_requests: &[PushRequest],
+    all_push_requests_count: usize,
+    truncated_from: Option<usize>,
+) -> String {
+    format!(
+        "Found {} eligible push requests out of {} total{}{}",
+        truncated_from.unwrap_or(push_requests.len()),
+        all_push_requests_count,
+        truncation_message(push_requests.len(), truncated_from),
+        if !push_requests.is_empty() {
+            let push_requests_message = push_requests
+                .iter()
+                .map(|pr| format!("• #{}: {} ({})\n", pr.id, pr.title, pr.url))
+                .collect::<String>();
+            format!(":\n{}", push_requests_message)
+        } else {
+            String::from("")
+        }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_support::{github_pull_json, mock_provider, push_request};
+    use mockito::{mock, Matcher};
+    use what_git::{ProviderCache, SCMKind};
+
+    static EVERYONE: AuthorFilter = AuthorFilter {
+        include: Vec::new(),
+        exclude: Vec::new(),
+    };
+
+    fn options(since_date: &DateTime<FixedOffset>, skip_drafts: bool) -> Options<'_> {
+        Options {
+            skip_drafts,
+            ..Options::new(since_date, "token", &EVERYONE)
+        }
+    }
+
+    #[test]
+    fn test_is_eligible_with_drafts() {
+        let since_date = DateTime::parse_from_rfc3339("2020-01-01T00:00:00-05:00").unwrap();
+        let draft = PushRequest {
+            is_draft: true,
+            ..push_request(1)
+        };
+        let ready = push_request(2);
+
+        assert!(is_eligible(&draft, &options(&since_date, false)));
+        assert!(!is_eligible(&draft, &options(&since_date, true)));
+        assert!(is_eligible(&ready, &options(&since_date, true)));
+    }
+
+    #[test]
+    fn test_is_eligible_by_staleness_field() {
+        let since_date = DateTime::parse_from_rfc3339("2020-01-01T00:00:00-05:00").unwrap();
+        // Opened long ago, but recently bumped by a bot.
+        let bumped = PushRequest {
+            updated_at: DateTime::parse_from_rfc3339("2020-06-01T00:00:00-05:00").unwrap(),
+            ..push_request(1)
+        };
+        let by_created = Options {
+            staleness_field: StalenessField::Created,
+            ..options(&since_date, false)
+        };
+        let by_updated = options(&since_date, false);
+
+        assert!(is_eligible(&bumped, &by_created));
+        assert!(!is_eligible(&bumped, &by_updated));
+        assert!(is_eligible(&push_request(2), &by_created));
+        assert!(is_eligible(&push_request(2), &by_updated));
+    }
+
+    #[test]
+    fn test_eligibility() {
+        let since_date = DateTime::parse_from_rfc3339("2020-01-01T00:00:00-05:00").unwrap();
+        let exclude = [Pattern::new("branch-1*").unwrap()];
+        let opts = Options {
+            exclude: &exclude,
+            ..options(&since_date, true)
+        };
+        let recent = PushRequest {
+            updated_at: DateTime::parse_from_rfc3339("2020-06-01T00:00:00-05:00").unwrap(),
+            ..push_request(2)
+        };
+        let from_fork = PushRequest {
+            source_project: 2,
+            ..push_request(3)
+        };
+        let draft = PushRequest {
+            is_draft: true,
+            ..push_request(4)
+        };
+
+        let explained = |pr: &PushRequest| {
+            let eligibility = eligibility(pr, &opts);
+            let explanation = eligibility.explain(pr, &opts);
+            (eligibility, explanation)
+        };
+        assert_eq!(
+            explained(&push_request(2)),
+            (
+                Eligibility::Eligible,
+                String::from("eligible, as it was last updated 2019-01-01, before 2020-01-01")
+            )
+        );
+        assert_eq!(
+            explained(&recent),
+            (
+                Eligibility::Recent,
+                String::from("kept, as it was last updated 2020-06-01, not before 2020-01-01")
+            )
+        );
+        assert_eq!(explained(&from_fork).0, Eligibility::FromFork);
+        assert_eq!(explained(&draft).0, Eligibility::Draft);
+        assert_eq!(
+            explained(&push_request(12)),
+            (
+                Eligibility::Excluded(String::from("branch-1*")),
+                String::from("skipped, as its source branch branch-12 matches --exclude branch-1*")
+            )
+        );
+    }
+
+    #[test]
+    fn test_is_eligible_by_author() {
+        let since_date = DateTime::parse_from_rfc3339("2020-01-01T00:00:00-05:00").unwrap();
+        let by = |author: Option<&str>| PushRequest {
+            author: author.map(String::from),
+            ..push_request(1)
+        };
+        let included = AuthorFilter {
+            include: vec![String::from("departed-dev")],
+            exclude: vec![],
+        };
+        let excluded = AuthorFilter {
+            include: vec![],
+            exclude: vec![String::from("renovate[bot]")],
+        };
+        let including = Options {
+            authors: &included,
+            ..options(&since_date, false)
+        };
+        let excluding = Options {
+            authors: &excluded,
+            ..options(&since_date, false)
+        };
+
+        assert!(is_eligible(&by(Some("Departed-Dev")), &including));
+        assert!(!is_eligible(&by(Some("octocat")), &including));
+        assert!(!is_eligible(&by(None), &including));
+        assert!(!is_eligible(&by(Some("renovate[bot]")), &excluding));
+        assert!(is_eligible(&by(Some("octocat")), &excluding));
+        assert!(is_eligible(&by(None), &excluding));
+    }
+
+    #[test]
+    fn test_select_eligible_tallies_summary() {
+        let since_date = DateTime::parse_from_rfc3339("2020-01-01T00:00:00-05:00").unwrap();
+        let exclude = [Pattern::new("branch-3").unwrap()];
+        let opts = Options {
+            exclude: &exclude,
+            ..options(&since_date, true)
+        };
+        let push_requests = vec![
+            push_request(1),
+            PushRequest {
+                is_draft: true,
+                ..push_request(2)
+            },
+            push_request(3),
+            PushRequest {
+                updated_at: DateTime::parse_from_rfc3339("2020-06-01T00:00:00-05:00").unwrap(),
+                ..push_request(4)
+            },
+        ];
+        let mut summary = CleanupSummary::default();
+
+        let eligible = select_eligible(push_requests, &opts, &mut summary);
+
+        assert_eq!(eligible.iter().map(|pr| pr.id).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(summary.examined, 4);
+        assert_eq!(summary.eligible, 1);
+        assert_eq!(summary.skipped, 2);
+        assert_eq!(summary.closed, 0);
+    }
+
+    #[test]
+    fn test_is_eligible_with_exclude() {
+        let since_date = DateTime::parse_from_rfc3339("2020-01-01T00:00:00-05:00").unwrap();
+        let exclude = [Pattern::new("branch-1*").unwrap()];
+        let opts = Options {
+            exclude: &exclude,
+            ..options(&since_date, false)
+        };
+
+        assert!(!is_eligible(&push_request(1), &opts));
+        assert!(!is_eligible(&push_request(12), &opts));
+        assert!(is_eligible(&push_request(2), &opts));
+    }
+
+    #[test]
+    fn test_is_eligible_with_target_branches() {
+        let since_date = DateTime::parse_from_rfc3339("2020-01-01T00:00:00-05:00").unwrap();
+        let target_branches = [Pattern::new("main").unwrap()];
+        let opts = Options {
+            target_branches: &target_branches,
+            ..options(&since_date, false)
+        };
+        let targeting = |id: i32, target_branch: &str| PushRequest {
+            target_branch: String::from(target_branch),
+            ..push_request(id)
+        };
+
+        assert!(is_eligible(&targeting(1, "main"), &opts));
+        assert!(!is_eligible(&targeting(2, "master"), &opts));
+        assert!(!is_eligible(&targeting(3, "release/2.0"), &opts));
+
+        let target_branches = [Pattern::new("release/*").unwrap()];
+        let opts = Options {
+            target_branches: &target_branches,
+            ..options(&since_date, false)
+        };
+        assert!(!is_eligible(&targeting(1, "main"), &opts));
+        assert!(is_eligible(&targeting(3, "release/2.0"), &opts));
+        assert!(is_eligible(
+            &targeting(4, "master"),
+            &options(&since_date, false)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_close_push_requests_continues_past_failures() {
+        let api_client = mock_provider(SCMKind::GitHub, "", "foxdie");
+        let mocks = [(1, 200), (2, 500), (3, 200)]
+            .iter()
+            .map(|(id, status)| {
+                mock("PATCH", &*format!("/repos/wayfair/foxdie/pulls/{}", id))
+                    .match_query(Matcher::Any)
+                    .with_status(*status)
+                    .create()
+            })
+            .collect::<Vec<_>>();
+        let push_requests = (1..=3).map(push_request).collect::<Vec<_>>();
+        let mut summary = CleanupSummary::default();
+
+        let result = close_push_requests(
+            &api_client,
+            &push_requests,
+            &CloseOptions::default(),
+            None,
+            &mut summary,
+        )
+        .await;
+
+        for mock in &mocks {
+            mock.assert();
+        }
+        match result {
+            Err(FoxdieError::PartialFailure(1, 3)) => {}
+            other => panic!("Expected one failure out of three, got {:?}", other),
+        }
+        assert_eq!(summary.closed, 2);
+        assert_eq!(summary.push_requests.unwrap().closed, vec![1, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_close_push_requests_comments_before_closing() {
+        let api_client = mock_provider(SCMKind::GitHub, "", "foxdie");
+        let commented = mock("POST", "/repos/wayfair/foxdie/issues/21/comments")
+            .match_body(Matcher::Json(serde_json::json!({ "body": "Stale." })))
+            .with_status(201)
+            .create();
+        let closed = mock("PATCH", "/repos/wayfair/foxdie/pulls/21")
+            .with_status(200)
+            .create();
+        let failed_comment = mock("POST", "/repos/wayfair/foxdie/issues/22/comments")
+            .with_status(500)
+            .create();
+        let not_closed = mock("PATCH", "/repos/wayfair/foxdie/pulls/22")
+            .expect(0)
+            .create();
+        let push_requests = vec![push_request(21), push_request(22)];
+        let mut summary = CleanupSummary::default();
+
+        let result = close_push_requests(
+            &api_client,
+            &push_requests,
+            &CloseOptions {
+                comment: Some("Stale."),
+                ..CloseOptions::default()
+            },
+            None,
+            &mut summary,
+        )
+        .await;
+
+        commented.assert();
+        closed.assert();
+        failed_comment.assert();
+        not_closed.assert();
+        match result {
+            Err(FoxdieError::PartialFailure(1, 2)) => {}
+            other => panic!("Expected one failure out of two, got {:?}", other),
+        }
+        assert_eq!(summary.closed, 1);
+        assert_eq!(summary.push_requests.unwrap().closed, vec![21]);
+    }
+
+    #[tokio::test]
+    async fn test_close_push_requests_deletes_source_branches() {
+        let api_client = mock_provider(SCMKind::GitHub, "", "source-branches");
+        let closed = mock(
+            "PATCH",
+            Matcher::Regex(String::from(
+                "^/repos/wayfair/source-branches/pulls/3[123]$",
+            )),
+        )
+        .with_status(200)
+        .expect(3)
+        .create();
+        let deleted = mock(
+            "DELETE",
+            "/repos/wayfair/source-branches/git/refs/heads/branch-31",
+        )
+        .with_status(204)
+        .create();
+        let from_fork = mock(
+            "DELETE",
+            "/repos/wayfair/source-branches/git/refs/heads/branch-32",
+        )
+        .expect(0)
+        .create();
+        let kept = mock(
+            "DELETE",
+            "/repos/wayfair/source-branches/git/refs/heads/branch-33",
+        )
+        .expect(0)
+        .create();
+        let same_repo = push_request(31);
+        let fork = PushRequest {
+            source_project: 2,
+            ..push_request(32)
+        };
+        let mut summary = CleanupSummary::default();
+
+        let result = close_push_requests(
+            &api_client,
+            &[same_repo, fork],
+            &CloseOptions {
+                delete_source_branch: Some(&SparedBranches::default()),
+                ..CloseOptions::default()
+            },
+            None,
+            &mut summary,
+        )
+        .await;
+        let result_without_flag = close_push_requests(
+            &api_client,
+            &[push_request(33)],
+            &CloseOptions::default(),
+            None,
+            &mut summary,
+        )
+        .await;
+
+        assert!(result.is_ok());
+        assert!(result_without_flag.is_ok());
+        closed.assert();
+        deleted.assert();
+        from_fork.assert();
+        kept.assert();
+        assert_eq!(summary.closed, 3);
+    }
+
+    #[tokio::test]
+    async fn test_close_push_requests_deletes_source_branches_on_gitlab() {
+        let api_client = mock_provider(SCMKind::Gitlab, "", "source-branches");
+        let closed = mock(
+            "PUT",
+            "/api/v4/projects/wayfair%2Fsource-branches/merge_requests/41",
+        )
+        .match_query(Matcher::UrlEncoded("state_event".into(), "close".into()))
+        .with_status(200)
+        .create();
+        let deleted = mock(
+            "DELETE",
+            "/api/v4/projects/wayfair%2Fsource-branches/repository/branches/branch-41",
+        )
+        .with_status(204)
+        .create();
+        let mut summary = CleanupSummary::default();
+
+        let result = close_push_requests(
+            &api_client,
+            &[push_request(41)],
+            &CloseOptions {
+                delete_source_branch: Some(&SparedBranches::default()),
+                ..CloseOptions::default()
+            },
+            None,
+            &mut summary,
+        )
+        .await;
+
+        assert!(result.is_ok());
+        closed.assert();
+        deleted.assert();
+    }
+
+    #[tokio::test]
+    async fn test_close_push_requests_counts_failed_source_branch_deletes_apart() {
+        let api_client = mock_provider(SCMKind::GitHub, "", "failed-delete");
+        let closed = mock("PATCH", "/repos/wayfair/failed-delete/pulls/42")
+            .with_status(200)
+            .create();
+        let not_deleted = mock(
+            "DELETE",
+            "/repos/wayfair/failed-delete/git/refs/heads/branch-42",
+        )
+        .with_status(422)
+        .create();
+        let mut summary = CleanupSummary::default();
+
+        let result = close_push_requests(
+            &api_client,
+            &[push_request(42)],
+            &CloseOptions {
+                delete_source_branch: Some(&SparedBranches::default()),
+                ..CloseOptions::default()
+            },
+            None,
+            &mut summary,
+        )
+        .await;
+
+        // The close went through, so only the delete, one of two operations, failed.
+        assert!(matches!(result, Err(FoxdieError::PartialFailure(1, 2))));
+        closed.assert();
+        not_deleted.assert();
+        assert_eq!(summary.closed, 1);
+    }
+
+    /// Looks up the branches to spare in the GitHub repository `wayfair/<repo>`, which protects `protected`, has
+    /// `default_branch` as its default branch, and has the pull requests `open` open.
+    async fn look_up_spared_branches(
+        repo: &str,
+        protected: &[&str],
+        default_branch: &str,
+        open: serde_json::Value,
+    ) -> SparedBranches {
+        let path = format!("/repos/wayfair/{}", repo);
+        let protected = protected
+            .iter()
+            .map(|name| serde_json::json!({ "name": name }))
+            .collect::<Vec<_>>();
+        let _protected = mock("GET", &*format!("{}/branches", path))
+            .match_query(Matcher::UrlEncoded("protected".into(), "true".into()))
+            .with_body(serde_json::json!(protected).to_string())
+            .create();
+        let _repository = mock("GET", &*path)
+            .with_body(serde_json::json!({ "id": 1, "default_branch": default_branch }).to_string())
+            .create();
+        let _open = mock("GET", &*format!("{}/pulls", path))
+            .match_query(Matcher::Any)
+            .with_body(open.to_string())
+            .create();
+        SparedBranches::look_up(&mock_provider(SCMKind::GitHub, "", repo))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_close_push_requests_spares_protected_source_branches() {
+        let spared = look_up_spared_branches(
+            "spare-protected",
+            &["release/*"],
+            "master",
+            serde_json::json!([]),
+        )
+        .await;
+        let api_client = mock_provider(SCMKind::GitHub, "", "spare-protected");
+        let closed = mock("PATCH", "/repos/wayfair/spare-protected/pulls/81")
+            .with_status(200)
+            .create();
+        let deleted = mock(
+            "DELETE",
+            Matcher::Regex(String::from(
+                "^/repos/wayfair/spare-protected/git/refs/heads/",
+            )),
+        )
+        .expect(0)
+        .create();
+        let release = PushRequest {
+            source_branch: String::from("release/1.0"),
+            ..push_request(81)
+        };
+        let mut summary = CleanupSummary::default();
+
+        let result = close_push_requests(
+            &api_client,
+            &[release],
+            &CloseOptions {
+                delete_source_branch: Some(&spared),
+                ..CloseOptions::default()
+            },
+            None,
+            &mut summary,
+        )
+        .await;
+
+        assert!(result.is_ok());
+        closed.assert();
+        deleted.assert();
+        assert_eq!(summary.closed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_close_push_requests_spares_the_default_branch() {
+        let spared =
+            look_up_spared_branches("spare-default", &[], "develop", serde_json::json!([])).await;
+        let api_client = mock_provider(SCMKind::GitHub, "", "spare-default");
+        let closed = mock("PATCH", "/repos/wayfair/spare-default/pulls/82")
+            .with_status(200)
+            .create();
+        let deleted = mock(
+            "DELETE",
+            Matcher::Regex(String::from(
+                "^/repos/wayfair/spare-default/git/refs/heads/",
+            )),
+        )
+        .expect(0)
+        .create();
+        let from_default = PushRequest {
+            source_branch: String::from("develop"),
+            ..push_request(82)
+        };
+        let mut summary = CleanupSummary::default();
+
+        let result = close_push_requests(
+            &api_client,
+            &[from_default],
+            &CloseOptions {
+                delete_source_branch: Some(&spared),
+                ..CloseOptions::default()
+            },
+            None,
+            &mut summary,
+        )
+        .await;
+
+        assert!(result.is_ok());
+        closed.assert();
+        deleted.assert();
+    }
+
+    #[tokio::test]
+    async fn test_close_push_requests_spares_source_branches_of_other_open_push_requests() {
+        let mut sharing = github_pull_json(84, "open", "2019-01-01T00:00:00Z");
+        sharing["head"]["ref"] = serde_json::json!("branch-83");
+        let spared = look_up_spared_branches(
+            "spare-open",
+            &[],
+            "master",
+            serde_json::json!([
+                github_pull_json(83, "open", "2019-01-01T00:00:00Z"),
+                sharing,
+                github_pull_json(85, "open", "2019-01-01T00:00:00Z"),
+            ]),
+        )
+        .await;
+        let api_client = mock_provider(SCMKind::GitHub, "", "spare-open");
+        let closed = mock(
+            "PATCH",
+            Matcher::Regex(String::from("^/repos/wayfair/spare-open/pulls/8[35]$")),
+        )
+        .with_status(200)
+        .expect(2)
+        .create();
+        let shared = mock(
+            "DELETE",
+            "/repos/wayfair/spare-open/git/refs/heads/branch-83",
+        )
+        .expect(0)
+        .create();
+        let deleted = mock(
+            "DELETE",
+            "/repos/wayfair/spare-open/git/refs/heads/branch-85",
+        )
+        .with_status(204)
+        .create();
+        let mut summary = CleanupSummary::default();
+
+        let result = close_push_requests(
+            &api_client,
+            &[push_request(83), push_request(85)],
+            &CloseOptions {
+                delete_source_branch: Some(&spared),
+                ..CloseOptions::default()
+            },
+            None,
+            &mut summary,
+        )
+        .await;
+
+        assert!(result.is_ok());
+        closed.assert();
+        shared.assert();
+        deleted.assert();
+    }
+
+    #[tokio::test]
+    async fn test_close_push_requests_skips_merged_and_locked_on_gitlab() {
+        let api_client = mock_provider(SCMKind::Gitlab, "", "not-open");
+        let closed = mock(
+            "PUT",
+            "/api/v4/projects/wayfair%2Fnot-open/merge_requests/51",
+        )
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .create();
+        let not_closed = mock(
+            "PUT",
+            Matcher::Regex(r"^/api/v4/projects/wayfair%2Fnot-open/merge_requests/5[23]".into()),
+        )
+        .match_query(Matcher::Any)
+        .with_status(405)
+        .expect(0)
+        .create();
+        let merged = PushRequest {
+            is_open: false,
+            is_merged: true,
+            ..push_request(52)
+        };
+        let locked = PushRequest {
+            is_open: false,
+            ..push_request(53)
+        };
+        let mut summary = CleanupSummary::default();
+
+        let result = close_push_requests(
+            &api_client,
+            &[push_request(51), merged, locked],
+            &CloseOptions::default(),
+            None,
+            &mut summary,
+        )
+        .await;
+
+        assert!(result.is_ok());
+        closed.assert();
+        not_closed.assert();
+        assert_eq!(summary.closed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_close_push_requests_treats_timed_out_close_as_closed() {
+        let api_client = mock_provider(SCMKind::Gitlab, "", "timed-out");
+        // The close went through, but the gateway gave up waiting for the response.
+        let closed = mock(
+            "PUT",
+            "/api/v4/projects/wayfair%2Ftimed-out/merge_requests/71",
+        )
+        .match_query(Matcher::Any)
+        .with_status(504)
+        .expect(1)
+        .create();
+        let fetched = mock(
+            "GET",
+            "/api/v4/projects/wayfair%2Ftimed-out/merge_requests/71",
+        )
+        .with_body(
+            r#"{
+                    "iid": 71,
+                    "title": "PR 71",
+                    "created_at": "2019-01-01T00:00:00Z",
+                    "updated_at": "2019-01-02T00:00:00Z",
+                    "target_branch": "master",
+                    "source_branch": "branch-71",
+                    "source_project_id": 1,
+                    "target_project_id": 1,
+                    "web_url": "https://gitlab.com/wayfair/timed-out/merge_requests/71",
+                    "state": "closed"
+                }"#,
+        )
+        .create();
+        let mut summary = CleanupSummary::default();
+
+        let result = close_push_requests(
+            &api_client,
+            &[push_request(71)],
+            &CloseOptions::default(),
+            None,
+            &mut summary,
+        )
+        .await;
+
+        assert!(result.is_ok());
+        closed.assert();
+        fetched.assert();
+        assert_eq!(summary.closed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_close_push_requests_retries_close_while_still_open() {
+        let api_client = mock_provider(SCMKind::GitHub, "", "still-open");
+        let closed = mock("PATCH", "/repos/wayfair/still-open/pulls/72")
+            .with_status(502)
+            .expect(2)
+            .create();
+        let fetched = mock("GET", "/repos/wayfair/still-open/pulls/72")
+            .with_body(github_pull_json(72, "open", "2019-01-02T00:00:00Z").to_string())
+            .create();
+        let mut summary = CleanupSummary::default();
+
+        let result = close_push_requests(
+            &api_client,
+            &[push_request(72)],
+            &CloseOptions::default(),
+            None,
+            &mut summary,
+        )
+        .await;
+
+        assert!(matches!(result, Err(FoxdieError::PartialFailure(1, 1))));
+        closed.assert();
+        fetched.assert();
+        assert_eq!(summary.closed, 0);
+    }
+
+    #[tokio::test]
+    async fn test_clean_push_requests_in_repos_carries_on_past_failures() {
+        // The repositories' host is known to be GitHub Enterprise, served by the mock server, so that no probes are made.
+        let provider_cache = ProviderCache::default();
+        provider_cache.remember("git.example.com", &mockito::server_url(), SCMKind::GitHub);
+        let user = mock("GET", "/user")
+            .with_status(200)
+            .with_body("{}")
+            .expect(3)
+            .create();
+        let listed = ["listed-repo", "second-listed-repo", "third-listed-repo"]
+            .iter()
+            .map(|repo| {
+                mock("GET", &*format!("/repos/wayfair/{}/pulls", repo))
+                    .match_query(Matcher::Any)
+                    .with_status(200)
+                    .with_body("[]")
+                    .create()
+            })
+            .collect::<Vec<_>>();
+        let since_date = DateTime::parse_from_rfc3339("2020-01-01T00:00:00-05:00").unwrap();
+        let allowed_hosts = [
+            String::from("git.example.com"),
+            String::from("bitbucket.org"),
+            String::from("127.0.0.1"),
+        ];
+        let opts = Options {
+            allowed_hosts: Some(&allowed_hosts),
+            concurrency: 2,
+            client_options: ClientOptions {
+                provider_cache: Some(&provider_cache),
+                ..ClientOptions::default()
+            },
+            ..options(&since_date, false)
+        };
+        let urls = [
+            "https://git.example.com/wayfair/third-listed-repo",
+            "https://git.example.com/wayfair/listed-repo",
+            "https://gitlab.com/wayfair/disallowed-repo",
+            "git@bitbucket.org:wayfair/unsupported-repo.git",
+            "https://git.example.com/wayfair/second-listed-repo",
+        ]
+        .iter()
+        .map(|url| url.to_string())
+        .collect::<Vec<_>>();
+        let mut summaries = vec![];
+
+        let result = clean_push_requests_in_repos(&urls, &opts, &mut summaries).await;
+
+        user.assert();
+        for mock in &listed {
+            mock.assert();
+        }
+        match result {
+            Err(FoxdieError::PartialFailure(2, 5)) => {}
+            other => panic!("Expected two failures out of five, got {:?}", other),
+        }
+        let outcomes = summaries
+            .iter()
+            .map(|summary| (&*summary.repo_url, summary.error.is_some()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("git@bitbucket.org:wayfair/unsupported-repo.git", true),
+                ("https://git.example.com/wayfair/listed-repo", false),
+                ("https://git.example.com/wayfair/second-listed-repo", false),
+                ("https://git.example.com/wayfair/third-listed-repo", false),
+                ("https://gitlab.com/wayfair/disallowed-repo", true),
+            ]
+        );
+        assert_eq!(summaries[1].provider.as_deref(), Some("GitHub"));
+    }
+
+    #[tokio::test]
+    async fn test_clean_push_requests_in_group_limits_across_projects() {
+        let project = |name: &str| {
+            serde_json::json!({
+                "path_with_namespace": format!("wayfair/platform/{}", name),
+                "web_url": format!("https://gitlab.example.com/wayfair/platform/{}", name),
+            })
+        };
+        let first_page = mock("GET", "/api/v4/groups/wayfair%2Fplatform/projects")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("include_subgroups".into(), "true".into()),
+                Matcher::UrlEncoded("archived".into(), "false".into()),
+            ]))
+            .with_header(
+                "link",
+                &format!(
+                    "<{}/api/v4/groups/wayfair%2Fplatform/projects?page=2>; rel=\"next\"",
+                    mockito::server_url()
+                ),
+            )
+            .with_body(serde_json::json!([project("group-alpha")]).to_string())
+            .create();
+        let second_page = mock("GET", "/api/v4/groups/wayfair%2Fplatform/projects")
+            .match_query(Matcher::UrlEncoded("page".into(), "2".into()))
+            .with_body(serde_json::json!([project("group-beta")]).to_string())
+            .create();
+        let _user = mock("GET", "/api/v4/user").with_body("{}").create();
+        let merge_request = |iid: i32| {
+            serde_json::json!({
+                "iid": iid,
+                "title": format!("MR {}", iid),
+                "created_at": "2019-01-01T00:00:00Z",
+                "updated_at": "2019-01-02T00:00:00Z",
+                "target_branch": "master",
+                "source_branch": format!("branch-{}", iid),
+                "source_project_id": 1,
+                "target_project_id": 1,
+                "web_url": format!("https://gitlab.example.com/merge_requests/{}", iid),
+                "work_in_progress": false
+            })
+        };
+        let mocks = [("group-alpha", [1, 2]), ("group-beta", [3, 4])]
+            .iter()
+            .flat_map(|(name, iids)| {
+                let path = format!("/api/v4/projects/wayfair%2Fplatform%2F{}", name);
+                vec![
+                    mock("HEAD", &*format!("{}/merge_requests", path))
+                        .match_query(Matcher::Any)
+                        .create(),
+                    mock("GET", &*format!("{}/merge_requests", path))
+                        .match_query(Matcher::Any)
+                        .with_body(
+                            serde_json::json!(iids
+                                .iter()
+                                .map(|iid| merge_request(*iid))
+                                .collect::<Vec<_>>())
+                            .to_string(),
+                        )
+                        .create(),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let closed = [("group-alpha", 1), ("group-alpha", 2), ("group-beta", 3)]
+            .iter()
+            .map(|(name, iid)| {
+                mock(
+                    "PUT",
+                    &*format!(
+                        "/api/v4/projects/wayfair%2Fplatform%2F{}/merge_requests/{}",
+                        name, iid
+                    ),
+                )
+                .match_query(Matcher::Any)
+                .with_status(200)
+                .create()
+            })
+            .collect::<Vec<_>>();
+        let over_limit = mock(
+            "PUT",
+            "/api/v4/projects/wayfair%2Fplatform%2Fgroup-beta/merge_requests/4",
+        )
+        .match_query(Matcher::Any)
+        .expect(0)
+        .create();
+        let since_date = DateTime::parse_from_rfc3339("2020-01-01T00:00:00-05:00").unwrap();
+        let opts = Options {
+            should_delete: true,
+            limit: Some(3),
+            ..options(&since_date, false)
+        };
+        let mut summaries = vec![];
+
+        clean_push_requests_in_group(
+            &mockito::server_url(),
+            "wayfair/platform",
+            &opts,
+            &mut summaries,
+        )
+        .await
+        .unwrap();
+
+        first_page.assert();
+        second_page.assert();
+        for mock in mocks.iter().chain(&closed) {
+            mock.assert();
+        }
+        over_limit.assert();
+        let closed_by_project = summaries
+            .iter()
+            .map(|summary| (&*summary.repo_url, summary.closed))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            closed_by_project,
+            vec![
+                ("https://gitlab.example.com/wayfair/platform/group-alpha", 2),
+                ("https://gitlab.example.com/wayfair/platform/group-beta", 1),
+            ]
+        );
+        assert_eq!(summaries[0].provider.as_deref(), Some("Gitlab"));
+    }
+
+    #[tokio::test]
+    async fn test_clean_push_requests_in_group_without_group_access() {
+        let _forbidden = mock("GET", "/api/v4/groups/wayfair%2Fforbidden/projects")
+            .match_query(Matcher::Any)
+            .with_status(403)
+            .create();
+        let since_date = DateTime::parse_from_rfc3339("2020-01-01T00:00:00-05:00").unwrap();
+        let mut summaries = vec![];
+
+        let result = clean_push_requests_in_group(
+            &mockito::server_url(),
+            "wayfair/forbidden",
+            &options(&since_date, false),
+            &mut summaries,
+        )
+        .await;
+
+        match result {
+            Err(FoxdieError::Api(err)) => assert_eq!(
+                err.source.downcast_ref::<crate::services::AccessError>(),
+                Some(&crate::services::AccessError {
+                    kind: crate::services::AccessErrorKind::Forbidden,
+                    target: crate::services::AccessTarget::Group("wayfair/forbidden".into()),
+                })
+            ),
+            other => panic!("Expected the group to be forbidden, got {:?}", other),
+        }
+        assert!(summaries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_for_each_concurrently_is_bounded() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let urls = (0..6).map(|i| format!("repo-{}", i)).collect::<Vec<_>>();
+        let in_flight = AtomicUsize::new(0);
+        let most_in_flight = AtomicUsize::new(0);
+
+        let mut finished = for_each_concurrently(&urls, 2, |url| {
+            let (in_flight, most_in_flight) = (&in_flight, &most_in_flight);
+            async move {
+                let now_in_flight = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                most_in_flight.fetch_max(now_in_flight, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                url.clone()
+            }
+        })
+        .await;
+
+        assert_eq!(most_in_flight.load(Ordering::SeqCst), 2);
+        finished.sort();
+        assert_eq!(finished, urls);
+    }
+
+    #[tokio::test]
+    async fn test_throttle_is_shared_by_each_host() {
+        let throttles = HostThrottles::default();
+        let first = throttles.for_url("https://github.com/wayfair/foxdie");
+        let second = throttles.for_url("git@github.com:wayfair/what_git.git");
+        let elsewhere = throttles.for_url("https://gitlab.com/wayfair/foxdie");
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(!Arc::ptr_eq(&first, &elsewhere));
+
+        let delay = Duration::from_millis(50);
+        let started = Instant::now();
+        futures::join!(first.wait(delay), second.wait(delay), elsewhere.wait(delay));
+        let elapsed = started.elapsed();
+        assert!(elapsed >= delay, "{:?}", elapsed);
+    }
+
+    #[tokio::test]
+    async fn test_clean_push_requests_fails_fast_on_rejected_token() {
+        let api_client = mock_provider(SCMKind::GitHub, "/rejected-token", "foxdie");
+        let user = mock("GET", "/rejected-token/user")
+            .with_status(401)
+            .create();
+        let listed = mock("GET", "/rejected-token/repos/wayfair/foxdie/pulls")
+            .match_query(Matcher::Any)
+            .expect(0)
+            .create();
+        let since_date = DateTime::parse_from_rfc3339("2020-01-01T00:00:00-05:00").unwrap();
+        let mut summary = CleanupSummary::default();
+
+        let result = clean_push_requests_with_client(
+            &api_client,
+            &options(&since_date, false),
+            &Throttle::default(),
+            &mut summary,
+            &mut vec![],
+        )
+        .await;
+
+        user.assert();
+        listed.assert();
+        match result {
+            Err(FoxdieError::AuthenticationFailed("GitHub")) => {}
+            other => panic!("Expected an authentication failure, got {:?}", other),
+        }
+        drop(listed);
+
+        let skipped = mock("GET", "/rejected-token/repos/wayfair/foxdie/pulls")
+            .match_query(Matcher::Any)
+            .with_body("[]")
+            .create();
+        let opts = Options {
+            skip_auth_check: true,
+            ..options(&since_date, false)
+        };
+        clean_push_requests_with_client(
+            &api_client,
+            &opts,
+            &Throttle::default(),
+            &mut summary,
+            &mut vec![],
+        )
+        .await
+        .unwrap();
+        skipped.assert();
+    }
+
+    #[tokio::test]
+    async fn test_clean_push_requests_in_all_states_only_closes_open_ones() {
+        let api_client = mock_provider(SCMKind::GitHub, "/all-states", "foxdie");
+        let pull =
+            |number: i32, state: &str| github_pull_json(number, state, "2019-01-01T00:00:00Z");
+        let _user = mock("GET", "/all-states/user").with_body("{}").create();
+        let listed = mock("GET", "/all-states/repos/wayfair/foxdie/pulls")
+            .match_query(Matcher::UrlEncoded("state".into(), "all".into()))
+            .with_body(serde_json::json!([pull(1, "open"), pull(2, "closed")]).to_string())
+            .create();
+        let closed = mock("PATCH", "/all-states/repos/wayfair/foxdie/pulls/1")
+            .with_status(200)
+            .create();
+        let already_closed = mock("PATCH", "/all-states/repos/wayfair/foxdie/pulls/2")
+            .expect(0)
+            .create();
+        let since_date = DateTime::parse_from_rfc3339("2020-01-01T00:00:00-05:00").unwrap();
+        let opts = Options {
+            should_delete: true,
+            state: PushRequestState::All,
+            ..options(&since_date, false)
+        };
+        let mut summary = CleanupSummary::default();
+
+        clean_push_requests_with_client(
+            &api_client,
+            &opts,
+            &Throttle::default(),
+            &mut summary,
+            &mut vec![],
+        )
+        .await
+        .unwrap();
+
+        listed.assert();
+        closed.assert();
+        already_closed.assert();
+        assert_eq!(summary.examined, 2);
+        assert_eq!(summary.closed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_clean_push_requests_skips_archived_repositories() {
+        let pulls =
+            serde_json::json!([github_pull_json(1, "open", "2019-01-01T00:00:00Z")]).to_string();
+        let since_date = DateTime::parse_from_rfc3339("2020-01-01T00:00:00-05:00").unwrap();
+        let opts = Options {
+            should_delete: true,
+            ..options(&since_date, false)
+        };
+
+        for (prefix, archived) in &[("archived", true), ("not-archived", false)] {
+            let api_client = mock_provider(SCMKind::GitHub, &format!("/{}", prefix), "foxdie");
+            let _user = mock("GET", &*format!("/{}/user", prefix))
+                .with_body("{}")
+                .create();
+            let _listed = mock("GET", &*format!("/{}/repos/wayfair/foxdie/pulls", prefix))
+                .match_query(Matcher::Any)
+                .with_body(&pulls)
+                .create();
+            let repository = mock("GET", &*format!("/{}/repos/wayfair/foxdie", prefix))
+                .with_body(serde_json::json!({ "id": 1, "archived": archived }).to_string())
+                .create();
+            let closed = mock(
+                "PATCH",
+                &*format!("/{}/repos/wayfair/foxdie/pulls/1", prefix),
+            )
+            .with_status(200)
+            .expect(if *archived { 0 } else { 1 })
+            .create();
+            let mut summary = CleanupSummary::default();
+
+            clean_push_requests_with_client(
+                &api_client,
+                &opts,
+                &Throttle::default(),
+                &mut summary,
+                &mut vec![],
+            )
+            .await
+            .unwrap();
+
+            repository.assert();
+            closed.assert();
+            assert_eq!(summary.eligible, 1);
+            assert_eq!(summary.closed, if *archived { 0 } else { 1 });
+        }
+    }
+
+    #[tokio::test]
+    async fn test_close_planned_push_requests_skips_drifted() {
+        // Only #1 of the two planned push requests is still open; #2 was closed after the plan was made.
+        let pulls =
+            serde_json::json!([github_pull_json(1, "open", "2020-06-01T00:00:00Z")]).to_string();
+        let api_client = mock_provider(SCMKind::GitHub, "/from-plan", "foxdie");
+        let _user = mock("GET", "/from-plan/user").with_body("{}").create();
+        let _listed = mock("GET", "/from-plan/repos/wayfair/foxdie/pulls")
+            .match_query(Matcher::Any)
+            .with_body(&pulls)
+            .create();
+        let _repository = mock("GET", "/from-plan/repos/wayfair/foxdie")
+            .with_body(serde_json::json!({ "id": 1 }).to_string())
+            .create();
+        let closed = mock("PATCH", "/from-plan/repos/wayfair/foxdie/pulls/1")
+            .with_status(200)
+            .create();
+        let drifted = mock("PATCH", "/from-plan/repos/wayfair/foxdie/pulls/2")
+            .expect(0)
+            .create();
+        // The cutoff would make neither push request eligible; a plan is carried out without deciding afresh.
+        let since_date = DateTime::parse_from_rfc3339("2000-01-01T00:00:00-05:00").unwrap();
+        let opts = Options {
+            should_delete: true,
+            ..options(&since_date, false)
+        };
+        let mut summary = CleanupSummary::default();
+
+        close_planned_push_requests(
+            &api_client,
+            &[1, 2],
+            &opts,
+            &Throttle::default(),
+            &mut summary,
+        )
+        .await
+        .unwrap();
+
+        closed.assert();
+        drifted.assert();
+        assert_eq!(summary.examined, 2);
+        assert_eq!(summary.skipped, 1);
+        assert_eq!(summary.closed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_close_push_requests_waits_between_closes() {
+        let api_client = mock_provider(SCMKind::GitHub, "", "throttled");
+        let closed = mock(
+            "PATCH",
+            Matcher::Regex(String::from("^/repos/wayfair/throttled/pulls/[123]$")),
+        )
+        .with_status(200)
+        .expect(3)
+        .create();
+        let push_requests = (1..=3).map(push_request).collect::<Vec<_>>();
+        let close_opts = CloseOptions {
+            delay: Duration::from_millis(50),
+            ..CloseOptions::default()
+        };
+        let mut summary = CleanupSummary::default();
+
+        let started = std::time::Instant::now();
+        close_push_requests(&api_client, &push_requests, &close_opts, None, &mut summary)
+            .await
+            .unwrap();
+
+        closed.assert();
+        // Two waits, between the first and second close and between the second and third.
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        assert_eq!(summary.closed, 3);
+    }
+
+    #[tokio::test]
+    async fn test_close_push_requests_in_batches() {
+        let api_client = mock_provider(SCMKind::GitHub, "", "batched");
+        let mocks = [(1, 200), (2, 500), (3, 200)]
+            .iter()
+            .map(|(id, status)| {
+                mock("PATCH", &*format!("/repos/wayfair/batched/pulls/{}", id))
+                    .with_status(*status)
+                    .create()
+            })
+            .collect::<Vec<_>>();
+        let push_requests = (1..=3).map(push_request).collect::<Vec<_>>();
+        let batching = Batching {
+            size: 2,
+            confirm: false,
+            delay: Duration::from_millis(0),
+        };
+        let mut summary = CleanupSummary::default();
+
+        let result = close_push_requests_in_batches(
+            &api_client,
+            &push_requests,
+            &CloseOptions::default(),
+            None,
+            Some(&batching),
+            &mut summary,
+        )
+        .await;
+
+        for mock in &mocks {
+            mock.assert();
+        }
+        match result {
+            Err(FoxdieError::PartialFailure(1, 3)) => {}
+            other => panic!("Expected one failure out of three, got {:?}", other),
+        }
+        assert_eq!(summary.closed, 2);
+        assert_eq!(summary.push_requests.unwrap().closed, vec![1, 3]);
+    }
+
+    #[test]
+    fn test_format_push_requests_to_close_with_limit() {
+        let mut push_requests = (1..=3).map(push_request).collect::<Vec<_>>();
+        let truncated_from = truncate_to_limit(&mut push_requests, Some(1));
+        assert_eq!(push_requests.len(), 1);
+        assert_eq!(
+            format_push_requests_to_close(&push_requests, 5, truncated_from),
+            "Found 3 eligible push requests out of 5 total (showing/acting on first 1 of 3):\n\
+             • #1: PR 1 (https://github.com/wayfair/foxdie/pull/1)\n"
+        );
+    }
+
+    #[test]
+    fn test_format_push_requests_to_close_without_limit() {
+        let push_requests = (1..=2).map(push_request).collect::<Vec<_>>();
+        assert_eq!(
+            format_push_requests_to_close(&push_requests, 2, None),
+            "Found 2 eligible push requests out of 2 total:\n\
+             • #1: PR 1 (https://github.com/wayfair/foxdie/pull/1)\n\
+             • #2: PR 2 (https://github.com/wayfair/foxdie/pull/2)\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reopen_push_requests_recreates_deleted_source_branches() {
+        let provider_cache = ProviderCache::default();
+        provider_cache.remember(
+            "git.example.com",
+            &format!("{}/reopen-journal", mockito::server_url()),
+            SCMKind::GitHub,
+        );
+        let path = std::env::temp_dir().join(format!(
+            "foxdie-test-reopen-journal-{}.ndjson",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        {
+            let mut journal = Journal::open(&path).unwrap();
+            for (id, source_sha) in [(91, Some("abc91")), (92, None)] {
+                journal
+                    .record(JournalAction::ClosedPushRequest {
+                        id,
+                        url: format!("https://git.example.com/wayfair/foxdie/pull/{}", id),
+                        source_branch: format!("branch-{}", id),
+                        source_sha: source_sha.map(String::from),
+                    })
+                    .unwrap();
+            }
+        }
+        let _user = mock("GET", "/reopen-journal/user").with_body("{}").create();
+        let _missing = mock(
+            "GET",
+            Matcher::Regex(String::from(
+                "^/reopen-journal/repos/wayfair/foxdie/branches/branch-9[12]$",
+            )),
+        )
+        .with_status(404)
+        .create();
+        let created = mock("POST", "/reopen-journal/repos/wayfair/foxdie/git/refs")
+            .match_body(Matcher::Json(serde_json::json!({
+                "ref": "refs/heads/branch-91",
+                "sha": "abc91"
+            })))
+            .with_status(201)
+            .create();
+        let reopened = mock("PATCH", "/reopen-journal/repos/wayfair/foxdie/pulls/91")
+            .with_status(200)
+            .create();
+        let not_reopened = mock("PATCH", "/reopen-journal/repos/wayfair/foxdie/pulls/92")
+            .expect(0)
+            .create();
+
+        let result = reopen_push_requests(
+            "https://git.example.com/wayfair/foxdie",
+            &[],
+            path.to_str(),
+            "token",
+            &ClientOptions {
+                provider_cache: Some(&provider_cache),
+                ..ClientOptions::default()
+            },
+            None,
+            None,
+        )
+        .await;
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(result.is_ok());
+        created.assert();
+        reopened.assert();
+        not_reopened.assert();
+    }
+}
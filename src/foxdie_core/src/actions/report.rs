@@ -0,0 +1,1401 @@
+// Copyright (c) 2018-2019, Wayfair LLC
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+//  * Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//    disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//    following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING,
+// BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED.
+// IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY,
+// OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+// STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE,
+// EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use super::plan::{RED, RESET, YELLOW};
+use super::{
+    append_output_file, combined_result, create_output_file, open_repository, select_remotes,
+    truncate_to_limit, verify_token,
+};
+use crate::error::FoxdieError;
+use crate::services::{
+    get_api_client_for_remote, git, protection_source, ProtectedBranch, ProtectionSource,
+    PushRequest, PushRequestState, ReviewState, SCMProvider,
+};
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use futures::stream::{self, StreamExt};
+use log::{info, warn};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+use what_git::ClientOptions;
+
+/// The number of remotes whose reports are generated at once.
+const CONCURRENT_REMOTES: usize = 4;
+
+/// The number of push requests whose reviews are looked up at once.
+const CONCURRENT_REVIEW_REQUESTS: usize = 8;
+
+/// Branches last updated more than this many days ago are shown in red in the text report.
+const STALE_DAYS: i64 = 90;
+
+/// Branches last updated more than this many days ago, but not yet stale, are shown in yellow in the text report.
+const AGING_DAYS: i64 = 30;
+
+#[non_exhaustive]
+pub struct Options<'a> {
+    pub first_parent: bool,
+    /// Whether to only report branches whose tip was authored by the user configured in Git.
+    pub mine: bool,
+    /// Whether to list the open push requests attached to each branch. Requires `token`.
+    pub with_push_requests: bool,
+    /// Whether to look up the review state of the push request from each branch. Requires `token`, and costs an extra
+    /// request per open push request.
+    pub with_review_state: bool,
+    /// Whether to list remote-tracking branches that no longer exist on their remote. The repository is left as it is,
+    /// without fetching or pruning.
+    pub orphaned_refs: bool,
+    /// Whether to count the branches on each remote that each protection rule matches, to find rules that are too
+    /// broad or that match nothing.
+    pub audit_protection: bool,
+    /// When set, branch protection rules are also fetched from each remote's SCM provider.
+    pub token: Option<&'a str>,
+    /// Whether to skip checking that the SCM accepts `token` before using it.
+    pub skip_auth_check: bool,
+    pub allowed_hosts: Option<&'a [String]>,
+    pub raw_response_dir: Option<&'a Path>,
+    pub client_options: ClientOptions<'a>,
+    pub ssh_key: Option<&'a Path>,
+    pub ssh_passphrase: Option<&'a str>,
+    /// The branch to measure divergence against for each remote, by remote name, in place of the remote's default
+    /// branch.
+    pub base_branches: &'a HashMap<String, String>,
+    /// Whether to print the reports to stdout as a single JSON object once they are all generated.
+    pub json: bool,
+    /// The format the report is written to the output path in.
+    pub format: OutputFormat,
+    /// Whether to append each report to the output path as a line of JSON, rather than overwriting it, to keep a
+    /// history of reports. Only applies to `OutputFormat::Json`.
+    pub append: bool,
+    /// Whether to insert the time the run started into the name of the output file, so that each run writes its own.
+    pub timestamp_output: bool,
+    /// When set, only this many of the stalest branches are reported for each remote.
+    pub top: Option<usize>,
+    /// How many threads to compute how far each branch has diverged on, each with its own handle on the repository.
+    /// With 1, it is computed on the current thread.
+    pub jobs: usize,
+    /// The remotes to report on, by name. When empty, every remote is.
+    pub remotes: &'a [String],
+}
+
+impl<'a> Options<'a> {
+    /// Options for a JSON report on every remote, measuring divergence against `base_branches` where given, on the
+    /// current thread and with every other option left off. Set the fields to change the rest.
+    pub fn new(base_branches: &'a HashMap<String, String>) -> Self {
+        Options {
+            first_parent: false,
+            mine: false,
+            with_push_requests: false,
+            with_review_state: false,
+            orphaned_refs: false,
+            audit_protection: false,
+            token: None,
+            skip_auth_check: false,
+            allowed_hosts: None,
+            raw_response_dir: None,
+            client_options: ClientOptions::default(),
+            ssh_key: None,
+            ssh_passphrase: None,
+            base_branches,
+            json: false,
+            format: OutputFormat::Json,
+            append: false,
+            timestamp_output: false,
+            top: None,
+            jobs: 1,
+            remotes: &[],
+        }
+    }
+}
+
+/// The formats a report can be written to disk in.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum OutputFormat {
+    Json,
+    /// A self-contained HTML page with a sortable table of branches for each remote.
+    Html,
+}
+
+pub async fn report<P>(
+    output_path: &Option<P>,
+    repo_path: Option<P>,
+    opts: Options<'_>,
+) -> Result<(), FoxdieError>
+where
+    P: AsRef<Path>,
+{
+    let started = Instant::now();
+    let output_path = output_path.as_ref().map(|path| {
+        if opts.timestamp_output {
+            timestamped_path(path.as_ref(), Utc::now())
+        } else {
+            path.as_ref().to_path_buf()
+        }
+    });
+    let repo = open_repository(repo_path)?;
+    let remotes = select_remotes(&repo, opts.remotes)?;
+    let current_branch = git::get_current_branch(&repo)?;
+    let identity = if opts.mine {
+        Some(git::get_configured_identity(&repo)?)
+    } else {
+        None
+    };
+
+    let outcomes = stream::iter(&remotes)
+        .map(|remote_name| {
+            let identity = identity.as_ref();
+            let (repo, current_branch, opts) = (&repo, &current_branch, &opts);
+            async move {
+                let result =
+                    report_for_remote_name(repo, remote_name, current_branch, identity, opts).await;
+                (remote_name, result)
+            }
+        })
+        .buffer_unordered(CONCURRENT_REMOTES)
+        .collect::<Vec<_>>()
+        .await;
+    let mut reports = vec![];
+    let mut errors = vec![];
+    for (remote_name, result) in outcomes {
+        match result {
+            Ok(report) => reports.push(report),
+            Err(err) => {
+                if remotes.len() > 1 {
+                    warn!(
+                        "Failed to report on {}, carrying on with the other remotes: {}",
+                        remote_name, err
+                    );
+                }
+                errors.push(err);
+            }
+        }
+    }
+    reports.sort_by(|a, b| a.remote_name.cmp(&b.remote_name));
+
+    for report in &reports {
+        print_report(report);
+        if let (Some(ref p), OutputFormat::Json) = (&output_path, opts.format) {
+            write_report_to_disk(report, p, opts.append)?;
+        }
+    }
+    if let (Some(ref p), OutputFormat::Html) = (&output_path, opts.format) {
+        if opts.append {
+            warn!(
+                "HTML reports can't be appended to, so {} is overwritten.",
+                p.display()
+            );
+        }
+        create_output_file(p)?.write_all(render_html(&reports).as_bytes())?;
+    }
+    info!("{}", format_report_summary(&reports, started.elapsed()));
+    if opts.json {
+        println!(
+            "{}",
+            serde_json::to_string(&ReportSummary { remotes: &reports })?
+        );
+    }
+    combined_result(errors, remotes.len())
+}
+
+/// Fetches `remote_name` and generates its report, looking up its protected branches and push requests concurrently.
+async fn report_for_remote_name(
+    repo: &git::Repository,
+    remote_name: &str,
+    current_branch: &git::Branch<'_>,
+    identity: Option<&git::Identity>,
+    opts: &Options<'_>,
+) -> Result<Report, FoxdieError> {
+    let mut remote = repo.find_remote(remote_name)?;
+    let credentials = git::Credentials {
+        token: opts.token,
+        ssh_key: opts.ssh_key,
+        ssh_passphrase: opts.ssh_passphrase,
+        timeout: opts.client_options.timeout,
+    };
+    let orphaned_refs = if opts.orphaned_refs {
+        git::get_orphaned_tracking_branches(repo, &mut remote, &credentials)?
+    } else {
+        git::fetch_refs(&mut remote, &credentials)?;
+        vec![]
+    };
+    let api_client = api_client_for_remote(&remote, opts).await?;
+    let list_push_requests = async {
+        match api_client {
+            Some(ref api_client) if opts.with_push_requests || opts.with_review_state => {
+                Ok(api_client
+                    .list_push_requests(PushRequestState::Opened)
+                    .await?)
+            }
+            _ => Ok(vec![]),
+        }
+    };
+    let base_branch = git::get_base_branch(
+        repo,
+        remote_name,
+        opts.base_branches.get(remote_name).map(String::as_str),
+    );
+    let (protected_branches, push_requests) = futures::try_join!(
+        protected_branches_for_remote(
+            api_client.as_ref(),
+            &remote,
+            current_branch,
+            base_branch.as_ref()
+        ),
+        list_push_requests,
+    )?;
+    let review_states = match api_client {
+        Some(ref api_client) if opts.with_review_state => {
+            review_states(api_client, &push_requests).await
+        }
+        _ => HashMap::new(),
+    };
+    let mut report = report_for_remote(
+        repo,
+        &remote,
+        base_branch.as_ref().unwrap_or(current_branch),
+        &RemoteLookups {
+            push_requests: &push_requests,
+            review_states: &review_states,
+            protected_branches: &protected_branches,
+            identity,
+        },
+        opts,
+    )?;
+    report.orphaned_refs = orphaned_refs;
+    Ok(report)
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    remote_name: String,
+    remote_url: String,
+    items: Vec<ReportItem>,
+    /// Remote-tracking branches whose branch no longer exists on the remote. Only filled in when the report is run with
+    /// `--orphaned-refs`.
+    orphaned_refs: Vec<String>,
+    /// The branches each protection rule matches. Only filled in when the report is run with `--audit-protection`.
+    protection_audit: Vec<ProtectionAudit>,
+}
+
+/// The branches on a remote that a single protection rule matches.
+#[derive(Debug, PartialEq, Serialize)]
+struct ProtectionAudit {
+    pattern: String,
+    source: String,
+    matches: usize,
+    branches: Vec<String>,
+}
+
+/// The reports for every remote, as printed by `--json`.
+#[derive(Debug, Serialize)]
+struct ReportSummary<'a> {
+    remotes: &'a [Report],
+}
+
+#[derive(Debug, Serialize)]
+struct ReportItem {
+    upstream_diverged: usize,
+    downstream_diverged: usize,
+    /// Whether the tip of this branch is already in the history of the base branch, so deleting it would lose nothing.
+    fully_merged: bool,
+    branch: String,
+    commit: String,
+    author: String,
+    last_updated: DateTime<Utc>,
+    was_merge: bool,
+    has_push_request: bool,
+    /// Where the rule protecting this branch came from, if it is protected.
+    protection_source: Option<String>,
+    /// The open push requests from or into this branch. Only filled in when the report is run with `--with-prs`.
+    open_pr_numbers: Vec<i32>,
+    /// The review state of the open push request from this branch. Only filled in when the report is run with
+    /// `--with-review-state`.
+    review_state: Option<String>,
+    message: String,
+}
+
+/// Looks up the review state of each of `push_requests` by number. Push requests whose reviews can't be read are left
+/// out, rather than failing the report.
+async fn review_states(
+    api_client: &SCMProvider,
+    push_requests: &[PushRequest],
+) -> HashMap<i32, ReviewState> {
+    stream::iter(push_requests)
+        .map(|pr| async move { (pr.id, api_client.review_state(pr.id).await) })
+        .buffer_unordered(CONCURRENT_REVIEW_REQUESTS)
+        .filter_map(|(id, result)| async move {
+            match result {
+                Ok(state) => Some((id, state)),
+                Err(err) => {
+                    warn!("Could not read the reviews of push request {}: {}", id, err);
+                    None
+                }
+            }
+        })
+        .collect()
+        .await
+}
+
+/// Returns a client for `remote`'s SCM provider if a token was given and the provider is supported.
+async fn api_client_for_remote(
+    remote: &git::Remote<'_>,
+    opts: &Options<'_>,
+) -> Result<Option<SCMProvider>, FoxdieError> {
+    let token = match opts.token {
+        Some(token) => token,
+        None => return Ok(None),
+    };
+    match get_api_client_for_remote(
+        remote,
+        token,
+        &opts.client_options,
+        opts.allowed_hosts,
+        opts.raw_response_dir,
+    )
+    .await
+    {
+        Ok(api_client) => {
+            if !opts.skip_auth_check {
+                verify_token(&api_client).await?;
+            }
+            Ok(Some(api_client))
+        }
+        Err(err @ FoxdieError::UnsupportedProvider(_)) => {
+            warn!("{}", err);
+            Ok(None)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Collects the rules protecting branches on `remote`: the remote's protected branches when there is an `api_client`,
+/// plus the current branch's upstream and the remote's base branch.
+async fn protected_branches_for_remote(
+    api_client: Option<&SCMProvider>,
+    remote: &git::Remote<'_>,
+    current_branch: &git::Branch<'_>,
+    base_branch: Option<&git::Branch<'_>>,
+) -> Result<Vec<ProtectedBranch>, FoxdieError> {
+    let mut protected_branches = match api_client {
+        Some(api_client) => api_client.list_protected_branches().await?,
+        None => vec![],
+    };
+    let upstream = current_branch.upstream().ok();
+    let remote_name = remote.name().unwrap_or_default();
+    let tracking_prefix = format!("refs/remotes/{}/", remote_name);
+    for full_ref in upstream
+        .as_ref()
+        .into_iter()
+        .chain(base_branch)
+        .filter_map(|branch| branch.get().name())
+        .filter(|full_ref| full_ref.starts_with(&tracking_prefix))
+    {
+        protected_branches.push(ProtectedBranch::named(
+            &git::bare_branch_name(full_ref, remote_name),
+            ProtectionSource::Default,
+        ));
+    }
+    Ok(protected_branches)
+}
+
+/// What was looked up about a remote's branches before reporting on them.
+struct RemoteLookups<'a> {
+    push_requests: &'a [PushRequest],
+    review_states: &'a HashMap<i32, ReviewState>,
+    protected_branches: &'a [ProtectedBranch],
+    /// When set, only branches whose tip was authored by this identity are reported.
+    identity: Option<&'a git::Identity>,
+}
+
+fn report_for_remote(
+    repo: &git::Repository,
+    remote: &git::Remote,
+    current_branch: &git::Branch,
+    lookups: &RemoteLookups,
+    opts: &Options,
+) -> Result<Report, FoxdieError> {
+    let remote_name = remote.name().unwrap_or_default().to_string();
+    let branches = git::get_remote_branches(repo, &remote_name)?
+        .filter_map(Result::ok)
+        .map(|pair| pair.0)
+        .collect::<Vec<_>>();
+    info!("Generating report for {} branches...", branches.len());
+    let remote_url = remote.url().unwrap_or_default().to_string();
+    let divergences = divergences(
+        repo,
+        current_branch,
+        &branches,
+        opts.first_parent,
+        opts.jobs,
+    )?;
+    let mut report_items = branches
+        .iter()
+        .zip(divergences)
+        .filter_map(|(branch, divergence)| {
+            report_for_branch(repo, &remote_name, branch, divergence?, lookups)
+        })
+        .collect::<Vec<_>>();
+    sort_by_staleness(&mut report_items, opts.top);
+    let protection_audit = if opts.audit_protection {
+        let branch_names = branches
+            .iter()
+            .filter_map(|branch| branch.name().ok().flatten())
+            .map(|name| git::bare_branch_name(name, &remote_name))
+            .collect::<Vec<_>>();
+        audit_protection(&branch_names, lookups.protected_branches)
+    } else {
+        vec![]
+    };
+    Ok(Report {
+        remote_name,
+        remote_url,
+        items: report_items,
+        orphaned_refs: vec![],
+        protection_audit,
+    })
+}
+
+/// How far a branch has diverged from the branch it is measured against.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Divergence {
+    upstream: usize,
+    downstream: usize,
+    /// Whether the branch's tip is in the history of the branch it is measured against.
+    fully_merged: bool,
+}
+
+/// Works out how far `branch` has diverged from `current_branch`, along first parents only with `first_parent`.
+/// Returns nothing when either branch can't be resolved.
+fn divergence(
+    repo: &git::Repository,
+    current_branch: &git::Branch,
+    branch: &git::Branch,
+    first_parent: bool,
+) -> Option<Divergence> {
+    let (upstream, downstream) = if first_parent {
+        git::get_first_parent_divergence_between_branches(repo, current_branch, branch).ok()?
+    } else {
+        git::get_divergence_between_branches(repo, current_branch, branch).ok()?
+    };
+    let fully_merged = git::is_merged_into(repo, current_branch, branch).ok()?;
+    Some(Divergence {
+        upstream,
+        downstream,
+        fully_merged,
+    })
+}
+
+/// Works out the `divergence` of each of `branches`, in order, splitting them between up to `jobs` threads. As a
+/// repository can't be shared between threads, each opens its own handle on `repo` and looks its branches up again by
+/// reference name.
+fn divergences(
+    repo: &git::Repository,
+    current_branch: &git::Branch,
+    branches: &[git::Branch],
+    first_parent: bool,
+    jobs: usize,
+) -> Result<Vec<Option<Divergence>>, FoxdieError> {
+    let current_ref = current_branch.get().name().map(String::from);
+    let (current_ref, jobs) = match current_ref {
+        Some(current_ref) if jobs > 1 && branches.len() > 1 => (current_ref, jobs),
+        _ => {
+            return Ok(branches
+                .iter()
+                .map(|branch| divergence(repo, current_branch, branch, first_parent))
+                .collect())
+        }
+    };
+    let branch_refs = branches
+        .iter()
+        .map(|branch| branch.get().name().map(String::from))
+        .collect::<Vec<_>>();
+    let chunk_size = branch_refs.len().div_ceil(jobs);
+    let path = repo.path();
+    let chunks = thread::scope(|scope| {
+        let workers = branch_refs
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let current_ref = &current_ref;
+                scope.spawn(move || -> Result<Vec<Option<Divergence>>, git::Error> {
+                    let repo = git::open_repository(path)?;
+                    let current_branch = git::Branch::wrap(repo.find_reference(current_ref)?);
+                    Ok(chunk
+                        .iter()
+                        .map(|branch_ref| {
+                            let branch =
+                                git::Branch::wrap(repo.find_reference(branch_ref.as_ref()?).ok()?);
+                            divergence(&repo, &current_branch, &branch, first_parent)
+                        })
+                        .collect())
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .map(|worker| {
+                worker
+                    .join()
+                    .expect("A thread computing divergences panicked.")
+            })
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+    Ok(chunks.into_iter().flatten().collect())
+}
+
+fn report_for_branch(
+    repo: &git::Repository,
+    remote_name: &str,
+    branch: &git::Branch,
+    divergence: Divergence,
+    lookups: &RemoteLookups,
+) -> Option<ReportItem> {
+    let push_requests = lookups.push_requests;
+    let branch_name = branch.name().ok()??;
+    let commit = git::commit_for_branch(repo, branch).ok()?;
+    let hash = commit.id().to_string();
+    let signature = commit.author();
+    if let Some(identity) = lookups.identity {
+        if !identity.matches(signature.name(), signature.email()) {
+            return None;
+        }
+    }
+    let author = git::author_name(&commit);
+    let last_updated = Utc.timestamp(commit.time().seconds(), 0);
+    let bare_name = git::bare_branch_name(branch_name, remote_name);
+    let has_push_request = push_requests.iter().any(|pr| pr.source_branch == bare_name);
+    let open_pr_numbers = open_pr_numbers(&bare_name, push_requests);
+    let review_state = review_state(&bare_name, push_requests, lookups.review_states);
+    let protection_source =
+        protection_source(&bare_name, lookups.protected_branches).map(|source| source.to_string());
+    let message = git::commit_message(&commit);
+    Some(ReportItem {
+        upstream_diverged: divergence.upstream,
+        downstream_diverged: divergence.downstream,
+        fully_merged: divergence.fully_merged,
+        branch: branch_name.to_string(),
+        commit: hash,
+        author,
+        last_updated,
+        was_merge: false,
+        has_push_request,
+        protection_source,
+        open_pr_numbers,
+        review_state,
+        message,
+    })
+}
+
+/// Sorts `items` from the least to the most recently updated, keeping items updated at the same time in their original
+/// order, and keeps only the first `top` of them if it is set.
+fn sort_by_staleness(items: &mut Vec<ReportItem>, top: Option<usize>) {
+    items.sort_by_key(|item| item.last_updated);
+    truncate_to_limit(items, top);
+}
+
+/// Returns the numbers of the push requests in `push_requests` from or into `branch`.
+fn open_pr_numbers(branch: &str, push_requests: &[PushRequest]) -> Vec<i32> {
+    push_requests
+        .iter()
+        .filter(|pr| pr.source_branch == branch || pr.target_branch == branch)
+        .map(|pr| pr.id)
+        .collect()
+}
+
+/// Returns the review state of the first push request in `push_requests` from `branch`, if it was looked up.
+fn review_state(
+    branch: &str,
+    push_requests: &[PushRequest],
+    review_states: &HashMap<i32, ReviewState>,
+) -> Option<String> {
+    push_requests
+        .iter()
+        .filter(|pr| pr.source_branch == branch)
+        .find_map(|pr| review_states.get(&pr.id))
+        .map(ReviewState::to_string)
+}
+
+/// Lists the branches among `branch_names` that each of `protected_branches` matches. A branch matched by several rules
+/// is listed under each of them.
+fn audit_protection(
+    branch_names: &[String],
+    protected_branches: &[ProtectedBranch],
+) -> Vec<ProtectionAudit> {
+    protected_branches
+        .iter()
+        .map(|protected| {
+            let branches = branch_names
+                .iter()
+                .filter(|name| protected.matches_branch(name))
+                .cloned()
+                .collect::<Vec<_>>();
+            ProtectionAudit {
+                pattern: protected.pattern.to_string(),
+                source: protected.source.to_string(),
+                matches: branches.len(),
+                branches,
+            }
+        })
+        .collect()
+}
+
+fn print_report(report: &Report) {
+    info!(
+        "Report for {} ({})\n=================================",
+        report.remote_name, report.remote_url
+    );
+    if !report.items.is_empty() {
+        let colored =
+            io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty());
+        info!("{}", render_items(&report.items, Utc::now(), colored));
+    }
+    if !report.orphaned_refs.is_empty() {
+        info!(
+            "{}",
+            format_orphaned_refs(&report.orphaned_refs, &report.remote_name)
+        );
+    }
+    if !report.protection_audit.is_empty() {
+        info!(
+            "{}",
+            format_protection_audit(&report.protection_audit, &report.remote_name)
+        );
+    }
+}
+
+fn format_protection_audit(audits: &[ProtectionAudit], remote_name: &str) -> String {
+    format!(
+        "Branches matched by each protection rule on {}:\n{}",
+        remote_name,
+        audits
+            .iter()
+            .map(|audit| {
+                let branches = if audit.branches.is_empty() {
+                    String::new()
+                } else {
+                    format!(": {}", audit.branches.join(", "))
+                };
+                format!(
+                    "• {} ({}) matches {} branches{}\n",
+                    audit.pattern, audit.source, audit.matches, branches
+                )
+            })
+            .collect::<String>()
+    )
+}
+
+/// Describes the totals of `reports` in a single line.
+fn format_report_summary(reports: &[Report], elapsed: Duration) -> String {
+    let items = reports.iter().flat_map(|report| &report.items);
+    let (count, protected_count, with_push_request_count) =
+        items.fold((0, 0, 0), |(count, protected, with_pr), item| {
+            (
+                count + 1,
+                protected + item.protection_source.iter().count(),
+                with_pr + usize::from(item.has_push_request),
+            )
+        });
+    format!(
+        "Reported on {} branches across {} remotes: {} protected, {} with a push request. Took {:.1}s.",
+        count,
+        reports.len(),
+        protected_count,
+        with_push_request_count,
+        elapsed.as_secs_f64()
+    )
+}
+
+/// The number of whole days between `last_updated` and `now`. Dates in the future count as zero days old.
+fn age_in_days(last_updated: DateTime<Utc>, now: DateTime<Utc>) -> i64 {
+    (now - last_updated).num_days().max(0)
+}
+
+/// Renders `items` as a table with one column-aligned row per branch. Rows are wrapped in ANSI color codes by how long
+/// ago the branch was last updated when `colored` is set.
+fn render_items(items: &[ReportItem], now: DateTime<Utc>, colored: bool) -> String {
+    let header = [
+        "Branch",
+        "Author",
+        "Last updated",
+        "Age (days)",
+        "Ahead/behind",
+        "PR",
+    ]
+    .iter()
+    .map(|title| title.to_string())
+    .collect::<Vec<_>>();
+    let rows = items
+        .iter()
+        .map(|item| {
+            vec![
+                item.branch.clone(),
+                item.author.clone(),
+                item.last_updated.format("%Y-%m-%d").to_string(),
+                age_in_days(item.last_updated, now).to_string(),
+                format!("{}/{}", item.upstream_diverged, item.downstream_diverged),
+                String::from(if item.has_push_request { "yes" } else { "" }),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let widths = (0..header.len())
+        .map(|column| {
+            rows.iter()
+                .chain(std::iter::once(&header))
+                .map(|row| row[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+    let format_row = |row: &[String]| {
+        row.iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    let mut lines = vec![format_row(&header)];
+    for (item, row) in items.iter().zip(&rows) {
+        let line = format_row(row);
+        let age = age_in_days(item.last_updated, now);
+        let color = if age > STALE_DAYS {
+            Some(RED)
+        } else if age > AGING_DAYS {
+            Some(YELLOW)
+        } else {
+            None
+        };
+        lines.push(match color {
+            Some(color) if colored => format!("{}{}{}", color, line, RESET),
+            _ => line,
+        });
+    }
+    lines.join("\n")
+}
+
+fn format_orphaned_refs(orphaned_refs: &[String], remote_name: &str) -> String {
+    format!(
+        "{} remote-tracking branches no longer exist on {}. Run `git fetch --prune {}` to remove them:\n{}",
+        orphaned_refs.len(),
+        remote_name,
+        remote_name,
+        orphaned_refs
+            .iter()
+            .map(|name| format!("• {}\n", name))
+            .collect::<String>()
+    )
+}
+
+/// Writes `report` to `path` as JSON. With `append`, it is added to the end of the file as a single line instead of
+/// replacing the file, so that the file holds one report per line.
+fn write_report_to_disk<P>(report: &Report, path: P, append: bool) -> Result<(), FoxdieError>
+where
+    P: AsRef<Path>,
+{
+    let mut json = serde_json::to_vec(report)?;
+    let mut file = if append {
+        json.push(b'\n');
+        append_output_file(path.as_ref())?
+    } else {
+        create_output_file(path.as_ref())?
+    };
+    file.write_all(&json).map_err(FoxdieError::from)
+}
+
+/// Inserts the RFC 3339 timestamp of `at` into the file name of `path`, before its extension, as in
+/// `report-2021-01-01T00:00:00Z.json`.
+fn timestamped_path(path: &Path, at: DateTime<Utc>) -> PathBuf {
+    let timestamp = at.to_rfc3339_opts(SecondsFormat::Secs, true);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, timestamp, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, timestamp),
+    };
+    path.with_file_name(file_name)
+}
+
+/// Renders `reports` as a self-contained HTML page, with a table of branches for each remote that can be sorted by
+/// clicking on its column headers.
+fn render_html(reports: &[Report]) -> String {
+    let sections = reports
+        .iter()
+        .map(|report| {
+            let rows = report
+                .items
+                .iter()
+                .map(|item| {
+                    format!(
+                        "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                        escape_html(&item.branch),
+                        escape_html(&item.author),
+                        item.last_updated.format("%Y-%m-%d %H:%M"),
+                        item.upstream_diverged,
+                        item.downstream_diverged,
+                        escape_html(item.protection_source.as_deref().unwrap_or_default()),
+                        item.open_pr_numbers
+                            .iter()
+                            .map(|id| format!("#{}", id))
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                        escape_html(item.message.lines().next().unwrap_or_default()),
+                    )
+                })
+                .collect::<String>();
+            format!(
+                "<h2>{} <small>{}</small></h2>\n<table>\n<thead><tr><th>Branch</th><th>Author</th><th>Last updated</th>\
+                 <th>Ahead</th><th>Behind</th><th>Protection</th><th>Push requests</th><th>Message</th></tr></thead>\n\
+                 <tbody>\n{}</tbody>\n</table>\n",
+                escape_html(&report.remote_name),
+                escape_html(&report.remote_url),
+                rows
+            )
+        })
+        .collect::<String>();
+    format!("{}{}{}", HTML_HEADER, sections, HTML_FOOTER)
+}
+
+const HTML_HEADER: &str = "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>Foxdie report</title>
+<style>
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; width: 100%; margin-bottom: 2em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; }
+th { background: #eee; cursor: pointer; user-select: none; }
+small { color: #666; font-weight: normal; }
+</style>
+</head>
+<body>
+<h1>Foxdie report</h1>
+";
+
+const HTML_FOOTER: &str = "<script>
+document.querySelectorAll('th').forEach(function (th) {
+  th.addEventListener('click', function () {
+    var tbody = th.closest('table').querySelector('tbody');
+    var index = Array.prototype.indexOf.call(th.parentNode.children, th);
+    var ascending = th.dataset.order !== 'asc';
+    th.dataset.order = ascending ? 'asc' : 'desc';
+    var rows = Array.prototype.slice.call(tbody.rows);
+    rows.sort(function (a, b) {
+      var x = a.cells[index].textContent, y = b.cells[index].textContent;
+      var order = isNaN(x) || isNaN(y) ? x.localeCompare(y) : x - y;
+      return ascending ? order : -order;
+    });
+    rows.forEach(function (row) { tbody.appendChild(row); });
+  });
+});
+</script>
+</body>
+</html>
+";
+
+/// Escapes the characters in `s` that are special in HTML text and attribute values.
+fn escape_html(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            '&' => String::from("&amp;"),
+            '<' => String::from("&lt;"),
+            '>' => String::from("&gt;"),
+            '"' => String::from("&quot;"),
+            '\'' => String::from("&#39;"),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::git::test_support::{commit, TestRepo};
+    use crate::services::test_support::push_request;
+
+    fn report_item(
+        branch: &str,
+        protection_source: Option<&str>,
+        has_push_request: bool,
+    ) -> ReportItem {
+        ReportItem {
+            upstream_diverged: 0,
+            downstream_diverged: 0,
+            fully_merged: false,
+            branch: branch.to_string(),
+            commit: String::from("0000000"),
+            author: String::from("Foxdie"),
+            last_updated: Utc.timestamp(0, 0),
+            was_merge: false,
+            has_push_request,
+            protection_source: protection_source.map(String::from),
+            open_pr_numbers: vec![],
+            review_state: None,
+            message: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_report_on_selected_remote() {
+        let repo = TestRepo::init("report-remote");
+        let head = commit(&repo, "Initial", &[]);
+        repo.branch("main", &head, false).unwrap();
+        repo.set_head("refs/heads/main").unwrap();
+        // Left over from an earlier fetch of upstream, which isn't a selected remote.
+        repo.reference("refs/remotes/upstream/stale", head.id(), false, "test")
+            .unwrap();
+        let mut remote_repos = vec![];
+        for name in &["origin", "upstream"] {
+            let remote_repo = TestRepo::init_bare(&format!("report-remote-{}", name));
+            let branch_name = format!("{}-branch", name);
+            remote_repo
+                .branch(&branch_name, &commit(&remote_repo, name, &[]), false)
+                .unwrap();
+            repo.remote(name, remote_repo.path().to_str().unwrap())
+                .unwrap();
+            remote_repos.push(remote_repo);
+        }
+        let output_path = Some(repo.path().join("report.json"));
+        let remotes = [String::from("origin")];
+        let base_branches = HashMap::new();
+        let opts = Options {
+            remotes: &remotes,
+            ..Options::new(&base_branches)
+        };
+
+        report(&output_path, Some(repo.path().to_path_buf()), opts)
+            .await
+            .unwrap();
+
+        let written: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(repo.path().join("report.json")).unwrap())
+                .unwrap();
+        assert_eq!(written["remote_name"], "origin");
+        let items = written["items"].as_array().unwrap();
+        assert!(items
+            .iter()
+            .any(|item| item["branch"] == "origin/origin-branch"));
+        assert!(items
+            .iter()
+            .all(|item| !item["branch"].as_str().unwrap().starts_with("upstream/")));
+        assert!(repo
+            .find_branch("upstream/upstream-branch", git2::BranchType::Remote)
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_report_carries_on_past_unreachable_remote() {
+        let repo = TestRepo::init("report-dead");
+        repo.branch("main", &commit(&repo, "Initial", &[]), false)
+            .unwrap();
+        repo.set_head("refs/heads/main").unwrap();
+        let origin = TestRepo::init_bare("report-dead-origin");
+        origin
+            .branch("feature", &commit(&origin, "Feature", &[]), false)
+            .unwrap();
+        repo.remote("origin", origin.path().to_str().unwrap())
+            .unwrap();
+        // A fork that has since been deleted.
+        repo.remote("dead-fork", repo.path().join("gone.git").to_str().unwrap())
+            .unwrap();
+        let output_path = Some(repo.path().join("report.json"));
+        let base_branches = HashMap::new();
+        let opts = Options::new(&base_branches);
+
+        let result = report(&output_path, Some(repo.path().to_path_buf()), opts).await;
+
+        match result {
+            Err(FoxdieError::PartialFailure(1, 2)) => {}
+            other => panic!("Expected one failure out of two, got {:?}", other),
+        }
+        let written: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(repo.path().join("report.json")).unwrap())
+                .unwrap();
+        assert_eq!(written["remote_name"], "origin");
+        assert_eq!(written["items"][0]["branch"], "origin/feature");
+    }
+
+    #[test]
+    fn test_report_for_branch_with_invalid_utf8() {
+        let repo = TestRepo::init("report-utf8");
+        let base = commit(&repo, "Initial", &[]);
+        // Written by hand, as signatures built through git2 have to be valid UTF-8.
+        let mut raw = format!("tree {}\nparent {}\n", base.tree_id(), base.id()).into_bytes();
+        raw.extend_from_slice(
+            b"author Jos\xe9 <jose@example.com> 1500000000 +0000\n\
+              committer Jos\xe9 <jose@example.com> 1500000000 +0000\n\
+              \n\
+              Fix caf\xe9\n",
+        );
+        let legacy = repo
+            .odb()
+            .unwrap()
+            .write(git2::ObjectType::Commit, &raw)
+            .unwrap();
+        repo.reference("refs/remotes/origin/legacy", legacy, false, "test")
+            .unwrap();
+        let current_branch = repo.branch("main", &base, false).unwrap();
+        let branch = repo
+            .find_branch("origin/legacy", git2::BranchType::Remote)
+            .unwrap();
+        let lookups = RemoteLookups {
+            push_requests: &[],
+            review_states: &HashMap::new(),
+            protected_branches: &[],
+            identity: None,
+        };
+
+        let divergence = divergence(&repo, &current_branch, &branch, false).unwrap();
+        let item = report_for_branch(&repo, "origin", &branch, divergence, &lookups).unwrap();
+
+        assert_eq!(item.branch, "origin/legacy");
+        assert_eq!(item.author, "Jos\u{FFFD}");
+        assert_eq!(item.message, "Fix caf\u{FFFD}\n");
+    }
+
+    #[test]
+    fn test_divergences_match_across_jobs() {
+        let repo = TestRepo::init("report-divergences");
+        let commit_onto = |message: &str, parents: &[git2::Oid]| {
+            let parents = parents
+                .iter()
+                .map(|oid| repo.find_commit(*oid).unwrap())
+                .collect::<Vec<_>>();
+            commit(&repo, message, &parents.iter().collect::<Vec<_>>()).id()
+        };
+        let mut history = vec![commit_onto("Initial", &[])];
+        for i in 1..20 {
+            let parent = history[i - 1];
+            history.push(commit_onto(&format!("Main {}", i), &[parent]));
+        }
+        let main = repo
+            .branch("main", &repo.find_commit(history[19]).unwrap(), false)
+            .unwrap();
+        for i in 0..200 {
+            let mut tip = history[i % 20];
+            for j in 0..i % 7 {
+                tip = commit_onto(&format!("Branch {} commit {}", i, j), &[tip]);
+            }
+            if i % 5 == 0 {
+                tip = commit_onto(
+                    &format!("Merge into branch {}", i),
+                    &[tip, history[(i + 3) % 20]],
+                );
+            }
+            repo.reference(
+                &format!("refs/remotes/origin/branch-{}", i),
+                tip,
+                false,
+                "test",
+            )
+            .unwrap();
+        }
+        let branches = git::get_remote_branches(&repo, "origin")
+            .unwrap()
+            .filter_map(Result::ok)
+            .map(|pair| pair.0)
+            .collect::<Vec<_>>();
+
+        for first_parent in [false, true] {
+            let serial = divergences(&repo, &main, &branches, first_parent, 1).unwrap();
+            for jobs in [2, 3, 8, 500] {
+                assert_eq!(
+                    divergences(&repo, &main, &branches, first_parent, jobs).unwrap(),
+                    serial,
+                    "{} jobs diverged from the serial path",
+                    jobs
+                );
+            }
+            assert_eq!(serial.len(), 200);
+            assert!(serial.iter().all(Option::is_some));
+        }
+    }
+
+    #[test]
+    fn test_format_report_summary() {
+        let report = |remote_name: &str, items| Report {
+            remote_name: remote_name.to_string(),
+            remote_url: String::new(),
+            items,
+            orphaned_refs: vec![],
+            protection_audit: vec![],
+        };
+        let reports = vec![
+            report(
+                "origin",
+                vec![
+                    report_item("origin/master", Some("default"), false),
+                    report_item("origin/feature", None, true),
+                    report_item("origin/stale", None, false),
+                ],
+            ),
+            report(
+                "upstream",
+                vec![report_item("upstream/release", Some("server"), true)],
+            ),
+        ];
+        assert_eq!(
+            format_report_summary(&reports, Duration::from_millis(400)),
+            "Reported on 4 branches across 2 remotes: 2 protected, 2 with a push request. Took 0.4s."
+        );
+    }
+
+    #[test]
+    fn test_sort_by_staleness() {
+        let updated_at = |branch: &str, days_ago: i64| ReportItem {
+            last_updated: Utc.timestamp(1_600_000_000 - days_ago * 86_400, 0),
+            ..report_item(branch, None, false)
+        };
+        let items = || {
+            vec![
+                updated_at("origin/recent", 1),
+                updated_at("origin/oldest", 90),
+                updated_at("origin/tied-first", 30),
+                updated_at("origin/tied-second", 30),
+                updated_at("origin/old", 60),
+            ]
+        };
+        let branches = |items: &[ReportItem]| {
+            items
+                .iter()
+                .map(|item| item.branch.as_str())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        let mut sorted = items();
+        sort_by_staleness(&mut sorted, None);
+        assert_eq!(
+            branches(&sorted),
+            "origin/oldest origin/old origin/tied-first origin/tied-second origin/recent"
+        );
+
+        let mut top = items();
+        sort_by_staleness(&mut top, Some(2));
+        assert_eq!(branches(&top), "origin/oldest origin/old");
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html(r#"Fix <script> & "quotes" in 'names'"#),
+            "Fix &lt;script&gt; &amp; &quot;quotes&quot; in &#39;names&#39;"
+        );
+    }
+
+    #[test]
+    fn test_render_html() {
+        let mut item = report_item("origin/<evil>", Some("server"), true);
+        item.open_pr_numbers = vec![3, 4];
+        item.message = String::from("Add <b>bold</b> text\n\nWith a body.");
+        let reports = vec![Report {
+            remote_name: String::from("origin"),
+            remote_url: String::from("https://github.com/wayfair/foxdie"),
+            items: vec![item],
+            orphaned_refs: vec![],
+            protection_audit: vec![],
+        }];
+
+        let html = render_html(&reports);
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.ends_with("</html>\n"));
+        assert!(html.contains("<h2>origin <small>https://github.com/wayfair/foxdie</small></h2>"));
+        assert!(html.contains(
+            "<tr><td>origin/&lt;evil&gt;</td><td>Foxdie</td><td>1970-01-01 00:00</td><td>0</td><td>0</td>\
+             <td>server</td><td>#3, #4</td><td>Add &lt;b&gt;bold&lt;/b&gt; text</td></tr>"
+        ));
+        assert!(!html.contains("<evil>"));
+    }
+
+    #[test]
+    fn test_write_report_to_disk_appends_lines() {
+        let path = env::temp_dir().join(format!(
+            "foxdie-test-report-append-{}.json",
+            std::process::id()
+        ));
+        let report = |remote_name: &str| Report {
+            remote_name: remote_name.to_string(),
+            remote_url: format!("https://github.com/wayfair/{}", remote_name),
+            items: vec![report_item("origin/feature", None, false)],
+            orphaned_refs: vec![],
+            protection_audit: vec![],
+        };
+
+        write_report_to_disk(&report("first"), &path, true).unwrap();
+        write_report_to_disk(&report("second"), &path, true).unwrap();
+        let appended = std::fs::read_to_string(&path).unwrap();
+        write_report_to_disk(&report("third"), &path, false).unwrap();
+        let overwritten = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let remote_names = appended
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()["remote_name"].clone()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(remote_names, vec!["first", "second"]);
+        assert!(appended.ends_with('\n'));
+        let written: serde_json::Value = serde_json::from_str(&overwritten).unwrap();
+        assert_eq!(written["remote_name"], "third");
+    }
+
+    #[test]
+    fn test_timestamped_path() {
+        let at = Utc.timestamp(1_609_459_200, 0);
+        assert_eq!(
+            timestamped_path(Path::new("reports/report.json"), at),
+            Path::new("reports/report-2021-01-01T00:00:00Z.json")
+        );
+        assert_eq!(
+            timestamped_path(Path::new("report"), at),
+            Path::new("report-2021-01-01T00:00:00Z")
+        );
+    }
+
+    #[test]
+    fn test_age_in_days() {
+        let now = Utc.timestamp(1_600_000_000, 0);
+        assert_eq!(age_in_days(now, now), 0);
+        assert_eq!(age_in_days(now - chrono::Duration::hours(47), now), 1);
+        assert_eq!(age_in_days(now - chrono::Duration::days(120), now), 120);
+        assert_eq!(age_in_days(now + chrono::Duration::days(1), now), 0);
+    }
+
+    #[test]
+    fn test_render_items() {
+        let now = Utc.timestamp(1_600_000_000, 0);
+        let mut stale = report_item("origin/stale-feature", None, true);
+        stale.last_updated = now - chrono::Duration::days(120);
+        stale.upstream_diverged = 12;
+        stale.downstream_diverged = 3;
+        let mut new = report_item("origin/new", None, false);
+        new.author = String::from("Somebody Else");
+        new.last_updated = now - chrono::Duration::days(2);
+        let items = [stale, new];
+
+        assert_eq!(
+            render_items(&items, now, false),
+            "Branch                Author         Last updated  Age (days)  Ahead/behind  PR\n\
+             origin/stale-feature  Foxdie         2020-05-16    120         12/3          yes\n\
+             origin/new            Somebody Else  2020-09-11    2           0/0"
+        );
+        let colored = render_items(&items, now, true);
+        assert!(colored.contains(&format!("{}origin/stale-feature", RED)));
+        assert!(!colored.contains(&format!("{}origin/new", RED)));
+    }
+
+    #[test]
+    fn test_format_orphaned_refs() {
+        assert_eq!(
+            format_orphaned_refs(
+                &[
+                    String::from("origin/gone"),
+                    String::from("origin/also-gone")
+                ],
+                "origin"
+            ),
+            "2 remote-tracking branches no longer exist on origin. \
+             Run `git fetch --prune origin` to remove them:\n\
+             • origin/gone\n\
+             • origin/also-gone\n"
+        );
+    }
+
+    #[test]
+    fn test_audit_protection() {
+        let branch_names = ["master", "release/1.0", "release/2.0", "feature"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        let protected_branches = vec![
+            ProtectedBranch {
+                pattern: glob::Pattern::new("release/*").unwrap(),
+                source: ProtectionSource::Server,
+            },
+            ProtectedBranch {
+                pattern: glob::Pattern::new("relase/*").unwrap(),
+                source: ProtectionSource::Server,
+            },
+            ProtectedBranch::named("master", ProtectionSource::Default),
+        ];
+
+        let audits = audit_protection(&branch_names, &protected_branches);
+
+        assert_eq!(
+            audits
+                .iter()
+                .map(|audit| (audit.pattern.as_str(), audit.matches))
+                .collect::<Vec<_>>(),
+            vec![("release/*", 2), ("relase/*", 0), ("master", 1)]
+        );
+        assert_eq!(audits[0].branches, vec!["release/1.0", "release/2.0"]);
+        assert_eq!(
+            format_protection_audit(&audits, "origin"),
+            "Branches matched by each protection rule on origin:\n\
+             • release/* (server) matches 2 branches: release/1.0, release/2.0\n\
+             • relase/* (server) matches 0 branches\n\
+             • master (default) matches 1 branches: master\n"
+        );
+    }
+
+    #[test]
+    fn test_open_pr_numbers() {
+        let targeting = |id: i32, source_branch: &str, target_branch: &str| PushRequest {
+            source_branch: source_branch.to_string(),
+            target_branch: target_branch.to_string(),
+            ..push_request(id)
+        };
+        let push_requests = vec![
+            targeting(1, "feature", "master"),
+            targeting(2, "fix", "feature"),
+            targeting(3, "other", "master"),
+        ];
+        assert_eq!(open_pr_numbers("feature", &push_requests), vec![1, 2]);
+        assert_eq!(open_pr_numbers("master", &push_requests), vec![1, 3]);
+        assert!(open_pr_numbers("stale", &push_requests).is_empty());
+    }
+
+    #[test]
+    fn test_review_state() {
+        let push_requests = vec![
+            PushRequest {
+                source_branch: String::from("feature"),
+                ..push_request(1)
+            },
+            PushRequest {
+                source_branch: String::from("fix"),
+                target_branch: String::from("feature"),
+                ..push_request(2)
+            },
+        ];
+        let review_states = vec![(1, ReviewState::ChangesRequested)]
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        assert_eq!(
+            review_state("feature", &push_requests, &review_states),
+            Some(String::from("changes_requested"))
+        );
+        assert_eq!(review_state("fix", &push_requests, &review_states), None);
+        assert_eq!(review_state("master", &push_requests, &review_states), None);
+    }
+}
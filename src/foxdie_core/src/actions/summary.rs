@@ -0,0 +1,293 @@
+// Copyright (c) 2018-2019, Wayfair LLC
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+//  * Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//    disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//    following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING,
+// BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED.
+// IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY,
+// OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+// STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE,
+// EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::error::FoxdieError;
+use serde::Serialize;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+/// The outcome of cleaning up a single repository, or a single remote of a repository.
+#[derive(Debug, Default, Serialize)]
+pub struct CleanupSummary {
+    pub repo_url: String,
+    pub provider: Option<String>,
+    /// The number of branches or push requests looked at.
+    pub examined: usize,
+    pub eligible: usize,
+    /// The number of stale branches or push requests left alone, such as those that are protected or excluded.
+    pub skipped: usize,
+    pub deleted: usize,
+    pub closed: usize,
+    pub error: Option<String>,
+    /// Set when cleaning up branches.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branches: Option<BranchNames>,
+    /// Set when cleaning up push requests.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub push_requests: Option<PushRequestIds>,
+}
+
+/// The names of the branches on a remote that were eligible for deletion, and of those that were deleted.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct BranchNames {
+    pub eligible: Vec<String>,
+    pub deleted: Vec<String>,
+}
+
+/// The numbers of the push requests that were eligible to be closed, and of those that were closed.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct PushRequestIds {
+    pub eligible: Vec<i32>,
+    pub closed: Vec<i32>,
+}
+
+impl CleanupSummary {
+    pub fn new(repo_url: &str) -> Self {
+        CleanupSummary {
+            repo_url: repo_url.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct Totals {
+    repos: usize,
+    examined: usize,
+    eligible: usize,
+    skipped: usize,
+    deleted: usize,
+    closed: usize,
+    errors: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct BatchSummary<'a> {
+    repos: &'a [CleanupSummary],
+    totals: Totals,
+}
+
+fn totals(summaries: &[CleanupSummary]) -> Totals {
+    summaries
+        .iter()
+        .fold(Totals::default(), |totals, summary| Totals {
+            repos: totals.repos + 1,
+            examined: totals.examined + summary.examined,
+            eligible: totals.eligible + summary.eligible,
+            skipped: totals.skipped + summary.skipped,
+            deleted: totals.deleted + summary.deleted,
+            closed: totals.closed + summary.closed,
+            errors: totals.errors + summary.error.iter().count(),
+        })
+}
+
+fn batch_summary_json(summaries: &[CleanupSummary]) -> serde_json::Result<String> {
+    serde_json::to_string(&BatchSummary {
+        repos: summaries,
+        totals: totals(summaries),
+    })
+}
+
+/// Writes the per-repository outcomes of a run, along with their totals, to `path` as JSON.
+pub fn write_batch_summary<P>(summaries: &[CleanupSummary], path: P) -> Result<(), FoxdieError>
+where
+    P: AsRef<Path>,
+{
+    let mut file = File::create(path)?;
+    let json = batch_summary_json(summaries)?;
+    file.write_all(json.as_bytes()).map_err(FoxdieError::from)
+}
+
+/// Describes the totals of a run over `summaries` in a single line. `objects` names what was cleaned up, and `verb` what
+/// was done to them. In a dry run, nothing is acted on, so the number of objects that would have been is given too.
+pub fn format_run_summary(
+    summaries: &[CleanupSummary],
+    objects: &str,
+    verb: &str,
+    dry_run: bool,
+    elapsed: Duration,
+) -> String {
+    let totals = totals(summaries);
+    format!(
+        "Examined {} {}: {} eligible, {} skipped, {} {}{}. Took {:.1}s.",
+        totals.examined,
+        objects,
+        totals.eligible,
+        totals.skipped,
+        totals.deleted + totals.closed,
+        verb,
+        if dry_run {
+            format!(" (dry run; would have {} {})", verb, totals.eligible)
+        } else {
+            String::new()
+        },
+        elapsed.as_secs_f64()
+    )
+}
+
+/// Prints the per-repository outcomes of a run, along with their totals, to stdout as a single line of JSON.
+pub fn print_summary(summaries: &[CleanupSummary]) -> Result<(), FoxdieError> {
+    println!("{}", batch_summary_json(summaries)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_totals() {
+        let summaries = vec![
+            CleanupSummary {
+                eligible: 4,
+                deleted: 3,
+                ..CleanupSummary::new("https://github.com/wayfair/foxdie")
+            },
+            CleanupSummary {
+                eligible: 2,
+                closed: 1,
+                error: Some(String::from("1 of 2 operations failed")),
+                ..CleanupSummary::new("https://gitlab.com/wayfair/foxdie")
+            },
+        ];
+        assert_eq!(
+            totals(&summaries),
+            Totals {
+                repos: 2,
+                examined: 0,
+                eligible: 6,
+                skipped: 0,
+                deleted: 3,
+                closed: 1,
+                errors: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_format_run_summary() {
+        let summaries = vec![
+            CleanupSummary {
+                examined: 10,
+                eligible: 4,
+                skipped: 2,
+                ..CleanupSummary::new("origin")
+            },
+            CleanupSummary {
+                examined: 5,
+                eligible: 1,
+                skipped: 1,
+                ..CleanupSummary::new("upstream")
+            },
+        ];
+        assert_eq!(
+            format_run_summary(
+                &summaries,
+                "branches",
+                "deleted",
+                true,
+                Duration::from_millis(1500)
+            ),
+            "Examined 15 branches: 5 eligible, 3 skipped, 0 deleted (dry run; would have deleted 5). Took 1.5s."
+        );
+
+        let summaries = vec![CleanupSummary {
+            examined: 8,
+            eligible: 3,
+            closed: 2,
+            ..CleanupSummary::new("https://github.com/wayfair/foxdie")
+        }];
+        assert_eq!(
+            format_run_summary(
+                &summaries,
+                "push requests",
+                "closed",
+                false,
+                Duration::from_secs(2)
+            ),
+            "Examined 8 push requests: 3 eligible, 0 skipped, 2 closed. Took 2.0s."
+        );
+    }
+
+    #[test]
+    fn test_batch_summary_json_for_dry_runs() {
+        let summaries = vec![
+            CleanupSummary {
+                provider: Some(String::from("GitHub")),
+                eligible: 2,
+                branches: Some(BranchNames {
+                    eligible: vec![String::from("feature/a"), String::from("feature/b")],
+                    deleted: vec![],
+                }),
+                ..CleanupSummary::new("https://github.com/wayfair/foxdie")
+            },
+            CleanupSummary {
+                provider: Some(String::from("Gitlab")),
+                eligible: 1,
+                push_requests: Some(PushRequestIds {
+                    eligible: vec![7],
+                    closed: vec![],
+                }),
+                ..CleanupSummary::new("https://gitlab.com/wayfair/foxdie")
+            },
+        ];
+        let json: serde_json::Value =
+            serde_json::from_str(&batch_summary_json(&summaries).unwrap()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "repos": [
+                    {
+                        "repo_url": "https://github.com/wayfair/foxdie",
+                        "provider": "GitHub",
+                        "examined": 0,
+                        "eligible": 2,
+                        "skipped": 0,
+                        "deleted": 0,
+                        "closed": 0,
+                        "error": null,
+                        "branches": { "eligible": ["feature/a", "feature/b"], "deleted": [] },
+                    },
+                    {
+                        "repo_url": "https://gitlab.com/wayfair/foxdie",
+                        "provider": "Gitlab",
+                        "examined": 0,
+                        "eligible": 1,
+                        "skipped": 0,
+                        "deleted": 0,
+                        "closed": 0,
+                        "error": null,
+                        "push_requests": { "eligible": [7], "closed": [] },
+                    },
+                ],
+                "totals": {
+                    "repos": 2,
+                    "examined": 0,
+                    "eligible": 3,
+                    "skipped": 0,
+                    "deleted": 0,
+                    "closed": 0,
+                    "errors": 0,
+                },
+            })
+        );
+    }
+}
@@ -0,0 +1,273 @@
+// Copyright (c) 2018-2019, Wayfair LLC
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+//  * Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//    disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//    following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING,
+// BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED.
+// IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY,
+// OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+// STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE,
+// EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::services::{git, ApiError};
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum FoxdieError {
+    UnsupportedProvider(String),
+    PartialFailure(usize, usize),
+    DisallowedHost(String, String),
+    NotAGitRepository(PathBuf),
+    InvalidConfig(PathBuf, String),
+    MissingArgument(&'static str),
+    ConflictingArguments(&'static str, &'static str),
+    NotATerminal(&'static str),
+    AuthenticationFailed(&'static str),
+    UnknownRemote(String),
+    OutputIsADirectory(PathBuf),
+    InvalidTokenFile(PathBuf, String),
+    InvalidCaCert(PathBuf, String),
+    InvalidReposFile(PathBuf, String),
+    Api(ApiError),
+    Git(git::Error),
+    Reqwest(reqwest::Error),
+    SerdeJson(serde_json::Error),
+    Io(io::Error),
+}
+
+impl FoxdieError {
+    /// The code to exit with when a run fails with this error, so that scripts can tell failures apart. 0 is left for
+    /// success and 1 for panics and other unexpected failures.
+    ///
+    /// | Code | Failure |
+    /// |------|---------|
+    /// | 2 | The SCM isn't supported, or its host isn't allowed |
+    /// | 3 | A request to the SCM's API failed, including for authentication |
+    /// | 4 | A Git operation failed, or the directory isn't a Git repository |
+    /// | 5 | A file couldn't be read or written |
+    /// | 6 | The arguments or config file are invalid |
+    /// | 7 | Some of the deletions or closes failed |
+    pub fn exit_code(&self) -> i32 {
+        match *self {
+            FoxdieError::UnsupportedProvider(_) | FoxdieError::DisallowedHost(..) => 2,
+            FoxdieError::Api(_)
+            | FoxdieError::Reqwest(_)
+            | FoxdieError::AuthenticationFailed(_) => 3,
+            FoxdieError::Git(_) | FoxdieError::NotAGitRepository(_) => 4,
+            FoxdieError::Io(_)
+            | FoxdieError::SerdeJson(_)
+            | FoxdieError::InvalidTokenFile(..)
+            | FoxdieError::InvalidCaCert(..)
+            | FoxdieError::InvalidReposFile(..)
+            | FoxdieError::OutputIsADirectory(_) => 5,
+            FoxdieError::InvalidConfig(..)
+            | FoxdieError::MissingArgument(_)
+            | FoxdieError::ConflictingArguments(..)
+            | FoxdieError::NotATerminal(_)
+            | FoxdieError::UnknownRemote(_) => 6,
+            FoxdieError::PartialFailure(..) => 7,
+        }
+    }
+}
+
+impl fmt::Display for FoxdieError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FoxdieError::UnsupportedProvider(ref url) => {
+                write!(f, "Unsupported provider for url: {}", url)
+            }
+            FoxdieError::DisallowedHost(ref host, ref url) => write!(
+                f,
+                "Refusing to contact {} for url {}, as it is not in the list of allowed hosts",
+                host, url
+            ),
+            FoxdieError::NotAGitRepository(ref path) => write!(
+                f,
+                "{} is not a Git repository; run Foxdie from inside a repository or pass a valid DIRECTORY",
+                path.display()
+            ),
+            FoxdieError::InvalidConfig(ref path, ref reason) => {
+                write!(f, "Could not read config file {}: {}", path.display(), reason)
+            }
+            FoxdieError::MissingArgument(name) => write!(
+                f,
+                "--{} must be passed on the command line or set in the config file",
+                name
+            ),
+            FoxdieError::ConflictingArguments(first, second) => write!(
+                f,
+                "--{} and --{} cannot be used together; pass only one of them",
+                first, second
+            ),
+            FoxdieError::NotATerminal(name) => write!(
+                f,
+                "--{} prompts on stdin, which isn't a terminal; run it from an interactive shell",
+                name
+            ),
+            FoxdieError::AuthenticationFailed(provider) => write!(
+                f,
+                "Authentication failed for {}: check that the token is valid and has the scopes Foxdie needs",
+                provider
+            ),
+            FoxdieError::UnknownRemote(ref name) => write!(
+                f,
+                "The repository has no remote named {}; run `git remote` to list them",
+                name
+            ),
+            FoxdieError::OutputIsADirectory(ref path) => write!(
+                f,
+                "Cannot write output to {}, as it is a directory; pass a file path instead",
+                path.display()
+            ),
+            FoxdieError::InvalidTokenFile(ref path, ref reason) => {
+                write!(f, "Could not read a token from {}: {}", path.display(), reason)
+            }
+            FoxdieError::InvalidCaCert(ref path, ref reason) => write!(
+                f,
+                "Could not read a PEM certificate from {}: {}",
+                path.display(),
+                reason
+            ),
+            FoxdieError::InvalidReposFile(ref path, ref reason) => write!(
+                f,
+                "Could not read repositories from {}: {}",
+                path.display(),
+                reason
+            ),
+            FoxdieError::PartialFailure(failed, attempted) => write!(
+                f,
+                "{} of {} operations failed; see the log above for details",
+                failed, attempted
+            ),
+            FoxdieError::Api(ref err) => write!(f, "API error: {}", err),
+            FoxdieError::Git(ref err) => write!(f, "Git error: {}", err),
+            FoxdieError::Reqwest(ref err) => write!(f, "Reqwest error: {}", err),
+            FoxdieError::SerdeJson(ref err) => write!(f, "Serde JSON error: {}", err),
+            FoxdieError::Io(ref err) => write!(f, "Io error: {}", err),
+        }
+    }
+}
+
+impl error::Error for FoxdieError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            FoxdieError::UnsupportedProvider(_) => None,
+            FoxdieError::PartialFailure(..) => None,
+            FoxdieError::DisallowedHost(..) => None,
+            FoxdieError::NotAGitRepository(_) => None,
+            FoxdieError::InvalidConfig(..) => None,
+            FoxdieError::MissingArgument(_) => None,
+            FoxdieError::ConflictingArguments(..) => None,
+            FoxdieError::NotATerminal(_) => None,
+            FoxdieError::AuthenticationFailed(_) => None,
+            FoxdieError::UnknownRemote(_) => None,
+            FoxdieError::OutputIsADirectory(_) => None,
+            FoxdieError::InvalidTokenFile(..) => None,
+            FoxdieError::InvalidCaCert(..) => None,
+            FoxdieError::InvalidReposFile(..) => None,
+            FoxdieError::Api(ref err) => Some(err),
+            FoxdieError::Git(ref err) => Some(err),
+            FoxdieError::Reqwest(ref err) => Some(err),
+            FoxdieError::SerdeJson(ref err) => Some(err),
+            FoxdieError::Io(ref err) => Some(err),
+        }
+    }
+}
+
+impl From<ApiError> for FoxdieError {
+    fn from(err: ApiError) -> Self {
+        FoxdieError::Api(err)
+    }
+}
+
+impl From<git::Error> for FoxdieError {
+    fn from(err: git::Error) -> Self {
+        FoxdieError::Git(err)
+    }
+}
+
+impl From<reqwest::Error> for FoxdieError {
+    fn from(err: reqwest::Error) -> Self {
+        FoxdieError::Reqwest(err)
+    }
+}
+
+impl From<serde_json::Error> for FoxdieError {
+    fn from(err: serde_json::Error) -> Self {
+        FoxdieError::SerdeJson(err)
+    }
+}
+
+impl From<dialoguer::Error> for FoxdieError {
+    fn from(err: dialoguer::Error) -> Self {
+        match err {
+            dialoguer::Error::IO(err) => FoxdieError::Io(err),
+        }
+    }
+}
+
+impl From<io::Error> for FoxdieError {
+    fn from(err: io::Error) -> Self {
+        FoxdieError::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_code() {
+        let path = || PathBuf::from("foxdie.toml");
+        let reason = || String::from("unreadable");
+        let reqwest_error = reqwest::Client::new().get("not a url").build().unwrap_err();
+        let serde_json_error = serde_json::from_str::<u8>("not json").unwrap_err();
+        let cases = vec![
+            (FoxdieError::UnsupportedProvider(String::from("url")), 2),
+            (
+                FoxdieError::DisallowedHost(String::from("host"), String::from("url")),
+                2,
+            ),
+            (
+                FoxdieError::Api(ApiError {
+                    source: Box::new(io::Error::other("401")),
+                    request_id: None,
+                }),
+                3,
+            ),
+            (FoxdieError::Reqwest(reqwest_error), 3),
+            (FoxdieError::AuthenticationFailed("GitHub"), 3),
+            (FoxdieError::Git(git::Error::from_str("bad ref")), 4),
+            (FoxdieError::NotAGitRepository(path()), 4),
+            (
+                FoxdieError::Io(io::Error::new(io::ErrorKind::NotFound, "gone")),
+                5,
+            ),
+            (FoxdieError::SerdeJson(serde_json_error), 5),
+            (FoxdieError::InvalidTokenFile(path(), reason()), 5),
+            (FoxdieError::InvalidCaCert(path(), reason()), 5),
+            (FoxdieError::InvalidReposFile(path(), reason()), 5),
+            (FoxdieError::OutputIsADirectory(path()), 5),
+            (FoxdieError::InvalidConfig(path(), reason()), 6),
+            (FoxdieError::MissingArgument("since"), 6),
+            (FoxdieError::ConflictingArguments("token", "token-file"), 6),
+            (FoxdieError::NotATerminal("interactive"), 6),
+            (FoxdieError::UnknownRemote(String::from("fork")), 6),
+            (FoxdieError::PartialFailure(1, 3), 7),
+        ];
+        for (err, code) in cases {
+            assert_eq!(err.exit_code(), code, "{:?}", err);
+        }
+    }
+}
@@ -0,0 +1,77 @@
+// Copyright (c) 2018-2019, Wayfair LLC
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+//  * Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//    disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//    following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING,
+// BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED.
+// IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY,
+// OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+// STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE,
+// EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! `foxdie_core` is the cleanup logic behind the `foxdie` command line tool, for tools that would rather call it than
+//! shell out to the binary. Each action takes a typed `Options` struct, the same one the command line fills in from its
+//! arguments, and records what it did in a [`CleanupSummary`]. `Options::new` takes what each action can't do without
+//! and leaves everything else off, and new options may be added without a breaking change.
+//!
+//! ```
+//! use chrono::DateTime;
+//! use foxdie_core::actions::push_requests::Options;
+//! use foxdie_core::actions::AuthorFilter;
+//! use foxdie_core::{clean_push_requests, CleanupSummary};
+//! use mockito::{mock, Matcher};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), foxdie_core::FoxdieError> {
+//! // Stand in for GitHub Enterprise with a mock server that has one stale pull request open.
+//! std::env::set_var("GITHUB_BASE_URL", mockito::server_url());
+//! let _user = mock("GET", "/user").with_body("{}").create();
+//! let _pulls = mock("GET", "/repos/wayfair/foxdie/pulls")
+//!     .match_query(Matcher::Any)
+//!     .with_body(
+//!         r#"[{
+//!             "id": 1,
+//!             "html_url": "https://git.example.com/wayfair/foxdie/pull/7",
+//!             "number": 7,
+//!             "title": "Stale",
+//!             "created_at": "2019-01-01T00:00:00Z",
+//!             "updated_at": "2019-01-02T00:00:00Z",
+//!             "head": { "label": "wayfair:stale", "ref": "stale", "sha": "abc", "repo": { "id": 1 } },
+//!             "base": { "label": "wayfair:master", "ref": "master", "sha": "def", "repo": { "id": 1 } }
+//!         }]"#,
+//!     )
+//!     .create();
+//!
+//! let since_date = DateTime::parse_from_rfc3339("2020-01-01T00:00:00Z").unwrap();
+//! let allowed_hosts = [String::from("git.example.com"), String::from("127.0.0.1")];
+//! let authors = AuthorFilter::default();
+//! let mut opts = Options::new(&since_date, "token", &authors);
+//! opts.allowed_hosts = Some(&allowed_hosts);
+//! let url = "https://git.example.com/wayfair/foxdie";
+//! let mut summary = CleanupSummary::new(url);
+//! clean_push_requests(url, &opts, &mut summary).await?;
+//!
+//! assert_eq!(summary.eligible, 1);
+//! assert_eq!(summary.closed, 0);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod actions;
+pub mod error;
+pub mod progress;
+pub mod services;
+
+pub use actions::local::clean_remote_branches;
+pub use actions::push_requests::clean_push_requests;
+pub use actions::report::report;
+pub use actions::summary::CleanupSummary;
+pub use error::FoxdieError;
@@ -0,0 +1,202 @@
+// Copyright (c) 2018-2019, Wayfair LLC
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+//  * Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//    disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//    following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING,
+// BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED.
+// IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY,
+// OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+// STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE,
+// EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Progress indicators for fetches and paginated listings, which can otherwise take long enough on big repositories to
+//! look like a hang. They are drawn on stderr, and only when both stdout and stderr are terminals, so that neither
+//! piped output nor logs redirected to a file pick them up.
+
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Whether progress indicators are drawn. Off until `set_enabled` turns them on.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// The shortest time between redraws, so that frequent updates such as Git's transfer progress don't flood stderr.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The width of a progress bar, in characters.
+const BAR_WIDTH: usize = 30;
+
+const SPINNER_FRAMES: [char; 4] = ['|', '/', '-', '\\'];
+
+/// Turns progress indicators on or off for the rest of the run. They stay off when stdout or stderr isn't a terminal.
+pub fn set_enabled(enabled: bool) {
+    let enabled = enabled && io::stdout().is_terminal() && io::stderr().is_terminal();
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// A progress indicator on a single line of stderr: a bar when the total amount of work is known, and a spinner with a
+/// counter otherwise. The line is cleared when the indicator is dropped.
+#[derive(Debug)]
+pub struct Progress {
+    message: String,
+    unit: &'static str,
+    done: usize,
+    total: Option<usize>,
+    enabled: bool,
+    last_drawn: Option<Instant>,
+}
+
+impl Progress {
+    /// Creates a spinner that counts `unit`s of work, such as pages, without knowing how many there will be.
+    pub fn spinner(message: &str, unit: &'static str) -> Self {
+        Progress::new(message, unit, None)
+    }
+
+    /// Creates a bar for `total` `unit`s of work.
+    pub fn bar(message: &str, unit: &'static str, total: usize) -> Self {
+        Progress::new(message, unit, Some(total))
+    }
+
+    fn new(message: &str, unit: &'static str, total: Option<usize>) -> Self {
+        Progress {
+            message: message.to_string(),
+            unit,
+            done: 0,
+            total,
+            enabled: is_enabled(),
+            last_drawn: None,
+        }
+    }
+
+    /// Records that another unit of work is done.
+    pub fn inc(&mut self) {
+        self.done += 1;
+        self.draw();
+    }
+
+    /// Records that `done` of `total` units of work are done.
+    pub fn set(&mut self, done: usize, total: usize) {
+        self.done = done;
+        self.total = Some(total);
+        self.draw();
+    }
+
+    fn draw(&mut self) {
+        if !self.enabled || self.total == Some(0) {
+            return;
+        }
+        let finished = self.total == Some(self.done);
+        if !finished
+            && self
+                .last_drawn
+                .is_some_and(|drawn| drawn.elapsed() < REDRAW_INTERVAL)
+        {
+            return;
+        }
+        self.last_drawn = Some(Instant::now());
+        let mut stderr = io::stderr();
+        let _ = write!(stderr, "\r{}\x1b[K", self.line());
+        let _ = stderr.flush();
+    }
+
+    fn line(&self) -> String {
+        match self.total {
+            Some(total) => {
+                let filled = (self.done.min(total) * BAR_WIDTH)
+                    .checked_div(total)
+                    .unwrap_or(0);
+                format!(
+                    "{} [{}{}] {}/{} {}",
+                    self.message,
+                    "#".repeat(filled),
+                    "-".repeat(BAR_WIDTH - filled),
+                    self.done,
+                    total,
+                    self.unit
+                )
+            }
+            None => format!(
+                "{} {} {} {}",
+                self.message,
+                SPINNER_FRAMES[self.done % SPINNER_FRAMES.len()],
+                self.done,
+                self.unit
+            ),
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        if self.last_drawn.is_some() {
+            let mut stderr = io::stderr();
+            let _ = write!(stderr, "\r\x1b[K");
+            let _ = stderr.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_without_a_terminal() {
+        set_enabled(true);
+        assert_eq!(
+            is_enabled(),
+            io::stdout().is_terminal() && io::stderr().is_terminal()
+        );
+        set_enabled(false);
+        assert!(!is_enabled());
+
+        let mut bar = Progress::bar("Fetching origin", "objects", 3);
+        let mut spinner = Progress::spinner("Listing pull requests", "pages");
+        for _ in 0..3 {
+            bar.inc();
+            spinner.inc();
+        }
+        bar.set(10, 20);
+        assert!(bar.last_drawn.is_none());
+        assert!(spinner.last_drawn.is_none());
+    }
+
+    #[test]
+    fn test_progress_line() {
+        let mut bar = Progress::bar("Fetching origin", "objects", 4);
+        assert_eq!(
+            bar.line(),
+            format!("Fetching origin [{}] 0/4 objects", "-".repeat(30))
+        );
+        bar.set(1, 4);
+        assert_eq!(
+            bar.line(),
+            format!(
+                "Fetching origin [{}{}] 1/4 objects",
+                "#".repeat(7),
+                "-".repeat(23)
+            )
+        );
+        bar.set(0, 0);
+        assert_eq!(
+            bar.line(),
+            "Fetching origin [------------------------------] 0/0 objects"
+        );
+
+        let mut spinner = Progress::spinner("Listing pull requests", "pages");
+        spinner.inc();
+        assert_eq!(spinner.line(), "Listing pull requests / 1 pages");
+    }
+}
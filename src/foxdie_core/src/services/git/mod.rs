@@ -0,0 +1,1107 @@
+// Copyright (c) 2018-2019, Wayfair LLC
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+//  * Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//    disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//    following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING,
+// BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED.
+// IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY,
+// OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+// STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE,
+// EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::progress::Progress;
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use git2::{self, Commit};
+use log::{debug, info, warn};
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+pub use git2::{Branch, Error, ErrorClass, ErrorCode, Oid, Remote, Repository};
+
+pub fn open_repository<P>(path: P) -> Result<Repository, Error>
+where
+    P: AsRef<Path>,
+{
+    Repository::open(path)
+}
+
+/// The username sent alongside an API token over HTTPS. GitHub accepts any username with a token, and Gitlab expects
+/// this one.
+const TOKEN_USERNAME: &str = "oauth2";
+
+/// What Foxdie can authenticate with, and how long it waits, when fetching from or pushing to a remote.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Credentials<'a> {
+    /// The API token, offered as the password to HTTPS remotes.
+    pub token: Option<&'a str>,
+    /// A private key file to use for SSH remotes instead of the SSH agent.
+    pub ssh_key: Option<&'a Path>,
+    /// The passphrase for `ssh_key`, if it has one.
+    pub ssh_passphrase: Option<&'a str>,
+    /// How long a fetch or push may take before it is cancelled. libgit2 can only be cancelled from its callbacks, so
+    /// this is checked when authenticating and whenever the remote reports progress. A connection that stalls without
+    /// reporting any isn't cancelled.
+    pub timeout: Option<Duration>,
+}
+
+/// Where the credential offered to a remote comes from.
+#[derive(Debug, PartialEq)]
+enum CredentialSource<'a> {
+    SshKeyFile(&'a Path),
+    SshAgent,
+    Token(&'a str),
+    CredentialHelper,
+}
+
+/// Picks where to get a credential of one of `allowed_types` for `url`. For SSH, an explicitly configured key takes
+/// precedence over the SSH agent. Otherwise, the token is used for HTTPS remotes, and the configured credential helper
+/// for anything else.
+fn credential_source<'a>(
+    url: &str,
+    allowed_types: git2::CredentialType,
+    credentials: &Credentials<'a>,
+) -> CredentialSource<'a> {
+    if allowed_types.contains(git2::CredentialType::SSH_KEY)
+        || allowed_types.contains(git2::CredentialType::SSH_CUSTOM)
+    {
+        match credentials.ssh_key {
+            Some(path) => CredentialSource::SshKeyFile(path),
+            None => CredentialSource::SshAgent,
+        }
+    } else {
+        match credentials.token {
+            Some(token)
+                if allowed_types.contains(git2::CredentialType::USER_PASS_PLAINTEXT)
+                    && url.starts_with("https://") =>
+            {
+                CredentialSource::Token(token)
+            }
+            _ => CredentialSource::CredentialHelper,
+        }
+    }
+}
+
+fn credentials_for(
+    config: &git2::Config,
+    url: &str,
+    username_from_url: Option<&str>,
+    allowed_types: git2::CredentialType,
+    credentials: &Credentials,
+) -> Result<git2::Cred, Error> {
+    match credential_source(url, allowed_types, credentials) {
+        CredentialSource::SshKeyFile(path) => {
+            let username = username_from_url
+                .expect("A username in the URL is required for SSH and Git to work.");
+            git2::Cred::ssh_key(username, None, path, credentials.ssh_passphrase)
+        }
+        CredentialSource::SshAgent => {
+            let username = username_from_url
+                .expect("A username in the URL is required for SSH and Git to work.");
+            git2::Cred::ssh_key_from_agent(username)
+        }
+        CredentialSource::Token(token) => {
+            git2::Cred::userpass_plaintext(username_from_url.unwrap_or(TOKEN_USERNAME), token)
+        }
+        CredentialSource::CredentialHelper => {
+            git2::Cred::credential_helper(config, url, username_from_url)
+        }
+    }
+}
+
+/// Whether `deadline` has passed, warning when it has, as the operation is then cancelled.
+fn timed_out(deadline: Option<Instant>) -> bool {
+    match deadline {
+        Some(deadline) if Instant::now() >= deadline => {
+            warn!("Cancelling the fetch or push, as it took longer than --timeout allows");
+            true
+        }
+        _ => false,
+    }
+}
+
+fn authorized_remote_callbacks<'a>(
+    credentials: &Credentials<'a>,
+) -> Result<git2::RemoteCallbacks<'a>, Error> {
+    let config = git2::Config::open_default()?;
+    let credentials = *credentials;
+    let deadline = credentials.timeout.map(|timeout| Instant::now() + timeout);
+    let mut cbs = git2::RemoteCallbacks::new();
+    cbs.credentials(move |url, username_from_url, allowed_types| {
+        if timed_out(deadline) {
+            return Err(Error::from_str("timed out"));
+        }
+        credentials_for(&config, url, username_from_url, allowed_types, &credentials)
+    });
+    cbs.sideband_progress(move |data| {
+        if timed_out(deadline) {
+            return false;
+        }
+        use std::io::{self, Write};
+        use std::str;
+
+        debug!(
+            "remote: {}",
+            str::from_utf8(data).unwrap_or("err: unable to convert data to utf8 string\n")
+        );
+        io::stdout()
+            .flush()
+            .expect("could not flush libgit IO stream");
+        true
+    });
+    let mut progress = Progress::bar("Receiving objects", "objects", 0);
+    cbs.transfer_progress(move |stats| {
+        progress.set(stats.received_objects(), stats.total_objects());
+        !timed_out(deadline)
+    });
+    Ok(cbs)
+}
+
+/// Fetches the remote's configured refspecs, pruning remote-tracking branches whose branch has been deleted upstream.
+pub fn fetch_refs(remote: &mut Remote, credentials: &Credentials) -> Result<(), Error> {
+    let mut opts = git2::FetchOptions::new();
+    opts.remote_callbacks(authorized_remote_callbacks(credentials)?);
+    opts.prune(git2::FetchPrune::On);
+    info!(
+        "Fetching remote refs from {} ({})",
+        remote.name().unwrap_or("[UNKNOWN REMOTE NAME]"),
+        remote.url().unwrap_or("[UNKNOWN REMOTE URL]")
+    );
+    let refs: [&str; 0] = [];
+    remote.fetch(&refs, Some(&mut opts), None)
+}
+
+/// Returns the remote-tracking branches for `remote` whose branch the remote no longer advertises, such as `origin/x`
+/// after `x` was deleted on the server. Only the remote's advertised refs are listed; nothing is fetched or pruned.
+pub fn get_orphaned_tracking_branches(
+    repo: &Repository,
+    remote: &mut Remote,
+    credentials: &Credentials,
+) -> Result<Vec<String>, Error> {
+    let remote_name = remote.name().unwrap_or_default().to_string();
+    let connection = remote.connect_auth(
+        git2::Direction::Fetch,
+        Some(authorized_remote_callbacks(credentials)?),
+        None,
+    )?;
+    let advertised = connection
+        .list()?
+        .iter()
+        .filter_map(|head| head.name().strip_prefix("refs/heads/"))
+        .map(String::from)
+        .collect::<Vec<_>>();
+    drop(connection);
+
+    let mut orphaned = vec![];
+    for reference in repo.references_glob(&format!("refs/remotes/{}/*", remote_name))? {
+        let reference = reference?;
+        if reference.kind() == Some(git2::ReferenceType::Symbolic) {
+            // Skip `origin/HEAD`, which points at another tracking branch rather than a branch of its own.
+            continue;
+        }
+        if let Some(name) = reference.name() {
+            if !advertised.contains(&bare_branch_name(name, &remote_name)) {
+                orphaned.push(reference.shorthand().unwrap_or(name).to_string());
+            }
+        }
+    }
+    Ok(orphaned)
+}
+
+pub fn get_current_branch(repo: &Repository) -> Result<Branch<'_>, Error> {
+    let head = repo.head()?;
+    if head.is_branch() {
+        Ok(Branch::wrap(head))
+    } else {
+        Err(Error::from_str("HEAD must be a branch"))
+    }
+}
+
+/// Finds the branch that branches on `remote_name` are measured against: the remote-tracking branch for `configured`
+/// when one is given, and otherwise the remote's default branch, as recorded by `refs/remotes/<remote>/HEAD`.
+pub fn get_base_branch<'repo>(
+    repo: &'repo Repository,
+    remote_name: &str,
+    configured: Option<&str>,
+) -> Option<Branch<'repo>> {
+    if let Some(branch_name) = configured {
+        let full_ref = format!("refs/remotes/{}/{}", remote_name, branch_name);
+        match repo.find_reference(&full_ref) {
+            Ok(reference) => return Some(Branch::wrap(reference)),
+            Err(_) => warn!(
+                "Base branch {} was not found on {}; using its default branch instead",
+                branch_name, remote_name
+            ),
+        }
+    }
+    repo.find_reference(&format!("refs/remotes/{}/HEAD", remote_name))
+        .and_then(|head| head.resolve())
+        .map(Branch::wrap)
+        .ok()
+}
+
+/// Finds the name of the default branch of `remote_name`, as recorded by `refs/remotes/<remote>/HEAD`. Clones record it,
+/// but plain fetches, such as those CI checkouts often make, don't.
+pub fn get_default_branch_name(repo: &Repository, remote_name: &str) -> Option<String> {
+    let head = repo
+        .find_reference(&format!("refs/remotes/{}/HEAD", remote_name))
+        .ok()?;
+    head.symbolic_target()
+        .map(|target| bare_branch_name(target, remote_name))
+}
+
+/// Reduces a reference to a branch on `remote_name` to the branch's bare name, whichever layout the reference is written
+/// in: `refs/remotes/origin/feature`, `remotes/origin/feature`, `origin/feature`, `refs/heads/feature` and `feature` all
+/// become `feature`.
+pub fn bare_branch_name(full_ref: &str, remote_name: &str) -> String {
+    if let Some(name) = full_ref.strip_prefix("refs/heads/") {
+        return name.to_string();
+    }
+    let tracking_name = full_ref
+        .strip_prefix("refs/remotes/")
+        .or_else(|| full_ref.strip_prefix("remotes/"))
+        .unwrap_or(full_ref);
+    tracking_name
+        .strip_prefix(remote_name)
+        .and_then(|name| name.strip_prefix('/'))
+        .unwrap_or(tracking_name)
+        .to_string()
+}
+
+/// Returns the remote-tracking branches of `remote_name`, leaving out symbolic refs such as `origin/HEAD`, which only
+/// point at another branch. The branches of other remotes are left out too, so that they are never taken for branches
+/// on `remote_name`.
+pub fn get_remote_branches<'repo>(
+    repo: &'repo Repository,
+    remote_name: &str,
+) -> Result<impl Iterator<Item = Result<(Branch<'repo>, git2::BranchType), Error>> + 'repo, Error> {
+    let prefix = format!("refs/remotes/{}/", remote_name);
+    Ok(repo
+        .branches(Some(git2::BranchType::Remote))?
+        .filter(move |res| match res {
+            Ok((branch, _)) => {
+                branch.get().name_bytes().starts_with(prefix.as_bytes()) && !is_symbolic(branch)
+            }
+            Err(_) => true,
+        }))
+}
+
+/// Finds the remote-tracking branch for `branch_name` on `remote_name`, if it exists.
+pub fn find_remote_branch<'repo>(
+    repo: &'repo Repository,
+    remote_name: &str,
+    branch_name: &str,
+) -> Result<Option<Branch<'repo>>, Error> {
+    match repo.find_branch(
+        &format!("{}/{}", remote_name, branch_name),
+        git2::BranchType::Remote,
+    ) {
+        Ok(branch) => Ok(Some(branch)),
+        Err(err) if err.code() == ErrorCode::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Whether `branch` is a symbolic ref, such as `origin/HEAD`, rather than a branch of its own.
+fn is_symbolic(branch: &Branch) -> bool {
+    branch.get().symbolic_target().is_some()
+        || branch
+            .name()
+            .ok()
+            .flatten()
+            .is_some_and(|name| name.ends_with("/HEAD"))
+}
+
+/// Returns the names of local branches that were checked out, according to the `HEAD` reflog, at or after `date`.
+pub fn get_branches_checked_out_since(
+    repo: &Repository,
+    date: &DateTime<FixedOffset>,
+) -> Result<Vec<String>, Error> {
+    let reflog = repo.reflog("HEAD")?;
+    let mut branch_names = reflog
+        .iter()
+        .filter(|entry| entry.committer().when().seconds() >= date.timestamp())
+        .filter_map(|entry| {
+            entry
+                .message()
+                .and_then(checkout_target_from_reflog_message)
+                .map(String::from)
+        })
+        .collect::<Vec<_>>();
+    branch_names.sort();
+    branch_names.dedup();
+    Ok(branch_names)
+}
+
+/// Returns the names of the authors of every commit, on any branch or tag, committed at or after `date`. The history is
+/// walked once, newest first, stopping at the first commit before `date`.
+pub fn get_authors_active_since(
+    repo: &Repository,
+    date: &DateTime<FixedOffset>,
+) -> Result<HashSet<String>, Error> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(git2::Sort::TIME)?;
+    revwalk.push_glob("*")?;
+    let mut authors = HashSet::new();
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        if commit.time().seconds() < date.timestamp() {
+            break;
+        }
+        authors.insert(author_name(&commit));
+    }
+    Ok(authors)
+}
+
+/// Extracts the destination of a checkout from a reflog message of the form `checkout: moving from <old> to <new>`.
+fn checkout_target_from_reflog_message(message: &str) -> Option<&str> {
+    let moves = message.strip_prefix("checkout: moving from ")?;
+    moves
+        .rsplit(" to ")
+        .next()
+        .filter(|target| !target.is_empty())
+}
+
+pub fn get_divergence_between_branches(
+    repo: &Repository,
+    left: &Branch,
+    right: &Branch,
+) -> Result<(usize, usize), Error> {
+    let left_oid = branch_to_oid(left)?;
+    let right_oid = branch_to_oid(right)?;
+    repo.graph_ahead_behind(left_oid, right_oid)
+}
+
+/// Returns whether the tip of `branch` is already in the history of `base`, so that deleting `branch` would lose no
+/// commits.
+pub fn is_merged_into(repo: &Repository, base: &Branch, branch: &Branch) -> Result<bool, Error> {
+    let base_oid = branch_to_oid(base)?;
+    let branch_oid = branch_to_oid(branch)?;
+    Ok(base_oid == branch_oid || repo.graph_descendant_of(base_oid, branch_oid)?)
+}
+
+/// Like [`get_divergence_between_branches`], but only counts commits along each branch's first-parent history, which
+/// ignores the commits merged in from other branches.
+pub fn get_first_parent_divergence_between_branches(
+    repo: &Repository,
+    left: &Branch,
+    right: &Branch,
+) -> Result<(usize, usize), Error> {
+    let left_oid = branch_to_oid(left)?;
+    let right_oid = branch_to_oid(right)?;
+    Ok((
+        count_first_parent_commits(repo, left_oid, right_oid)?,
+        count_first_parent_commits(repo, right_oid, left_oid)?,
+    ))
+}
+
+/// Counts the commits along `from`'s first-parent history that are not reachable from `hidden`.
+fn count_first_parent_commits(
+    repo: &Repository,
+    from: git2::Oid,
+    hidden: git2::Oid,
+) -> Result<usize, Error> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.simplify_first_parent()?;
+    revwalk.push(from)?;
+    revwalk.hide(hidden)?;
+    revwalk.try_fold(0, |count, oid| oid.map(|_| count + 1))
+}
+
+/// Returns when the latest commit on `branch` was committed.
+pub fn last_commit_date(repo: &Repository, branch: &Branch) -> Result<DateTime<Utc>, Error> {
+    let commit = commit_for_branch(repo, branch)?;
+    Ok(Utc.timestamp(commit.time().seconds(), 0))
+}
+
+/// Which date is taken as when a branch was last worked on.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ActivityMetric {
+    /// When the latest commit on the branch was committed. Rebasing or cherry-picking old work makes this recent.
+    Committer,
+    /// When the latest commit on the branch was authored, which survives rebases and cherry-picks.
+    Author,
+    /// When the branch's reference was last updated locally, such as by a fetch, according to its reflog.
+    Reflog,
+}
+
+/// Returns when `branch` was last worked on, as measured by `metric`. A branch without a reflog falls back to when its
+/// latest commit was committed.
+pub fn last_activity_date(
+    repo: &Repository,
+    branch: &Branch,
+    metric: ActivityMetric,
+) -> Result<DateTime<Utc>, Error> {
+    match metric {
+        ActivityMetric::Committer => last_commit_date(repo, branch),
+        ActivityMetric::Author => {
+            let commit = commit_for_branch(repo, branch)?;
+            let authored = commit.author().when();
+            Ok(Utc.timestamp(authored.seconds(), 0))
+        }
+        ActivityMetric::Reflog => {
+            let name = branch
+                .get()
+                .name()
+                .ok_or_else(|| Error::from_str("The branch's name is not valid UTF-8"))?;
+            // The newest entry comes first.
+            let reflog = repo.reflog(name)?;
+            let updated = reflog.get(0).map(|entry| entry.committer().when());
+            match updated {
+                Some(updated) => Ok(Utc.timestamp(updated.seconds(), 0)),
+                None => last_commit_date(repo, branch),
+            }
+        }
+    }
+}
+
+/// Who the repository's Git config says commits are authored by.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Identity {
+    pub name: Option<String>,
+    pub email: Option<String>,
+}
+
+impl Identity {
+    /// Whether a commit by the author with `name` and `email` was made by this identity. Emails are compared without
+    /// regard to case, and names are only compared when the identity has no email.
+    pub fn matches(&self, name: Option<&str>, email: Option<&str>) -> bool {
+        match (&self.email, &self.name) {
+            (Some(own_email), _) => {
+                email.is_some_and(|email| email.eq_ignore_ascii_case(own_email))
+            }
+            (None, Some(own_name)) => name == Some(own_name.as_str()),
+            (None, None) => false,
+        }
+    }
+}
+
+/// Reads `user.name` and `user.email` from the repository's Git config, failing if neither is set.
+pub fn get_configured_identity(repo: &Repository) -> Result<Identity, Error> {
+    let config = repo.config()?;
+    let identity = Identity {
+        name: config.get_string("user.name").ok(),
+        email: config.get_string("user.email").ok(),
+    };
+    if identity.name.is_none() && identity.email.is_none() {
+        return Err(Error::from_str(
+            "Neither user.name nor user.email is set in the Git config",
+        ));
+    }
+    Ok(identity)
+}
+
+pub fn commit_for_branch<'repo>(
+    repo: &'repo git2::Repository,
+    branch: &Branch,
+) -> Result<Commit<'repo>, Error> {
+    let oid = branch_to_oid(branch)?;
+    repo.find_commit(oid)
+}
+
+/// Returns the name of the author of `commit`. Bytes that aren't valid UTF-8, as in names recorded by older tools, are
+/// replaced with U+FFFD.
+pub fn author_name(commit: &Commit) -> String {
+    String::from_utf8_lossy(commit.author().name_bytes()).into_owned()
+}
+
+/// Returns the message of `commit`, replacing bytes that aren't valid UTF-8 like `author_name`.
+pub fn commit_message(commit: &Commit) -> String {
+    String::from_utf8_lossy(commit.message_bytes()).into_owned()
+}
+
+/// Deletes the local branches whose upstream is one of `deleted`: branches just deleted from the remote named
+/// `remote_name`, by name on the remote, paired with the commit each pointed to. A local branch is kept, with a warning,
+/// when it is checked out or has commits its upstream didn't. Returns the names of the local branches deleted.
+pub fn prune_local_branches(
+    repo: &Repository,
+    remote_name: &str,
+    deleted: &[(String, Oid)],
+) -> Result<Vec<String>, Error> {
+    let tracking_prefix = format!("refs/remotes/{}/", remote_name);
+    let mut pruned = vec![];
+    for (mut branch, _) in repo
+        .branches(Some(git2::BranchType::Local))?
+        .filter_map(Result::ok)
+    {
+        let name = match branch.name() {
+            Ok(Some(name)) => name.to_string(),
+            _ => continue,
+        };
+        let remote_tip = match upstream_ref_name(repo, &branch)
+            .as_deref()
+            .and_then(|upstream| upstream.strip_prefix(&tracking_prefix))
+            .and_then(|upstream| deleted.iter().find(|(deleted, _)| deleted == upstream))
+        {
+            Some((_, oid)) => *oid,
+            None => continue,
+        };
+        if branch.is_head() {
+            warn!("Kept the local branch {}, as it is checked out.", name);
+            continue;
+        }
+        let tip = branch_to_oid(&branch)?;
+        if tip != remote_tip && !repo.graph_descendant_of(remote_tip, tip)? {
+            warn!(
+                "Kept the local branch {}, as it has commits that were never pushed to {}.",
+                name, remote_name
+            );
+            continue;
+        }
+        branch.delete()?;
+        pruned.push(name);
+    }
+    Ok(pruned)
+}
+
+/// Returns the full name of the remote-tracking branch configured as `branch`'s upstream, whether or not it still
+/// exists.
+fn upstream_ref_name(repo: &Repository, branch: &Branch) -> Option<String> {
+    let refname = branch.get().name()?;
+    repo.branch_upstream_name(refname)
+        .ok()?
+        .as_str()
+        .map(String::from)
+}
+
+fn branch_to_oid(branch: &Branch) -> Result<git2::Oid, Error> {
+    branch
+        .get()
+        .resolve()?
+        .target()
+        .ok_or_else(|| Error::from_str("Could not peel OID from branch"))
+}
+
+/// Pushes `refspecs` to the remote named `remote`. Returns the references the remote rejected, each paired with the
+/// status message reported for it.
+pub fn push_to_remote(
+    repo: &Repository,
+    remote: &str,
+    refspecs: &[&str],
+    credentials: &Credentials,
+) -> Result<Vec<(String, String)>, Error> {
+    let mut remote = repo.find_remote(remote)?;
+    let mut rejected = vec![];
+    {
+        let mut cbs = authorized_remote_callbacks(credentials)?;
+        cbs.push_update_reference(|refname, status| {
+            if let Some(message) = status {
+                rejected.push((refname.to_string(), message.to_string()));
+            }
+            Ok(())
+        });
+        let mut opts = git2::PushOptions::new();
+        opts.remote_callbacks(cbs);
+        remote.push(refspecs, Some(&mut opts))?;
+    }
+    Ok(rejected)
+}
+
+/// Fixtures for the tests of every module that works with Git repositories.
+#[cfg(test)]
+pub(crate) mod test_support {
+    use super::{Commit, Repository};
+    use std::env;
+    use std::fs;
+    use std::ops::Deref;
+    use std::path::{Path, PathBuf};
+    use std::process;
+
+    /// A repository in a temporary directory of its own, which is removed when the `TestRepo` is dropped, so that
+    /// nothing is left behind when an assertion fails first.
+    pub(crate) struct TestRepo {
+        path: PathBuf,
+        repo: Repository,
+    }
+
+    impl TestRepo {
+        /// Initializes a repository with a working directory in a temporary directory named after `name`, which must
+        /// be unique among the tests.
+        pub(crate) fn init(name: &str) -> Self {
+            let path = temp_path(name);
+            let repo = Repository::init(&path).unwrap();
+            TestRepo { path, repo }
+        }
+
+        /// Initializes a bare repository in a temporary directory named after `name`, as `init` does.
+        pub(crate) fn init_bare(name: &str) -> Self {
+            let path = temp_path(name);
+            let repo = Repository::init_bare(&path).unwrap();
+            TestRepo { path, repo }
+        }
+
+        /// The repository's working directory, or the repository itself when it is bare.
+        pub(crate) fn path(&self) -> &Path {
+            &self.path
+        }
+
+        /// Points `refs/remotes/<remote_name>/<name>` at a commit by `signature` for each of `names`, as though they
+        /// had been fetched. Each branch gets a commit of its own, as references to the same commit compare equal.
+        pub(crate) fn add_remote_branches(
+            &self,
+            remote_name: &str,
+            names: &[&str],
+            signature: &git2::Signature,
+        ) {
+            for name in names {
+                let tip = commit_as(&self.repo, signature, name, &[]);
+                let full_ref = format!("refs/remotes/{}/{}", remote_name, name);
+                self.repo
+                    .reference(&full_ref, tip.id(), false, "test")
+                    .unwrap();
+            }
+        }
+    }
+
+    impl Deref for TestRepo {
+        type Target = Repository;
+
+        fn deref(&self) -> &Repository {
+            &self.repo
+        }
+    }
+
+    impl Drop for TestRepo {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("foxdie-test-{}-{}", name, process::id()))
+    }
+
+    /// Commits an empty tree on top of `parents`, as Foxdie and at the current time, without moving any branch.
+    pub(crate) fn commit<'repo>(
+        repo: &'repo Repository,
+        message: &str,
+        parents: &[&Commit],
+    ) -> Commit<'repo> {
+        let signature = git2::Signature::now("Foxdie", "foxdie@example.com").unwrap();
+        commit_as(repo, &signature, message, parents)
+    }
+
+    /// Commits an empty tree on top of `parents`, authored and committed by `signature`, without moving any branch.
+    pub(crate) fn commit_as<'repo>(
+        repo: &'repo Repository,
+        signature: &git2::Signature,
+        message: &str,
+        parents: &[&Commit],
+    ) -> Commit<'repo> {
+        let tree_oid = repo.treebuilder(None).unwrap().write().unwrap();
+        let tree = repo.find_tree(tree_oid).unwrap();
+        let oid = repo
+            .commit(None, signature, signature, message, &tree, parents)
+            .unwrap();
+        repo.find_commit(oid).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_support::{commit, commit_as, TestRepo};
+    use super::*;
+
+    #[test]
+    fn test_get_authors_active_since() {
+        let repo = TestRepo::init("active-authors");
+        let inactive = git2::Signature::new(
+            "Inactive Dev",
+            "inactive@example.com",
+            &git2::Time::new(1_500_000_000, 0),
+        )
+        .unwrap();
+        let active = git2::Signature::now("Active Dev", "active@example.com").unwrap();
+        let old = commit_as(&repo, &inactive, "Old", &[]);
+        let new = commit_as(&repo, &active, "New", &[&old]);
+        repo.reference("refs/heads/main", old.id(), false, "test")
+            .unwrap();
+        // Only on someone else's branch, not the one checked out.
+        repo.reference("refs/remotes/origin/feature", new.id(), false, "test")
+            .unwrap();
+        let since = (Utc::now() - chrono::Duration::days(30)).into();
+
+        let authors = get_authors_active_since(&repo, &since).unwrap();
+
+        assert!(authors.contains("Active Dev"));
+        assert!(!authors.contains("Inactive Dev"));
+    }
+
+    #[test]
+    fn test_last_activity_date() {
+        let repo = TestRepo::init("activity-date");
+        let signature_at = |seconds| {
+            git2::Signature::new("Dev", "dev@example.com", &git2::Time::new(seconds, 0)).unwrap()
+        };
+        // Authored long ago, but rebased recently.
+        let tree_oid = repo.treebuilder(None).unwrap().write().unwrap();
+        let tree = repo.find_tree(tree_oid).unwrap();
+        let oid = repo
+            .commit(
+                None,
+                &signature_at(1_500_000_000),
+                &signature_at(1_600_000_000),
+                "Rebased",
+                &tree,
+                &[],
+            )
+            .unwrap();
+        let name = "refs/remotes/origin/rebased";
+        repo.reference(name, oid, false, "test").unwrap();
+        let branch = repo
+            .find_branch("origin/rebased", git2::BranchType::Remote)
+            .unwrap();
+        let date = |metric| {
+            last_activity_date(&repo, &branch, metric)
+                .unwrap()
+                .timestamp()
+        };
+
+        assert_eq!(date(ActivityMetric::Committer), 1_600_000_000);
+        assert_eq!(date(ActivityMetric::Author), 1_500_000_000);
+
+        let mut reflog = repo.reflog(name).unwrap();
+        reflog
+            .append(oid, &signature_at(1_550_000_000), Some("fetch"))
+            .unwrap();
+        reflog.write().unwrap();
+        assert_eq!(date(ActivityMetric::Reflog), 1_550_000_000);
+    }
+
+    #[test]
+    fn test_first_parent_divergence_on_merge_heavy_history() {
+        let repo = TestRepo::init("divergence");
+        // A --- B --- M   (mainline)
+        //  \         /
+        //   S1 --- S2     (merged side branch)
+        let a = commit(&repo, "A", &[]);
+        let b = commit(&repo, "B", &[&a]);
+        let s1 = commit(&repo, "S1", &[&a]);
+        let s2 = commit(&repo, "S2", &[&s1]);
+        let m = commit(&repo, "M", &[&b, &s2]);
+        let mainline = repo.branch("mainline", &m, false).unwrap();
+        let base = repo.branch("base", &a, false).unwrap();
+
+        assert_eq!(
+            get_divergence_between_branches(&repo, &mainline, &base).unwrap(),
+            (4, 0)
+        );
+        assert_eq!(
+            get_first_parent_divergence_between_branches(&repo, &mainline, &base).unwrap(),
+            (2, 0)
+        );
+    }
+
+    #[test]
+    fn test_is_merged_into() {
+        let repo = TestRepo::init("merged-into");
+        // A --- B --- M   (base)
+        //  \         /
+        //   S1 --- S2     (merged)
+        //                //     D           (diverged)
+        let a = commit(&repo, "A", &[]);
+        let b = commit(&repo, "B", &[&a]);
+        let s1 = commit(&repo, "S1", &[&a]);
+        let s2 = commit(&repo, "S2", &[&s1]);
+        let d = commit(&repo, "D", &[&s1]);
+        let m = commit(&repo, "M", &[&b, &s2]);
+        let base = repo.branch("base", &m, false).unwrap();
+        let merged = repo.branch("merged", &s2, false).unwrap();
+        let diverged = repo.branch("diverged", &d, false).unwrap();
+        let same = repo.branch("same", &m, false).unwrap();
+
+        assert!(is_merged_into(&repo, &base, &merged).unwrap());
+        assert!(is_merged_into(&repo, &base, &same).unwrap());
+        assert!(!is_merged_into(&repo, &base, &diverged).unwrap());
+    }
+
+    #[test]
+    fn test_prune_local_branches() {
+        let repo = TestRepo::init("prune-local");
+        repo.remote("origin", "https://git.example.com/wayfair/foxdie.git")
+            .unwrap();
+        let base = commit(&repo, "base", &[]);
+        let mut deleted = vec![];
+        for name in &["stale", "ahead", "current", "untracked"] {
+            let tip = commit(&repo, name, &[&base]);
+            repo.reference(
+                &format!("refs/remotes/origin/{}", name),
+                tip.id(),
+                false,
+                "test",
+            )
+            .unwrap();
+            let local_tip = match *name {
+                "ahead" => commit(&repo, "unpushed", &[&tip]),
+                _ => tip.clone(),
+            };
+            let mut local = repo.branch(name, &local_tip, false).unwrap();
+            if *name != "untracked" {
+                local
+                    .set_upstream(Some(&format!("origin/{}", name)))
+                    .unwrap();
+                deleted.push((name.to_string(), tip.id()));
+            }
+        }
+        repo.set_head("refs/heads/current").unwrap();
+
+        let pruned = prune_local_branches(&repo, "origin", &deleted).unwrap();
+
+        assert_eq!(pruned, vec!["stale"]);
+        let local = |name| repo.find_branch(name, git2::BranchType::Local).is_ok();
+        assert!(!local("stale"));
+        assert!(local("ahead"));
+        assert!(local("current"));
+        assert!(local("untracked"));
+    }
+
+    #[test]
+    fn test_fetch_refs_prunes_deleted_branches() {
+        let upstream = TestRepo::init_bare("prune-upstream");
+        let downstream = TestRepo::init("prune-downstream");
+        let initial = commit(&upstream, "Initial", &[]);
+        upstream.branch("kept", &initial, false).unwrap();
+        let mut deleted = upstream.branch("deleted", &initial, false).unwrap();
+        let mut remote = downstream
+            .remote("origin", upstream.path().to_str().unwrap())
+            .unwrap();
+
+        fetch_refs(&mut remote, &Credentials::default()).unwrap();
+        assert!(downstream
+            .find_branch("origin/deleted", git2::BranchType::Remote)
+            .is_ok());
+
+        deleted.delete().unwrap();
+        fetch_refs(&mut remote, &Credentials::default()).unwrap();
+        assert!(downstream
+            .find_branch("origin/deleted", git2::BranchType::Remote)
+            .is_err());
+        assert!(downstream
+            .find_branch("origin/kept", git2::BranchType::Remote)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_get_orphaned_tracking_branches() {
+        let upstream = TestRepo::init_bare("orphans-upstream");
+        let downstream = TestRepo::init("orphans-downstream");
+        let initial = commit(&upstream, "Initial", &[]);
+        upstream.branch("kept", &initial, false).unwrap();
+        let mut deleted = upstream.branch("deleted", &initial, false).unwrap();
+        let mut remote = downstream
+            .remote("origin", upstream.path().to_str().unwrap())
+            .unwrap();
+        fetch_refs(&mut remote, &Credentials::default()).unwrap();
+        deleted.delete().unwrap();
+
+        assert_eq!(
+            get_orphaned_tracking_branches(&downstream, &mut remote, &Credentials::default())
+                .unwrap(),
+            vec![String::from("origin/deleted")]
+        );
+        assert!(downstream
+            .find_branch("origin/deleted", git2::BranchType::Remote)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_get_remote_branches_skips_symbolic_refs_and_other_remotes() {
+        let repo = TestRepo::init("symbolic");
+        let initial = commit(&repo, "Initial", &[]);
+        for full_ref in &[
+            "refs/remotes/origin/main",
+            "refs/remotes/origin/feature",
+            "refs/remotes/upstream/feature",
+            "refs/remotes/origin-mirror/feature",
+        ] {
+            repo.reference(full_ref, initial.id(), false, "test")
+                .unwrap();
+        }
+        repo.reference_symbolic(
+            "refs/remotes/origin/HEAD",
+            "refs/remotes/origin/main",
+            false,
+            "test",
+        )
+        .unwrap();
+
+        let mut names = get_remote_branches(&repo, "origin")
+            .unwrap()
+            .filter_map(Result::ok)
+            .filter_map(|(branch, _)| branch.name().ok().flatten().map(String::from))
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["origin/feature", "origin/main"]);
+    }
+
+    #[test]
+    fn test_get_base_branch() {
+        let repo = TestRepo::init("base");
+        let initial = commit(&repo, "Initial", &[]);
+        for full_ref in &["refs/remotes/origin/main", "refs/remotes/upstream/master"] {
+            repo.reference(full_ref, initial.id(), false, "").unwrap();
+        }
+        repo.reference_symbolic(
+            "refs/remotes/origin/HEAD",
+            "refs/remotes/origin/main",
+            false,
+            "",
+        )
+        .unwrap();
+        let base_name = |remote_name, configured| {
+            get_base_branch(&repo, remote_name, configured)
+                .and_then(|branch| branch.get().name().map(String::from))
+        };
+
+        assert_eq!(
+            base_name("upstream", Some("master")),
+            Some(String::from("refs/remotes/upstream/master"))
+        );
+        assert_eq!(
+            base_name("origin", None),
+            Some(String::from("refs/remotes/origin/main"))
+        );
+        assert_eq!(
+            base_name("origin", Some("missing")),
+            Some(String::from("refs/remotes/origin/main"))
+        );
+        assert_eq!(base_name("upstream", None), None);
+    }
+
+    #[test]
+    fn test_identity_matches() {
+        let identity = Identity {
+            name: Some(String::from("Foxdie")),
+            email: Some(String::from("foxdie@example.com")),
+        };
+        assert!(identity.matches(Some("Someone Else"), Some("Foxdie@Example.com")));
+        assert!(!identity.matches(Some("Foxdie"), Some("other@example.com")));
+        assert!(!identity.matches(Some("Foxdie"), None));
+
+        let name_only = Identity {
+            name: Some(String::from("Foxdie")),
+            email: None,
+        };
+        assert!(name_only.matches(Some("Foxdie"), Some("foxdie@example.com")));
+        assert!(!name_only.matches(Some("Other"), None));
+    }
+
+    #[test]
+    fn test_get_configured_identity() {
+        let repo = TestRepo::init("identity");
+        let mut config = repo
+            .config()
+            .unwrap()
+            .open_level(git2::ConfigLevel::Local)
+            .unwrap();
+        config.set_str("user.name", "Foxdie").unwrap();
+        config.set_str("user.email", "foxdie@example.com").unwrap();
+
+        assert_eq!(
+            get_configured_identity(&repo).unwrap(),
+            Identity {
+                name: Some(String::from("Foxdie")),
+                email: Some(String::from("foxdie@example.com")),
+            }
+        );
+    }
+
+    #[test]
+    fn test_credentials_for_https_with_token() {
+        let config = git2::Config::new().unwrap();
+        let credentials = Credentials {
+            token: Some("token"),
+            ..Credentials::default()
+        };
+        let cred = credentials_for(
+            &config,
+            "https://github.com/wayfair/foxdie.git",
+            None,
+            git2::CredentialType::USER_PASS_PLAINTEXT,
+            &credentials,
+        )
+        .unwrap();
+        assert_eq!(
+            git2::CredentialType::from_bits_truncate(cred.credtype()),
+            git2::CredentialType::USER_PASS_PLAINTEXT
+        );
+
+        assert!(credentials_for(
+            &config,
+            "http://github.com/wayfair/foxdie.git",
+            None,
+            git2::CredentialType::USER_PASS_PLAINTEXT,
+            &credentials,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_credential_source_for_ssh() {
+        let url = "git@github.com:wayfair/foxdie.git";
+        let key = Path::new("/home/foxdie/.ssh/id_rsa");
+        let with_key = Credentials {
+            token: Some("token"),
+            ssh_key: Some(key),
+            ..Credentials::default()
+        };
+        assert_eq!(
+            credential_source(url, git2::CredentialType::SSH_KEY, &with_key),
+            CredentialSource::SshKeyFile(key)
+        );
+        assert_eq!(
+            credential_source(url, git2::CredentialType::SSH_KEY, &Credentials::default()),
+            CredentialSource::SshAgent
+        );
+    }
+
+    #[test]
+    fn test_bare_branch_name() {
+        for full_ref in &[
+            "refs/remotes/origin/feature/foo",
+            "remotes/origin/feature/foo",
+            "origin/feature/foo",
+            "refs/heads/feature/foo",
+            "feature/foo",
+        ] {
+            assert_eq!(bare_branch_name(full_ref, "origin"), "feature/foo");
+        }
+        assert_eq!(
+            bare_branch_name("origin/origin/foo", "origin"),
+            "origin/foo"
+        );
+        assert_eq!(
+            bare_branch_name("feature/origin/foo", "origin"),
+            "feature/origin/foo"
+        );
+        assert_eq!(bare_branch_name("originals/foo", "origin"), "originals/foo");
+    }
+
+    #[test]
+    fn test_checkout_target_from_reflog_message() {
+        assert_eq!(
+            checkout_target_from_reflog_message("checkout: moving from master to feature/foo"),
+            Some("feature/foo")
+        );
+        assert_eq!(
+            checkout_target_from_reflog_message("checkout: moving from feature/foo to master"),
+            Some("master")
+        );
+        assert_eq!(
+            checkout_target_from_reflog_message("commit: Add a --limit flag"),
+            None
+        );
+        assert_eq!(
+            checkout_target_from_reflog_message("pull: Fast-forward"),
+            None
+        );
+    }
+}
@@ -0,0 +1,522 @@
+// Copyright (c) 2018-2019, Wayfair LLC
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+//  * Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//    disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//    following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING,
+// BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED.
+// IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY,
+// OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+// STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE,
+// EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+mod v1;
+
+use std::convert::TryFrom;
+
+use self::v1::*;
+use super::{
+    access_error_for_status, deserialize_each, encode_branch_path, error_for_status,
+    exists_for_status, listing_message, read_json, summarize_reviews, token_accepted_for_status,
+    AccessTarget, ApiResult, PushRequest, PushRequestState, ReviewState, SCMProviderImpl,
+};
+use crate::progress::Progress;
+use async_trait::async_trait;
+use log::debug;
+use reqwest::header;
+use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::Client;
+use std::path::{Path, PathBuf};
+use what_git::ClientOptions;
+
+/// The number of items requested per page. Gitea caps this at its configured maximum, which may be lower, so the number
+/// of pages is worked out from the size of the first page rather than from this.
+const PAGE_LIMIT: usize = 50;
+
+/// The header identifying a request, for reference in support requests. Gitea doesn't set one itself, but the reverse
+/// proxies it is usually deployed behind do.
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+#[derive(Debug)]
+pub struct Gitea {
+    client: Client,
+    base_url: String,
+    owner: String,
+    repo: String,
+    raw_response_dir: Option<PathBuf>,
+}
+
+impl Gitea {
+    pub fn new(
+        base_url: &str,
+        token: &str,
+        client_options: &ClientOptions<'_>,
+        owner: &str,
+        repo: &str,
+    ) -> Self {
+        let mut headers = HeaderMap::new();
+        headers.append(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(&format!("token {}", token))
+                .expect("Token should be convertible to a header string"),
+        );
+        let client = what_git::client_builder(client_options)
+            .default_headers(headers)
+            .build()
+            .expect("Gitea client failed to construct itself");
+        Gitea::with_client(client, base_url, owner, repo)
+    }
+
+    /// Creates a `Gitea` that makes requests with a preconfigured `client`. Unlike `Gitea::new`, no authorization or
+    /// other default headers are added, so the client is expected to carry whatever it needs.
+    pub fn with_client(client: Client, base_url: &str, owner: &str, repo: &str) -> Self {
+        Gitea {
+            client,
+            base_url: From::from(base_url),
+            owner: From::from(owner),
+            repo: From::from(repo),
+            raw_response_dir: None,
+        }
+    }
+
+    /// Writes the body of every response to a file in `dir` before deserializing it, if `dir` is set.
+    pub fn dumping_raw_responses_to(mut self, dir: Option<&Path>) -> Self {
+        self.raw_response_dir = dir.map(Path::to_path_buf);
+        self
+    }
+
+    fn construct_base_url(&self) -> String {
+        format!("{}/repos/{}/{}", self.base_url, self.owner, self.repo)
+    }
+
+    /// Names the repository as `owner/repo`, for error messages.
+    fn repository_name(&self) -> String {
+        format!("{}/{}", self.owner, self.repo)
+    }
+
+    /// Looks up the repository itself.
+    async fn repository(&self) -> ApiResult<Repository> {
+        let url = self.construct_base_url();
+        debug!("{}", url);
+        let resp = access_error_for_status(
+            self.client.get(&*url).send().await?,
+            REQUEST_ID_HEADER,
+            AccessTarget::Repository(self.repository_name()),
+        )
+        .await?;
+        read_json(resp, self.raw_response_dir.as_deref()).await
+    }
+
+    async fn update_pull_request_state(
+        &self,
+        id: i32,
+        state: PullRequestStateEvent,
+    ) -> ApiResult<()> {
+        let url = format!("{}/pulls/{}", self.construct_base_url(), id);
+        let resp = self
+            .client
+            .patch(&*url)
+            .json(&PullRequestOptions { state })
+            .send()
+            .await?;
+        error_for_status(resp, REQUEST_ID_HEADER).await.map(|_| ())
+    }
+
+    /// Requests every page of `url`, using the total count Gitea reports with the first page to tell how many follow.
+    async fn paginated_request<Intermediate, Output>(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+    ) -> ApiResult<Vec<Output>>
+    where
+        Intermediate: serde::de::DeserializeOwned,
+        Output: TryFrom<Intermediate>,
+    {
+        let (mut values, pages) = self.request_page(url, query, 1).await?;
+        let total_pages = pages.total_pages(values.len());
+        let mut progress = Progress::bar(&listing_message(url), "pages", total_pages);
+        progress.inc();
+        for page in 2..=total_pages {
+            let (mut page_values, _) = self.request_page(url, query, page).await?;
+            values.append(&mut page_values);
+            progress.inc();
+        }
+        Ok(deserialize_each::<Intermediate>(values)
+            .into_iter()
+            .map(TryFrom::try_from)
+            .filter_map(Result::ok)
+            .collect())
+    }
+
+    async fn request_page(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+        page: usize,
+    ) -> ApiResult<(Vec<serde_json::Value>, Pages)> {
+        debug!("{} (page {})", url, page);
+        let resp = self
+            .client
+            .get(url)
+            .query(query)
+            .query(&[("page", page), ("limit", PAGE_LIMIT)])
+            .send()
+            .await?;
+        let resp = access_error_for_status(
+            resp,
+            REQUEST_ID_HEADER,
+            AccessTarget::Repository(self.repository_name()),
+        )
+        .await?;
+        let pages = Pages::new(resp.headers());
+        let values = read_json(resp, self.raw_response_dir.as_deref()).await?;
+        Ok((values, pages))
+    }
+}
+
+#[async_trait]
+impl SCMProviderImpl for Gitea {
+    fn name(&self) -> &'static str {
+        "Gitea"
+    }
+
+    async fn list_push_requests(&self, state: PushRequestState) -> ApiResult<Vec<PushRequest>> {
+        self.paginated_request::<PullRequest, _>(
+            &format!("{}/pulls", self.construct_base_url()),
+            &[("state", state.gitea_value())],
+        )
+        .await
+    }
+
+    async fn is_push_request_open(&self, id: i32) -> ApiResult<bool> {
+        let url = format!("{}/pulls/{}", self.construct_base_url(), id);
+        debug!("{}", url);
+        let resp =
+            error_for_status(self.client.get(&*url).send().await?, REQUEST_ID_HEADER).await?;
+        let pr: PullRequest = read_json(resp, self.raw_response_dir.as_deref()).await?;
+        Ok(pr.is_open())
+    }
+
+    async fn close_push_request(&self, id: i32) -> ApiResult<()> {
+        self.update_pull_request_state(id, PullRequestStateEvent::Closed)
+            .await
+    }
+
+    async fn reopen_push_request(&self, id: i32) -> ApiResult<()> {
+        self.update_pull_request_state(id, PullRequestStateEvent::Reopen)
+            .await
+    }
+
+    async fn comment_on_push_request(&self, id: i32, body: &str) -> ApiResult<()> {
+        // Like GitHub, Gitea treats pull requests as issues for the purposes of comments.
+        let url = format!("{}/issues/{}/comments", self.construct_base_url(), id);
+        let resp = self
+            .client
+            .post(&*url)
+            .json(&IssueCommentOptions { body })
+            .send()
+            .await?;
+        error_for_status(resp, REQUEST_ID_HEADER).await.map(|_| ())
+    }
+
+    async fn review_state(&self, id: i32) -> ApiResult<ReviewState> {
+        let reviews = self
+            .paginated_request::<Review, Review>(
+                &format!("{}/pulls/{}/reviews", self.construct_base_url(), id),
+                &[],
+            )
+            .await?;
+        Ok(summarize_reviews(
+            reviews
+                .iter()
+                .map(|review| (review.reviewer(), review.verdict())),
+        ))
+    }
+
+    async fn list_protected_branches(&self) -> ApiResult<Vec<super::ProtectedBranch>> {
+        let url = format!("{}/branch_protections", self.construct_base_url());
+        debug!("{}", url);
+        let resp = access_error_for_status(
+            self.client.get(&*url).send().await?,
+            REQUEST_ID_HEADER,
+            AccessTarget::Repository(self.repository_name()),
+        )
+        .await?;
+        let protections: Vec<BranchProtection> =
+            deserialize_each(read_json(resp, self.raw_response_dir.as_deref()).await?);
+        Ok(protections
+            .into_iter()
+            .map(TryFrom::try_from)
+            .filter_map(Result::ok)
+            .collect())
+    }
+
+    async fn list_undeletable_branches(&self) -> ApiResult<Vec<super::ProtectedBranch>> {
+        // As on GitHub, the branch protections and the default branch cover every branch Gitea refuses to delete.
+        Ok(vec![])
+    }
+
+    async fn delete_branch(&self, branch: &str) -> ApiResult<()> {
+        let url = format!(
+            "{}/branches/{}",
+            self.construct_base_url(),
+            encode_branch_path(branch)
+        );
+        let resp = self.client.delete(&*url).send().await?;
+        error_for_status(resp, REQUEST_ID_HEADER).await.map(|_| ())
+    }
+
+    async fn create_branch(&self, branch: &str, sha: &str) -> ApiResult<()> {
+        let url = format!("{}/branches", self.construct_base_url());
+        let resp = self
+            .client
+            .post(&*url)
+            .json(&CreateBranchOptions {
+                new_branch_name: branch,
+                old_ref_name: sha,
+            })
+            .send()
+            .await?;
+        error_for_status(resp, REQUEST_ID_HEADER).await.map(|_| ())
+    }
+
+    async fn branch_exists(&self, branch: &str) -> ApiResult<bool> {
+        let url = format!(
+            "{}/branches/{}",
+            self.construct_base_url(),
+            encode_branch_path(branch)
+        );
+        let resp = self.client.get(&*url).send().await?;
+        exists_for_status(resp, REQUEST_ID_HEADER).await
+    }
+
+    async fn default_branch(&self) -> ApiResult<Option<String>> {
+        Ok(self.repository().await?.default_branch)
+    }
+
+    async fn is_archived(&self) -> ApiResult<bool> {
+        Ok(self.repository().await?.archived)
+    }
+
+    async fn verify_token(&self) -> ApiResult<bool> {
+        let url = format!("{}/user", self.base_url);
+        debug!("{}", url);
+        token_accepted_for_status(self.client.get(&*url).send().await?, REQUEST_ID_HEADER).await
+    }
+}
+
+/// Gitea's pagination headers. Unlike Gitlab, Gitea only reports the total number of items across every page.
+#[derive(Debug)]
+struct Pages {
+    total_items: Option<usize>,
+}
+
+impl Pages {
+    fn new(headers: &HeaderMap) -> Self {
+        Pages {
+            total_items: Pages::x_header(headers, "x-total-count"),
+        }
+    }
+
+    fn x_header(headers: &HeaderMap, key: &'static str) -> Option<usize> {
+        headers
+            .get(key)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.parse::<usize>().ok())
+    }
+
+    /// The number of pages holding every item, given `per_page` items on each full page.
+    fn total_pages(&self, per_page: usize) -> usize {
+        match self.total_items {
+            Some(total_items) if per_page > 0 => total_items.div_ceil(per_page),
+            _ => 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, Matcher};
+
+    fn pull_request_json(number: i32) -> String {
+        format!(
+            r#"{{
+                "id": {number},
+                "html_url": "https://gitea.example.com/wayfair/gitea/pulls/{number}",
+                "number": {number},
+                "title": "PR {number}",
+                "state": "open",
+                "created_at": "2019-01-01T00:00:00Z",
+                "updated_at": "2019-01-02T00:00:00Z",
+                "head": {{ "label": "branch-{number}", "ref": "branch-{number}", "sha": "abc", "repo_id": 1, "repo": {{ "id": 1 }} }},
+                "base": {{ "label": "master", "ref": "master", "sha": "def", "repo_id": 1, "repo": {{ "id": 1 }} }}
+            }}"#,
+            number = number
+        )
+    }
+
+    #[tokio::test]
+    async fn test_list_push_requests_across_pages() {
+        let gitea = Gitea::with_client(Client::new(), &mockito::server_url(), "wayfair", "gitea");
+        let page = |page: &str, numbers: &[i32]| {
+            mock("GET", "/repos/wayfair/gitea/pulls")
+                .match_query(Matcher::AllOf(vec![
+                    Matcher::UrlEncoded("state".into(), "open".into()),
+                    Matcher::UrlEncoded("page".into(), page.into()),
+                ]))
+                .with_header("x-total-count", "3")
+                .with_body(format!(
+                    "[{}]",
+                    numbers
+                        .iter()
+                        .map(|&number| pull_request_json(number))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                ))
+                .create()
+        };
+        let first = page("1", &[1, 2]);
+        let second = page("2", &[3]);
+
+        let push_requests = gitea
+            .list_push_requests(PushRequestState::Opened)
+            .await
+            .unwrap();
+
+        first.assert();
+        second.assert();
+        assert_eq!(
+            push_requests
+                .iter()
+                .map(|pr| (pr.id, pr.source_branch.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(1, "branch-1"), (2, "branch-2"), (3, "branch-3")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_close_and_reopen_push_request() {
+        let gitea = Gitea::new(
+            &mockito::server_url(),
+            "token",
+            &ClientOptions {
+                user_agent: "foxdie/test",
+                ..ClientOptions::default()
+            },
+            "wayfair",
+            "gitea",
+        );
+        let closed = mock("PATCH", "/repos/wayfair/gitea/pulls/4")
+            .match_header("authorization", "token token")
+            .match_header("user-agent", "foxdie/test")
+            .match_body(Matcher::Json(serde_json::json!({ "state": "closed" })))
+            .with_status(201)
+            .create();
+        let reopened = mock("PATCH", "/repos/wayfair/gitea/pulls/5")
+            .match_body(Matcher::Json(serde_json::json!({ "state": "open" })))
+            .with_status(201)
+            .create();
+
+        assert!(gitea.close_push_request(4).await.is_ok());
+        assert!(gitea.reopen_push_request(5).await.is_ok());
+        closed.assert();
+        reopened.assert();
+    }
+
+    #[tokio::test]
+    async fn test_list_protected_branches() {
+        let gitea = Gitea::new(
+            &mockito::server_url(),
+            "token",
+            &ClientOptions {
+                user_agent: "foxdie/test",
+                ..ClientOptions::default()
+            },
+            "wayfair",
+            "gitea",
+        );
+        let protections = mock("GET", "/repos/wayfair/gitea/branch_protections")
+            .with_body(
+                r#"[
+                    { "branch_name": "master", "rule_name": "master" },
+                    { "branch_name": "", "rule_name": "release/*" }
+                ]"#,
+            )
+            .create();
+
+        let protected_branches = gitea.list_protected_branches().await.unwrap();
+
+        protections.assert();
+        assert!(protected_branches[0].matches_branch("master"));
+        assert!(protected_branches[1].matches_branch("release/1.0"));
+    }
+
+    #[tokio::test]
+    async fn test_delete_branch() {
+        let gitea = Gitea::with_client(Client::new(), &mockito::server_url(), "wayfair", "gitea");
+        let deleted = mock("DELETE", "/repos/wayfair/gitea/branches/fix/issue%2312")
+            .with_status(204)
+            .create();
+        let _exists = mock("GET", "/repos/wayfair/gitea/branches/fix/issue%2312")
+            .with_body("{}")
+            .create();
+
+        assert!(gitea.delete_branch("fix/issue#12").await.is_ok());
+        assert!(gitea.branch_exists("fix/issue#12").await.unwrap());
+        deleted.assert();
+    }
+
+    #[tokio::test]
+    async fn test_create_branch() {
+        let gitea = Gitea::with_client(Client::new(), &mockito::server_url(), "wayfair", "gitea");
+        let created = mock("POST", "/repos/wayfair/gitea/branches")
+            .match_body(Matcher::Json(serde_json::json!({
+                "new_branch_name": "feature/restored",
+                "old_ref_name": "abc123"
+            })))
+            .with_status(201)
+            .create();
+
+        assert!(gitea
+            .create_branch("feature/restored", "abc123")
+            .await
+            .is_ok());
+        created.assert();
+    }
+
+    #[tokio::test]
+    async fn test_review_state() {
+        let gitea = Gitea::with_client(Client::new(), &mockito::server_url(), "wayfair", "gitea");
+        let reviews = mock("GET", "/repos/wayfair/gitea/pulls/6/reviews")
+            .match_query(Matcher::Any)
+            .with_body(
+                r#"[
+                    { "id": 1, "user": { "login": "alice" }, "state": "APPROVED", "dismissed": false },
+                    { "id": 2, "user": { "login": "bob" }, "state": "REQUEST_CHANGES", "dismissed": false }
+                ]"#,
+            )
+            .create();
+
+        assert_eq!(
+            gitea.review_state(6).await.unwrap(),
+            ReviewState::ChangesRequested
+        );
+        reviews.assert();
+    }
+
+    #[test]
+    fn test_total_pages() {
+        let pages = |total_items| Pages { total_items };
+        assert_eq!(pages(Some(101)).total_pages(50), 3);
+        assert_eq!(pages(Some(100)).total_pages(50), 2);
+        assert_eq!(pages(Some(0)).total_pages(0), 1);
+        assert_eq!(pages(None).total_pages(50), 1);
+    }
+}
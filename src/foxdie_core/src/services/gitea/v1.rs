@@ -0,0 +1,248 @@
+// Copyright (c) 2018-2019, Wayfair LLC
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+//  * Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//    disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//    following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING,
+// BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED.
+// IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY,
+// OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+// STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE,
+// EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+use std::convert::TryFrom;
+
+use super::{PushRequest, ReviewState};
+use chrono::{DateTime, FixedOffset};
+use glob::{Pattern, PatternError};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PullRequestOptions {
+    pub state: PullRequestStateEvent,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssueCommentOptions<'a> {
+    pub body: &'a str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CreateBranchOptions<'a> {
+    pub new_branch_name: &'a str,
+    pub old_ref_name: &'a str,
+}
+
+#[derive(Debug, Copy, Clone, Serialize)]
+pub enum PullRequestStateEvent {
+    #[serde(rename = "closed")]
+    Closed,
+    #[serde(rename = "open")]
+    Reopen,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PullRequest {
+    pub html_url: String,
+    pub number: i32,
+    pub title: String,
+    pub created_at: DateTime<FixedOffset>,
+    pub updated_at: DateTime<FixedOffset>,
+    pub head: GitData,
+    pub base: GitData,
+    #[serde(default)]
+    pub draft: bool,
+    /// Either `open` or `closed`.
+    pub state: Option<String>,
+    #[serde(default)]
+    pub merged: bool,
+    /// Missing when the author's account has been deleted.
+    pub user: Option<User>,
+    /// Whether Gitea can merge the pull request without conflicts.
+    pub mergeable: Option<bool>,
+}
+
+impl PullRequest {
+    /// Whether this pull request is a draft, either by its flag or, on Gitea versions without one, by the default
+    /// work-in-progress title prefixes.
+    fn is_draft(&self) -> bool {
+        const DRAFT_PREFIXES: [&str; 2] = ["wip:", "[wip]"];
+        let title = self.title.trim_start().to_lowercase();
+        self.draft
+            || DRAFT_PREFIXES
+                .iter()
+                .any(|prefix| title.starts_with(prefix))
+    }
+
+    /// Whether this pull request is still open.
+    pub fn is_open(&self) -> bool {
+        self.state.as_deref().is_none_or(|state| state == "open")
+    }
+}
+
+impl TryFrom<PullRequest> for PushRequest {
+    type Error = ();
+
+    fn try_from(pr: PullRequest) -> Result<Self, Self::Error> {
+        let is_draft = pr.is_draft();
+        let is_open = pr.is_open();
+        let author = pr.user.map(|user| user.login);
+        Ok(PushRequest {
+            url: pr.html_url,
+            id: pr.number,
+            title: pr.title,
+            created_at: pr.created_at,
+            updated_at: pr.updated_at,
+            // Like GitHub, Gitea omits the repository once it has been deleted.
+            target_project: pr.base.repo.ok_or(())?.id,
+            target_branch: pr.base.git_ref,
+            source_project: pr.head.repo.ok_or(())?.id,
+            source_branch: pr.head.git_ref,
+            source_sha: pr.head.sha,
+            is_draft,
+            is_open,
+            is_merged: pr.merged,
+            author,
+            mergeable: pr.mergeable,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitData {
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+    #[serde(default)]
+    pub sha: Option<String>,
+    pub repo: Option<Repository>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Repository {
+    pub id: i32,
+    /// Only sent when the repository itself is requested, rather than as part of a pull request.
+    pub default_branch: Option<String>,
+    #[serde(default)]
+    pub archived: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Review {
+    /// Missing when the reviewer's account has been deleted.
+    pub user: Option<User>,
+    pub state: String,
+    #[serde(default)]
+    pub dismissed: bool,
+}
+
+impl Review {
+    pub fn reviewer(&self) -> String {
+        self.user
+            .as_ref()
+            .map(|user| user.login.clone())
+            .unwrap_or_default()
+    }
+
+    /// The verdict this review gives, if it gives one. A dismissed review withdraws its reviewer's verdict.
+    pub fn verdict(&self) -> Option<ReviewState> {
+        if self.dismissed {
+            return Some(ReviewState::AwaitingReview);
+        }
+        match &*self.state {
+            "APPROVED" => Some(ReviewState::Approved),
+            "REQUEST_CHANGES" => Some(ReviewState::ChangesRequested),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct User {
+    pub login: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BranchProtection {
+    pub branch_name: String,
+    /// The pattern the rule applies to. Only sent by Gitea 1.17 and later, which allow rules to use glob patterns.
+    #[serde(default)]
+    pub rule_name: Option<String>,
+}
+
+impl TryFrom<BranchProtection> for super::super::ProtectedBranch {
+    type Error = PatternError;
+
+    fn try_from(protection: BranchProtection) -> Result<Self, Self::Error> {
+        let name = protection
+            .rule_name
+            .filter(|rule_name| !rule_name.is_empty())
+            .unwrap_or(protection.branch_name);
+        Ok(Self {
+            pattern: Pattern::new(&name)?,
+            source: super::super::ProtectionSource::Server,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pull_request(title: &str, draft: bool) -> PullRequest {
+        let date = DateTime::parse_from_rfc3339("2019-01-01T00:00:00-05:00").unwrap();
+        let git_data = |git_ref: &str| GitData {
+            git_ref: git_ref.to_string(),
+            sha: None,
+            repo: Some(Repository {
+                id: 1,
+                default_branch: None,
+                archived: false,
+            }),
+        };
+        PullRequest {
+            html_url: String::from("https://gitea.example.com/wayfair/foxdie/pulls/1"),
+            number: 1,
+            title: title.to_string(),
+            created_at: date,
+            updated_at: date,
+            head: git_data("feature"),
+            base: git_data("master"),
+            draft,
+            state: Some(String::from("open")),
+            merged: false,
+            user: None,
+            mergeable: None,
+        }
+    }
+
+    #[test]
+    fn test_is_draft() {
+        assert!(pull_request("Add a feature", true).is_draft());
+        assert!(pull_request("WIP: Add a feature", false).is_draft());
+        assert!(pull_request("[WIP] Add a feature", false).is_draft());
+        assert!(!pull_request("Add a feature", false).is_draft());
+    }
+
+    #[test]
+    fn test_protected_branch_from_rule_name() {
+        let protection = |rule_name: Option<&str>| BranchProtection {
+            branch_name: String::from("master"),
+            rule_name: rule_name.map(String::from),
+        };
+        let pattern = |protection| {
+            super::super::super::ProtectedBranch::try_from(protection)
+                .unwrap()
+                .pattern
+        };
+
+        assert_eq!(pattern(protection(Some("release/*"))).as_str(), "release/*");
+        assert_eq!(pattern(protection(Some(""))).as_str(), "master");
+        assert_eq!(pattern(protection(None)).as_str(), "master");
+    }
+}
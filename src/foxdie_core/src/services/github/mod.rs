@@ -0,0 +1,890 @@
+// Copyright (c) 2018-2019, Wayfair LLC
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+//  * Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//    disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//    following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING,
+// BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED.
+// IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY,
+// OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+// STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE,
+// EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+mod v3;
+
+use std::convert::TryFrom;
+
+use self::v3::*;
+use super::links::Links;
+use super::{
+    access_error_for_status, deserialize_each, encode_branch_path, error_for_status,
+    exists_for_status, listing_message, read_json, summarize_reviews, token_accepted_for_status,
+    AccessTarget, ApiResult, PushRequest, PushRequestState, ReviewState, SCMProviderImpl,
+};
+use crate::progress::Progress;
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use log::debug;
+use reqwest::header;
+use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::Client;
+use std::path::{Path, PathBuf};
+use what_git::ClientOptions;
+
+/// The number of pages of a paginated request that are requested at once.
+const CONCURRENT_PAGES: usize = 8;
+
+/// The most items GitHub returns in a page, which is also how many are requested unless a smaller page size is set.
+const MAX_PAGE_SIZE: usize = 100;
+
+/// The header GitHub uses to identify a request, for reference in support requests.
+const REQUEST_ID_HEADER: &str = "x-github-request-id";
+
+#[derive(Debug)]
+pub struct GitHub {
+    client: Client,
+    base_url: String,
+    owner: String,
+    repo: String,
+    /// The number of items requested in each page of a listing.
+    page_size: usize,
+    raw_response_dir: Option<PathBuf>,
+}
+
+impl GitHub {
+    pub fn new(
+        base_url: &str,
+        token: &str,
+        client_options: &ClientOptions<'_>,
+        owner: &str,
+        repo: &str,
+    ) -> Self {
+        let mut headers = HeaderMap::new();
+        if !client_options.default_headers.contains_key(header::ACCEPT) {
+            headers.append(
+                header::ACCEPT,
+                HeaderValue::from_static("application/vnd.github.v3+json"),
+            );
+        }
+        headers.append(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token))
+                .expect("Token should be convertible to a header string"),
+        );
+        let client = what_git::client_builder(client_options)
+            .default_headers(headers)
+            .build()
+            .expect("GitHub client failed to construct itself");
+        GitHub::with_client(client, base_url, owner, repo)
+    }
+
+    /// Creates a `GitHub` that makes requests with a preconfigured `client`. Unlike `GitHub::new`, no authorization or
+    /// other default headers are added, so the client is expected to carry whatever it needs.
+    pub fn with_client(client: Client, base_url: &str, owner: &str, repo: &str) -> Self {
+        GitHub {
+            client,
+            base_url: From::from(base_url),
+            owner: From::from(owner),
+            repo: From::from(repo),
+            page_size: MAX_PAGE_SIZE,
+            raw_response_dir: None,
+        }
+    }
+
+    /// Requests `size` items in each page of a listing, if set, up to GitHub's maximum of 100. Otherwise, as many as
+    /// GitHub allows are.
+    pub fn with_page_size(mut self, size: Option<usize>) -> Self {
+        self.page_size = size.map_or(MAX_PAGE_SIZE, |size| size.clamp(1, MAX_PAGE_SIZE));
+        self
+    }
+
+    /// Writes the body of every response to a file in `dir` before deserializing it, if `dir` is set.
+    pub fn dumping_raw_responses_to(mut self, dir: Option<&Path>) -> Self {
+        self.raw_response_dir = dir.map(Path::to_path_buf);
+        self
+    }
+
+    fn construct_base_url(&self) -> String {
+        format!("{}/repos/{}/{}", self.base_url, self.owner, self.repo)
+    }
+
+    /// Names the repository as `owner/repo`, for error messages.
+    fn repository_name(&self) -> String {
+        format!("{}/{}", self.owner, self.repo)
+    }
+
+    /// Looks up the repository itself.
+    async fn repository(&self) -> ApiResult<Repository> {
+        let url = self.construct_base_url();
+        debug!("{}", url);
+        let resp = access_error_for_status(
+            self.client.get(&*url).send().await?,
+            REQUEST_ID_HEADER,
+            AccessTarget::Repository(self.repository_name()),
+        )
+        .await?;
+        read_json(resp, self.raw_response_dir.as_deref()).await
+    }
+
+    async fn update_pull_request_state(
+        &self,
+        id: i32,
+        state: PullRequestStateEvent,
+    ) -> ApiResult<()> {
+        let url = format!("{}/pulls/{}", self.construct_base_url(), id);
+        let resp = self
+            .client
+            .patch(&*url)
+            .json(&PullRequestOptions { state })
+            .send()
+            .await?;
+        error_for_status(resp, REQUEST_ID_HEADER).await.map(|_| ())
+    }
+
+    async fn paginated_request<Query, Intermediate, Output>(
+        &self,
+        url: &str,
+        query: Query,
+    ) -> ApiResult<Vec<Output>>
+    where
+        Query: serde::Serialize,
+        Intermediate: serde::de::DeserializeOwned,
+        Output: TryFrom<Intermediate>,
+    {
+        debug!("{}", url);
+        let mut progress = Progress::spinner(&listing_message(url), "pages");
+
+        let initial_resp = access_error_for_status(
+            self.client
+                .get(url)
+                .query(&query)
+                .query(&[("per_page", self.page_size)])
+                .send()
+                .await?,
+            REQUEST_ID_HEADER,
+            AccessTarget::Repository(self.repository_name()),
+        )
+        .await?;
+        let mut headers = initial_resp.headers().clone();
+        let page_items = read_json(initial_resp, self.raw_response_dir.as_deref()).await?;
+
+        let mut items: Vec<Output> = deserialize_each::<Intermediate>(page_items)
+            .into_iter()
+            .map(TryFrom::try_from)
+            .filter_map(Result::ok)
+            .collect::<Vec<_>>();
+        progress.inc();
+
+        // When GitHub says how many pages there are, request the rest of them at once.
+        let remaining_pages = headers
+            .get(header::LINK)
+            .and_then(|h| h.to_str().ok())
+            .map(Links::parse_from_rfc5988)
+            .and_then(|links| links.remaining_page_urls());
+        if let Some(page_urls) = remaining_pages {
+            progress.set(1, page_urls.len() + 1);
+            let pages = stream::iter(page_urls)
+                .map(|page_url| self.request_page(page_url))
+                .buffered(CONCURRENT_PAGES)
+                .inspect_ok(|_| progress.inc())
+                .try_collect::<Vec<_>>()
+                .await?;
+            for page_items in pages {
+                items.extend(
+                    deserialize_each::<Intermediate>(page_items)
+                        .into_iter()
+                        .map(TryFrom::try_from)
+                        .filter_map(Result::ok),
+                );
+            }
+            return Ok(items);
+        }
+
+        while let Some(link_header) = headers.get(header::LINK).and_then(|h| h.to_str().ok()) {
+            let links = Links::parse_from_rfc5988(link_header);
+            if let Some(next) = links.next() {
+                debug!("{}", next.uri);
+                let resp =
+                    error_for_status(self.client.get(&*next.uri).send().await?, REQUEST_ID_HEADER)
+                        .await?;
+                headers = resp.headers().clone();
+                let mut push_requests = read_json(resp, self.raw_response_dir.as_deref())
+                    .await
+                    .map(|page_items| {
+                        deserialize_each::<Intermediate>(page_items)
+                            .into_iter()
+                            .map(TryFrom::try_from)
+                            .filter_map(Result::ok)
+                            .collect::<Vec<_>>()
+                    })?;
+                items.append(&mut push_requests);
+                progress.inc();
+            } else {
+                break;
+            }
+        }
+
+        Ok(items)
+    }
+
+    async fn request_page(&self, url: String) -> ApiResult<Vec<serde_json::Value>> {
+        debug!("{}", url);
+        let resp =
+            error_for_status(self.client.get(&*url).send().await?, REQUEST_ID_HEADER).await?;
+        read_json(resp, self.raw_response_dir.as_deref()).await
+    }
+}
+
+#[async_trait]
+impl SCMProviderImpl for GitHub {
+    fn name(&self) -> &'static str {
+        "GitHub"
+    }
+
+    async fn list_push_requests(&self, state: PushRequestState) -> ApiResult<Vec<PushRequest>> {
+        self.paginated_request::<_, PullRequest, _>(
+            &format!("{}/pulls", self.construct_base_url()),
+            &[("state", state.github_value())],
+        )
+        .await
+    }
+
+    async fn is_push_request_open(&self, id: i32) -> ApiResult<bool> {
+        let url = format!("{}/pulls/{}", self.construct_base_url(), id);
+        debug!("{}", url);
+        let resp =
+            error_for_status(self.client.get(&*url).send().await?, REQUEST_ID_HEADER).await?;
+        let pr: PullRequest = read_json(resp, self.raw_response_dir.as_deref()).await?;
+        Ok(pr.is_open())
+    }
+
+    async fn close_push_request(&self, id: i32) -> ApiResult<()> {
+        self.update_pull_request_state(id, PullRequestStateEvent::Closed)
+            .await
+    }
+
+    async fn reopen_push_request(&self, id: i32) -> ApiResult<()> {
+        self.update_pull_request_state(id, PullRequestStateEvent::Reopen)
+            .await
+    }
+
+    async fn comment_on_push_request(&self, id: i32, body: &str) -> ApiResult<()> {
+        // GitHub treats pull requests as issues for the purposes of conversation comments.
+        let url = format!("{}/issues/{}/comments", self.construct_base_url(), id);
+        let resp = self
+            .client
+            .post(&*url)
+            .json(&IssueCommentOptions { body })
+            .send()
+            .await?;
+        error_for_status(resp, REQUEST_ID_HEADER).await.map(|_| ())
+    }
+
+    async fn review_state(&self, id: i32) -> ApiResult<ReviewState> {
+        let reviews = self
+            .paginated_request::<_, Review, Review>(
+                &format!("{}/pulls/{}/reviews", self.construct_base_url(), id),
+                (),
+            )
+            .await?;
+        Ok(summarize_reviews(
+            reviews
+                .iter()
+                .map(|review| (review.reviewer(), review.verdict())),
+        ))
+    }
+
+    async fn list_protected_branches(&self) -> ApiResult<Vec<super::ProtectedBranch>> {
+        self.paginated_request::<_, ProtectedBranch, _>(
+            &format!("{}/branches", self.construct_base_url()),
+            &[("protected", true)],
+        )
+        .await
+    }
+
+    async fn list_undeletable_branches(&self) -> ApiResult<Vec<super::ProtectedBranch>> {
+        // The protected branches and the default branch are all GitHub refuses to delete, and are looked up already.
+        Ok(vec![])
+    }
+
+    async fn delete_branch(&self, branch: &str) -> ApiResult<()> {
+        let url = format!(
+            "{}/git/refs/heads/{}",
+            self.construct_base_url(),
+            encode_branch_path(branch)
+        );
+        let resp = self.client.delete(&*url).send().await?;
+        error_for_status(resp, REQUEST_ID_HEADER).await.map(|_| ())
+    }
+
+    async fn create_branch(&self, branch: &str, sha: &str) -> ApiResult<()> {
+        let url = format!("{}/git/refs", self.construct_base_url());
+        let resp = self
+            .client
+            .post(&*url)
+            .json(&CreateRefOptions {
+                git_ref: format!("refs/heads/{}", branch),
+                sha,
+            })
+            .send()
+            .await?;
+        error_for_status(resp, REQUEST_ID_HEADER).await.map(|_| ())
+    }
+
+    async fn branch_exists(&self, branch: &str) -> ApiResult<bool> {
+        let url = format!(
+            "{}/branches/{}",
+            self.construct_base_url(),
+            encode_branch_path(branch)
+        );
+        let resp = self.client.get(&*url).send().await?;
+        exists_for_status(resp, REQUEST_ID_HEADER).await
+    }
+
+    async fn default_branch(&self) -> ApiResult<Option<String>> {
+        Ok(self.repository().await?.default_branch)
+    }
+
+    async fn is_archived(&self) -> ApiResult<bool> {
+        Ok(self.repository().await?.archived)
+    }
+
+    async fn verify_token(&self) -> ApiResult<bool> {
+        let url = format!("{}/user", self.base_url);
+        debug!("{}", url);
+        token_accepted_for_status(self.client.get(&*url).send().await?, REQUEST_ID_HEADER).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{AccessError, AccessErrorKind, AccessTarget};
+    use mockito::{mock, Matcher};
+
+    fn pull_request_json(number: i32) -> String {
+        format!(
+            r#"{{
+                "id": {number},
+                "html_url": "https://github.com/wayfair/foxdie/pull/{number}",
+                "number": {number},
+                "title": "PR {number}",
+                "created_at": "2019-01-01T00:00:00Z",
+                "updated_at": "2019-01-02T00:00:00Z",
+                "head": {{ "label": "wayfair:branch-{number}", "ref": "branch-{number}", "sha": "abc", "repo": {repo} }},
+                "base": {{ "label": "wayfair:master", "ref": "master", "sha": "def", "repo": {repo} }}
+            }}"#,
+            number = number,
+            repo = r#"{
+                "id": 1,
+                "name": "foxdie",
+                "full_name": "wayfair/foxdie",
+                "html_url": "https://github.com/wayfair/foxdie",
+                "fork": false,
+                "created_at": "2018-01-01T00:00:00Z",
+                "updated_at": "2019-01-01T00:00:00Z",
+                "pushed_at": "2019-01-01T00:00:00Z"
+            }"#
+        )
+    }
+
+    #[test]
+    fn test_pull_request_author() {
+        let with_author = pull_request_json(1).replacen(
+            r#""title""#,
+            r#""user": { "login": "octocat", "id": 583231, "type": "User" }, "title""#,
+            1,
+        );
+        let push_request = |json: &str| {
+            PushRequest::try_from(serde_json::from_str::<PullRequest>(json).unwrap()).unwrap()
+        };
+
+        assert_eq!(
+            push_request(&with_author).author.as_deref(),
+            Some("octocat")
+        );
+        assert_eq!(push_request(&pull_request_json(2)).author, None);
+        assert_eq!(
+            push_request(&pull_request_json(2)).source_sha.as_deref(),
+            Some("abc")
+        );
+        let ghost = pull_request_json(3).replacen(r#""title""#, r#""user": null, "title""#, 1);
+        assert_eq!(push_request(&ghost).author, None);
+    }
+
+    #[tokio::test]
+    async fn test_list_push_requests_requests_full_pages() {
+        let github =
+            GitHub::with_client(Client::new(), &mockito::server_url(), "wayfair", "per-page");
+        let listed = mock("GET", "/repos/wayfair/per-page/pulls")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("state".into(), "open".into()),
+                Matcher::UrlEncoded("per_page".into(), "100".into()),
+            ]))
+            .with_body(format!(
+                "[{}, {}]",
+                pull_request_json(1),
+                pull_request_json(2)
+            ))
+            .expect(1)
+            .create();
+
+        let push_requests = github
+            .list_push_requests(PushRequestState::Opened)
+            .await
+            .unwrap();
+
+        listed.assert();
+        assert_eq!(push_requests.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_list_push_requests_for_inaccessible_repository() {
+        for (status, repo, expected) in &[
+            (401, "unauthorized", AccessErrorKind::Unauthorized),
+            (403, "forbidden", AccessErrorKind::Forbidden),
+            (404, "missing", AccessErrorKind::NotFound),
+        ] {
+            let github =
+                GitHub::with_client(Client::new(), &mockito::server_url(), "wayfair", repo);
+            let _listed = mock("GET", &*format!("/repos/wayfair/{}/pulls", repo))
+                .match_query(Matcher::Any)
+                .with_status(*status)
+                .with_header(REQUEST_ID_HEADER, "ABCD:1234")
+                .create();
+
+            let err = github
+                .list_push_requests(PushRequestState::Opened)
+                .await
+                .unwrap_err();
+
+            assert_eq!(err.request_id.as_deref(), Some("ABCD:1234"));
+            assert_eq!(
+                err.source.downcast_ref::<AccessError>(),
+                Some(&AccessError {
+                    kind: *expected,
+                    target: AccessTarget::Repository(format!("wayfair/{}", repo)),
+                })
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_new_sends_custom_headers() {
+        let mut default_headers = HeaderMap::new();
+        default_headers.insert(
+            "X-GitHub-Api-Version",
+            HeaderValue::from_static("2022-11-28"),
+        );
+        default_headers.insert(
+            header::ACCEPT,
+            HeaderValue::from_static("application/vnd.github+json"),
+        );
+        let github = GitHub::new(
+            &format!("{}/custom-headers", mockito::server_url()),
+            "token",
+            &ClientOptions {
+                user_agent: "audit-bot/1.0",
+                default_headers,
+                ..ClientOptions::default()
+            },
+            "wayfair",
+            "foxdie",
+        );
+        let _repository = mock("GET", "/custom-headers/repos/wayfair/foxdie")
+            .match_header("user-agent", "audit-bot/1.0")
+            .match_header("x-github-api-version", "2022-11-28")
+            .match_header("accept", "application/vnd.github+json")
+            .match_header("authorization", "Bearer token")
+            .with_body(r#"{ "id": 1, "default_branch": "main" }"#)
+            .create();
+
+        assert_eq!(
+            github.default_branch().await.unwrap().as_deref(),
+            Some("main")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_default_branch() {
+        let github = GitHub::with_client(Client::new(), &mockito::server_url(), "wayfair", "trunk");
+        let _repository = mock("GET", "/repos/wayfair/trunk")
+            .with_body(r#"{ "id": 1, "default_branch": "trunk" }"#)
+            .create();
+
+        assert_eq!(
+            github.default_branch().await.unwrap().as_deref(),
+            Some("trunk")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_push_requests_follows_link_headers() {
+        let github =
+            GitHub::with_client(Client::new(), &mockito::server_url(), "wayfair", "foxdie");
+        let next_page = format!(
+            "{}/repos/wayfair/foxdie/pulls?state=open&page=2",
+            mockito::server_url()
+        );
+        let first = mock("GET", "/repos/wayfair/foxdie/pulls")
+            .match_query(Matcher::UrlEncoded("state".into(), "open".into()))
+            .with_header(
+                "link",
+                &format!(
+                    r#"<{}>; rel="next", <{}>; rel="last""#,
+                    next_page, next_page
+                ),
+            )
+            .with_body(format!(
+                "[{}, {}]",
+                pull_request_json(1),
+                pull_request_json(2)
+            ))
+            .create();
+        let second = mock("GET", "/repos/wayfair/foxdie/pulls")
+            .match_query(Matcher::UrlEncoded("page".into(), "2".into()))
+            .with_body(format!("[{}]", pull_request_json(3)))
+            .create();
+
+        let push_requests = github
+            .list_push_requests(PushRequestState::Opened)
+            .await
+            .unwrap();
+
+        first.assert();
+        second.assert();
+        assert_eq!(
+            push_requests.iter().map(|pr| pr.id).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_push_requests_with_page_size() {
+        for (page_size, repo, expected) in &[
+            (Some(2), "page-size", "2"),
+            (Some(500), "page-size-capped", "100"),
+            (None, "page-size-default", "100"),
+        ] {
+            let github =
+                GitHub::with_client(Client::new(), &mockito::server_url(), "wayfair", repo)
+                    .with_page_size(*page_size);
+            let listed = mock("GET", &*format!("/repos/wayfair/{}/pulls", repo))
+                .match_query(Matcher::UrlEncoded("per_page".into(), (*expected).into()))
+                .with_body(format!("[{}]", pull_request_json(1)))
+                .expect(1)
+                .create();
+
+            github
+                .list_push_requests(PushRequestState::Opened)
+                .await
+                .unwrap();
+
+            listed.assert();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_push_requests_requests_remaining_pages_concurrently() {
+        let github = GitHub::with_client(Client::new(), &mockito::server_url(), "wayfair", "paged");
+        let page_url = |page: i32| {
+            format!(
+                "{}/repos/wayfair/paged/pulls?state=open&page={}",
+                mockito::server_url(),
+                page
+            )
+        };
+        let first = mock("GET", "/repos/wayfair/paged/pulls")
+            .match_query(Matcher::UrlEncoded("state".into(), "open".into()))
+            .with_header(
+                "link",
+                &format!(
+                    r#"<{}>; rel="next", <{}>; rel="last""#,
+                    page_url(2),
+                    page_url(4)
+                ),
+            )
+            .with_body(format!("[{}]", pull_request_json(1)))
+            .create();
+        let pages = (2..=4)
+            .map(|page| {
+                mock("GET", "/repos/wayfair/paged/pulls")
+                    .match_query(Matcher::AllOf(vec![
+                        Matcher::UrlEncoded("state".into(), "open".into()),
+                        Matcher::UrlEncoded("page".into(), page.to_string()),
+                    ]))
+                    .with_body(format!(
+                        "[{}, {}]",
+                        pull_request_json(page * 10),
+                        pull_request_json(page * 10 + 1)
+                    ))
+                    .create()
+            })
+            .collect::<Vec<_>>();
+
+        let push_requests = github
+            .list_push_requests(PushRequestState::Opened)
+            .await
+            .unwrap();
+
+        first.assert();
+        for page in &pages {
+            page.assert();
+        }
+        assert_eq!(
+            push_requests.iter().map(|pr| pr.id).collect::<Vec<_>>(),
+            vec![1, 20, 21, 30, 31, 40, 41]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_push_requests_skips_unreadable_records() {
+        let github =
+            GitHub::with_client(Client::new(), &mockito::server_url(), "wayfair", "tolerant");
+        let _page = mock("GET", "/repos/wayfair/tolerant/pulls")
+            .match_query(Matcher::Any)
+            .with_body(format!(
+                r#"[{}, {{"number": "not a number"}}, {}]"#,
+                pull_request_json(1),
+                pull_request_json(3)
+            ))
+            .create();
+
+        let push_requests = github
+            .list_push_requests(PushRequestState::Opened)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            push_requests.iter().map(|pr| pr.id).collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete_branch() {
+        let github = GitHub::new(
+            &mockito::server_url(),
+            "token",
+            &ClientOptions {
+                user_agent: "foxdie/test",
+                ..ClientOptions::default()
+            },
+            "wayfair",
+            "foxdie",
+        );
+        let deleted = mock(
+            "DELETE",
+            "/repos/wayfair/foxdie/git/refs/heads/feature/stale",
+        )
+        .match_header("authorization", "Bearer token")
+        .match_header("user-agent", "foxdie/test")
+        .with_status(204)
+        .create();
+        let missing = mock("DELETE", "/repos/wayfair/foxdie/git/refs/heads/missing")
+            .with_status(422)
+            .create();
+        let escaped = mock(
+            "DELETE",
+            "/repos/wayfair/foxdie/git/refs/heads/fix/issue%2312",
+        )
+        .with_status(204)
+        .create();
+
+        assert!(github.delete_branch("feature/stale").await.is_ok());
+        assert!(github.delete_branch("missing").await.is_err());
+        assert!(github.delete_branch("fix/issue#12").await.is_ok());
+        deleted.assert();
+        missing.assert();
+        escaped.assert();
+    }
+
+    #[tokio::test]
+    async fn test_create_branch() {
+        let github = GitHub::new(
+            &mockito::server_url(),
+            "token",
+            &ClientOptions::default(),
+            "wayfair",
+            "foxdie",
+        );
+        let created = mock("POST", "/repos/wayfair/foxdie/git/refs")
+            .match_body(Matcher::Json(serde_json::json!({
+                "ref": "refs/heads/feature/restored",
+                "sha": "abc123"
+            })))
+            .with_status(201)
+            .create();
+
+        assert!(github
+            .create_branch("feature/restored", "abc123")
+            .await
+            .is_ok());
+        created.assert();
+    }
+
+    #[tokio::test]
+    async fn test_close_and_reopen_push_request() {
+        let github = GitHub::new(
+            &mockito::server_url(),
+            "token",
+            &ClientOptions {
+                user_agent: "foxdie/test",
+                ..ClientOptions::default()
+            },
+            "wayfair",
+            "foxdie",
+        );
+        let closed = mock("PATCH", "/repos/wayfair/foxdie/pulls/11")
+            .match_body(Matcher::Json(serde_json::json!({ "state": "closed" })))
+            .create();
+        let reopened = mock("PATCH", "/repos/wayfair/foxdie/pulls/12")
+            .match_body(Matcher::Json(serde_json::json!({ "state": "open" })))
+            .create();
+
+        assert!(github.close_push_request(11).await.is_ok());
+        assert!(github.reopen_push_request(12).await.is_ok());
+        closed.assert();
+        reopened.assert();
+    }
+
+    #[tokio::test]
+    async fn test_comment_on_push_request() {
+        let github = GitHub::new(
+            &mockito::server_url(),
+            "token",
+            &ClientOptions {
+                user_agent: "foxdie/test",
+                ..ClientOptions::default()
+            },
+            "wayfair",
+            "foxdie",
+        );
+        let commented = mock("POST", "/repos/wayfair/foxdie/issues/13/comments")
+            .match_body(Matcher::Json(
+                serde_json::json!({ "body": "Closing as stale." }),
+            ))
+            .with_status(201)
+            .create();
+
+        assert!(github
+            .comment_on_push_request(13, "Closing as stale.")
+            .await
+            .is_ok());
+        commented.assert();
+    }
+
+    #[tokio::test]
+    async fn test_review_state() {
+        let github =
+            GitHub::with_client(Client::new(), &mockito::server_url(), "wayfair", "foxdie");
+        let reviews = mock("GET", "/repos/wayfair/foxdie/pulls/12/reviews")
+            .match_query(Matcher::Exact("per_page=100".into()))
+            .with_body(
+                r#"[
+                    { "id": 1, "user": { "login": "alice" }, "state": "CHANGES_REQUESTED" },
+                    { "id": 2, "user": { "login": "bob" }, "state": "APPROVED" },
+                    { "id": 3, "user": { "login": "alice" }, "state": "COMMENTED" },
+                    { "id": 4, "user": { "login": "alice" }, "state": "APPROVED" }
+                ]"#,
+            )
+            .create();
+
+        assert_eq!(
+            github.review_state(12).await.unwrap(),
+            ReviewState::Approved
+        );
+        reviews.assert();
+    }
+
+    #[tokio::test]
+    async fn test_branch_exists() {
+        let github = GitHub::new(
+            &mockito::server_url(),
+            "token",
+            &ClientOptions {
+                user_agent: "foxdie/test",
+                ..ClientOptions::default()
+            },
+            "wayfair",
+            "foxdie",
+        );
+        let _present = mock("GET", "/repos/wayfair/foxdie/branches/present")
+            .with_body("{}")
+            .create();
+        let _absent = mock("GET", "/repos/wayfair/foxdie/branches/absent")
+            .with_status(404)
+            .create();
+        let _escaped = mock("GET", "/repos/wayfair/foxdie/branches/fix%2312")
+            .with_body("{}")
+            .create();
+
+        assert!(github.branch_exists("present").await.unwrap());
+        assert!(!github.branch_exists("absent").await.unwrap());
+        assert!(github.branch_exists("fix#12").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_error_includes_response_body() {
+        let github = GitHub::new(
+            &format!("{}/error-body", mockito::server_url()),
+            "token",
+            &ClientOptions::default(),
+            "wayfair",
+            "foxdie",
+        );
+        let rejected = mock(
+            "DELETE",
+            "/error-body/repos/wayfair/foxdie/git/refs/heads/protected",
+        )
+        .with_status(422)
+        .with_body(r#"{"message": "Protected branch update failed for refs/heads/protected."}"#)
+        .create();
+
+        let err = github.delete_branch("protected").await.unwrap_err();
+        rejected.assert();
+        let message = err.to_string();
+        assert!(
+            message.starts_with("HTTP status 422 Unprocessable Entity for "),
+            "{}",
+            message
+        );
+        assert!(
+            message.ends_with(
+                r#": {"message": "Protected branch update failed for refs/heads/protected."}"#
+            ),
+            "{}",
+            message
+        );
+    }
+
+    #[tokio::test]
+    async fn test_error_includes_request_id() {
+        let github = GitHub::new(
+            &mockito::server_url(),
+            "token",
+            &ClientOptions {
+                user_agent: "foxdie/test",
+                ..ClientOptions::default()
+            },
+            "wayfair",
+            "foxdie",
+        );
+        let failed = mock("DELETE", "/repos/wayfair/foxdie/git/refs/heads/unlucky")
+            .with_status(500)
+            .with_header("x-github-request-id", "CAFE:1234:5678")
+            .create();
+
+        let err = github.delete_branch("unlucky").await.unwrap_err();
+        failed.assert();
+        assert_eq!(err.request_id.as_deref(), Some("CAFE:1234:5678"));
+        assert!(err.to_string().ends_with("(request ID: CAFE:1234:5678)"));
+    }
+}
@@ -0,0 +1,173 @@
+// Copyright (c) 2018-2019, Wayfair LLC
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+//  * Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//    disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//    following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING,
+// BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED.
+// IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY,
+// OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+// STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE,
+// EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use std::convert::TryFrom;
+
+use super::{PushRequest, ReviewState};
+use chrono::{DateTime, FixedOffset};
+use glob::{Pattern, PatternError};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PullRequestOptions {
+    pub state: PullRequestStateEvent,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssueCommentOptions<'a> {
+    pub body: &'a str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CreateRefOptions<'a> {
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+    pub sha: &'a str,
+}
+
+#[derive(Debug, Copy, Clone, Serialize)]
+pub enum PullRequestStateEvent {
+    #[serde(rename = "closed")]
+    Closed,
+    #[serde(rename = "open")]
+    Reopen,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PullRequest {
+    pub html_url: String,
+    pub number: i32,
+    pub title: String,
+    pub created_at: DateTime<FixedOffset>,
+    pub updated_at: DateTime<FixedOffset>,
+    pub head: GitData,
+    pub base: GitData,
+    #[serde(default)]
+    pub draft: bool,
+    /// Either `open` or `closed`.
+    pub state: Option<String>,
+    /// Set once the pull request has been merged.
+    #[serde(default)]
+    pub merged_at: Option<DateTime<FixedOffset>>,
+    /// Missing when the author's account has been deleted.
+    pub user: Option<User>,
+    /// Only sent when the pull request itself is requested, and even then not until GitHub has checked it.
+    #[serde(default)]
+    pub mergeable: Option<bool>,
+}
+
+impl PullRequest {
+    /// Whether this pull request is still open. Pull requests listed by state don't always say which they're in, and
+    /// are taken to be open.
+    pub fn is_open(&self) -> bool {
+        self.state.as_deref().is_none_or(|state| state == "open")
+    }
+}
+
+impl TryFrom<PullRequest> for PushRequest {
+    type Error = ();
+
+    fn try_from(pr: PullRequest) -> Result<Self, Self::Error> {
+        let is_open = pr.is_open();
+        Ok(PushRequest {
+            url: pr.html_url,
+            id: pr.number,
+            title: pr.title,
+            created_at: pr.created_at,
+            updated_at: pr.updated_at,
+            // GitHub omits the repository once it has been deleted, as happens to the heads of abandoned forks.
+            target_project: pr.base.repo.ok_or(())?.id,
+            target_branch: pr.base.git_ref,
+            source_project: pr.head.repo.ok_or(())?.id,
+            source_branch: pr.head.git_ref,
+            source_sha: pr.head.sha,
+            is_draft: pr.draft,
+            is_open,
+            is_merged: pr.merged_at.is_some(),
+            author: pr.user.map(|user| user.login),
+            mergeable: pr.mergeable,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitData {
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+    #[serde(default)]
+    pub sha: Option<String>,
+    pub repo: Option<Repository>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Repository {
+    pub id: i32,
+    /// Only sent when the repository itself is requested, rather than as part of a pull request.
+    pub default_branch: Option<String>,
+    #[serde(default)]
+    pub archived: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Review {
+    /// Missing when the reviewer's account has been deleted.
+    pub user: Option<User>,
+    pub state: String,
+}
+
+impl Review {
+    pub fn reviewer(&self) -> String {
+        self.user
+            .as_ref()
+            .map(|user| user.login.clone())
+            .unwrap_or_default()
+    }
+
+    /// The verdict this review gives, if it gives one. A dismissed review withdraws its reviewer's verdict.
+    pub fn verdict(&self) -> Option<ReviewState> {
+        match &*self.state {
+            "APPROVED" => Some(ReviewState::Approved),
+            "CHANGES_REQUESTED" => Some(ReviewState::ChangesRequested),
+            "DISMISSED" => Some(ReviewState::AwaitingReview),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct User {
+    pub login: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProtectedBranch {
+    pub name: String,
+}
+
+impl TryFrom<ProtectedBranch> for super::super::ProtectedBranch {
+    type Error = PatternError;
+
+    fn try_from(branch: ProtectedBranch) -> Result<Self, Self::Error> {
+        let pattern = Pattern::new(&branch.name)?;
+        Ok(Self {
+            pattern,
+            source: super::super::ProtectionSource::Server,
+        })
+    }
+}
@@ -0,0 +1,989 @@
+// Copyright (c) 2018-2019, Wayfair LLC
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+//  * Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//    disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//    following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING,
+// BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED.
+// IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY,
+// OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+// STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE,
+// EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+mod v4;
+
+use self::v4::*;
+use super::links::Links;
+use super::{
+    access_error_for_status, deserialize_each, error_for_status, exists_for_status,
+    listing_message, read_json, token_accepted_for_status, AccessTarget, ApiResult, PushRequest,
+    PushRequestState, ReviewState, SCMProviderImpl, PATH_SEGMENT_ENCODE_SET,
+};
+use crate::progress::Progress;
+use async_trait::async_trait;
+use log::debug;
+use percent_encoding::utf8_percent_encode;
+use reqwest::header::{self, HeaderMap, HeaderValue};
+use reqwest::{Client, Response};
+use serde::de::DeserializeOwned;
+use std::path::{Path, PathBuf};
+use what_git::ClientOptions;
+
+/// The most items Gitlab returns in a page, which is also how many are requested unless a smaller page size is set.
+const MAX_PAGE_SIZE: usize = 100;
+
+/// The header Gitlab uses to identify a request, for reference in support requests.
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+#[derive(Debug)]
+pub struct Gitlab {
+    client: Client,
+    base_url: String,
+    owner: String,
+    repo: String,
+    /// When set, the project is addressed by this ID rather than by its owner and name.
+    project_id: Option<u64>,
+    /// The number of items requested in each page of a listing.
+    page_size: usize,
+    raw_response_dir: Option<PathBuf>,
+}
+
+impl Gitlab {
+    pub fn new(
+        base_url: &str,
+        token: &str,
+        client_options: &ClientOptions<'_>,
+        owner: &str,
+        repo: &str,
+    ) -> Self {
+        Gitlab::with_client(
+            authenticated_client(token, client_options),
+            base_url,
+            owner,
+            repo,
+        )
+    }
+
+    /// Creates a `Gitlab` that makes requests with a preconfigured `client`. Unlike `Gitlab::new`, no private token
+    /// header is added, so the client is expected to carry whatever it needs.
+    pub fn with_client(client: Client, base_url: &str, owner: &str, repo: &str) -> Self {
+        Gitlab {
+            client,
+            base_url: From::from(base_url),
+            owner: From::from(owner),
+            repo: From::from(repo),
+            project_id: None,
+            page_size: MAX_PAGE_SIZE,
+            raw_response_dir: None,
+        }
+    }
+
+    /// Requests `size` items in each page of a listing, if set, up to Gitlab's maximum of 100. Otherwise, as many as
+    /// Gitlab allows are.
+    pub fn with_page_size(mut self, size: Option<usize>) -> Self {
+        self.page_size = size.map_or(MAX_PAGE_SIZE, |size| size.clamp(1, MAX_PAGE_SIZE));
+        self
+    }
+
+    /// Writes the body of every response to a file in `dir` before deserializing it, if `dir` is set.
+    pub fn dumping_raw_responses_to(mut self, dir: Option<&Path>) -> Self {
+        self.raw_response_dir = dir.map(Path::to_path_buf);
+        self
+    }
+
+    /// Addresses the project by its numeric `id`, if set, instead of by its owner and name. The ID keeps working after
+    /// the project is renamed or moved.
+    pub fn with_project_id(mut self, id: Option<u64>) -> Self {
+        self.project_id = id;
+        self
+    }
+
+    fn construct_base_url(&self) -> String {
+        if let Some(id) = self.project_id {
+            return format!("{}/api/v4/projects/{}", self.base_url, id);
+        }
+        let namespace = format!("{}/{}", self.owner, self.repo);
+        let namespace_encoded = utf8_percent_encode(&namespace[..], PATH_SEGMENT_ENCODE_SET);
+        format!("{}/api/v4/projects/{}", self.base_url, namespace_encoded)
+    }
+
+    /// Names the project as `owner/repo`, or by its ID when it is addressed by one, for error messages.
+    fn repository_name(&self) -> String {
+        match self.project_id {
+            Some(id) => format!("project {}", id),
+            None => format!("{}/{}", self.owner, self.repo),
+        }
+    }
+
+    /// Looks up the project itself.
+    async fn project(&self) -> ApiResult<Project> {
+        let url = self.construct_base_url();
+        debug!("{}", url);
+        let resp = access_error_for_status(
+            self.client.get(&*url).send().await?,
+            REQUEST_ID_HEADER,
+            AccessTarget::Repository(self.repository_name()),
+        )
+        .await?;
+        read_json(resp, self.raw_response_dir.as_deref()).await
+    }
+
+    async fn update_merge_request_state(
+        &self,
+        id: i32,
+        state_event: MergeRequestStateEvent,
+    ) -> ApiResult<()> {
+        let url = format!("{}/merge_requests/{}", self.construct_base_url(), id);
+        let resp = self
+            .client
+            .put(&*url)
+            .query(&MergeRequestOptions { state_event })
+            .send()
+            .await?;
+        error_for_status(resp, REQUEST_ID_HEADER)
+            .await
+            .map(|_res| ())
+    }
+
+    async fn merge_requests_for_page(
+        &self,
+        state: &str,
+        page: &str,
+    ) -> ApiResult<Vec<MergeRequest>> {
+        let url = format!("{}/merge_requests", self.construct_base_url());
+        debug!("{}", url);
+        let resp = self
+            .client
+            .get(&*url)
+            .query(&[("state", state), ("page", page)])
+            .query(&[("per_page", self.page_size)])
+            .send()
+            .await?;
+        let merge_requests = read_json(
+            access_error_for_status(
+                resp,
+                REQUEST_ID_HEADER,
+                AccessTarget::Repository(self.repository_name()),
+            )
+            .await?,
+            self.raw_response_dir.as_deref(),
+        )
+        .await?;
+        Ok(deserialize_each(merge_requests))
+    }
+
+    /// Lists merge requests by following each page's `Link` header to the next, as Gitlab requires once it switches
+    /// to keyset pagination for large result sets.
+    async fn merge_requests_following_links(
+        &self,
+        url: &str,
+        state: &str,
+    ) -> ApiResult<Vec<MergeRequest>> {
+        let resp = access_error_for_status(
+            self.client
+                .get(url)
+                .query(&[("state", state)])
+                .query(&[("per_page", self.page_size)])
+                .send()
+                .await?,
+            REQUEST_ID_HEADER,
+            AccessTarget::Repository(self.repository_name()),
+        )
+        .await?;
+        follow_next_links(&self.client, resp, self.raw_response_dir.as_deref()).await
+    }
+
+    /// Lists the merge requests in the Gitlab `state` by following `Link` headers when Gitlab sends them, and otherwise
+    /// by page number, when Gitlab reports the totals.
+    async fn merge_requests_in_state(&self, state: &str) -> ApiResult<Vec<MergeRequest>> {
+        let url = format!("{}/merge_requests", self.construct_base_url());
+        debug!("{}", url);
+        let query = [("state", state)];
+
+        let head = access_error_for_status(
+            self.client
+                .head(&*url)
+                .query(&query)
+                .query(&[("per_page", self.page_size)])
+                .send()
+                .await?,
+            REQUEST_ID_HEADER,
+            AccessTarget::Repository(self.repository_name()),
+        )
+        .await?;
+        let headers = head.headers();
+        let pages = Pages::new(headers);
+
+        if next_link(headers).is_some() {
+            self.merge_requests_following_links(&url, state).await
+        } else if let Pages {
+            current: Some(current),
+            total_items: Some(total_items),
+            total_pages: Some(total_pages),
+            ..
+        } = pages
+        {
+            let mut items = Vec::with_capacity(total_items);
+            let mut progress = Progress::bar(
+                &listing_message(&url),
+                "pages",
+                total_pages.saturating_sub(current) + 1,
+            );
+            for page in current..=total_pages {
+                let mut merge_requests = self
+                    .merge_requests_for_page(state, &page.to_string())
+                    .await?;
+                items.append(&mut merge_requests);
+                progress.inc();
+            }
+            Ok(items)
+        } else {
+            self.merge_requests_following_links(&url, state).await
+        }
+    }
+}
+
+#[async_trait]
+impl SCMProviderImpl for Gitlab {
+    fn name(&self) -> &'static str {
+        "Gitlab"
+    }
+
+    async fn list_push_requests(&self, state: PushRequestState) -> ApiResult<Vec<PushRequest>> {
+        let mut push_requests = vec![];
+        for state in state.gitlab_values() {
+            let merge_requests = self.merge_requests_in_state(state).await?;
+            push_requests.extend(merge_requests.into_iter().map(PushRequest::from));
+        }
+        Ok(push_requests)
+    }
+
+    async fn is_push_request_open(&self, id: i32) -> ApiResult<bool> {
+        let url = format!("{}/merge_requests/{}", self.construct_base_url(), id);
+        debug!("{}", url);
+        let resp =
+            error_for_status(self.client.get(&*url).send().await?, REQUEST_ID_HEADER).await?;
+        let mr: MergeRequest = read_json(resp, self.raw_response_dir.as_deref()).await?;
+        Ok(mr.is_open())
+    }
+
+    async fn close_push_request(&self, id: i32) -> ApiResult<()> {
+        self.update_merge_request_state(id, MergeRequestStateEvent::Close)
+            .await
+    }
+
+    async fn reopen_push_request(&self, id: i32) -> ApiResult<()> {
+        self.update_merge_request_state(id, MergeRequestStateEvent::Reopen)
+            .await
+    }
+
+    async fn comment_on_push_request(&self, id: i32, body: &str) -> ApiResult<()> {
+        let url = format!("{}/merge_requests/{}/notes", self.construct_base_url(), id);
+        let resp = self
+            .client
+            .post(&*url)
+            .query(&NoteOptions { body })
+            .send()
+            .await?;
+        error_for_status(resp, REQUEST_ID_HEADER)
+            .await
+            .map(|_res| ())
+    }
+
+    async fn review_state(&self, id: i32) -> ApiResult<ReviewState> {
+        let url = format!(
+            "{}/merge_requests/{}/approvals",
+            self.construct_base_url(),
+            id
+        );
+        let resp =
+            error_for_status(self.client.get(&*url).send().await?, REQUEST_ID_HEADER).await?;
+        let approvals: Approvals = read_json(resp, self.raw_response_dir.as_deref()).await?;
+        Ok(approvals.into())
+    }
+
+    async fn list_protected_branches(&self) -> ApiResult<Vec<super::ProtectedBranch>> {
+        let url = format!("{}/protected_branches", self.construct_base_url());
+        let resp = access_error_for_status(
+            self.client.get(&*url).send().await?,
+            REQUEST_ID_HEADER,
+            AccessTarget::Repository(self.repository_name()),
+        )
+        .await?;
+        let protected_branches: Vec<ProtectedBranch> =
+            deserialize_each(read_json(resp, self.raw_response_dir.as_deref()).await?);
+        Ok(protected_branches
+            .into_iter()
+            .map(From::from)
+            .filter_map(Result::ok)
+            .collect())
+    }
+
+    async fn list_undeletable_branches(&self) -> ApiResult<Vec<super::ProtectedBranch>> {
+        let url = format!("{}/repository/branches", self.construct_base_url());
+        debug!("{}", url);
+        let resp = access_error_for_status(
+            self.client
+                .get(&*url)
+                .query(&[("per_page", self.page_size)])
+                .send()
+                .await?,
+            REQUEST_ID_HEADER,
+            AccessTarget::Repository(self.repository_name()),
+        )
+        .await?;
+        let branches: Vec<Branch> =
+            follow_next_links(&self.client, resp, self.raw_response_dir.as_deref()).await?;
+        Ok(branches.iter().filter_map(Branch::undeletable).collect())
+    }
+
+    async fn delete_branch(&self, branch: &str) -> ApiResult<()> {
+        let url = format!(
+            "{}/repository/branches/{}",
+            self.construct_base_url(),
+            utf8_percent_encode(branch, PATH_SEGMENT_ENCODE_SET)
+        );
+        let resp = self.client.delete(&*url).send().await?;
+        error_for_status(resp, REQUEST_ID_HEADER)
+            .await
+            .map(|_res| ())
+    }
+
+    async fn create_branch(&self, branch: &str, sha: &str) -> ApiResult<()> {
+        let url = format!("{}/repository/branches", self.construct_base_url());
+        let resp = self
+            .client
+            .post(&*url)
+            .query(&BranchOptions {
+                branch,
+                git_ref: sha,
+            })
+            .send()
+            .await?;
+        error_for_status(resp, REQUEST_ID_HEADER)
+            .await
+            .map(|_res| ())
+    }
+
+    async fn branch_exists(&self, branch: &str) -> ApiResult<bool> {
+        let url = format!(
+            "{}/repository/branches/{}",
+            self.construct_base_url(),
+            utf8_percent_encode(branch, PATH_SEGMENT_ENCODE_SET)
+        );
+        let resp = self.client.get(&*url).send().await?;
+        exists_for_status(resp, REQUEST_ID_HEADER).await
+    }
+
+    async fn default_branch(&self) -> ApiResult<Option<String>> {
+        Ok(self.project().await?.default_branch)
+    }
+
+    async fn is_archived(&self) -> ApiResult<bool> {
+        Ok(self.project().await?.archived)
+    }
+
+    async fn verify_token(&self) -> ApiResult<bool> {
+        let url = format!("{}/api/v4/user", self.base_url);
+        debug!("{}", url);
+        token_accepted_for_status(self.client.get(&*url).send().await?, REQUEST_ID_HEADER).await
+    }
+}
+
+/// Builds a client that authenticates with Gitlab by sending `token` as its private token.
+pub(super) fn authenticated_client(token: &str, client_options: &ClientOptions<'_>) -> Client {
+    let mut headers = HeaderMap::new();
+    headers.append(
+        "private-token",
+        HeaderValue::from_str(token).expect("Token should be convertible to a header string"),
+    );
+    what_git::client_builder(client_options)
+        .default_headers(headers)
+        .build()
+        .expect("Gitlab client failed to construct itself.")
+}
+
+/// Lists the projects in the group at the path `group` on the Gitlab instance at `base_url`, including those in its
+/// subgroups but not those archived, following each page's `Link` header to the next.
+pub(super) async fn list_group_projects(
+    client: &Client,
+    base_url: &str,
+    group: &str,
+    raw_response_dir: Option<&Path>,
+) -> ApiResult<Vec<GroupProject>> {
+    let url = format!(
+        "{}/api/v4/groups/{}/projects",
+        base_url,
+        utf8_percent_encode(group, PATH_SEGMENT_ENCODE_SET)
+    );
+    debug!("{}", url);
+    let resp = access_error_for_status(
+        client
+            .get(&*url)
+            .query(&[("include_subgroups", "true"), ("archived", "false")])
+            .query(&[("per_page", MAX_PAGE_SIZE)])
+            .send()
+            .await?,
+        REQUEST_ID_HEADER,
+        AccessTarget::Group(group.to_string()),
+    )
+    .await?;
+    follow_next_links(client, resp, raw_response_dir).await
+}
+
+/// Reads every page of a listing, starting with the page in `first_response` and following each page's `Link` header
+/// to the next, exactly as given. Under keyset pagination, the next page is addressed by an opaque cursor rather than a
+/// page number, so the link is the only way to reach it.
+async fn follow_next_links<T>(
+    client: &Client,
+    first_response: Response,
+    raw_response_dir: Option<&Path>,
+) -> ApiResult<Vec<T>>
+where
+    T: DeserializeOwned,
+{
+    let mut progress = Progress::spinner(&listing_message(first_response.url().as_str()), "pages");
+    let mut resp = first_response;
+    let mut items = vec![];
+    loop {
+        let next = next_link(resp.headers());
+        let page = read_json(resp, raw_response_dir).await?;
+        items.extend(deserialize_each::<T>(page));
+        progress.inc();
+        match next {
+            Some(next) => {
+                debug!("{}", next);
+                resp =
+                    error_for_status(client.get(&*next).send().await?, REQUEST_ID_HEADER).await?;
+            }
+            None => return Ok(items),
+        }
+    }
+}
+
+/// Reads the URL of the next page from the `Link` header in `headers`, if there is one.
+fn next_link(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(header::LINK)
+        .and_then(|h| h.to_str().ok())
+        .map(Links::parse_from_rfc5988)
+        .and_then(|links| links.next().map(|link| link.uri.clone()))
+}
+
+#[allow(dead_code)]
+#[derive(Debug)]
+struct Pages {
+    current: Option<usize>,
+    total_items: Option<usize>,
+    total_pages: Option<usize>,
+    per_page: Option<usize>,
+    previous: Option<usize>,
+    next: Option<usize>,
+}
+
+impl Pages {
+    fn new(headers: &HeaderMap) -> Self {
+        Pages {
+            current: Pages::x_header(headers, "x-page"),
+            total_items: Pages::x_header(headers, "x-total"),
+            total_pages: Pages::x_header(headers, "x-total-pages"),
+            per_page: Pages::x_header(headers, "x-per-page"),
+            previous: Pages::x_header(headers, "x-prev-page"),
+            next: Pages::x_header(headers, "x-next-page"),
+        }
+    }
+
+    fn x_header(headers: &HeaderMap, key: &'static str) -> Option<usize> {
+        headers
+            .get(key)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.parse::<usize>().ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, Matcher};
+
+    fn merge_request_json(iid: i32) -> serde_json::Value {
+        serde_json::json!({
+            "iid": iid,
+            "title": format!("MR {}", iid),
+            "created_at": "2019-01-01T00:00:00Z",
+            "updated_at": "2019-01-02T00:00:00Z",
+            "target_branch": "master",
+            "source_branch": format!("branch-{}", iid),
+            "source_project_id": 1,
+            "target_project_id": 1,
+            "web_url": format!("https://gitlab.com/wayfair/foxdie/merge_requests/{}", iid),
+            "work_in_progress": false
+        })
+    }
+
+    #[tokio::test]
+    async fn test_list_push_requests_requests_full_pages() {
+        let gitlab =
+            Gitlab::with_client(Client::new(), &mockito::server_url(), "wayfair", "per-page");
+        let head = mock("HEAD", "/api/v4/projects/wayfair%2Fper-page/merge_requests")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("state".into(), "opened".into()),
+                Matcher::UrlEncoded("per_page".into(), "100".into()),
+            ]))
+            .with_header("x-page", "1")
+            .with_header("x-total", "2")
+            .with_header("x-total-pages", "1")
+            .expect(1)
+            .create();
+        let listed = mock("GET", "/api/v4/projects/wayfair%2Fper-page/merge_requests")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("page".into(), "1".into()),
+                Matcher::UrlEncoded("per_page".into(), "100".into()),
+            ]))
+            .with_body(
+                serde_json::json!([merge_request_json(1), merge_request_json(2)]).to_string(),
+            )
+            .expect(1)
+            .create();
+
+        let push_requests = gitlab
+            .list_push_requests(PushRequestState::Opened)
+            .await
+            .unwrap();
+
+        head.assert();
+        listed.assert();
+        assert_eq!(push_requests.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_list_push_requests_with_page_size() {
+        let gitlab = Gitlab::with_client(
+            Client::new(),
+            &mockito::server_url(),
+            "wayfair",
+            "page-size",
+        )
+        .with_page_size(Some(2));
+        let per_page = Matcher::UrlEncoded("per_page".into(), "2".into());
+        let head = mock(
+            "HEAD",
+            "/api/v4/projects/wayfair%2Fpage-size/merge_requests",
+        )
+        .match_query(per_page.clone())
+        .with_header("x-page", "1")
+        .with_header("x-total", "2")
+        .with_header("x-total-pages", "1")
+        .expect(1)
+        .create();
+        let listed = mock("GET", "/api/v4/projects/wayfair%2Fpage-size/merge_requests")
+            .match_query(per_page)
+            .with_body(
+                serde_json::json!([merge_request_json(1), merge_request_json(2)]).to_string(),
+            )
+            .expect(1)
+            .create();
+
+        let push_requests = gitlab
+            .list_push_requests(PushRequestState::Opened)
+            .await
+            .unwrap();
+
+        head.assert();
+        listed.assert();
+        assert_eq!(push_requests.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_list_closed_push_requests_includes_merged() {
+        let gitlab =
+            Gitlab::with_client(Client::new(), &mockito::server_url(), "wayfair", "merged");
+        let in_state = |state: &str, merge_request: serde_json::Value| {
+            let query = Matcher::UrlEncoded("state".into(), state.into());
+            let body = serde_json::json!([merge_request]).to_string();
+            (
+                mock("HEAD", "/api/v4/projects/wayfair%2Fmerged/merge_requests")
+                    .match_query(query.clone())
+                    .with_header("x-page", "1")
+                    .with_header("x-total", "1")
+                    .with_header("x-total-pages", "1")
+                    .create(),
+                mock("GET", "/api/v4/projects/wayfair%2Fmerged/merge_requests")
+                    .match_query(query)
+                    .with_body(body)
+                    .create(),
+            )
+        };
+        let mut merged = merge_request_json(2);
+        merged["state"] = "merged".into();
+        let _closed = in_state("closed", merge_request_json(1));
+        let _merged = in_state("merged", merged);
+
+        let push_requests = gitlab
+            .list_push_requests(PushRequestState::Closed)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            push_requests
+                .iter()
+                .map(|pr| (pr.id, pr.is_merged))
+                .collect::<Vec<_>>(),
+            vec![(1, false), (2, true)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_push_requests_for_missing_project() {
+        let gitlab = Gitlab::with_client(Client::new(), &mockito::server_url(), "wayfair", "gone")
+            .with_project_id(Some(404));
+        let _head = mock("HEAD", "/api/v4/projects/404/merge_requests")
+            .match_query(Matcher::Any)
+            .with_status(404)
+            .create();
+
+        let err = gitlab
+            .list_push_requests(PushRequestState::Opened)
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Repository project 404 was not found. It may have been renamed, moved or deleted, or the token may not \
+             have access to it."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_default_branch() {
+        let gitlab = Gitlab::with_client(Client::new(), &mockito::server_url(), "wayfair", "trunk");
+        let _project = mock("GET", "/api/v4/projects/wayfair%2Ftrunk")
+            .with_body(r#"{ "id": 1, "default_branch": "trunk" }"#)
+            .create();
+
+        assert_eq!(
+            gitlab.default_branch().await.unwrap().as_deref(),
+            Some("trunk")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_undeletable_branches() {
+        let gitlab = Gitlab::with_client(Client::new(), &mockito::server_url(), "wayfair", "flags");
+        let first_page = mock(
+            "GET",
+            "/api/v4/projects/wayfair%2Fflags/repository/branches",
+        )
+        .match_query(Matcher::UrlEncoded("per_page".into(), "100".into()))
+        .with_header(
+            "link",
+            &format!(
+                "<{}/api/v4/projects/wayfair%2Fflags/repository/branches?page=2>; rel=\"next\"",
+                mockito::server_url()
+            ),
+        )
+        .with_body(
+            r#"[{ "name": "main", "protected": true, "default": true },
+                    { "name": "feature", "protected": false, "default": false }]"#,
+        )
+        .create();
+        let second_page = mock(
+            "GET",
+            "/api/v4/projects/wayfair%2Fflags/repository/branches",
+        )
+        .match_query(Matcher::UrlEncoded("page".into(), "2".into()))
+        .with_body(r#"[{ "name": "group-protected", "protected": true, "default": false }]"#)
+        .create();
+
+        let undeletable = gitlab.list_undeletable_branches().await.unwrap();
+
+        first_page.assert();
+        second_page.assert();
+        assert_eq!(
+            undeletable
+                .iter()
+                .map(|branch| (branch.pattern.as_str(), branch.source))
+                .collect::<Vec<_>>(),
+            vec![
+                ("main", super::super::ProtectionSource::Default),
+                ("group-protected", super::super::ProtectionSource::Server),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_push_requests_follows_keyset_cursors() {
+        let gitlab =
+            Gitlab::with_client(Client::new(), &mockito::server_url(), "wayfair", "cursors");
+        let page_url = |cursor: &str| {
+            format!(
+                "{}/api/v4/projects/wayfair%2Fcursors/merge_requests?state=opened&per_page=100&pagination=keyset&cursor={}",
+                mockito::server_url(),
+                cursor
+            )
+        };
+        let next_link = |cursor: &str| format!(r#"<{}>; rel="next""#, page_url(cursor));
+        // Totals are sent too, to check that the links take precedence over synthesized page numbers.
+        let _head = mock("HEAD", "/api/v4/projects/wayfair%2Fcursors/merge_requests")
+            .match_query(Matcher::Any)
+            .with_header("x-page", "1")
+            .with_header("x-total", "3")
+            .with_header("x-total-pages", "3")
+            .with_header("link", &next_link("eyJpZCI6IjEifQ"))
+            .create();
+        let numbered = mock("GET", "/api/v4/projects/wayfair%2Fcursors/merge_requests")
+            .match_query(Matcher::UrlEncoded("page".into(), "2".into()))
+            .expect(0)
+            .create();
+        let first = mock("GET", "/api/v4/projects/wayfair%2Fcursors/merge_requests")
+            .match_query(Matcher::Exact("state=opened&per_page=100".into()))
+            .with_header("link", &next_link("eyJpZCI6IjEifQ"))
+            .with_body(serde_json::json!([merge_request_json(1)]).to_string())
+            .expect(1)
+            .create();
+        let second = mock("GET", "/api/v4/projects/wayfair%2Fcursors/merge_requests")
+            .match_query(Matcher::UrlEncoded(
+                "cursor".into(),
+                "eyJpZCI6IjEifQ".into(),
+            ))
+            .with_header("link", &next_link("eyJpZCI6IjIifQ"))
+            .with_body(serde_json::json!([merge_request_json(2)]).to_string())
+            .expect(1)
+            .create();
+        let third = mock("GET", "/api/v4/projects/wayfair%2Fcursors/merge_requests")
+            .match_query(Matcher::UrlEncoded(
+                "cursor".into(),
+                "eyJpZCI6IjIifQ".into(),
+            ))
+            .with_body(serde_json::json!([merge_request_json(3)]).to_string())
+            .expect(1)
+            .create();
+
+        let push_requests = gitlab
+            .list_push_requests(PushRequestState::Opened)
+            .await
+            .unwrap();
+
+        numbered.assert();
+        first.assert();
+        second.assert();
+        third.assert();
+        assert_eq!(
+            push_requests.iter().map(|pr| pr.id).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_push_requests_follows_link_headers_without_totals() {
+        let gitlab =
+            Gitlab::with_client(Client::new(), &mockito::server_url(), "wayfair", "keyset");
+        let next_page = format!(
+            "{}/api/v4/projects/wayfair%2Fkeyset/merge_requests?state=opened&per_page=100&page=2",
+            mockito::server_url()
+        );
+        let _head = mock("HEAD", "/api/v4/projects/wayfair%2Fkeyset/merge_requests")
+            .match_query(Matcher::Any)
+            .with_header("x-page", "1")
+            .with_header("x-per-page", "100")
+            .create();
+        let first = mock("GET", "/api/v4/projects/wayfair%2Fkeyset/merge_requests")
+            .match_query(Matcher::Exact("state=opened&per_page=100".into()))
+            .with_header("link", &format!(r#"<{}>; rel="next""#, next_page))
+            .with_body(
+                serde_json::json!([merge_request_json(1), merge_request_json(2)]).to_string(),
+            )
+            .expect(1)
+            .create();
+        let second = mock("GET", "/api/v4/projects/wayfair%2Fkeyset/merge_requests")
+            .match_query(Matcher::UrlEncoded("page".into(), "2".into()))
+            .with_body(serde_json::json!([merge_request_json(3)]).to_string())
+            .expect(1)
+            .create();
+
+        let push_requests = gitlab
+            .list_push_requests(PushRequestState::Opened)
+            .await
+            .unwrap();
+
+        first.assert();
+        second.assert();
+        assert_eq!(
+            push_requests.iter().map(|pr| pr.id).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_close_push_request() {
+        let gitlab =
+            Gitlab::with_client(Client::new(), &mockito::server_url(), "wayfair", "foxdie");
+        let closed = mock("PUT", "/api/v4/projects/wayfair%2Ffoxdie/merge_requests/7")
+            .match_query(Matcher::UrlEncoded("state_event".into(), "close".into()))
+            .with_status(200)
+            .create();
+
+        assert!(gitlab.close_push_request(7).await.is_ok());
+        closed.assert();
+    }
+
+    #[tokio::test]
+    async fn test_close_push_request_by_project_id() {
+        let gitlab =
+            Gitlab::with_client(Client::new(), &mockito::server_url(), "wayfair", "renamed")
+                .with_project_id(Some(4242));
+        let closed = mock("PUT", "/api/v4/projects/4242/merge_requests/8")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .create();
+
+        assert!(gitlab.close_push_request(8).await.is_ok());
+        closed.assert();
+    }
+
+    #[tokio::test]
+    async fn test_comment_on_push_request() {
+        let gitlab = Gitlab::new(
+            &mockito::server_url(),
+            "token",
+            &ClientOptions {
+                user_agent: "foxdie/test",
+                ..ClientOptions::default()
+            },
+            "wayfair",
+            "foxdie",
+        );
+        let commented = mock(
+            "POST",
+            "/api/v4/projects/wayfair%2Ffoxdie/merge_requests/9/notes",
+        )
+        .match_query(Matcher::UrlEncoded(
+            "body".into(),
+            "Closing as stale.".into(),
+        ))
+        .with_status(201)
+        .create();
+
+        assert!(gitlab
+            .comment_on_push_request(9, "Closing as stale.")
+            .await
+            .is_ok());
+        commented.assert();
+    }
+
+    #[tokio::test]
+    async fn test_review_state() {
+        let gitlab = Gitlab::new(
+            &mockito::server_url(),
+            "token",
+            &ClientOptions {
+                user_agent: "foxdie/test",
+                ..ClientOptions::default()
+            },
+            "wayfair",
+            "foxdie",
+        );
+        let approvals = mock(
+            "GET",
+            "/api/v4/projects/wayfair%2Ffoxdie/merge_requests/10/approvals",
+        )
+        .with_body(r#"{ "id": 1, "iid": 10, "approved": true, "approvals_left": 0 }"#)
+        .create();
+
+        assert_eq!(
+            gitlab.review_state(10).await.unwrap(),
+            ReviewState::Approved
+        );
+        approvals.assert();
+    }
+
+    #[tokio::test]
+    async fn test_reopen_push_request() {
+        let gitlab = Gitlab::new(
+            &mockito::server_url(),
+            "token",
+            &ClientOptions {
+                user_agent: "foxdie/test",
+                ..ClientOptions::default()
+            },
+            "wayfair",
+            "foxdie",
+        );
+        let reopened = mock("PUT", "/api/v4/projects/wayfair%2Ffoxdie/merge_requests/8")
+            .match_query(Matcher::UrlEncoded("state_event".into(), "reopen".into()))
+            .with_status(200)
+            .create();
+
+        assert!(gitlab.reopen_push_request(8).await.is_ok());
+        reopened.assert();
+    }
+
+    #[tokio::test]
+    async fn test_delete_branch() {
+        let gitlab = Gitlab::new(
+            &mockito::server_url(),
+            "token",
+            &ClientOptions {
+                user_agent: "foxdie/test",
+                ..ClientOptions::default()
+            },
+            "wayfair",
+            "foxdie",
+        );
+        let deleted = mock(
+            "DELETE",
+            "/api/v4/projects/wayfair%2Ffoxdie/repository/branches/feature%2Fstale",
+        )
+        .match_header("private-token", "token")
+        .match_header("user-agent", "foxdie/test")
+        .with_status(204)
+        .create();
+        let missing = mock(
+            "DELETE",
+            "/api/v4/projects/wayfair%2Ffoxdie/repository/branches/missing",
+        )
+        .with_status(404)
+        .with_header("x-request-id", "01F0ABCDEF")
+        .create();
+
+        assert!(gitlab.delete_branch("feature/stale").await.is_ok());
+        let err = gitlab.delete_branch("missing").await.unwrap_err();
+        assert_eq!(err.request_id.as_deref(), Some("01F0ABCDEF"));
+        deleted.assert();
+        missing.assert();
+    }
+
+    #[tokio::test]
+    async fn test_create_branch() {
+        let gitlab = Gitlab::new(
+            &mockito::server_url(),
+            "token",
+            &ClientOptions::default(),
+            "wayfair",
+            "foxdie",
+        );
+        let created = mock(
+            "POST",
+            "/api/v4/projects/wayfair%2Ffoxdie/repository/branches",
+        )
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("branch".into(), "feature/restored".into()),
+            Matcher::UrlEncoded("ref".into(), "abc123".into()),
+        ]))
+        .with_status(201)
+        .create();
+
+        assert!(gitlab
+            .create_branch("feature/restored", "abc123")
+            .await
+            .is_ok());
+        created.assert();
+    }
+}
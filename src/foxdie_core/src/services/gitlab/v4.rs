@@ -0,0 +1,325 @@
+// Copyright (c) 2018-2019, Wayfair LLC
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+//  * Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//    disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//    following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING,
+// BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED.
+// IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY,
+// OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+// STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE,
+// EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use super::{PushRequest, ReviewState};
+use chrono::{DateTime, FixedOffset};
+use glob::{Pattern, PatternError};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeRequestOptions {
+    pub state_event: MergeRequestStateEvent,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NoteOptions<'a> {
+    pub body: &'a str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BranchOptions<'a> {
+    pub branch: &'a str,
+    #[serde(rename = "ref")]
+    pub git_ref: &'a str,
+}
+
+#[derive(Debug, Copy, Clone, Serialize)]
+pub enum MergeRequestStateEvent {
+    #[serde(rename = "close")]
+    Close,
+    #[serde(rename = "reopen")]
+    Reopen,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MergeRequest {
+    iid: i32,
+    title: String,
+    created_at: DateTime<FixedOffset>,
+    updated_at: DateTime<FixedOffset>,
+    target_branch: String,
+    source_branch: String,
+    /// The commit at the head of the source branch.
+    sha: Option<String>,
+    source_project_id: i32,
+    target_project_id: i32,
+    web_url: String,
+    #[serde(default)]
+    work_in_progress: bool,
+    #[serde(default)]
+    draft: bool,
+    /// One of `opened`, `closed`, `locked` or `merged`.
+    state: Option<String>,
+    author: Option<User>,
+    /// One of `can_be_merged`, `cannot_be_merged` or a state in which Gitlab is still checking.
+    merge_status: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct User {
+    username: String,
+}
+
+impl MergeRequest {
+    /// Whether this merge request is a draft, either by its flags or, on older Gitlab versions, by its title prefix.
+    fn is_draft(&self) -> bool {
+        const DRAFT_PREFIXES: [&str; 5] = ["draft:", "wip:", "[draft]", "[wip]", "(draft)"];
+        let title = self.title.trim_start().to_lowercase();
+        self.draft
+            || self.work_in_progress
+            || DRAFT_PREFIXES
+                .iter()
+                .any(|prefix| title.starts_with(prefix))
+    }
+
+    /// Whether this merge request is still open, rather than closed, locked or merged.
+    pub fn is_open(&self) -> bool {
+        self.state.as_deref().is_none_or(|state| state == "opened")
+    }
+}
+
+impl From<MergeRequest> for PushRequest {
+    fn from(mr: MergeRequest) -> Self {
+        let is_draft = mr.is_draft();
+        let is_open = mr.is_open();
+        PushRequest {
+            url: mr.web_url,
+            id: mr.iid,
+            title: mr.title,
+            created_at: mr.created_at,
+            updated_at: mr.updated_at,
+            target_project: mr.target_project_id,
+            target_branch: mr.target_branch,
+            source_project: mr.source_project_id,
+            source_branch: mr.source_branch,
+            source_sha: mr.sha,
+            is_draft,
+            is_open,
+            is_merged: mr.state.as_deref() == Some("merged"),
+            author: mr.author.map(|user| user.username),
+            mergeable: match mr.merge_status.as_deref() {
+                Some("can_be_merged") => Some(true),
+                Some("cannot_be_merged") => Some(false),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// The approval status of a merge request. Gitlab has no way to request changes, so a merge request is either approved
+/// or awaiting review.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Approvals {
+    pub approved: bool,
+}
+
+impl From<Approvals> for ReviewState {
+    fn from(approvals: Approvals) -> Self {
+        if approvals.approved {
+            ReviewState::Approved
+        } else {
+            ReviewState::AwaitingReview
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Project {
+    /// Missing when the project has no commits yet.
+    pub default_branch: Option<String>,
+    #[serde(default)]
+    pub archived: bool,
+}
+
+/// A project as listed in a group.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GroupProject {
+    /// The full path of the project, like `group/subgroup/project`.
+    pub path_with_namespace: String,
+    pub web_url: String,
+}
+
+/// A branch as listed in a project's repository.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Branch {
+    pub name: String,
+    /// Set when any protection rule matches the branch, including those inherited from the project's group.
+    #[serde(default)]
+    pub protected: bool,
+    /// Set when the branch is the project's default branch.
+    #[serde(default)]
+    pub default: bool,
+}
+
+impl Branch {
+    /// Protects this branch by its name if Gitlab won't delete it, as the default branch or a protected one.
+    pub fn undeletable(&self) -> Option<super::super::ProtectedBranch> {
+        let source = if self.default {
+            super::super::ProtectionSource::Default
+        } else if self.protected {
+            super::super::ProtectionSource::Server
+        } else {
+            return None;
+        };
+        Some(super::super::ProtectedBranch::named(&self.name, source))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProtectedBranch {
+    pub name: String,
+}
+
+impl From<ProtectedBranch> for Result<super::super::ProtectedBranch, PatternError> {
+    fn from(branch: ProtectedBranch) -> Self {
+        let pattern = Pattern::new(&branch.name)?;
+        Ok(super::super::ProtectedBranch {
+            pattern,
+            source: super::super::ProtectionSource::Server,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merge_request(title: &str, draft: bool) -> MergeRequest {
+        let date = DateTime::parse_from_rfc3339("2019-01-01T00:00:00-05:00").unwrap();
+        MergeRequest {
+            iid: 1,
+            title: title.to_string(),
+            created_at: date,
+            updated_at: date,
+            target_branch: String::from("master"),
+            source_branch: String::from("feature"),
+            sha: None,
+            source_project_id: 1,
+            target_project_id: 1,
+            web_url: String::from("https://gitlab.com/wayfair/foxdie/merge_requests/1"),
+            work_in_progress: false,
+            draft,
+            state: Some(String::from("opened")),
+            author: None,
+            merge_status: None,
+        }
+    }
+
+    #[test]
+    fn test_is_draft() {
+        assert!(merge_request("Add a feature", true).is_draft());
+        assert!(merge_request("Draft: Add a feature", false).is_draft());
+        assert!(merge_request("WIP: Add a feature", false).is_draft());
+        assert!(merge_request("[WIP] Add a feature", false).is_draft());
+        assert!(!merge_request("Add a feature", false).is_draft());
+        assert!(!merge_request("Add a drafting tool", false).is_draft());
+    }
+
+    #[test]
+    fn test_author() {
+        let json = serde_json::json!({
+            "iid": 1,
+            "title": "Add a feature",
+            "created_at": "2019-01-01T00:00:00Z",
+            "updated_at": "2019-01-02T00:00:00Z",
+            "target_branch": "master",
+            "source_branch": "feature",
+            "sha": "abc123",
+            "source_project_id": 1,
+            "target_project_id": 1,
+            "web_url": "https://gitlab.com/wayfair/foxdie/merge_requests/1",
+            "work_in_progress": false,
+            "author": { "id": 1, "username": "tanuki", "name": "Tanuki" }
+        });
+        let push_request = PushRequest::from(serde_json::from_value::<MergeRequest>(json).unwrap());
+        assert_eq!(push_request.author.as_deref(), Some("tanuki"));
+        assert_eq!(push_request.source_sha.as_deref(), Some("abc123"));
+        assert!(push_request.is_open);
+        assert_eq!(
+            PushRequest::from(merge_request("Add a feature", false)).author,
+            None
+        );
+    }
+
+    #[test]
+    fn test_state() {
+        let with_state = |state: &str| {
+            PushRequest::from(MergeRequest {
+                state: Some(state.to_string()),
+                ..merge_request("Add a feature", false)
+            })
+        };
+        let states = ["opened", "closed", "locked", "merged"]
+            .iter()
+            .map(|state| {
+                let push_request = with_state(state);
+                (push_request.is_open, push_request.is_merged)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            states,
+            vec![(true, false), (false, false), (false, false), (false, true)]
+        );
+    }
+
+    #[test]
+    fn test_mergeable() {
+        let with_merge_status = |merge_status: &str| {
+            PushRequest::from(MergeRequest {
+                merge_status: Some(merge_status.to_string()),
+                ..merge_request("Add a feature", false)
+            })
+            .mergeable
+        };
+        assert_eq!(with_merge_status("can_be_merged"), Some(true));
+        assert_eq!(with_merge_status("cannot_be_merged"), Some(false));
+        assert_eq!(with_merge_status("checking"), None);
+        assert_eq!(
+            PushRequest::from(merge_request("Add a feature", false)).mergeable,
+            None
+        );
+    }
+
+    #[test]
+    fn test_undeletable() {
+        use crate::services::ProtectionSource;
+
+        let branch = |name: &str, protected: bool, default: bool| Branch {
+            name: name.to_string(),
+            protected,
+            default,
+        };
+        let undeletable = [
+            branch("main", true, true),
+            branch("release/1.0", true, false),
+            branch("feature", false, false),
+        ]
+        .iter()
+        .filter_map(Branch::undeletable)
+        .map(|protected| (protected.pattern.to_string(), protected.source))
+        .collect::<Vec<_>>();
+        assert_eq!(
+            undeletable,
+            vec![
+                (String::from("main"), ProtectionSource::Default),
+                (String::from("release/1.0"), ProtectionSource::Server),
+            ]
+        );
+    }
+}
@@ -0,0 +1,138 @@
+// Copyright (c) 2018-2019, Wayfair LLC
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+//  * Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//    disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//    following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING,
+// BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED.
+// IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY,
+// OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+// STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE,
+// EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Parsing for RFC 5988 `Link` headers, which GitHub, and Gitlab when it uses keyset pagination, send to point at the
+//! other pages of a paginated response.
+
+use reqwest::Url;
+
+#[derive(Debug)]
+pub struct Links {
+    links: Vec<Link>,
+}
+
+#[allow(dead_code)]
+impl Links {
+    pub fn parse_from_rfc5988(header: &str) -> Self {
+        Links {
+            links: header
+                .split(',')
+                .map(Link::parse_from_rfc5988)
+                .collect::<_>(),
+        }
+    }
+
+    pub fn prev(&self) -> Option<&Link> {
+        self.links.iter().find(|l| l.rel == "prev")
+    }
+
+    pub fn next(&self) -> Option<&Link> {
+        self.links.iter().find(|l| l.rel == "next")
+    }
+
+    pub fn first(&self) -> Option<&Link> {
+        self.links.iter().find(|l| l.rel == "first")
+    }
+
+    pub fn last(&self) -> Option<&Link> {
+        self.links.iter().find(|l| l.rel == "last")
+    }
+
+    /// Builds the URL of every page from `next` to `last`, in order, if both links are present and numbered.
+    pub fn remaining_page_urls(&self) -> Option<Vec<String>> {
+        let next = Url::parse(&self.next()?.uri).ok()?;
+        let last = Url::parse(&self.last()?.uri).ok()?;
+        let page_of = |url: &Url| {
+            url.query_pairs()
+                .find(|(key, _)| key == "page")
+                .and_then(|(_, value)| value.parse::<u32>().ok())
+        };
+        let (next_page, last_page) = (page_of(&next)?, page_of(&last)?);
+        Some(
+            (next_page..=last_page)
+                .map(|page| {
+                    let mut url = last.clone();
+                    let query = last
+                        .query_pairs()
+                        .map(|(key, value)| {
+                            if key == "page" {
+                                (key.into_owned(), page.to_string())
+                            } else {
+                                (key.into_owned(), value.into_owned())
+                            }
+                        })
+                        .collect::<Vec<_>>();
+                    url.query_pairs_mut().clear().extend_pairs(query);
+                    url.to_string()
+                })
+                .collect(),
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct Link {
+    pub uri: String,
+    pub rel: String,
+}
+
+impl Link {
+    fn parse_from_rfc5988(header: &str) -> Self {
+        let mut components = header.split(';');
+        let uri = components
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .trim_start_matches('<')
+            .trim_end_matches('>')
+            .to_string();
+        let rel = components
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .trim_start_matches("rel=\"")
+            .trim_end_matches('"')
+            .to_string();
+        Link { uri, rel }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remaining_page_urls() {
+        let links = Links::parse_from_rfc5988(
+            r#"<https://api.github.com/repos/wayfair/foxdie/pulls?state=open&page=2>; rel="next", <https://api.github.com/repos/wayfair/foxdie/pulls?state=open&page=3>; rel="last""#,
+        );
+        assert_eq!(
+            links.remaining_page_urls(),
+            Some(vec![
+                String::from("https://api.github.com/repos/wayfair/foxdie/pulls?state=open&page=2"),
+                String::from("https://api.github.com/repos/wayfair/foxdie/pulls?state=open&page=3"),
+            ])
+        );
+
+        let links = Links::parse_from_rfc5988(
+            r#"<https://api.github.com/repos/wayfair/foxdie/pulls?state=open&page=2>; rel="next""#,
+        );
+        assert_eq!(links.remaining_page_urls(), None);
+    }
+}
@@ -0,0 +1,1102 @@
+// Copyright (c) 2018-2019, Wayfair LLC
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+//  * Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//    disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//    following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING,
+// BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED.
+// IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY,
+// OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT,
+// STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE,
+// EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! `foxdie_services` contains modules pertaining to integrations. Currently, interfaces to Gitlab, GitHub, Gitea, and
+//! Git are all located here.
+
+pub mod git;
+mod gitea;
+mod github;
+mod gitlab;
+mod links;
+
+use self::git::Remote;
+use self::gitea::Gitea;
+use self::github::GitHub;
+use self::gitlab::Gitlab;
+use crate::error::FoxdieError;
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset};
+use glob::Pattern;
+use log::{debug, error, warn};
+use percent_encoding::{utf8_percent_encode, AsciiSet};
+use reqwest::{Response, Url};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::env;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use what_git::{ClientOptions, SCMKind, SCM};
+
+/// Returns the token to authenticate with an SCM of the given `kind`: `GITHUB_TOKEN` or `GITLAB_TOKEN` when it is set
+/// and the SCM is GitHub or Gitlab respectively, and the shared `token` otherwise. This lets a single run authenticate
+/// with remotes on different providers. The variables are read through `var`, which is `env::var` outside of tests.
+fn provider_token<F>(kind: &SCMKind, token: &str, var: F) -> String
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let name = match kind {
+        SCMKind::GitHub => "GITHUB_TOKEN",
+        SCMKind::Gitlab => "GITLAB_TOKEN",
+        _ => return token.to_string(),
+    };
+    var(name)
+        .filter(|provider_token| !provider_token.is_empty())
+        .unwrap_or_else(|| token.to_string())
+}
+
+/// Reads the environment variable `name`, if it is set to valid Unicode.
+fn env_var(name: &str) -> Option<String> {
+    env::var(name).ok()
+}
+
+/// The characters percent-encoded in a single segment of a URL path, `/` included.
+const PATH_SEGMENT_ENCODE_SET: &AsciiSet = &percent_encoding::CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'<')
+    .add(b'>')
+    .add(b'`')
+    .add(b'#')
+    .add(b'?')
+    .add(b'{')
+    .add(b'}')
+    .add(b'/')
+    .add(b'%');
+
+/// Percent-encodes each `/`-separated segment of `branch` for use in a URL path, keeping the separators, so that a
+/// branch named `fix#12` isn't cut short at the `#`.
+fn encode_branch_path(branch: &str) -> String {
+    branch
+        .split('/')
+        .map(|segment| utf8_percent_encode(segment, PATH_SEGMENT_ENCODE_SET).to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Describes a paginated listing of `url` for its progress indicator, by the last segment of its path.
+fn listing_message(url: &str) -> String {
+    let path = url.split('?').next().unwrap_or(url);
+    format!("Listing {}", path.rsplit('/').next().unwrap_or(path))
+}
+
+/// Return an `SCMProvider` if the given Git remote can be associated with a known and supported Git SCM. Otherwise,
+/// return an error describing why not.
+pub async fn get_api_client_for_remote(
+    remote: &Remote<'_>,
+    token: &str,
+    client_options: &ClientOptions<'_>,
+    allowed_hosts: Option<&[String]>,
+    raw_response_dir: Option<&Path>,
+) -> Result<SCMProvider, FoxdieError> {
+    if let Some(url) = remote.url() {
+        get_api_client_for_url(
+            url,
+            token,
+            client_options,
+            None,
+            None,
+            allowed_hosts,
+            raw_response_dir,
+        )
+        .await
+    } else {
+        Err(FoxdieError::UnsupportedProvider(String::new()))
+    }
+}
+
+/// Return an `SCMProvider` if the given Git remote URL can be associated with a known and supported Git SCM.
+/// Otherwise, return an error describing why not.
+///
+/// If `allowed_hosts` is set, both the host of `url` and the host of the API it resolves to must be in it, so that
+/// the token is never sent anywhere unexpected. If `raw_response_dir` is set, the body of every API response is written
+/// to a file in it before being deserialized. Every request, including those made to detect the provider, is sent by a
+/// client built from `client_options`. For Gitlab, `project_id` addresses the project by its numeric ID in place of
+/// the owner and name in `url`. `page_size`, if set, is the number of items requested in each page of a listing on
+/// GitHub and Gitlab, rather than the most they allow.
+pub async fn get_api_client_for_url(
+    url: &str,
+    token: &str,
+    client_options: &ClientOptions<'_>,
+    project_id: Option<u64>,
+    page_size: Option<usize>,
+    allowed_hosts: Option<&[String]>,
+    raw_response_dir: Option<&Path>,
+) -> Result<SCMProvider, FoxdieError> {
+    verify_host_is_allowed(url, allowed_hosts)?;
+    match what_git::what_git_with_options(url, token, client_options).await {
+        Ok(description) => {
+            verify_host_is_allowed(&description.base_url, allowed_hosts)?;
+            let token = provider_token(&description.kind, token, env_var);
+            SCMProvider::from_scm_description(
+                description,
+                &token,
+                client_options,
+                project_id,
+                page_size,
+                raw_response_dir,
+            )
+            .ok_or_else(|| FoxdieError::UnsupportedProvider(url.to_string()))
+        }
+        Err(err) => {
+            error!("{}", err);
+            Err(FoxdieError::UnsupportedProvider(url.to_string()))
+        }
+    }
+}
+
+/// Returns an `SCMProvider` for each project in the Gitlab group at the path `group` on the Gitlab instance at `url`,
+/// subgroups included, along with the web URL of the project. Archived projects are left out, as their merge requests
+/// can't be closed. `url` names the instance alone, like `https://gitlab.example.com`, and is taken to be Gitlab
+/// without probing it. The other arguments are as for `get_api_client_for_url`.
+pub async fn get_api_clients_for_gitlab_group(
+    url: &str,
+    group: &str,
+    token: &str,
+    client_options: &ClientOptions<'_>,
+    page_size: Option<usize>,
+    allowed_hosts: Option<&[String]>,
+    raw_response_dir: Option<&Path>,
+) -> Result<Vec<(String, SCMProvider)>, FoxdieError> {
+    verify_host_is_allowed(url, allowed_hosts)?;
+    let base_url = Url::parse(url)
+        .ok()
+        .filter(|url| url.has_host())
+        .map(|url| url.origin().ascii_serialization())
+        .ok_or_else(|| FoxdieError::UnsupportedProvider(url.to_string()))?;
+    let token = provider_token(&SCMKind::Gitlab, token, env_var);
+    let client = gitlab::authenticated_client(&token, client_options);
+    let projects = gitlab::list_group_projects(&client, &base_url, group, raw_response_dir).await?;
+    Ok(projects
+        .into_iter()
+        .map(|project| {
+            let (owner, repo) = project
+                .path_with_namespace
+                .rsplit_once('/')
+                .unwrap_or(("", &project.path_with_namespace));
+            let api_client = SCMProvider {
+                inner: Box::new(
+                    Gitlab::with_client(client.clone(), &base_url, owner, repo)
+                        .with_page_size(page_size)
+                        .dumping_raw_responses_to(raw_response_dir),
+                ),
+            };
+            (project.web_url, api_client)
+        })
+        .collect())
+}
+
+/// Reads the project ID from a Gitlab URL of the form `https://gitlab.example.com/projects/<ID>`, which Gitlab
+/// redirects to the project's page.
+fn project_id_from_path(description: &SCM) -> Option<u64> {
+    if description.owner == "projects" {
+        description.repo.parse().ok()
+    } else {
+        None
+    }
+}
+
+fn verify_host_is_allowed(url: &str, allowed_hosts: Option<&[String]>) -> Result<(), FoxdieError> {
+    let allowed_hosts = if let Some(allowed_hosts) = allowed_hosts {
+        allowed_hosts
+    } else {
+        return Ok(());
+    };
+    let host = what_git::hostname(url).unwrap_or_default();
+    if allowed_hosts
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(&host))
+    {
+        Ok(())
+    } else {
+        Err(FoxdieError::DisallowedHost(host, url.to_string()))
+    }
+}
+
+#[async_trait]
+pub(crate) trait SCMProviderImpl {
+    fn name(&self) -> &'static str;
+    async fn list_push_requests(&self, state: PushRequestState) -> ApiResult<Vec<PushRequest>>;
+    async fn is_push_request_open(&self, id: i32) -> ApiResult<bool>;
+    async fn close_push_request(&self, id: i32) -> ApiResult<()>;
+    async fn reopen_push_request(&self, id: i32) -> ApiResult<()>;
+    async fn comment_on_push_request(&self, id: i32, body: &str) -> ApiResult<()>;
+    async fn review_state(&self, id: i32) -> ApiResult<ReviewState>;
+    async fn list_protected_branches(&self) -> ApiResult<Vec<ProtectedBranch>>;
+    async fn list_undeletable_branches(&self) -> ApiResult<Vec<ProtectedBranch>>;
+    async fn delete_branch(&self, branch: &str) -> ApiResult<()>;
+    async fn create_branch(&self, branch: &str, sha: &str) -> ApiResult<()>;
+    async fn branch_exists(&self, branch: &str) -> ApiResult<bool>;
+    async fn default_branch(&self) -> ApiResult<Option<String>>;
+    async fn is_archived(&self) -> ApiResult<bool>;
+    async fn verify_token(&self) -> ApiResult<bool>;
+}
+
+/// Wrapper for an `SCMProviderImpl` implementer. Bridges generic SCM API requests to the appropriate platform type.
+pub struct SCMProvider {
+    inner: Box<dyn SCMProviderImpl>,
+}
+
+impl SCMProvider {
+    /// The human-readable name of the SCM this provider talks to.
+    pub fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    /// Creates a provider for the repository `description` describes. For Gitlab, the project is addressed by
+    /// `project_id` if it is set, or by the ID in URLs of the form `https://gitlab.example.com/projects/<ID>`. On
+    /// GitHub and Gitlab, `page_size` items are requested in each page of a listing, if set.
+    pub(crate) fn from_scm_description(
+        description: SCM,
+        token: &str,
+        client_options: &ClientOptions<'_>,
+        project_id: Option<u64>,
+        page_size: Option<usize>,
+        raw_response_dir: Option<&Path>,
+    ) -> Option<Self> {
+        match description {
+            SCM {
+                kind: SCMKind::GitHub,
+                ..
+            } => Some(SCMProvider {
+                inner: Box::new(
+                    GitHub::new(
+                        &description.base_url,
+                        token,
+                        client_options,
+                        &description.owner,
+                        &description.repo,
+                    )
+                    .with_page_size(page_size)
+                    .dumping_raw_responses_to(raw_response_dir),
+                ),
+            }),
+            SCM {
+                kind: SCMKind::Gitlab,
+                ..
+            } => Some(SCMProvider {
+                inner: Box::new(
+                    Gitlab::new(
+                        &description.base_url,
+                        token,
+                        client_options,
+                        &description.owner,
+                        &description.repo,
+                    )
+                    .with_project_id(project_id.or_else(|| project_id_from_path(&description)))
+                    .with_page_size(page_size)
+                    .dumping_raw_responses_to(raw_response_dir),
+                ),
+            }),
+            SCM {
+                kind: SCMKind::Gitea,
+                ..
+            } => Some(SCMProvider {
+                inner: Box::new(
+                    Gitea::new(
+                        &description.base_url,
+                        token,
+                        client_options,
+                        &description.owner,
+                        &description.repo,
+                    )
+                    .dumping_raw_responses_to(raw_response_dir),
+                ),
+            }),
+            _ => None,
+        }
+    }
+
+    pub async fn list_push_requests(&self, state: PushRequestState) -> ApiResult<Vec<PushRequest>> {
+        self.inner.list_push_requests(state).await
+    }
+
+    /// Looks up whether the push request `id` is still open, rather than closed or merged.
+    pub async fn is_push_request_open(&self, id: i32) -> ApiResult<bool> {
+        self.inner.is_push_request_open(id).await
+    }
+
+    pub async fn close_push_request(&self, id: i32) -> ApiResult<()> {
+        self.inner.close_push_request(id).await
+    }
+
+    /// Closes the push request `id` unless it is no longer open. Safe to retry a close with, as a close that failed,
+    /// such as by timing out, may have gone through anyway.
+    pub async fn ensure_closed(&self, id: i32) -> ApiResult<()> {
+        if self.is_push_request_open(id).await? {
+            self.close_push_request(id).await
+        } else {
+            debug!("#{} is no longer open, so there is nothing to close", id);
+            Ok(())
+        }
+    }
+
+    pub async fn reopen_push_request(&self, id: i32) -> ApiResult<()> {
+        self.inner.reopen_push_request(id).await
+    }
+
+    pub async fn comment_on_push_request(&self, id: i32, body: &str) -> ApiResult<()> {
+        self.inner.comment_on_push_request(id, body).await
+    }
+
+    pub async fn review_state(&self, id: i32) -> ApiResult<ReviewState> {
+        self.inner.review_state(id).await
+    }
+
+    pub async fn list_protected_branches(&self) -> ApiResult<Vec<ProtectedBranch>> {
+        self.inner.list_protected_branches().await
+    }
+
+    /// Lists the branches the SCM marks as ones it won't delete, such as the default branch and those protected by rules
+    /// that `list_protected_branches` doesn't see, like Gitlab's group-level ones. Each is protected by its exact name.
+    /// Empty for SCMs whose branch listings carry no such marks, where the protection rules and default branch cover
+    /// the same ground.
+    pub async fn list_undeletable_branches(&self) -> ApiResult<Vec<ProtectedBranch>> {
+        self.inner.list_undeletable_branches().await
+    }
+
+    pub async fn delete_branch(&self, branch: &str) -> ApiResult<()> {
+        self.inner.delete_branch(branch).await
+    }
+
+    /// Creates the branch `branch`, pointing at the commit `sha`.
+    pub async fn create_branch(&self, branch: &str, sha: &str) -> ApiResult<()> {
+        self.inner.create_branch(branch, sha).await
+    }
+
+    pub async fn branch_exists(&self, branch: &str) -> ApiResult<bool> {
+        self.inner.branch_exists(branch).await
+    }
+
+    /// Looks up the name of the repository's default branch, if the SCM reports one.
+    pub async fn default_branch(&self) -> ApiResult<Option<String>> {
+        self.inner.default_branch().await
+    }
+
+    /// Looks up whether the repository is archived, and so can't be changed.
+    pub async fn is_archived(&self) -> ApiResult<bool> {
+        self.inner.is_archived().await
+    }
+
+    /// Checks whether the SCM accepts the token, by looking up the user it belongs to.
+    pub async fn verify_token(&self) -> ApiResult<bool> {
+        self.inner.verify_token().await
+    }
+}
+
+/// An error from a request to an SCM's API. Carries the ID the SCM assigned to the request, if it sent one, so that
+/// failures can be escalated to the SCM's support with a precise reference.
+#[derive(Debug)]
+pub struct ApiError {
+    pub source: Box<dyn error::Error + Send + Sync>,
+    pub request_id: Option<String>,
+}
+
+pub type ApiResult<T> = Result<T, ApiError>;
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.request_id {
+            Some(ref request_id) => write!(f, "{} (request ID: {})", self.source, request_id),
+            None => write!(f, "{}", self.source),
+        }
+    }
+}
+
+impl error::Error for ApiError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(err: reqwest::Error) -> Self {
+        ApiError {
+            source: Box::new(err),
+            request_id: None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for ApiError {
+    fn from(err: serde_json::Error) -> Self {
+        ApiError {
+            source: Box::new(err),
+            request_id: None,
+        }
+    }
+}
+
+/// Deserializes the JSON body of `response`. If `raw_response_dir` is set, the body is first written to a file in it,
+/// so that a payload that fails to deserialize can be attached to a bug report.
+async fn read_json<T>(response: Response, raw_response_dir: Option<&Path>) -> ApiResult<T>
+where
+    T: DeserializeOwned,
+{
+    let dir = match raw_response_dir {
+        Some(dir) => dir,
+        None => return Ok(response.json().await?),
+    };
+    let path = dir.join(raw_response_file_name(response.url()));
+    let body = response.bytes().await?;
+    if let Err(err) = fs::create_dir_all(dir).and_then(|_| fs::write(&path, &body)) {
+        warn!(
+            "Failed to write raw response to {}: {}",
+            path.display(),
+            err
+        );
+    }
+    Ok(serde_json::from_slice(&body)?)
+}
+
+/// Deserializes each of `values` on its own, logging and skipping any that don't match `T`, so that a single unexpected
+/// record doesn't fail a whole page of results.
+fn deserialize_each<T>(values: Vec<serde_json::Value>) -> Vec<T>
+where
+    T: DeserializeOwned,
+{
+    values
+        .into_iter()
+        .filter_map(|value| match serde_json::from_value(value) {
+            Ok(item) => Some(item),
+            Err(err) => {
+                warn!("Skipping a record that could not be read: {}", err);
+                None
+            }
+        })
+        .collect()
+}
+
+static RAW_RESPONSE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Names the file for a raw response from `url`, numbered so that the files sort in the order they were received.
+fn raw_response_file_name(url: &Url) -> String {
+    let index = RAW_RESPONSE_COUNT.fetch_add(1, Ordering::SeqCst);
+    let request = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+    let slug = request
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    format!("{:04}{}.json", index, slug)
+}
+
+/// Interprets the response to a request for a single resource as whether that resource exists.
+async fn exists_for_status(response: Response, request_id_header: &str) -> ApiResult<bool> {
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        Ok(false)
+    } else {
+        error_for_status(response, request_id_header)
+            .await
+            .map(|_| true)
+    }
+}
+
+/// Interprets the response to a request for the authenticated user as whether the token was accepted.
+async fn token_accepted_for_status(response: Response, request_id_header: &str) -> ApiResult<bool> {
+    match response.status() {
+        reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => Ok(false),
+        _ => error_for_status(response, request_id_header)
+            .await
+            .map(|_| true),
+    }
+}
+
+/// Why a repository or a Gitlab group couldn't be read, for the statuses that point at the token or the target's name
+/// rather than at the SCM itself. It carries the target, so that a run over many repositories says which one.
+#[derive(Debug, PartialEq)]
+pub struct AccessError {
+    pub kind: AccessErrorKind,
+    pub target: AccessTarget,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AccessErrorKind {
+    Unauthorized,
+    Forbidden,
+    NotFound,
+}
+
+/// What couldn't be read: a repository, as `owner/repo`, or a Gitlab group, by its full path.
+#[derive(Debug, PartialEq)]
+pub enum AccessTarget {
+    Repository(String),
+    Group(String),
+}
+
+impl fmt::Display for AccessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.kind, &self.target) {
+            (AccessErrorKind::Unauthorized, AccessTarget::Repository(repository)) => write!(
+                f,
+                "Authentication failed for {}. Check that the token is set and hasn't expired or been revoked.",
+                repository
+            ),
+            (AccessErrorKind::Forbidden, AccessTarget::Repository(repository)) => write!(
+                f,
+                "Access to {} was denied. Check that the token has the scopes needed to read and update it.",
+                repository
+            ),
+            (AccessErrorKind::NotFound, AccessTarget::Repository(repository)) => write!(
+                f,
+                "Repository {} was not found. It may have been renamed, moved or deleted, or the token may not \
+                 have access to it.",
+                repository
+            ),
+            (AccessErrorKind::Unauthorized, AccessTarget::Group(group)) => write!(
+                f,
+                "Authentication failed for group {}. Check that the token is set and hasn't expired or been revoked.",
+                group
+            ),
+            (AccessErrorKind::Forbidden, AccessTarget::Group(group)) => write!(
+                f,
+                "Access to group {} was denied. Check that the token has the read_api scope and can read the group.",
+                group
+            ),
+            (AccessErrorKind::NotFound, AccessTarget::Group(group)) => write!(
+                f,
+                "Group {} was not found. Check its full path, or that the token has the read_api scope and can read \
+                 the group.",
+                group
+            ),
+        }
+    }
+}
+
+impl error::Error for AccessError {}
+
+/// Like `error_for_status`, but turns the statuses for a `target` that can't be read into an `AccessError` naming it.
+/// Used for the first request made to a repository or group, where those statuses are most likely.
+async fn access_error_for_status(
+    response: Response,
+    request_id_header: &str,
+    target: AccessTarget,
+) -> ApiResult<Response> {
+    let kind = match response.status() {
+        reqwest::StatusCode::UNAUTHORIZED => AccessErrorKind::Unauthorized,
+        reqwest::StatusCode::FORBIDDEN => AccessErrorKind::Forbidden,
+        reqwest::StatusCode::NOT_FOUND => AccessErrorKind::NotFound,
+        _ => return error_for_status(response, request_id_header).await,
+    };
+    Err(access_api_error(
+        &response,
+        request_id_header,
+        AccessError { kind, target },
+    ))
+}
+
+/// Wraps `access_error` in an `ApiError`, with the request ID read from the `request_id_header` header of `response`.
+fn access_api_error<E>(response: &Response, request_id_header: &str, access_error: E) -> ApiError
+where
+    E: error::Error + Send + Sync + 'static,
+{
+    let request_id = response
+        .headers()
+        .get(request_id_header)
+        .and_then(|h| h.to_str().ok())
+        .map(String::from);
+    ApiError {
+        source: Box::new(access_error),
+        request_id,
+    }
+}
+
+/// Turns an unsuccessful response into an `ApiError`, reading the request ID from the `request_id_header` header. The
+/// error carries the body of the response, where SCMs explain why the request failed.
+async fn error_for_status(response: Response, request_id_header: &str) -> ApiResult<Response> {
+    if response.error_for_status_ref().is_err() {
+        let request_id = response
+            .headers()
+            .get(request_id_header)
+            .and_then(|h| h.to_str().ok())
+            .map(String::from);
+        let status = response.status().as_u16();
+        let url = response.url().to_string();
+        let body = response.text().await.unwrap_or_default();
+        return Err(ApiError {
+            source: Box::new(ResponseError { status, url, body }),
+            request_id,
+        });
+    }
+    Ok(response)
+}
+
+/// The longest response body, in characters, included in a `ResponseError`'s message.
+const MAX_ERROR_BODY_LENGTH: usize = 1000;
+
+/// An unsuccessful response from an SCM's API, with the body the SCM sent to explain it.
+#[derive(Debug, PartialEq)]
+pub struct ResponseError {
+    pub status: u16,
+    pub url: String,
+    pub body: String,
+}
+
+impl fmt::Display for ResponseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = reqwest::StatusCode::from_u16(self.status)
+            .ok()
+            .and_then(|status| status.canonical_reason())
+            .unwrap_or_default();
+        write!(f, "HTTP status {} {} for {}", self.status, reason, self.url)?;
+        let body = self.body.trim();
+        if body.is_empty() {
+            return Ok(());
+        }
+        match body.char_indices().nth(MAX_ERROR_BODY_LENGTH) {
+            Some((end, _)) => write!(f, ": {}…", &body[..end]),
+            None => write!(f, ": {}", body),
+        }
+    }
+}
+
+impl error::Error for ResponseError {}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PushRequestState {
+    Opened,
+    /// Closed push requests, whether they were merged or not.
+    Closed,
+    /// Push requests in any state.
+    All,
+}
+
+impl PushRequestState {
+    fn github_value(&self) -> &'static str {
+        match self {
+            PushRequestState::Opened => "open",
+            PushRequestState::Closed => "closed",
+            PushRequestState::All => "all",
+        }
+    }
+
+    /// Gitlab counts merged merge requests as neither opened nor closed, so closed ones are listed in two states.
+    fn gitlab_values(&self) -> &'static [&'static str] {
+        match self {
+            PushRequestState::Opened => &["opened"],
+            PushRequestState::Closed => &["closed", "merged"],
+            PushRequestState::All => &["all"],
+        }
+    }
+
+    fn gitea_value(&self) -> &'static str {
+        match self {
+            PushRequestState::Opened => "open",
+            PushRequestState::Closed => "closed",
+            PushRequestState::All => "all",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PushRequest {
+    pub url: String,
+    pub id: i32,
+    pub title: String,
+    pub created_at: DateTime<FixedOffset>,
+    pub updated_at: DateTime<FixedOffset>,
+    pub target_project: i32,
+    pub target_branch: String,
+    pub source_project: i32,
+    pub source_branch: String,
+    /// The commit the source branch pointed to when the push request was listed. Missing when the SCM doesn't say.
+    pub source_sha: Option<String>,
+    pub is_draft: bool,
+    /// Whether the push request is still open.
+    pub is_open: bool,
+    /// Whether the push request was merged, rather than left open or closed without merging.
+    pub is_merged: bool,
+    /// The username of whoever opened the push request. Missing when their account has been deleted.
+    pub author: Option<String>,
+    /// Whether the push request can be merged without conflicts. Missing when the SCM doesn't say when listing push
+    /// requests, or hasn't worked it out yet.
+    pub mergeable: Option<bool>,
+}
+
+/// Where a branch protection rule came from.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ProtectionSource {
+    /// A protected branch rule configured on the SCM provider.
+    Server,
+    /// A rule derived from the local repository, such as a recent checkout.
+    Local,
+    /// A rule Foxdie always applies, such as sparing the current branch.
+    Default,
+}
+
+impl fmt::Display for ProtectionSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProtectionSource::Server => write!(f, "server"),
+            ProtectionSource::Local => write!(f, "local"),
+            ProtectionSource::Default => write!(f, "default"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ProtectedBranch {
+    pub pattern: Pattern,
+    pub source: ProtectionSource,
+}
+
+impl ProtectedBranch {
+    /// Protects exactly the branch `name`, treating any glob characters in it literally.
+    pub fn named(name: &str, source: ProtectionSource) -> Self {
+        let pattern =
+            Pattern::new(&Pattern::escape(name)).expect("An escaped pattern is always valid.");
+        ProtectedBranch { pattern, source }
+    }
+
+    /// Given this branch's pattern string
+    pub fn matches_branch(&self, branch: &str) -> bool {
+        self.pattern.matches(branch)
+    }
+}
+
+/// Returns where the first rule in `protected_branches` that protects `branch` came from, if any rule does.
+pub fn protection_source(
+    branch: &str,
+    protected_branches: &[ProtectedBranch],
+) -> Option<ProtectionSource> {
+    protected_branches
+        .iter()
+        .find(|protected| protected.matches_branch(branch))
+        .map(|protected| protected.source)
+}
+
+/// How far the review of a push request has got.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ReviewState {
+    Approved,
+    ChangesRequested,
+    AwaitingReview,
+}
+
+impl fmt::Display for ReviewState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReviewState::Approved => write!(f, "approved"),
+            ReviewState::ChangesRequested => write!(f, "changes_requested"),
+            ReviewState::AwaitingReview => write!(f, "awaiting_review"),
+        }
+    }
+}
+
+/// Summarizes the reviews of a push request, given in the order they were submitted as pairs of reviewer and verdict.
+/// Only each reviewer's latest verdict counts, and reviews without one, such as plain comments, are passed as `None`
+/// and skipped. A single outstanding request for changes outweighs any number of approvals.
+fn summarize_reviews<I>(reviews: I) -> ReviewState
+where
+    I: IntoIterator<Item = (String, Option<ReviewState>)>,
+{
+    let mut latest = HashMap::new();
+    for (reviewer, verdict) in reviews {
+        if let Some(verdict) = verdict {
+            latest.insert(reviewer, verdict);
+        }
+    }
+    if latest
+        .values()
+        .any(|&verdict| verdict == ReviewState::ChangesRequested)
+    {
+        ReviewState::ChangesRequested
+    } else if latest
+        .values()
+        .any(|&verdict| verdict == ReviewState::Approved)
+    {
+        ReviewState::Approved
+    } else {
+        ReviewState::AwaitingReview
+    }
+}
+
+/// Fixtures for the tests of every module that talks to an SCM.
+#[cfg(test)]
+pub(crate) mod test_support {
+    use super::{PushRequest, SCMProvider};
+    use chrono::DateTime;
+    use what_git::{ClientOptions, SCMKind, SCM};
+
+    /// Builds the open push request `id`, from the branch `branch-<id>` into `master`, last updated on 1 January 2019.
+    /// Tests override the fields they care about with struct update syntax.
+    pub(crate) fn push_request(id: i32) -> PushRequest {
+        let date = DateTime::parse_from_rfc3339("2019-01-01T00:00:00-05:00").unwrap();
+        PushRequest {
+            url: format!("https://github.com/wayfair/foxdie/pull/{}", id),
+            id,
+            title: format!("PR {}", id),
+            created_at: date,
+            updated_at: date,
+            target_project: 1,
+            target_branch: String::from("master"),
+            source_project: 1,
+            source_branch: format!("branch-{}", id),
+            source_sha: None,
+            is_draft: false,
+            is_open: true,
+            is_merged: false,
+            author: None,
+            mergeable: None,
+        }
+    }
+
+    /// Builds the pull request `number` as GitHub lists it, in `state`, from the branch `branch-<number>` into `master`,
+    /// opened on 1 January 2019 and last updated on `updated_at`.
+    pub(crate) fn github_pull_json(
+        number: i32,
+        state: &str,
+        updated_at: &str,
+    ) -> serde_json::Value {
+        let git_data = |git_ref: &str| serde_json::json!({ "ref": git_ref, "repo": { "id": 1 } });
+        serde_json::json!({
+            "html_url": format!("https://github.com/wayfair/foxdie/pull/{}", number),
+            "number": number,
+            "title": format!("PR {}", number),
+            "created_at": "2019-01-01T00:00:00Z",
+            "updated_at": updated_at,
+            "head": git_data(&format!("branch-{}", number)),
+            "base": git_data("master"),
+            "state": state,
+        })
+    }
+
+    /// Builds a client for the repository `wayfair/<repo>` on an SCM of `kind`, whose API is served by the mock server
+    /// under the path `prefix`. A prefix keeps apart the mocks of tests that share a repository name.
+    pub(crate) fn mock_provider(kind: SCMKind, prefix: &str, repo: &str) -> SCMProvider {
+        SCMProvider::from_scm_description(
+            SCM {
+                kind,
+                base_url: format!("{}{}", mockito::server_url(), prefix),
+                owner: String::from("wayfair"),
+                repo: String::from(repo),
+            },
+            "token",
+            &ClientOptions::default(),
+            None,
+            None,
+            None,
+        )
+        .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::mock;
+    use std::process;
+
+    #[test]
+    fn test_push_request_state_values() {
+        let values = |state: PushRequestState| {
+            (
+                state.github_value(),
+                state.gitlab_values(),
+                state.gitea_value(),
+            )
+        };
+        assert_eq!(
+            values(PushRequestState::Opened),
+            ("open", &["opened"][..], "open")
+        );
+        assert_eq!(
+            values(PushRequestState::Closed),
+            ("closed", &["closed", "merged"][..], "closed")
+        );
+        assert_eq!(values(PushRequestState::All), ("all", &["all"][..], "all"));
+    }
+
+    #[tokio::test]
+    async fn test_read_json_dumps_raw_response() {
+        let dir = env::temp_dir().join(format!("foxdie-test-raw-responses-{}", process::id()));
+        let _mock = mock("GET", "/raw-response")
+            .with_body(r#"{"unexpected": true}"#)
+            .create();
+        let response = reqwest::get(&*format!("{}/raw-response", mockito::server_url()))
+            .await
+            .unwrap();
+
+        let result: ApiResult<Vec<i32>> = read_json(response, Some(&dir)).await;
+
+        let dumped = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect::<Vec<_>>();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(result.is_err());
+        assert_eq!(dumped, vec![String::from(r#"{"unexpected": true}"#)]);
+    }
+
+    #[test]
+    fn test_deserialize_each_skips_bad_records() {
+        let values = vec![
+            serde_json::json!(1),
+            serde_json::json!("two"),
+            serde_json::json!(3),
+        ];
+        assert_eq!(deserialize_each::<i32>(values), vec![1, 3]);
+    }
+
+    #[test]
+    fn test_summarize_reviews() {
+        let review = |reviewer: &str, verdict| (reviewer.to_string(), verdict);
+        assert_eq!(summarize_reviews(vec![]), ReviewState::AwaitingReview);
+        assert_eq!(
+            summarize_reviews(vec![
+                review("a", Some(ReviewState::ChangesRequested)),
+                review("b", Some(ReviewState::Approved)),
+                review("a", None),
+            ]),
+            ReviewState::ChangesRequested
+        );
+        assert_eq!(
+            summarize_reviews(vec![
+                review("a", Some(ReviewState::ChangesRequested)),
+                review("b", Some(ReviewState::Approved)),
+                review("a", Some(ReviewState::Approved)),
+            ]),
+            ReviewState::Approved
+        );
+        // A dismissed review leaves its reviewer awaiting review again.
+        assert_eq!(
+            summarize_reviews(vec![
+                review("a", Some(ReviewState::Approved)),
+                review("a", Some(ReviewState::AwaitingReview)),
+            ]),
+            ReviewState::AwaitingReview
+        );
+    }
+
+    #[test]
+    fn test_protection_source() {
+        let protected_branches = vec![
+            ProtectedBranch::named("release/[1]", ProtectionSource::Default),
+            ProtectedBranch {
+                pattern: Pattern::new("release/*").unwrap(),
+                source: ProtectionSource::Server,
+            },
+            ProtectedBranch::named("my-feature", ProtectionSource::Local),
+        ];
+        assert_eq!(
+            protection_source("release/[1]", &protected_branches),
+            Some(ProtectionSource::Default)
+        );
+        assert_eq!(
+            protection_source("release/1", &protected_branches),
+            Some(ProtectionSource::Server)
+        );
+        assert_eq!(
+            protection_source("my-feature", &protected_branches),
+            Some(ProtectionSource::Local)
+        );
+        assert_eq!(protection_source("other", &protected_branches), None);
+    }
+
+    #[test]
+    fn test_provider_token() {
+        let provider_tokens = |name: &str| match name {
+            "GITHUB_TOKEN" => Some(String::from("github-token")),
+            "GITLAB_TOKEN" => Some(String::from("gitlab-token")),
+            _ => None,
+        };
+        assert_eq!(
+            provider_token(&SCMKind::GitHub, "shared", provider_tokens),
+            "github-token"
+        );
+        assert_eq!(
+            provider_token(&SCMKind::Gitlab, "shared", provider_tokens),
+            "gitlab-token"
+        );
+        assert_eq!(
+            provider_token(&SCMKind::Gitea, "shared", provider_tokens),
+            "shared"
+        );
+
+        let empty = |_: &str| Some(String::new());
+        assert_eq!(provider_token(&SCMKind::Gitlab, "shared", empty), "shared");
+        let unset = |_: &str| None;
+        assert_eq!(provider_token(&SCMKind::GitHub, "shared", unset), "shared");
+    }
+
+    #[test]
+    fn test_project_id_from_path() {
+        let description = |owner: &str, repo: &str| SCM {
+            kind: SCMKind::Gitlab,
+            base_url: String::from("https://gitlab.example.com"),
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+        };
+        assert_eq!(
+            project_id_from_path(&description("projects", "4242")),
+            Some(4242)
+        );
+        assert_eq!(
+            project_id_from_path(&description("projects", "foxdie")),
+            None
+        );
+        assert_eq!(project_id_from_path(&description("wayfair", "4242")), None);
+    }
+
+    #[test]
+    fn test_verify_host_is_allowed() {
+        let allowed_hosts = vec![
+            String::from("github.com"),
+            String::from("gitlab.example.com"),
+        ];
+        assert!(verify_host_is_allowed("https://github.com/wayfair/foxdie", None).is_ok());
+        assert!(
+            verify_host_is_allowed("https://github.com/wayfair/foxdie", Some(&allowed_hosts))
+                .is_ok()
+        );
+        assert!(verify_host_is_allowed(
+            "git@GITLAB.example.com:wayfair/foxdie.git",
+            Some(&allowed_hosts)
+        )
+        .is_ok());
+        match verify_host_is_allowed("https://githib.com/wayfair/foxdie", Some(&allowed_hosts)) {
+            Err(FoxdieError::DisallowedHost(host, _)) => assert_eq!(host, "githib.com"),
+            other => panic!("Expected a disallowed host error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_response_error_display() {
+        let error = |body: &str| ResponseError {
+            status: 404,
+            url: String::from("https://api.github.com/repos/wayfair/foxdie"),
+            body: body.to_string(),
+        };
+        assert_eq!(
+            error("").to_string(),
+            "HTTP status 404 Not Found for https://api.github.com/repos/wayfair/foxdie"
+        );
+        assert_eq!(
+            error(" {\"message\": \"Not Found\"}\n").to_string(),
+            "HTTP status 404 Not Found for https://api.github.com/repos/wayfair/foxdie: {\"message\": \"Not Found\"}"
+        );
+        let long = error(&"é".repeat(MAX_ERROR_BODY_LENGTH + 1)).to_string();
+        assert!(long.ends_with(&format!(": {}…", "é".repeat(MAX_ERROR_BODY_LENGTH))));
+    }
+}
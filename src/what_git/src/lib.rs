@@ -18,7 +18,7 @@
 // EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 //! `what_git` provides an easy mechanism for associating a given Git repository URL with its source. It supports
-//! either GitHub, GitHub Enterprise, Gitlab, or Gitlab Enterprise repositories. Use this crate to structure
+//! either GitHub, GitHub Enterprise, Gitlab, Gitlab Enterprise, or self-hosted Gitea repositories. Use this crate to structure
 //! SCM-agnostic code with minimal branching.
 //!
 //! # About
@@ -27,15 +27,119 @@
 //! and a personal access token to the API service your repository is associated with. Provide each of those to the
 //! [`what_git::what_git`] function, and that's it.
 //!
+//! Repositories on GitHub Enterprise or self-hosted Gitlab are detected by probing their hosts. To skip the probes, set
+//! `GITHUB_BASE_URL` or `GITLAB_BASE_URL` to the base URL of the API. For GitHub Enterprise Server, that includes the
+//! `/api/v3` suffix, as in `https://github.example.com/api/v3`.
+//!
+//! Probes identify themselves with [`what_git::DEFAULT_USER_AGENT`], and go through the proxies set in the `HTTP_PROXY`
+//! and `HTTPS_PROXY` environment variables, except for hosts listed in `NO_PROXY`. Hosts whose egress policies require a
+//! particular agent or proxy can be probed with [`what_git::what_git_with_options`] instead, and
+//! [`what_git::client_builder`] builds clients configured the same way for any requests made after detection.
+//!
+//! When many repositories on the same host are detected in one run, set [`what_git::ClientOptions::provider_cache`] to
+//! a shared [`what_git::ProviderCache`], so that the host is only probed for the first of them.
 //!
 //! [`what_git::what_git`]: ./fn.what_git.html
+//! [`what_git::DEFAULT_USER_AGENT`]: ./constant.DEFAULT_USER_AGENT.html
+//! [`what_git::what_git_with_options`]: ./fn.what_git_with_options.html
+//! [`what_git::client_builder`]: ./fn.client_builder.html
+//! [`what_git::ClientOptions::provider_cache`]: ./struct.ClientOptions.html#structfield.provider_cache
+//! [`what_git::ProviderCache`]: ./struct.ProviderCache.html
 
-use reqwest::header;
-use reqwest::{Client, Url};
+use reqwest::header::{self, HeaderMap};
+use reqwest::{Certificate, Client, ClientBuilder, Proxy, RequestBuilder, Url};
+use std::collections::HashMap;
 use std::env;
 use std::error;
 use std::fmt;
 use std::result;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// The number of times a probe is attempted before a connection failure is taken as final.
+const PROBE_ATTEMPTS: u32 = 3;
+
+/// The delay before the first retry of a probe. Each later retry waits twice as long as the one before it.
+const PROBE_BACKOFF: Duration = Duration::from_millis(250);
+
+/// The `User-Agent` probes are sent with by [`what_git::what_git`].
+///
+/// [`what_git::what_git`]: ./fn.what_git.html
+pub const DEFAULT_USER_AGENT: &str = concat!("what_git/", env!("CARGO_PKG_VERSION"));
+
+/// How the HTTP clients built by [`what_git::client_builder`], including those used for probes, connect to a host.
+///
+/// [`what_git::client_builder`]: ./fn.client_builder.html
+#[derive(Clone, Debug)]
+pub struct ClientOptions<'a> {
+    /// The `User-Agent` sent with every request.
+    pub user_agent: &'a str,
+    /// Headers sent with every request, such as `X-GitHub-Api-Version` for hosts that pin an API version. Clients built
+    /// for an SCM's API add their own authorization headers on top of these.
+    pub default_headers: HeaderMap,
+    /// The proxy every request is sent through. When unset, the proxies in the `HTTP_PROXY`, `HTTPS_PROXY` and
+    /// `NO_PROXY` environment variables are used.
+    pub proxy: Option<Proxy>,
+    /// A root certificate trusted in addition to the system's, for hosts whose certificates are signed by a private CA.
+    pub root_certificate: Option<Certificate>,
+    /// Whether to accept any certificate, including expired and self-signed ones. This leaves every request open to
+    /// interception, so prefer `root_certificate` wherever possible.
+    pub accept_invalid_certs: bool,
+    /// How long each request may take, from connecting until the whole response has been read. When unset, requests
+    /// never time out.
+    pub timeout: Option<Duration>,
+    /// Where the SCMs detected by probing hosts are remembered, so that each host is only probed once. When unset,
+    /// every repository on a host that isn't known without probing is probed anew.
+    pub provider_cache: Option<&'a ProviderCache>,
+}
+
+impl Default for ClientOptions<'_> {
+    fn default() -> Self {
+        ClientOptions {
+            user_agent: DEFAULT_USER_AGENT,
+            default_headers: HeaderMap::new(),
+            proxy: None,
+            root_certificate: None,
+            accept_invalid_certs: false,
+            timeout: None,
+            provider_cache: None,
+        }
+    }
+}
+
+/// Remembers the kind and API base URL of the SCM detected on each host probed by
+/// [`what_git::what_git_with_options`], for as long as it lives. Share one across a run through
+/// [`what_git::ClientOptions::provider_cache`].
+///
+/// [`what_git::what_git_with_options`]: ./fn.what_git_with_options.html
+/// [`what_git::ClientOptions::provider_cache`]: ./struct.ClientOptions.html#structfield.provider_cache
+#[derive(Debug, Default)]
+pub struct ProviderCache {
+    hosts: Mutex<HashMap<String, (String, SCMKind)>>,
+}
+
+impl ProviderCache {
+    /// Records that the SCM on `hostname` is of `kind`, with its API at `base_url`, so that the host is never probed.
+    /// Useful for hosts whose SCM is known ahead of time, such as mock servers in tests.
+    pub fn remember(&self, hostname: &str, base_url: &str, kind: SCMKind) {
+        self.insert(&hostname.to_lowercase(), (base_url.to_string(), kind));
+    }
+
+    fn get(&self, hostname: &str) -> Option<(String, SCMKind)> {
+        self.hosts
+            .lock()
+            .expect("The provider cache lock was poisoned.")
+            .get(hostname)
+            .cloned()
+    }
+
+    fn insert(&self, hostname: &str, detected: (String, SCMKind)) {
+        self.hosts
+            .lock()
+            .expect("The provider cache lock was poisoned.")
+            .insert(hostname.to_string(), detected);
+    }
+}
 
 /// Determines what source control management (SCM) solution a repository URL belongs to. Returns a
 /// [`what_git::Result`] type describing the structure of the URL and the associated [`what_git::SCMKind`], or some
@@ -59,9 +163,96 @@ use std::result;
 /// [`what_git::SCMKind`]: ./enum.SCMKind.html
 /// [`what_git::Error`]: ./enum.Error.html
 pub async fn what_git(repository: &str, token: &str) -> Result {
+    what_git_with_options(repository, token, &ClientOptions::default()).await
+}
+
+/// Like [`what_git::what_git`], but sends `user_agent` as the `User-Agent` of any probes made to the repository's host.
+///
+/// [`what_git::what_git`]: ./fn.what_git.html
+pub async fn what_git_with_user_agent(repository: &str, token: &str, user_agent: &str) -> Result {
+    let options = ClientOptions {
+        user_agent,
+        ..ClientOptions::default()
+    };
+    what_git_with_options(repository, token, &options).await
+}
+
+/// Like [`what_git::what_git`], but makes any probes to the repository's host with a client configured by `options`.
+///
+/// [`what_git::what_git`]: ./fn.what_git.html
+pub async fn what_git_with_options(
+    repository: &str,
+    token: &str,
+    options: &ClientOptions<'_>,
+) -> Result {
     let url_str = scrub_git_url_if_needed(repository);
     let url = Url::parse(&url_str).map_err(|_| Error::UnknownProvider(url_str.to_string()))?;
-    metadata_for_url(&url, token).await
+    let client = client_builder(options)
+        .build()
+        .map_err(Error::InvalidClient)?;
+    metadata_for_url(&url, token, &client, options.provider_cache).await
+}
+
+/// Returns a builder for HTTP clients configured by `options`, as the probes made by
+/// [`what_git::what_git_with_options`] are.
+///
+/// [`what_git::what_git_with_options`]: ./fn.what_git_with_options.html
+pub fn client_builder(options: &ClientOptions<'_>) -> ClientBuilder {
+    let mut builder = Client::builder()
+        .user_agent(options.user_agent)
+        .default_headers(options.default_headers.clone())
+        .danger_accept_invalid_certs(options.accept_invalid_certs);
+    if let Some(ref proxy) = options.proxy {
+        builder = builder.proxy(proxy.clone());
+    }
+    if let Some(ref certificate) = options.root_certificate {
+        builder = builder.add_root_certificate(certificate.clone());
+    }
+    if let Some(timeout) = options.timeout {
+        builder = builder.timeout(timeout);
+    }
+    builder
+}
+
+/// Determines what kind of SCM a repository URL belongs to from its host alone, without making any requests or needing
+/// a token. Recognizes github.com, gitlab.com and bitbucket.org, the last of which is
+/// [`what_git::SCMKind::Unsupported`], along with any host once `GITHUB_BASE_URL` or `GITLAB_BASE_URL` is set. Returns
+/// `None` for other hosts, which [`what_git::what_git`] would have to probe.
+///
+/// # Examples
+///
+/// ```
+/// use what_git::{kind_for_url, SCMKind};
+///
+/// assert_eq!(
+///     kind_for_url("git@github.com:rust-lang/rust.git"),
+///     Some(SCMKind::GitHub)
+/// );
+/// ```
+/// [`what_git::SCMKind::Unsupported`]: ./enum.SCMKind.html#variant.Unsupported
+/// [`what_git::what_git`]: ./fn.what_git.html
+pub fn kind_for_url(url: &str) -> Option<SCMKind> {
+    hostname(url)
+        .and_then(|hostname| known_host(&hostname))
+        .map(|(_, kind)| kind)
+}
+
+/// Returns the hostname of a repository URL, accepting the same URL forms as [`what_git::what_git`]. Use this to vet a
+/// repository's host before any request is made to it.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(
+///     what_git::hostname("git@github.com:rust-lang/rust.git"),
+///     Some("github.com".to_string())
+/// );
+/// ```
+/// [`what_git::what_git`]: ./fn.what_git.html
+pub fn hostname(repository: &str) -> Option<String> {
+    Url::parse(&scrub_git_url_if_needed(repository))
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_lowercase))
 }
 
 /// Remove various non-standard decorations, such as SSH decorations, from a URL string to get a string conforming to
@@ -76,69 +267,48 @@ fn scrub_git_url_if_needed(repository: &str) -> String {
     }
 }
 
+/// Guesses at the repository owner and name from the first two non-empty path components of `url`, without any `.git`
+/// suffix on the name. Empty components, such as those left by a trailing slash, are skipped.
+fn owner_and_repo(url: &Url) -> Option<(&str, &str)> {
+    let mut path_components = url.path_segments()?.filter(|segment| !segment.is_empty());
+    let owner = path_components.next()?;
+    let repo = path_components.next()?;
+    let repo = repo.strip_suffix(".git").unwrap_or(repo);
+    if repo.is_empty() {
+        None
+    } else {
+        Some((owner, repo))
+    }
+}
+
 /// Determines what source control management (SCM) solution a repository URL belongs to. Returns a [`what_git::Result`]
 /// type describing the structure of the URL and the associated [`what_git::SCMKind`], or some error of type
 /// [`what_git::Error`].
-async fn metadata_for_url(url: &Url, token: &str) -> Result {
-    // Extract the first two path components in the URL to guess at the repository owner and name.
-    let path_components = url
-        .path_segments()
-        .expect(
-            "URL path components could not be represented.
-    This is likely because it is not a valid URL for this tool.",
-        )
-        .take(2)
-        .collect::<Vec<&str>>();
-    let (owner, mut repo) = if let [own, rep] = path_components[..] {
-        (own, rep)
-    } else {
-        return Err(Error::UnknownProvider(url.to_string()));
-    };
-    if let Some(idx) = repo.rfind(".git") {
-        repo = &repo[..idx];
-    }
+async fn metadata_for_url(
+    url: &Url,
+    token: &str,
+    client: &Client,
+    cache: Option<&ProviderCache>,
+) -> Result {
+    let (owner, repo) =
+        owner_and_repo(url).ok_or_else(|| Error::UnknownProvider(url.to_string()))?;
 
-    // Extract the hostname
+    // Extract the hostname. Hosts of `git://` URLs keep their case, so it is normalized here.
     let hostname = url
         .domain()
+        .map(str::to_lowercase)
         .ok_or_else(|| Error::UnknownProvider(url.to_string()))?;
 
-    let base_url: String;
-    let kind: SCMKind;
-
-    if hostname == "github.com" || hostname == "www.github.com" {
-        // 1. If the repository is located on GitHub.com, proceed
-        base_url = "https://api.github.com".to_string();
-        kind = SCMKind::GitHub;
-    } else if hostname == "gitlab.com" || hostname == "www.gitlab.com" {
-        // 2. If the repository is located on Gitlab.com, proceed
-        base_url = "https://gitlab.com".to_string();
-        kind = SCMKind::Gitlab;
-    } else if let Ok(base) = env::var("GITHUB_BASE_URL") {
-        // 3. If the user has manually specified an API base URL for a GitHub repository, proceed
-        base_url = base;
-        kind = SCMKind::GitHub;
-    } else if let Ok(base) = env::var("GITLAB_BASE_URL") {
-        // 4. If the user has manually specified an API base URL for a Gitlab repository, proceed
-        base_url = base;
-        kind = SCMKind::GitHub;
-    } else {
-        // 5. Attempt to connect to an SCM's API using known unique endpoints, and match on the possible successes.
-        let base_url_candidate = format!("https://{}", hostname);
-        let github_result = verify_github(&base_url_candidate, token).await;
-        let gitlab_result = verify_gitlab(&base_url_candidate, token).await;
-        match (github_result, gitlab_result) {
-            (Ok(true), _) => {
-                base_url = format!("{}/api/v3", base_url_candidate);
-                kind = SCMKind::GitHub;
-            }
-            (_, Ok(true)) => {
-                base_url = base_url_candidate;
-                kind = SCMKind::Gitlab;
-            }
-            _ => return Err(Error::UnknownProvider(url.to_string())),
-        };
-    }
+    let (base_url, kind) = match known_host(&hostname) {
+        Some((_, SCMKind::Unsupported)) => return Err(Error::UnknownProvider(url.to_string())),
+        Some(known) => known,
+        None => {
+            let base_url_candidate = format!("https://{}", hostname);
+            detect_host(client, &hostname, &base_url_candidate, token, cache)
+                .await
+                .ok_or_else(|| Error::UnknownProvider(url.to_string()))?
+        }
+    };
     Ok(SCM {
         base_url,
         kind,
@@ -147,30 +317,133 @@ async fn metadata_for_url(url: &Url, token: &str) -> Result {
     })
 }
 
-// Attempt to connect to the GitHub `/zen` endpoint, which is unique to GitHub's API.
-async fn verify_github(base_url: &str, token: &str) -> result::Result<bool, reqwest::Error> {
-    let url = format!("{}/api/v3/zen", base_url);
+// Attempt to connect to an SCM's API at `base_url` using known unique endpoints, in order of preference, stopping at
+// the first that answers so that no other SCM's API is sent the token. What is found is remembered in `cache` under
+// `hostname`, and a host already found there isn't probed again.
+async fn detect_host(
+    client: &Client,
+    hostname: &str,
+    base_url: &str,
+    token: &str,
+    cache: Option<&ProviderCache>,
+) -> Option<(String, SCMKind)> {
+    if let Some(detected) = cache.and_then(|cache| cache.get(hostname)) {
+        return Some(detected);
+    }
+    let detected = if let Ok(Some(api_url)) = verify_github(client, base_url, token).await {
+        (api_url, SCMKind::GitHub)
+    } else if let Ok(true) = verify_gitlab(client, base_url, token).await {
+        (base_url.to_string(), SCMKind::Gitlab)
+    } else if let Ok(true) = verify_gitea(client, base_url, token).await {
+        (format!("{}/api/v1", base_url), SCMKind::Gitea)
+    } else {
+        return None;
+    };
+    if let Some(cache) = cache {
+        cache.insert(hostname, detected.clone());
+    }
+    Some(detected)
+}
 
-    Client::new()
-        .get(&*url)
-        .header(header::ACCEPT, "application/vnd.github.v3+json")
-        .header(header::AUTHORIZATION, format!("Bearer {}", token))
-        .header(header::USER_AGENT, "com.wayfair.what_gitjson")
-        .send()
-        .await
-        .map(|res| res.status().is_success())
+// Classifies a lowercase hostname without making any requests, returning the base URL of its API and its kind. Hosts
+// that can only be told apart by probing them are `None`.
+fn known_host(hostname: &str) -> Option<(String, SCMKind)> {
+    match hostname {
+        // 1. If the repository is located on GitHub.com, proceed
+        "github.com" | "www.github.com" => {
+            Some(("https://api.github.com".to_string(), SCMKind::GitHub))
+        }
+        // 2. If the repository is located on Gitlab.com, proceed
+        "gitlab.com" | "www.gitlab.com" => {
+            Some(("https://gitlab.com".to_string(), SCMKind::Gitlab))
+        }
+        // 3. Bitbucket is recognized, but not supported
+        "bitbucket.org" | "www.bitbucket.org" => Some((
+            "https://api.bitbucket.org".to_string(),
+            SCMKind::Unsupported,
+        )),
+        // 4. If the user has manually specified an API base URL for a GitHub or Gitlab repository, proceed
+        _ => env::var("GITHUB_BASE_URL")
+            .map(|base| (base, SCMKind::GitHub))
+            .or_else(|_| env::var("GITLAB_BASE_URL").map(|base| (base, SCMKind::Gitlab)))
+            .ok(),
+    }
+}
+
+// Attempt to connect to the GitHub `/zen` endpoint, which is unique to GitHub's API. GitHub Enterprise Server serves
+// its API under `/api/v3`, while some hosts serve it from the root, so both are tried. Returns the base URL of the API
+// that answered, if one did.
+async fn verify_github(
+    client: &Client,
+    base_url: &str,
+    token: &str,
+) -> result::Result<Option<String>, reqwest::Error> {
+    let mut last_error = None;
+    for api_url in &[format!("{}/api/v3", base_url), base_url.to_string()] {
+        let url = format!("{}/zen", api_url);
+        let result = probe(
+            client
+                .get(&*url)
+                .header(header::ACCEPT, "application/vnd.github.v3+json")
+                .header(header::AUTHORIZATION, format!("Bearer {}", token)),
+        )
+        .await;
+        match result {
+            Ok(true) => return Ok(Some(api_url.clone())),
+            Ok(false) => {}
+            Err(err) => last_error = Some(err),
+        }
+    }
+    match last_error {
+        Some(err) => Err(err),
+        None => Ok(None),
+    }
 }
 
 // Attempt to connect to the Gitlab `/version` endpoint, which is unique to Gitlab's API.
-async fn verify_gitlab(base_url: &str, token: &str) -> result::Result<bool, reqwest::Error> {
+async fn verify_gitlab(
+    client: &Client,
+    base_url: &str,
+    token: &str,
+) -> result::Result<bool, reqwest::Error> {
     let url = format!("{}/api/v4/version", base_url);
 
-    Client::new()
-        .get(&*url)
-        .header("private-token", token)
-        .send()
-        .await
-        .map(|res| res.status().is_success())
+    probe(client.get(&*url).header("private-token", token)).await
+}
+
+// Attempt to connect to the Gitea `/version` endpoint, which Gitea serves under `/api/v1` rather than Gitlab's `/api/v4`.
+async fn verify_gitea(
+    client: &Client,
+    base_url: &str,
+    token: &str,
+) -> result::Result<bool, reqwest::Error> {
+    let url = format!("{}/api/v1/version", base_url);
+
+    probe(
+        client
+            .get(&*url)
+            .header(header::AUTHORIZATION, format!("token {}", token)),
+    )
+    .await
+}
+
+// Send `request` and report whether it succeeded. Failures to connect, such as transient DNS errors, are retried with
+// a backoff, but any HTTP response is taken as authoritative.
+async fn probe(request: RequestBuilder) -> result::Result<bool, reqwest::Error> {
+    let mut delay = PROBE_BACKOFF;
+    for _ in 1..PROBE_ATTEMPTS {
+        let attempt = request
+            .try_clone()
+            .expect("Probe requests have no body, so they can always be cloned.");
+        match attempt.send().await {
+            Err(err) if err.is_connect() || err.is_timeout() => {
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            result => return result.map(|res| res.status().is_success()),
+        }
+    }
+    request.send().await.map(|res| res.status().is_success())
 }
 
 /// Used to describe the structure of a repository on a supported source control management (SCM) solution.
@@ -185,12 +458,13 @@ pub struct SCM {
     pub repo: String,
 }
 
-/// Supported SCMs. Currently, `what_git` only supports GitHub and Gitlab.
-#[derive(Debug, PartialEq)]
+/// Supported SCMs. Currently, `what_git` only supports GitHub, Gitlab, and Gitea.
+#[derive(Debug, Clone, PartialEq)]
 pub enum SCMKind {
     Unsupported,
     GitHub,
     Gitlab,
+    Gitea,
 }
 
 pub type Result = result::Result<SCM, Error>;
@@ -198,12 +472,14 @@ pub type Result = result::Result<SCM, Error>;
 #[derive(Debug)]
 pub enum Error {
     UnknownProvider(String),
+    InvalidClient(reqwest::Error),
 }
 
 impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match *self {
             Error::UnknownProvider(_) => None,
+            Error::InvalidClient(ref err) => Some(err),
         }
     }
 }
@@ -211,12 +487,358 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Error::UnknownProvider(ref url) => write!(f, "Unknown provider for url {}", url),
+            Error::InvalidClient(ref err) => {
+                write!(f, "Could not build a client for probes: {}", err)
+            }
         }
     }
 }
 
 mod tests {
 
+    #[test]
+    fn test_probe_retries_connection_failures() {
+        // Bind to a free port and release it, so that connections to it are refused.
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let started = std::time::Instant::now();
+
+        let result = runtime.block_on(super::verify_gitlab(
+            &client(),
+            &format!("http://127.0.0.1:{}", port),
+            "token",
+        ));
+
+        assert!(result.is_err());
+        assert!(started.elapsed() >= super::PROBE_BACKOFF * 3);
+    }
+
+    #[test]
+    fn test_probe_does_not_retry_http_errors() {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).unwrap();
+            stream
+                .write_all(
+                    b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .unwrap();
+            // Any retry would connect while the server waits out the probe's backoff.
+            listener.set_nonblocking(true).unwrap();
+            std::thread::sleep(super::PROBE_BACKOFF * 2);
+            listener.accept().is_ok()
+        });
+
+        let result = runtime.block_on(super::verify_gitlab(
+            &client(),
+            &format!("http://127.0.0.1:{}", port),
+            "token",
+        ));
+
+        assert!(!result.unwrap());
+        assert!(!server.join().unwrap(), "The probe was retried");
+    }
+
+    /// Builds a client for probes, with the default `User-Agent`.
+    #[cfg(test)]
+    fn client() -> reqwest::Client {
+        super::client_builder(&super::ClientOptions::default())
+            .build()
+            .unwrap()
+    }
+
+    /// Serves HTTP requests on a new local port until the test ends, answering `200 OK` to requests for any of `paths`
+    /// and `404 Not Found` to anything else. Returns the server's base URL.
+    #[cfg(test)]
+    fn serve_paths(paths: &'static [&'static str]) -> String {
+        serve_paths_recording(paths).0
+    }
+
+    /// Like `serve_paths`, but also returns the path of every request served, in the order they were received.
+    #[cfg(test)]
+    fn serve_paths_recording(
+        paths: &'static [&'static str],
+    ) -> (String, std::sync::Arc<std::sync::Mutex<Vec<String>>>) {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let requested = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let recorded = std::sync::Arc::clone(&requested);
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut request = [0; 1024];
+                let len = stream.read(&mut request).unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..len]);
+                let path = request.split(' ').nth(1).unwrap_or_default();
+                recorded.lock().unwrap().push(path.to_string());
+                let status = if paths.contains(&path) {
+                    "200 OK"
+                } else {
+                    "404 Not Found"
+                };
+                let _ = stream.write_all(
+                    format!(
+                        "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                        status
+                    )
+                    .as_bytes(),
+                );
+            }
+        });
+        (format!("http://127.0.0.1:{}", port), requested)
+    }
+
+    #[test]
+    fn test_verify_github_on_enterprise_server() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let base_url = serve_paths(&["/api/v3/zen"]);
+
+        let result = runtime.block_on(super::verify_github(&client(), &base_url, "token"));
+
+        assert_eq!(result.unwrap(), Some(format!("{}/api/v3", base_url)));
+    }
+
+    #[test]
+    fn test_verify_github_at_api_root() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let base_url = serve_paths(&["/zen"]);
+
+        let result = runtime.block_on(super::verify_github(&client(), &base_url, "token"));
+
+        assert_eq!(result.unwrap(), Some(base_url));
+    }
+
+    #[test]
+    fn test_verify_github_on_other_hosts() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let base_url = serve_paths(&["/api/v4/version"]);
+
+        let result = runtime.block_on(super::verify_github(&client(), &base_url, "token"));
+
+        assert_eq!(result.unwrap(), None);
+    }
+
+    #[test]
+    fn test_verify_gitea() {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let len = stream.read(&mut request).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 20\r\nConnection: close\r\n\r\n{\"version\":\"1.15.0\"}")
+                .unwrap();
+            String::from_utf8_lossy(&request[..len]).to_lowercase()
+        });
+
+        let options = super::ClientOptions {
+            user_agent: "foxdie-test/1.0",
+            ..super::ClientOptions::default()
+        };
+        let client = super::client_builder(&options).build().unwrap();
+
+        let result = runtime.block_on(super::verify_gitea(
+            &client,
+            &format!("http://127.0.0.1:{}", port),
+            "token",
+        ));
+
+        assert!(result.unwrap());
+        let request = server.join().unwrap();
+        assert!(request.starts_with("get /api/v1/version "));
+        assert!(request.contains("authorization: token token\r\n"));
+        assert!(request.contains("user-agent: foxdie-test/1.0\r\n"));
+    }
+
+    #[test]
+    fn test_detect_host_probes_each_host_once() {
+        let (base_url, requested) = serve_paths_recording(&["/api/v4/version"]);
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let cache = super::ProviderCache::default();
+        let detect = |hostname| {
+            runtime.block_on(super::detect_host(
+                &client(),
+                hostname,
+                &base_url,
+                "token",
+                Some(&cache),
+            ))
+        };
+        let request_count = || requested.lock().unwrap().len();
+
+        let first = detect("git.example.com");
+        let probes = request_count();
+        let second = detect("git.example.com");
+
+        assert_eq!(first, Some((base_url.clone(), super::SCMKind::Gitlab)));
+        assert_eq!(second, first);
+        assert!(probes > 0);
+        assert_eq!(request_count(), probes);
+
+        detect("other.example.com");
+        assert!(request_count() > probes);
+    }
+
+    #[test]
+    fn test_detect_host_stops_at_github() {
+        let (base_url, requested) =
+            serve_paths_recording(&["/api/v3/zen", "/api/v4/version", "/api/v1/version"]);
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        let detected = runtime.block_on(super::detect_host(
+            &client(),
+            "git.example.com",
+            &base_url,
+            "token",
+            None,
+        ));
+
+        assert_eq!(
+            detected,
+            Some((format!("{}/api/v3", base_url), super::SCMKind::GitHub))
+        );
+        assert_eq!(*requested.lock().unwrap(), vec!["/api/v3/zen"]);
+    }
+
+    #[test]
+    fn test_hostname() {
+        assert_eq!(
+            super::hostname("https://github.com/wayfair/foxdie"),
+            Some("github.com".to_string())
+        );
+        assert_eq!(
+            super::hostname("git@GitLab.example.com:wayfair/foxdie.git"),
+            Some("gitlab.example.com".to_string())
+        );
+        assert_eq!(super::hostname("not a url"), None);
+    }
+
+    #[test]
+    fn test_kind_for_url() {
+        use super::{kind_for_url, SCMKind};
+        assert_eq!(
+            kind_for_url("https://github.com/wayfair/foxdie"),
+            Some(SCMKind::GitHub)
+        );
+        assert_eq!(
+            kind_for_url("git@www.GitHub.com:wayfair/foxdie.git"),
+            Some(SCMKind::GitHub)
+        );
+        assert_eq!(
+            kind_for_url("https://gitlab.com/wayfair/foxdie"),
+            Some(SCMKind::Gitlab)
+        );
+        assert_eq!(
+            kind_for_url("git@gitlab.com:wayfair/foxdie.git"),
+            Some(SCMKind::Gitlab)
+        );
+        assert_eq!(
+            kind_for_url("https://bitbucket.org/wayfair/foxdie"),
+            Some(SCMKind::Unsupported)
+        );
+        assert_eq!(kind_for_url("not a url"), None);
+        if std::env::var("GITHUB_BASE_URL").is_err() && std::env::var("GITLAB_BASE_URL").is_err() {
+            assert_eq!(kind_for_url("https://git.example.com/wayfair/foxdie"), None);
+        }
+    }
+
+    #[test]
+    fn test_owner_and_repo() {
+        let owner_and_repo = |repository: &str| {
+            let url = super::Url::parse(&super::scrub_git_url_if_needed(repository)).unwrap();
+            super::owner_and_repo(&url).map(|(owner, repo)| (owner.to_string(), repo.to_string()))
+        };
+        let expected = Some(("wayfair".to_string(), "foxdie".to_string()));
+        assert_eq!(
+            owner_and_repo("https://github.com/wayfair/foxdie"),
+            expected
+        );
+        assert_eq!(
+            owner_and_repo("https://github.com/wayfair/foxdie/"),
+            expected
+        );
+        assert_eq!(
+            owner_and_repo("https://github.com/wayfair/foxdie.git"),
+            expected
+        );
+        assert_eq!(
+            owner_and_repo("https://github.com/wayfair/foxdie.git/"),
+            expected
+        );
+        assert_eq!(
+            owner_and_repo("https://github.com//wayfair//foxdie"),
+            expected
+        );
+        assert_eq!(
+            owner_and_repo("git@github.com:wayfair/foxdie.git/"),
+            expected
+        );
+        assert_eq!(
+            owner_and_repo("https://github.com/wayfair/foxdie.github.io"),
+            Some(("wayfair".to_string(), "foxdie.github.io".to_string()))
+        );
+        assert_eq!(owner_and_repo("https://github.com/wayfair/"), None);
+        assert_eq!(owner_and_repo("https://github.com/wayfair/.git"), None);
+    }
+
+    #[test]
+    fn test_what_git_normalizes_github_urls() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        for repository in &[
+            "https://GitHub.com/wayfair/foxdie/",
+            "git@GitHub.com:wayfair/foxdie.git/",
+        ] {
+            let scm = runtime
+                .block_on(super::what_git(repository, "token"))
+                .unwrap();
+            assert_eq!(scm.kind, super::SCMKind::GitHub);
+            assert_eq!(scm.base_url, "https://api.github.com");
+            assert_eq!((&*scm.owner, &*scm.repo), ("wayfair", "foxdie"));
+        }
+    }
+
     #[test]
     fn test_scrub_git_url() {
         assert_eq!(
@@ -239,5 +861,13 @@ mod tests {
             super::scrub_git_url_if_needed("git@github.com:wayfair/foxdie.git"),
             "git://github.com/wayfair/foxdie.git"
         );
+        assert_eq!(
+            super::scrub_git_url_if_needed("git@github.com:wayfair/foxdie.git/"),
+            "git://github.com/wayfair/foxdie.git/"
+        );
+        assert_eq!(
+            super::scrub_git_url_if_needed("git@GitHub.com:wayfair/foxdie/"),
+            "git://GitHub.com/wayfair/foxdie/"
+        );
     }
 }